@@ -553,7 +553,7 @@ pub enum Source {
     Opaque,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelType {
     U8,
     U16,
@@ -158,7 +158,7 @@ impl Operations {
                     orientation = orientation.add_mirror_horizontally();
                 }
                 Operation::MirrorVertically => {
-                    orientation = orientation.add_mirror_vertically();
+                    orientation = add_mirror_vertically(orientation);
                 }
                 Operation::Rotate(rotation) => {
                     orientation = orientation.add_rotation(*rotation);
@@ -169,6 +169,163 @@ impl Operations {
 
         Some(orientation)
     }
+
+    /// Reduces operations to at most one clip followed by an orientation
+    ///
+    /// The `image_size` is the size of the image the operations are applied
+    /// to. It is required to transform the clip coordinates through rotations
+    /// and mirroring. The resulting clip is given in coordinates of the
+    /// original image.
+    ///
+    /// Returns `None` if a clip starts outside of the image.
+    ///
+    /// ```
+    /// # use glycin_common::{Operations, Operation};
+    /// # use gufo_common::orientation::Rotation;
+    /// let operations = Operations::new(vec![
+    ///     Operation::Rotate(Rotation::_90),
+    ///     Operation::Clip((10, 20, 30, 40)),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     operations.normalize((100, 50)).unwrap().operations(),
+    ///     &[
+    ///         Operation::Clip((40, 10, 40, 30)),
+    ///         Operation::Rotate(Rotation::_90)
+    ///     ]
+    /// );
+    /// ```
+    pub fn normalize(&self, (width, height): (u32, u32)) -> Option<Operations> {
+        let mut clip = (0, 0, width, height);
+        let mut orientation = Orientation::Id;
+
+        for operation in &self.operations {
+            match operation {
+                Operation::MirrorHorizontally => {
+                    orientation = orientation.add_mirror_horizontally();
+                }
+                Operation::MirrorVertically => {
+                    orientation = add_mirror_vertically(orientation);
+                }
+                Operation::Rotate(rotation) => {
+                    orientation = orientation.add_rotation(*rotation);
+                }
+                Operation::Clip((x, y, clip_width, clip_height)) => {
+                    let (current_x, current_y, current_width, current_height) = clip;
+
+                    let displayed_size =
+                        if matches!(orientation.rotate(), Rotation::_90 | Rotation::_270) {
+                            (current_height, current_width)
+                        } else {
+                            (current_width, current_height)
+                        };
+
+                    let clip_width = u32::min(*clip_width, displayed_size.0.checked_sub(*x)?);
+                    let clip_height = u32::min(*clip_height, displayed_size.1.checked_sub(*y)?);
+
+                    // Map the rectangle from displayed coordinates back to the
+                    // coordinates before applying the orientation
+                    let rect = rotate_rect(
+                        (*x, *y, clip_width, clip_height),
+                        displayed_size,
+                        Rotation::_0 - orientation.rotate(),
+                    );
+                    let (x, y, clip_width, clip_height) = if orientation.mirror() {
+                        mirror_rect(rect, (current_width, current_height))
+                    } else {
+                        rect
+                    };
+
+                    clip = (current_x + x, current_y + y, clip_width, clip_height);
+                }
+            }
+        }
+
+        let mut operations = Vec::new();
+
+        if clip != (0, 0, width, height) {
+            operations.push(Operation::Clip(clip));
+        }
+
+        operations.append(&mut Operations::new_orientation(orientation).operations);
+
+        Some(Operations::new(operations))
+    }
+
+    /// Returns operations that revert these operations
+    ///
+    /// Returns `None` if the operations contain a clip, since clipping can't
+    /// be reverted.
+    ///
+    /// ```
+    /// # use glycin_common::{Operations, Operation};
+    /// # use gufo_common::orientation::Rotation;
+    /// let operations = Operations::new(vec![
+    ///     Operation::Rotate(Rotation::_90),
+    ///     Operation::MirrorVertically,
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     operations.inverse().unwrap().operations(),
+    ///     &[Operation::MirrorHorizontally, Operation::Rotate(Rotation::_90)]
+    /// );
+    ///
+    /// assert!(
+    ///     Operations::new(vec![Operation::Clip((0, 0, 10, 10))])
+    ///         .inverse()
+    ///         .is_none()
+    /// );
+    /// ```
+    pub fn inverse(&self) -> Option<Operations> {
+        let orientation = self.orientation()?;
+
+        // Mirrored orientations are their own inverse
+        let inverse = if orientation.mirror() {
+            orientation
+        } else {
+            Orientation::new(false, Rotation::_0 - orientation.rotate())
+        };
+
+        Some(Operations::new_orientation(inverse))
+    }
+}
+
+/// Add vertical mirroring to an orientation
+///
+/// Vertical mirroring is horizontal mirroring followed by a rotation by 180°.
+/// Unlike [`Orientation::add_mirror_vertically`], this also works for
+/// orientations that contain a rotation by 90° or 270°.
+fn add_mirror_vertically(orientation: Orientation) -> Orientation {
+    orientation
+        .add_mirror_horizontally()
+        .add_rotation(Rotation::_180)
+}
+
+/// Rotate rectangle counter-clockwise within an image of the given size
+fn rotate_rect(
+    (x, y, width, height): (u32, u32, u32, u32),
+    (image_width, image_height): (u32, u32),
+    rotation: Rotation,
+) -> (u32, u32, u32, u32) {
+    match rotation {
+        Rotation::_0 => (x, y, width, height),
+        Rotation::_90 => (y, image_width - x - width, height, width),
+        Rotation::_180 => (
+            image_width - x - width,
+            image_height - y - height,
+            width,
+            height,
+        ),
+        Rotation::_270 => (image_height - y - height, x, height, width),
+    }
+}
+
+/// Mirror rectangle horizontally within an image of the given size
+fn mirror_rect(
+    (x, y, width, height): (u32, u32, u32, u32),
+    (image_width, _): (u32, u32),
+) -> (u32, u32, u32, u32) {
+    (image_width - x - width, y, width, height)
 }
 
 impl From<OperationsIntermediate> for Operations {
@@ -3,9 +3,11 @@ mod creator;
 mod editor;
 mod limits;
 mod loader;
+mod transcoder;
 
 pub use common::*;
 pub use creator::*;
 pub use editor::*;
 pub use limits::*;
 pub use loader::*;
+pub use transcoder::*;
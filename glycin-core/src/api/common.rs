@@ -109,6 +109,26 @@ impl GInputStreamSend {
     }
 }
 
+/// A version of an output stream that can be sent.
+///
+/// Using the stream from multiple threads is UB. Therefore the `new` function
+/// is unsafe.
+#[derive(Debug, Clone)]
+pub(crate) struct GOutputStreamSend(gio::OutputStream);
+
+unsafe impl Send for GOutputStreamSend {}
+unsafe impl Sync for GOutputStreamSend {}
+
+impl GOutputStreamSend {
+    pub(crate) unsafe fn new(stream: gio::OutputStream) -> Self {
+        Self(stream)
+    }
+
+    pub(crate) fn stream(&self) -> &gio::OutputStream {
+        &self.0
+    }
+}
+
 /// Image source for a loader/editor
 #[derive(Debug, Clone)]
 pub(crate) enum Source {
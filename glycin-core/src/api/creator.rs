@@ -1,7 +1,10 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
+use gio::prelude::*;
 use glib::object::IsA;
 use glib::prelude::*;
 use glycin_common::MemoryFormatInfo;
@@ -10,13 +13,17 @@ use glycin_utils::{
 };
 use gufo_common::physical_dimension::PixelDensity;
 
+use crate::api::GOutputStreamSend;
 #[cfg(feature = "builtin")]
 use crate::config;
 use crate::config::{Config, EditorConfig};
 use crate::error::{ErrorKind, ResultExt};
 use crate::pool::Pool;
-use crate::util::CancellableFuture;
-use crate::{Error, MimeType, Processor, ProcessorContext, SandboxSelector};
+use crate::util::{self, CancellableFuture, TimeoutFuture};
+use crate::{
+    ChromaSubsampling, Cicp, Error, ExrCompression, Limits, MimeType, Processor, ProcessorContext,
+    SandboxSelector, TextureCompression,
+};
 
 /// Builder pattern for creating images
 #[derive(Debug)]
@@ -26,9 +33,13 @@ pub struct Creator {
     pool: Arc<Pool>,
     pub(crate) cancellable: gio::Cancellable,
     pub(crate) sandbox_selector: SandboxSelector,
+    limits: Limits,
     encoding_options: glycin_utils::EncodingOptions,
     new_image: glycin_utils::NewImage<FungibleMemory>,
     new_frames: Vec<NewFrame>,
+    metadata_exif: Option<Vec<u8>>,
+    metadata_xmp: Option<Vec<u8>>,
+    source_jpeg: Option<Vec<u8>>,
     transform_memory_formats: bool,
 }
 
@@ -60,13 +71,27 @@ impl Creator {
             pool: Pool::global(),
             cancellable: gio::Cancellable::new(),
             sandbox_selector: SandboxSelector::default(),
+            limits: Limits::default(),
             encoding_options: glycin_utils::EncodingOptions::default(),
             new_image: glycin_utils::NewImage::new(glycin_utils::ImageDetails::new(1, 1), vec![]),
             new_frames: vec![],
+            metadata_exif: None,
+            metadata_xmp: None,
+            source_jpeg: None,
             transform_memory_formats: true,
         })
     }
 
+    /// Features the creator supports for `mime_type`
+    ///
+    /// This allows to check the supported features without instantiating a
+    /// creator.
+    pub async fn capabilities(mime_type: MimeType) -> Result<CreatorCapabilities, Error> {
+        let config = Config::cached().await.editor(&mime_type)?.clone();
+
+        Ok(CreatorCapabilities { config })
+    }
+
     pub fn add_frame(
         &mut self,
         width: u32,
@@ -148,17 +173,151 @@ impl Creator {
     pub fn create(self) -> Pin<Box<dyn Future<Output = Result<EncodedImage, Error>> + Send>> {
         Box::pin(async move {
             let cancellable = self.cancellable.clone();
+            let timeout = self.limits.inner.timeout;
 
-            self.create_internal().make_cancellable(cancellable).await
+            self.create_internal(None)
+                .make_cancellable(cancellable)
+                .enforce_timeout(timeout)
+                .await?
+                .ok_or_else(|| ErrorKind::unreachable().err())
         })
     }
 
-    async fn create_internal(self) -> Result<EncodedImage, Error> {
+    /// Encode an image and write it to `stream`
+    ///
+    /// The encoded image is streamed from the editor process and is never
+    /// kept in memory completely by this process. The stream is not closed.
+    ///
+    /// Formats that are written while encoding, like BMP, are also not kept
+    /// in memory completely by the editor process. Formats whose encoders
+    /// have to seek in the output, like TIFF and OpenEXR, are encoded
+    /// completely by the editor before they are streamed. With a
+    /// [target size](Self::set_target_size), the complete image is
+    /// transferred first, since its size has to be checked before writing.
+    ///
+    /// Data that have been written before an error occurred, including
+    /// cancellation, remain in the stream. Their size is available via
+    /// [`Error::partial_output_size()`].
+    ///
+    /// # Safety
+    ///
+    /// The provided stream must not be used until the returned future has
+    /// completed.
+    pub unsafe fn create_to(
+        self,
+        stream: impl IsA<gio::OutputStream>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        let stream = unsafe { GOutputStreamSend::new(stream.upcast()) };
+
+        self.create_to_send(stream)
+    }
+
+    pub(crate) fn create_to_send(
+        self,
+        stream: GOutputStreamSend,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        Box::pin(async move {
+            let cancellable = self.cancellable.clone();
+            let timeout = self.limits.inner.timeout;
+
+            let (released, output_released) = futures_channel::oneshot::channel();
+            let output = CreateOutput {
+                stream,
+                written: Arc::default(),
+                cancellable: gio::Cancellable::new(),
+                _released: released,
+            };
+            let written = output.written.clone();
+            let output_cancellable = output.cancellable.clone();
+
+            let result = self
+                .create_internal(Some(output))
+                .make_cancellable(cancellable)
+                .enforce_timeout(timeout)
+                .await;
+
+            if let Err(err) = result {
+                // Stop writing if creating was canceled or timed out and wait until the
+                // stream is no longer used
+                output_cancellable.cancel();
+                let _ = output_released.await;
+                return Err(err.with_partial_output_size(written.load(Ordering::Relaxed)));
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Encode an image and write it to `file`
+    ///
+    /// An existing `file` is only replaced after the image has been written
+    /// completely.
+    pub fn create_to_file(
+        self,
+        file: gio::File,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        Box::pin(async move {
+            let stream = util::spawn_blocking(move || {
+                let stream = file.replace(
+                    None,
+                    false,
+                    gio::FileCreateFlags::REPLACE_DESTINATION,
+                    gio::Cancellable::NONE,
+                )?;
+
+                // The stream is only used from one thread at a time
+                Ok::<_, Error>(ReplaceStream(Some(unsafe {
+                    GOutputStreamSend::new(stream.upcast())
+                })))
+            })
+            .await??;
+
+            self.create_to_send(stream.stream().clone()).await?;
+
+            stream.commit().await
+        })
+    }
+
+    /// Encode image and return it or write it to `output_stream`
+    ///
+    /// Returns `None` if the image was written to `output_stream`.
+    async fn create_internal(
+        self,
+        output: Option<CreateOutput>,
+    ) -> Result<Option<EncodedImage>, Error> {
         let mut new_image = self.new_image;
+        new_image.limits = self.limits.inner.clone();
+
+        if let Some(metadata_exif) = self.metadata_exif {
+            new_image.image_info.metadata_exif = Some(FungibleMemory::try_from_vec(metadata_exif)?);
+        }
+
+        if let Some(metadata_xmp) = self.metadata_xmp {
+            new_image.image_info.metadata_xmp = Some(FungibleMemory::try_from_vec(metadata_xmp)?);
+        }
+
+        if let Some(source_jpeg) = self.source_jpeg {
+            new_image.source_jpeg = Some(FungibleMemory::try_from_vec(source_jpeg)?);
+        }
+
+        if self.new_frames.len() > 1
+            && !self.config.creator_animation
+            && !self.config.creator_multi_page
+        {
+            tracing::warn!(
+                "Creator for '{}' doesn't support multiple frames. Additional frames might be ignored.",
+                self.mime_type
+            );
+        }
 
         for frame in self.new_frames {
             let mut frame = frame.frame()?;
 
+            let (max_width, max_height) = self.limits.inner.max_dimensions;
+            if frame.width > max_width || frame.height > max_height {
+                return Err(ErrorKind::TextureTooLarge.err());
+            }
+
             if self.transform_memory_formats {
                 let creator_memory_formats = self.config.creator_memory_formats();
 
@@ -181,6 +340,8 @@ impl Creator {
             new_image.frames.push(frame);
         }
 
+        let target_size = self.encoding_options.target_size;
+
         let editor_context =
             ProcessorContext::new_sourceless(self.mime_type, &self.sandbox_selector).await?;
 
@@ -188,28 +349,64 @@ impl Creator {
             .editor(self.pool.clone(), &self.cancellable)
             .await?;
 
-        match editor {
+        let (encoded_image, output) = match editor {
             #[cfg(feature = "external")]
             Processor::Binary(editor) => {
                 let process = editor.process.use_();
+                let new_image = new_image.into_other()?;
 
-                EncodedImage::new(
-                    process
-                        .create(
+                let output = match output {
+                    // The size has to be checked before anything is written
+                    Some(output) if target_size.is_none() => {
+                        let (reader, writer) = std::os::unix::net::UnixStream::pair()?;
+
+                        let copy = util::spawn_blocking(move || {
+                            let input_stream = gio_unix::InputStream::take_fd(reader.into());
+                            let result = output.copy_from(input_stream.upcast_ref());
+                            (output, result)
+                        });
+
+                        let create = process.create_to_fd(
                             &editor.mime_type,
-                            new_image.into_other()?,
-                            self.encoding_options,
-                        )
+                            &new_image,
+                            &self.encoding_options,
+                            writer.into(),
+                        );
+
+                        let (created, copied) = futures_util::future::join(create, copy).await;
+
+                        // A failed output also fails the editor, report the cause
+                        let (output, copied) = copied?;
+                        copied?;
+                        let created = created.err_context(&process)?;
+
+                        if created.is_some() {
+                            return Ok(None);
+                        }
+
+                        tracing::debug!(
+                            "Editor for '{}' doesn't support streaming, falling back to shared memory",
+                            editor.mime_type
+                        );
+
+                        Some(output)
+                    }
+                    output => output,
+                };
+
+                let encoded_image = EncodedImage::new(
+                    process
+                        .create(&editor.mime_type, new_image, self.encoding_options)
                         .await
                         .map(|x| x.into_fungible())
                         .err_context(&process)?,
                 )
-                .await
+                .await?;
+
+                (encoded_image, output)
             }
             #[cfg(feature = "builtin")]
             Processor::Builtin(builtin) => {
-                use glycin_utils::EditorImplementation;
-
                 let mime_type = builtin.mime_type.to_string();
                 let encoding_options = self.encoding_options;
 
@@ -219,7 +416,7 @@ impl Creator {
                     #[cfg(feature = "builtin-image-rs")]
                     config::BuiltinProcessor::ImageRs(_) => {
                         editor_function = Box::new(move || {
-                            glycin_image_rs::ImgEditor::create(
+                            glycin_utils::create::<glycin_image_rs::ImgEditor, _>(
                                 mime_type,
                                 new_image,
                                 encoding_options,
@@ -229,7 +426,11 @@ impl Creator {
                     #[cfg(feature = "builtin-test")]
                     config::BuiltinProcessor::Test(_) => {
                         editor_function = Box::new(move || {
-                            glycin_test::ImgEditor::create(mime_type, new_image, encoding_options)
+                            glycin_utils::create::<glycin_test::ImgEditor, _>(
+                                mime_type,
+                                new_image,
+                                encoding_options,
+                            )
                         });
                     }
                 }
@@ -240,9 +441,18 @@ impl Creator {
                 .await
                 .map_err(|e| ErrorKind::panic(e))??;
 
-                EncodedImage::new(encoded_image).await
+                (EncodedImage::new(encoded_image).await?, output)
+            }
+        };
+
+        if let Some(target_size) = target_size {
+            let size = encoded_image.data_ref().len();
+            if size as u64 > target_size {
+                return Err(ErrorKind::TargetSizeExceeded { size, target_size }.err());
             }
         }
+
+        encoded_image.write_or_return(output).await
     }
 
     pub fn set_encoding_quality(&mut self, quality: u8) -> Result<(), FeatureNotSupported> {
@@ -254,6 +464,40 @@ impl Creator {
         Ok(())
     }
 
+    /// Set quality of the alpha channel
+    ///
+    /// For formats that store the alpha channel separately, like lossy WebP.
+    /// The range is from 0 (fewest alpha levels) to 100 (alpha is stored
+    /// without loss).
+    pub fn set_encoding_alpha_quality(
+        &mut self,
+        alpha_quality: u8,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_alpha_quality {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.alpha_quality = Some(alpha_quality.min(100));
+        Ok(())
+    }
+
+    /// Encode with the highest quality that results in at most `target_size`
+    /// bytes
+    ///
+    /// The quality is searched for by the sandboxed editor and overrides
+    /// [`set_encoding_quality()`](Self::set_encoding_quality). The selected
+    /// quality is available via [`EncodedImage::quality()`]. If the image
+    /// can't be encoded within the target size, creating the image fails
+    /// with an error for which [`Error::is_target_size_exceeded()`] is `true`.
+    pub fn set_target_size(&mut self, target_size: u64) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_quality {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.target_size = Some(target_size);
+        Ok(())
+    }
+
     /// Set compression level
     ///
     /// This sets the lossless compression level. The range is from 0 (no
@@ -267,6 +511,168 @@ impl Creator {
         Ok(())
     }
 
+    /// Encode without any loss of information
+    pub fn set_encoding_lossless(&mut self, lossless: bool) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_lossless {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.lossless = Some(lossless);
+        Ok(())
+    }
+
+    /// Set resolution of the chroma channels
+    pub fn set_encoding_chroma_subsampling(
+        &mut self,
+        chroma_subsampling: ChromaSubsampling,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_chroma_subsampling {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.chroma_subsampling = Some(chroma_subsampling);
+        Ok(())
+    }
+
+    /// Store the image such that a lower resolution can be shown early
+    pub fn set_encoding_progressive(
+        &mut self,
+        progressive: bool,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_progressive {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.progressive = Some(progressive);
+        Ok(())
+    }
+
+    /// Set encoding effort
+    ///
+    /// The range is from 0 (fastest encoding) to 100 (smallest output).
+    pub fn set_encoding_effort(&mut self, effort: u8) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_effort {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.effort = Some(effort);
+        Ok(())
+    }
+
+    /// Set bits per channel in the encoded image
+    pub fn set_encoding_bit_depth(&mut self, bit_depth: u8) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_bit_depth {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.bit_depth = Some(bit_depth);
+        Ok(())
+    }
+
+    /// Store the image with a palette of at most `palette_size` colors
+    ///
+    /// The colors are reduced with dithering. Values are limited to be
+    /// between 2 and 256. Animated PNGs are always stored without a palette.
+    pub fn set_encoding_palette_size(
+        &mut self,
+        palette_size: u16,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_palette_size {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.palette_size = Some(palette_size.clamp(2, 256));
+        Ok(())
+    }
+
+    /// Set the block compression for GPU textures
+    pub fn set_encoding_texture_compression(
+        &mut self,
+        texture_compression: TextureCompression,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_texture_compression {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.texture_compression = Some(texture_compression);
+        Ok(())
+    }
+
+    /// Store mipmaps
+    ///
+    /// The mipmaps are scaled down from the first frame, halving the size
+    /// for each level until both sides are one pixel.
+    pub fn set_encoding_mipmaps(&mut self, mipmaps: bool) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_mipmaps {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.mipmaps = Some(mipmaps);
+        Ok(())
+    }
+
+    /// Set the compression method for OpenEXR images
+    pub fn set_encoding_exr_compression(
+        &mut self,
+        exr_compression: ExrCompression,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_exr_compression {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.exr_compression = Some(exr_compression);
+        Ok(())
+    }
+
+    /// Store the image as CMYK for print
+    ///
+    /// The frames are converted from their ICC profile, or sRGB if they
+    /// don't have one, to the CMYK color space described by `icc_profile`.
+    /// The CMYK profile is embedded in the image instead of the frames' ICC
+    /// profile.
+    pub fn set_encoding_cmyk_icc_profile(
+        &mut self,
+        icc_profile: Vec<u8>,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_encoding_cmyk_icc_profile {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.cmyk_icc_profile = Some(icc_profile);
+        Ok(())
+    }
+
+    /// Add the standard icon sizes to the image
+    ///
+    /// The missing sizes are scaled down from the largest frame.
+    pub fn set_icon_standard_sizes(
+        &mut self,
+        icon_standard_sizes: bool,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_icon_standard_sizes {
+            return Err(FeatureNotSupported);
+        }
+
+        self.encoding_options.icon_standard_sizes = Some(icon_standard_sizes);
+        Ok(())
+    }
+
+    /// Losslessly recompress an existing JPEG
+    ///
+    /// The JPEG is stored instead of the added frames, such that the original
+    /// file can be reconstructed bit-exactly via
+    /// [`Image::reconstruct_jpeg()`](crate::Image::reconstruct_jpeg). The
+    /// metadata of the JPEG are kept and most other encoding options are
+    /// ignored.
+    pub fn set_source_jpeg(&mut self, jpeg: Vec<u8>) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_jpeg_recompression {
+            return Err(FeatureNotSupported);
+        }
+
+        self.source_jpeg = Some(jpeg);
+        Ok(())
+    }
+
     pub fn set_metadata_key_value(
         &mut self,
         key_value: BTreeMap<String, String>,
@@ -279,6 +685,42 @@ impl Creator {
         Ok(())
     }
 
+    /// Set Exif metadata
+    ///
+    /// The data has to start with the TIFF header, without the `Exif\0\0`
+    /// prefix used in JPEG.
+    pub fn set_metadata_exif(&mut self, exif: Option<Vec<u8>>) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_metadata_exif && exif.is_some() {
+            return Err(FeatureNotSupported);
+        }
+
+        self.metadata_exif = exif;
+        Ok(())
+    }
+
+    /// Set XMP metadata
+    pub fn set_metadata_xmp(&mut self, xmp: Option<Vec<u8>>) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_metadata_xmp && xmp.is_some() {
+            return Err(FeatureNotSupported);
+        }
+
+        self.metadata_xmp = xmp;
+        Ok(())
+    }
+
+    /// Set how often an animation is played
+    ///
+    /// A value of zero means that the animation is repeated infinitely, which
+    /// is also the default.
+    pub fn set_loop_count(&mut self, loop_count: u32) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_animation {
+            return Err(FeatureNotSupported);
+        }
+
+        self.new_image.image_info.animation_loop_count = Some(loop_count);
+        Ok(())
+    }
+
     /// Transform texture to supported memory format
     ///
     /// Automatically transform the textures for each frame to a memory format
@@ -321,6 +763,170 @@ impl Creator {
         self.cancellable = cancellable.upcast();
         self
     }
+
+    pub fn pool(&mut self, pool: Arc<Pool>) -> &mut Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Limits for creating the image
+    ///
+    /// Frames that exceed the maximum dimensions are rejected before they are
+    /// sent to the encoder.
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+}
+
+/// Features supported by a [creator](Creator) for an image format
+///
+/// Returned by [`Creator::capabilities()`]. Setters for unsupported features
+/// return [`FeatureNotSupported`].
+#[derive(Debug, Clone)]
+pub struct CreatorCapabilities {
+    config: EditorConfig,
+}
+
+impl CreatorCapabilities {
+    /// Memory formats the format supports for writing
+    ///
+    /// Textures in other memory formats are transformed to one of these
+    /// formats unless disabled via
+    /// [`Creator::set_transform_memory_format()`].
+    pub fn memory_formats(&self) -> &BTreeSet<MemoryFormat> {
+        self.config.creator_memory_formats()
+    }
+
+    /// Supports images with more than one frame
+    pub fn supports_animation(&self) -> bool {
+        self.config.creator_animation
+    }
+
+    /// Supports storing each frame as a separate page, resolution, or layer
+    pub fn supports_multi_page(&self) -> bool {
+        self.config.creator_multi_page
+    }
+
+    /// See [`Creator::set_encoding_quality()`]
+    pub fn supports_encoding_quality(&self) -> bool {
+        self.config.creator_encoding_quality
+    }
+
+    /// See [`Creator::set_encoding_alpha_quality()`]
+    pub fn supports_encoding_alpha_quality(&self) -> bool {
+        self.config.creator_encoding_alpha_quality
+    }
+
+    /// See [`Creator::set_target_size()`]
+    pub fn supports_target_size(&self) -> bool {
+        self.config.creator_encoding_quality
+    }
+
+    /// See [`Creator::set_encoding_compression()`]
+    pub fn supports_encoding_compression(&self) -> bool {
+        self.config.creator_encoding_compression
+    }
+
+    /// See [`Creator::set_encoding_lossless()`]
+    pub fn supports_encoding_lossless(&self) -> bool {
+        self.config.creator_encoding_lossless
+    }
+
+    /// See [`Creator::set_encoding_chroma_subsampling()`]
+    pub fn supports_encoding_chroma_subsampling(&self) -> bool {
+        self.config.creator_encoding_chroma_subsampling
+    }
+
+    /// See [`Creator::set_encoding_progressive()`]
+    pub fn supports_encoding_progressive(&self) -> bool {
+        self.config.creator_encoding_progressive
+    }
+
+    /// See [`Creator::set_encoding_effort()`]
+    pub fn supports_encoding_effort(&self) -> bool {
+        self.config.creator_encoding_effort
+    }
+
+    /// See [`Creator::set_encoding_bit_depth()`]
+    pub fn supports_encoding_bit_depth(&self) -> bool {
+        self.config.creator_encoding_bit_depth
+    }
+
+    /// See [`Creator::set_encoding_palette_size()`]
+    pub fn supports_encoding_palette_size(&self) -> bool {
+        self.config.creator_encoding_palette_size
+    }
+
+    /// See [`Creator::set_encoding_texture_compression()`]
+    pub fn supports_encoding_texture_compression(&self) -> bool {
+        self.config.creator_encoding_texture_compression
+    }
+
+    /// See [`Creator::set_encoding_mipmaps()`]
+    pub fn supports_encoding_mipmaps(&self) -> bool {
+        self.config.creator_encoding_mipmaps
+    }
+
+    /// See [`Creator::set_encoding_exr_compression()`]
+    pub fn supports_encoding_exr_compression(&self) -> bool {
+        self.config.creator_encoding_exr_compression
+    }
+
+    /// See [`Creator::set_encoding_cmyk_icc_profile()`]
+    pub fn supports_encoding_cmyk_icc_profile(&self) -> bool {
+        self.config.creator_encoding_cmyk_icc_profile
+    }
+
+    /// See [`Creator::set_icon_standard_sizes()`]
+    pub fn supports_icon_standard_sizes(&self) -> bool {
+        self.config.creator_icon_standard_sizes
+    }
+
+    /// See [`Creator::set_source_jpeg()`]
+    pub fn supports_jpeg_recompression(&self) -> bool {
+        self.config.creator_jpeg_recompression
+    }
+
+    /// See [`Creator::set_metadata_exif()`]
+    pub fn supports_metadata_exif(&self) -> bool {
+        self.config.creator_metadata_exif
+    }
+
+    /// See [`Creator::set_metadata_xmp()`]
+    pub fn supports_metadata_xmp(&self) -> bool {
+        self.config.creator_metadata_xmp
+    }
+
+    /// See [`Creator::set_metadata_key_value()`]
+    pub fn supports_metadata_key_value(&self) -> bool {
+        self.config.creator_metadata_key_value
+    }
+
+    /// See [`NewFrame::set_color_icc_profile()`]
+    pub fn supports_color_icc_profile(&self) -> bool {
+        self.config.creator_color_icc_profile
+    }
+
+    /// See [`NewFrame::set_color_cicp()`]
+    pub fn supports_color_cicp(&self) -> bool {
+        self.config.creator_color_cicp
+    }
+
+    /// See [`NewFrame::set_pixel_density()`]
+    pub fn supports_pixel_density(&self) -> bool {
+        self.config.creator_pixel_density
+    }
+
+    /// See [`NewFrame::set_cursor_hotspot()`]
+    pub fn supports_cursor_hotspot(&self) -> bool {
+        self.config.creator_cursor_hotspot
+    }
+
+    /// See [`NewFrame::set_layer_name()`]
+    pub fn supports_layer_name(&self) -> bool {
+        self.config.creator_layer_name
+    }
 }
 
 /// Builder pattern for a new frame
@@ -334,7 +940,7 @@ pub struct NewFrame {
     //stride: Option<u32>,
     memory_format: MemoryFormat,
     texture: Vec<u8>,
-    //delay: Option<Duration>,
+    delay: Option<Duration>,
     details: glycin_utils::FrameDetails<FungibleMemory>,
     icc_profile: Option<Vec<u8>>,
 }
@@ -354,7 +960,7 @@ impl NewFrame {
             memory_format,
             texture,
             //stride: None,
-            //delay: None,
+            delay: None,
             details: Default::default(),
             icc_profile: Default::default(),
         }
@@ -372,6 +978,20 @@ impl NewFrame {
         Ok(())
     }
 
+    /// Set coding-independent code points (CICP) for the frame
+    ///
+    /// Allows to tag HDR content, like PQ or HLG transfer characteristics,
+    /// and wide gamut color primaries like Display P3.
+    pub fn set_color_cicp(&mut self, cicp: Option<Cicp>) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_color_cicp && cicp.is_some() {
+            return Err(FeatureNotSupported);
+        }
+
+        self.details.color_cicp = cicp.map(|x| x.to_bytes());
+
+        Ok(())
+    }
+
     pub fn set_pixel_density(
         &mut self,
         pixel_density: Option<PixelDensity>,
@@ -385,6 +1005,49 @@ impl NewFrame {
         Ok(())
     }
 
+    /// Duration to show the frame in an animation
+    pub fn set_delay(&mut self, delay: Option<Duration>) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_animation && delay.is_some() {
+            return Err(FeatureNotSupported);
+        }
+
+        self.delay = delay;
+
+        Ok(())
+    }
+
+    /// Position of the pointer within a cursor image
+    ///
+    /// The coordinates `(x, y)` are relative to the top left corner.
+    pub fn set_cursor_hotspot(
+        &mut self,
+        cursor_hotspot: Option<(u16, u16)>,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_cursor_hotspot && cursor_hotspot.is_some() {
+            return Err(FeatureNotSupported);
+        }
+
+        self.details.cursor_hotspot = cursor_hotspot;
+
+        Ok(())
+    }
+
+    /// Name of the layer the frame is stored as
+    ///
+    /// Only used by formats that store each frame as a separate layer.
+    pub fn set_layer_name(
+        &mut self,
+        layer_name: Option<String>,
+    ) -> Result<(), FeatureNotSupported> {
+        if !self.config.creator_layer_name && layer_name.is_some() {
+            return Err(FeatureNotSupported);
+        }
+
+        self.details.layer_name = layer_name;
+
+        Ok(())
+    }
+
     fn frame(self) -> Result<glycin_utils::Frame<FungibleMemory>, Error> {
         let texture = FungibleMemory::try_from_vec(self.texture)?;
         let mut frame = glycin_utils::Frame::<FungibleMemory>::new(
@@ -394,6 +1057,7 @@ impl NewFrame {
             texture,
         )?;
 
+        frame.delay = self.delay.into();
         frame.details = self.details;
 
         if let Some(icc_profile) = self.icc_profile {
@@ -425,4 +1089,103 @@ impl EncodedImage {
     pub fn data_full(&self) -> Vec<u8> {
         self.inner.data.to_vec()
     }
+
+    /// Quality that was selected to reach the target size
+    ///
+    /// Only set if [`Creator::set_target_size()`] was used.
+    pub fn quality(&self) -> Option<u8> {
+        self.inner.quality
+    }
+
+    /// Write the image to `output` or return it if there is no output
+    async fn write_or_return(self, output: Option<CreateOutput>) -> Result<Option<Self>, Error> {
+        let Some(output) = output else {
+            return Ok(Some(self));
+        };
+
+        util::spawn_blocking(move || {
+            output.write_all(self.data_ref())?;
+            Ok(None)
+        })
+        .await?
+    }
+}
+
+/// Stream that [`Creator::create_to()`] writes to
+struct CreateOutput {
+    stream: GOutputStreamSend,
+    /// Number of bytes written so far
+    written: Arc<AtomicU64>,
+    cancellable: gio::Cancellable,
+    /// Signals that the stream is no longer in use when dropped
+    _released: futures_channel::oneshot::Sender<()>,
+}
+
+impl CreateOutput {
+    fn write_all(&self, data: &[u8]) -> Result<(), glib::Error> {
+        let (written, err) = match self
+            .stream
+            .stream()
+            .write_all(data, Some(&self.cancellable))
+        {
+            Ok((written, err)) => (written, err),
+            Err(err) => (0, Some(err)),
+        };
+
+        self.written.fetch_add(written as u64, Ordering::Relaxed);
+
+        match err {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Write everything from `input_stream` until it is closed
+    fn copy_from(&self, input_stream: &gio::InputStream) -> Result<(), glib::Error> {
+        let mut buf = vec![0; 64 * 1024];
+
+        loop {
+            let n = input_stream.read(&mut buf, Some(&self.cancellable))?;
+            if n == 0 {
+                return Ok(());
+            }
+
+            self.write_all(&buf[..n])?;
+        }
+    }
+}
+
+/// Stream of [`gio::File::replace()`] that discards the written content
+/// unless it is committed
+struct ReplaceStream(Option<GOutputStreamSend>);
+
+impl ReplaceStream {
+    fn stream(&self) -> &GOutputStreamSend {
+        // Only `None` after `commit()` consumed the stream
+        self.0.as_ref().unwrap()
+    }
+
+    /// Close the stream, replacing the file with the written content
+    async fn commit(mut self) -> Result<(), Error> {
+        let stream = self.0.take();
+        util::spawn_blocking(move || {
+            if let Some(stream) = stream {
+                stream.stream().close(gio::Cancellable::NONE)?;
+            }
+            Ok(())
+        })
+        .await?
+    }
+}
+
+impl Drop for ReplaceStream {
+    fn drop(&mut self) {
+        if let Some(stream) = self.0.take() {
+            // Closing with a cancelled cancellable removes the temporary file
+            // and keeps the original file untouched
+            let cancellable = gio::Cancellable::new();
+            cancellable.cancel();
+            let _ = stream.stream().close(Some(&cancellable));
+        }
+    }
 }
@@ -8,9 +8,11 @@ use gio::glib;
 use gio::prelude::{IsA, *};
 #[cfg(feature = "builtin")]
 use glycin_utils::EditorImplementation;
+use glycin_utils::editing::{self, EditingFrame};
 use glycin_utils::safe_math::SafeConversion;
 use glycin_utils::{
-    ByteChanges, ByteData, CompleteEditorOutput, FungibleMemory, Operations, SparseEditorOutput,
+    ByteChanges, ByteData, CompleteEditorOutput, ExtendedMemoryFormat, FungibleMemory,
+    MemoryFormatInfo, Operations, SparseEditorOutput,
 };
 #[cfg(feature = "external")]
 use zbus::zvariant::OwnedObjectPath;
@@ -22,15 +24,19 @@ use crate::error::{ErrorKind, ResultExt};
 use crate::main_context::{MainContextSelector, ProvidesMainContext};
 #[cfg(feature = "external")]
 use crate::pool::PooledProcess;
-use crate::util::{self, CancellableFuture, ShortcutErrorFuture};
-use crate::{Error, MimeType, Pool, config};
+use crate::util::{self, CancellableFuture, ShortcutErrorFuture, TimeoutFuture};
+use crate::{Error, MimeType, Pool, config, journal, save};
 
 /// Builder pattern for editing images
 #[derive(Debug)]
 pub struct Editor {
     source: Source,
+    /// Copy of the source if created from bytes, used for verification
+    source_bytes: Option<glib::Bytes>,
     pool: Arc<Pool>,
     cancellable: gio::Cancellable,
+    limits: Limits,
+    verify_output: bool,
     pub(crate) sandbox_selector: SandboxSelector,
     pub(crate) main_context_selector: MainContextSelector,
 }
@@ -55,7 +61,9 @@ impl Editor {
     /// Create an editor with [`glib::Bytes`] as source
     pub fn new_bytes(bytes: glib::Bytes) -> Self {
         let stream = gio::MemoryInputStream::from_bytes(&bytes);
-        unsafe { Self::new_stream(stream) }
+        let mut editor = unsafe { Self::new_stream(stream) };
+        editor.source_bytes = Some(bytes);
+        editor
     }
 
     /// Create an editor with [`Vec<u8>`] as source
@@ -67,8 +75,11 @@ impl Editor {
     pub(crate) fn new_source(source: Source) -> Self {
         Self {
             source,
+            source_bytes: None,
             pool: Pool::global(),
             cancellable: gio::Cancellable::new(),
+            limits: Limits::default(),
+            verify_output: false,
             sandbox_selector: SandboxSelector::default(),
             main_context_selector: MainContextSelector::Auto,
         }
@@ -79,6 +90,20 @@ impl Editor {
         self
     }
 
+    pub fn pool(&mut self, pool: Arc<Pool>) -> &mut Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Limits for loading and editing the image
+    ///
+    /// The timeout applies to [`edit()`](Self::edit) and to each operation on
+    /// the resulting [`EditableImage`].
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn edit(self) -> Pin<Box<dyn Future<Output = Result<EditableImage, Error>> + Send>> {
         self.edit_with_sync(false)
     }
@@ -93,15 +118,23 @@ impl Editor {
         Box::pin(async move {
             let main_context = self.main_context();
             let cancellable = self.cancellable.clone();
+            let timeout = self.limits.inner.timeout;
 
-            let f =
-                move || async move { self.edit_internal(sync).await }.make_cancellable(cancellable);
+            let f = move || {
+                async move { self.edit_internal(sync).await }
+                    .make_cancellable(cancellable)
+                    .enforce_timeout(timeout)
+            };
 
             main_context.spawn_from_within(f).await?
         })
     }
 
     async fn edit_internal(mut self, sync: bool) -> Result<EditableImage, Error> {
+        if self.verify_output && self.source.file().is_none() && self.source_bytes.is_none() {
+            return Err(ErrorKind::VerificationUnavailable.err());
+        }
+
         let source: Source = self.source.send();
 
         let editor_context =
@@ -119,7 +152,8 @@ impl Editor {
                 let (external_reader, load_image_future) =
                     editor.source_transmission.spawn_external()?;
 
-                let editable_image_future = process.edit(external_reader, &editor.mime_type);
+                let editable_image_future =
+                    process.edit(external_reader, &editor.mime_type, &self.limits.inner);
 
                 let editable_image = editable_image_future
                     .join_abort_on_error(load_image_future)
@@ -151,7 +185,8 @@ impl Editor {
             #[cfg(feature = "builtin")]
             Processor::Builtin(builtin) => {
                 let mime_type = builtin.mime_type.to_string();
-                let details = glycin_utils::InitializationDetails::default();
+                let mut details = glycin_utils::InitializationDetails::default();
+                details.limits = self.limits.inner.clone();
                 let edit_function: Box<dyn FnOnce() -> _ + Send>;
 
                 let (reader, read_data_future) = builtin.source_transmission.spawn_builtin();
@@ -191,6 +226,21 @@ impl Editor {
         }
     }
 
+    /// Verify the output of edits before returning it
+    ///
+    /// The edited image is decoded again in a sandboxed loader. Its dimensions
+    /// have to match the original image with the operations applied. For
+    /// lossless edits, the pixels of the first frame also have to be identical.
+    /// If the verification fails, an error is returned for which
+    /// [`Error::is_verification_failed()`] is `true`.
+    ///
+    /// Verification is only possible if the editor was created from a file or
+    /// from bytes. The default is `false`.
+    pub fn verify_output(&mut self, verify_output: bool) -> &mut Self {
+        self.verify_output = verify_output;
+        self
+    }
+
     /// Sets the method by which the sandbox mechanism is selected.
     ///
     /// The default without calling this function is [`SandboxSelector::Auto`].
@@ -240,23 +290,55 @@ impl EditableImage {
         operations: &Operations,
     ) -> Pin<Box<dyn Future<Output = Result<SparseEdit, Error>> + Send>> {
         let operations = operations.to_owned();
-        Box::pin(self.apply_sparse_internal(operations))
+        let timeout = self.editor.limits.inner.timeout;
+
+        Box::pin(async move {
+            self.apply_sparse_internal(operations)
+                .enforce_timeout(timeout)
+                .await
+        })
     }
 
     async fn apply_sparse_internal(self, operations: Operations) -> Result<SparseEdit, Error> {
+        let editor_output = self.apply_sparse_output(&operations).await?;
+
+        if self.editor.verify_output {
+            let data = if let Some(byte_changes) = &editor_output.byte_changes {
+                let mut data = self.original_data().await?;
+                byte_changes.apply(&mut data)?;
+                data
+            } else if let Some(data) = &editor_output.data {
+                data.to_vec()
+            } else {
+                Vec::new()
+            };
+
+            self.verify(&operations, data, editor_output.info.lossless)
+                .await?;
+        }
+
+        SparseEdit::try_from(editor_output)
+    }
+
+    async fn apply_sparse_output(
+        &self,
+        operations: &Operations,
+    ) -> Result<SparseEditorOutput<FungibleMemory>, Error> {
+        let operations = operations.clone();
+
         match &self.image_editor {
             #[cfg(feature = "external")]
             ImageEditor::External(editor) => {
                 let process = editor.process.use_();
 
                 let mut editor_output = process
-                    .editor_apply_sparse(&operations, &self)
+                    .editor_apply_sparse(&operations, self)
                     .await
                     .err_context(&process)?;
 
                 editor_output.final_seal().await?;
 
-                SparseEdit::try_from(editor_output.into_fungible())
+                Ok(editor_output.into_fungible())
             }
             #[cfg(feature = "builtin")]
             ImageEditor::Builtin(editor) => {
@@ -275,13 +357,11 @@ impl EditableImage {
                     }
                 }
 
-                let editor_output = gio::spawn_blocking(|| {
+                gio::spawn_blocking(|| {
                     editor_function().map_err(|e| Error::from(e.into_editor_error()))
                 })
                 .await
-                .map_err(|e| ErrorKind::panic(e))??;
-
-                SparseEdit::try_from(editor_output)
+                .map_err(|e| ErrorKind::panic(e))?
             }
         }
     }
@@ -293,10 +373,36 @@ impl EditableImage {
     ) -> Pin<Box<dyn Future<Output = Result<Edit, Error>> + Send + '_>> {
         let operations = operations.to_owned();
 
-        Box::pin(self.apply_complete_internal(operations))
+        Box::pin(async move {
+            self.apply_complete_internal(operations)
+                .enforce_timeout(self.editor.limits.inner.timeout)
+                .await
+        })
     }
 
     async fn apply_complete_internal(&self, operations: Operations) -> Result<Edit, Error> {
+        let editor_output = self.apply_complete_output(&operations).await?;
+
+        if self.editor.verify_output {
+            self.verify(
+                &operations,
+                editor_output.data.to_vec(),
+                editor_output.info.lossless,
+            )
+            .await?;
+        }
+
+        Ok(Edit {
+            inner: editor_output,
+        })
+    }
+
+    async fn apply_complete_output(
+        &self,
+        operations: &Operations,
+    ) -> Result<CompleteEditorOutput<FungibleMemory>, Error> {
+        let operations = operations.clone();
+
         match &self.image_editor {
             #[cfg(feature = "external")]
             ImageEditor::External(editor) => {
@@ -310,9 +416,7 @@ impl EditableImage {
 
                 editor_output.final_seal().await?;
 
-                Ok(Edit {
-                    inner: editor_output,
-                })
+                Ok(editor_output)
             }
             #[cfg(feature = "builtin")]
             ImageEditor::Builtin(editor) => {
@@ -331,19 +435,265 @@ impl EditableImage {
                     }
                 }
 
-                let editor_output = gio::spawn_blocking(|| {
+                gio::spawn_blocking(|| {
                     apply_function().map_err(|e| Error::from(e.into_editor_error()))
                 })
                 .await
-                .map_err(|e| ErrorKind::panic(e))??;
+                .map_err(|e| ErrorKind::panic(e))?
+            }
+        }
+    }
+
+    /// Apply operations and save the result to `file`
+    ///
+    /// If `file` is the file the image was loaded from and the operations can
+    /// be applied via sparse changes, only those bytes are changed in the
+    /// file. Otherwise, the file is atomically replaced with the edited image
+    /// as described in [`Edit::save_to()`].
+    pub fn apply_and_save(
+        self,
+        operations: &Operations,
+        file: gio::File,
+        options: SaveOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        let operations = operations.to_owned();
+        Box::pin(self.apply_and_save_internal(operations, file, options))
+    }
+
+    async fn apply_and_save_internal(
+        self,
+        operations: Operations,
+        file: gio::File,
+        options: SaveOptions,
+    ) -> Result<(), Error> {
+        let is_source = self
+            .editor
+            .source
+            .file()
+            .is_some_and(|source| source.equal(&file));
+
+        if !is_source {
+            return self
+                .apply_complete(&operations)
+                .await?
+                .save_to(file, options)
+                .await;
+        }
 
-                Ok(Edit {
-                    inner: editor_output,
+        match self.apply_sparse(&operations).await? {
+            SparseEdit::Sparse(byte_changes) => {
+                let file_ = file.clone();
+                let modification_time = util::spawn_blocking(move || {
+                    if options.backup {
+                        save::backup(&file_)?;
+                    }
+
+                    if options.preserve_modification_time {
+                        save::modification_time(&file_).map(Some)
+                    } else {
+                        Ok(None)
+                    }
                 })
+                .await??;
+
+                let _ = SparseEdit::Sparse(byte_changes)
+                    .apply_to(file.clone())
+                    .await?;
+
+                if let Some(modification_time) = modification_time {
+                    util::spawn_blocking(move || {
+                        save::set_modification_time(&file, modification_time)
+                    })
+                    .await??;
+                }
+
+                Ok(())
+            }
+            SparseEdit::Complete(data) => {
+                util::spawn_blocking(move || save::replace(&file, &data, &options)).await?
             }
         }
     }
 
+    /// Decoded frame with the operations applied
+    ///
+    /// The operations are applied by the editor without encoding the image.
+    /// This allows to show the result of operations before saving them. The
+    /// `clip` and `scale` of the frame request are applied after the
+    /// operations. The frame is only ever scaled down, keeping the aspect
+    /// ratio.
+    pub fn preview(
+        &self,
+        operations: &Operations,
+        frame_request: FrameRequest,
+    ) -> Pin<Box<dyn Future<Output = Result<Frame, Error>> + Send + '_>> {
+        let operations = operations.to_owned();
+
+        Box::pin(async move {
+            self.preview_internal(operations, frame_request.request)
+                .enforce_timeout(self.editor.limits.inner.timeout)
+                .await
+        })
+    }
+
+    async fn preview_internal(
+        &self,
+        operations: Operations,
+        frame_request: glycin_utils::FrameRequest,
+    ) -> Result<Frame, Error> {
+        match &self.image_editor {
+            #[cfg(feature = "external")]
+            ImageEditor::External(editor) => {
+                let process = editor.process.use_();
+
+                let frame = process
+                    .editor_preview(&operations, frame_request, self)
+                    .await
+                    .err_context(&process)?;
+
+                Frame::from_editor(frame, &self.editor.limits).await
+            }
+            #[cfg(feature = "builtin")]
+            ImageEditor::Builtin(editor) => {
+                let preview_function: Box<dyn FnOnce() -> _ + Send + 'static>;
+
+                match editor {
+                    #[cfg(feature = "builtin-image-rs")]
+                    ImageEditorBuiltin::ImageRs(editor) => {
+                        let editor = editor.clone();
+                        preview_function =
+                            Box::new(move || editor.preview(operations, frame_request));
+                    }
+                    #[cfg(feature = "builtin-test")]
+                    ImageEditorBuiltin::Test(editor) => {
+                        let editor = editor.clone();
+                        preview_function =
+                            Box::new(move || editor.preview(operations, frame_request));
+                    }
+                }
+
+                let frame: glycin_utils::Frame<FungibleMemory> = gio::spawn_blocking(|| {
+                    preview_function().map_err(|e| Error::from(e.into_editor_error()))
+                })
+                .await
+                .map_err(|e| ErrorKind::panic(e))??;
+
+                Frame::from_editor(frame, &self.editor.limits).await
+            }
+        }
+    }
+
+    /// Content of the image the editor was created for
+    async fn original_data(&self) -> Result<Vec<u8>, Error> {
+        if let Some(bytes) = &self.editor.source_bytes {
+            Ok(bytes.to_vec())
+        } else if let Some(file) = self.editor.source.file() {
+            util::spawn_blocking(move || {
+                let (data, _) = file.load_contents(gio::Cancellable::NONE)?;
+                Ok::<_, Error>(data.to_vec())
+            })
+            .await?
+        } else {
+            Err(ErrorKind::VerificationUnavailable.err())
+        }
+    }
+
+    /// Loader for verification with the same settings as the editor
+    fn verification_loader(&self, mut loader: Loader) -> Loader {
+        loader
+            .sandbox_selector(self.editor.sandbox_selector)
+            .pool(self.editor.pool.clone())
+            .cancellable(self.editor.cancellable.clone())
+            .limits(self.editor.limits.clone())
+            .color_convert_icc_srgb(false);
+
+        loader
+    }
+
+    /// Check that `data` decodes to the original image with the operations
+    /// applied
+    async fn verify(
+        &self,
+        operations: &Operations,
+        data: Vec<u8>,
+        lossless: bool,
+    ) -> Result<(), Error> {
+        let original = if let Some(bytes) = &self.editor.source_bytes {
+            Loader::new_bytes(bytes.clone())
+        } else if let Some(file) = self.editor.source.file() {
+            Loader::new(file)
+        } else {
+            return Err(ErrorKind::VerificationUnavailable.err());
+        };
+
+        let mut original = self.verification_loader(original).load().await?;
+        let original_frame = original.next_frame().await?;
+
+        let mut output = self
+            .verification_loader(Loader::new_vec(data))
+            .load()
+            .await?;
+        let output_frame = output.next_frame().await?;
+
+        let memory_format = original_frame.memory_format();
+        let editing_frame = EditingFrame {
+            width: original_frame.width(),
+            height: original_frame.height(),
+            stride: original_frame.stride(),
+            memory_format: ExtendedMemoryFormat::Basic(memory_format),
+            texture: FungibleMemory::from_vec(original_frame.buf_slice().to_vec()),
+        };
+
+        let operations = operations.clone();
+        let expected = util::spawn_blocking(move || {
+            editing::apply_operations(editing_frame, &operations)
+                .map_err(|err| ErrorKind::VerificationFailed(err.to_string()).err())
+        })
+        .await??;
+
+        let expected_dimensions = (expected.width, expected.height);
+        let output_dimensions = (output_frame.width(), output_frame.height());
+
+        if expected_dimensions != output_dimensions {
+            return Err(ErrorKind::VerificationFailed(format!(
+                "Expected dimensions {expected_dimensions:?} but output has {output_dimensions:?}"
+            ))
+            .err());
+        }
+
+        if lossless {
+            if output_frame.memory_format() != memory_format {
+                return Err(ErrorKind::VerificationFailed(format!(
+                    "Lossless edit changed memory format from {memory_format:?} to {:?}",
+                    output_frame.memory_format()
+                ))
+                .err());
+            }
+
+            let row_len = (expected.width.usize() * memory_format.n_bytes().usize())
+                .min(expected.stride.usize());
+            let expected_rows = expected.texture.chunks(expected.stride.usize().max(1));
+            let output_rows = output_frame
+                .buf_slice()
+                .chunks(output_frame.stride().usize().max(1));
+
+            for (y, (expected_row, output_row)) in expected_rows
+                .zip(output_rows)
+                .take(expected.height.usize())
+                .enumerate()
+            {
+                if expected_row.get(..row_len) != output_row.get(..row_len) {
+                    return Err(ErrorKind::VerificationFailed(format!(
+                        "Lossless edit changed pixels in row {y}"
+                    ))
+                    .err());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// List all configured image editors
     pub async fn supported_formats() -> BTreeMap<MimeType, config::EditorConfig> {
         let config = config::Config::cached().await;
@@ -421,6 +771,66 @@ impl Edit {
     pub fn is_lossless(&self) -> bool {
         self.inner.info.lossless
     }
+
+    /// Save the edited image to `file`
+    ///
+    /// The image is written to a temporary file in the same directory, synced
+    /// to disk, and then atomically renamed to `file`. If writing fails at any
+    /// point, an existing `file` stays untouched.
+    pub async fn save_to(self, file: gio::File, options: SaveOptions) -> Result<(), Error> {
+        util::spawn_blocking(move || save::replace(&file, &self.inner.data, &options)).await?
+    }
+}
+
+/// Options for [`Edit::save_to()`] and [`EditableImage::apply_and_save()`]
+#[derive(Debug, Clone)]
+pub struct SaveOptions {
+    pub(crate) backup: bool,
+    pub(crate) preserve_attributes: bool,
+    pub(crate) preserve_modification_time: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            backup: false,
+            preserve_attributes: true,
+            preserve_modification_time: false,
+        }
+    }
+}
+
+impl SaveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep a copy of the replaced file with a `~` appended to the name
+    ///
+    /// Defaults to `false`.
+    pub fn backup(&mut self, backup: bool) -> &mut Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Copy permissions, ownership, and extended attributes of the replaced
+    /// file
+    ///
+    /// Saving fails if permissions or ownership can't be kept. Extended
+    /// attributes and the SELinux context are only kept where supported.
+    /// Defaults to `true`.
+    pub fn preserve_attributes(&mut self, preserve_attributes: bool) -> &mut Self {
+        self.preserve_attributes = preserve_attributes;
+        self
+    }
+
+    /// Keep the modification time of the replaced file
+    ///
+    /// Defaults to `false`.
+    pub fn preserve_modification_time(&mut self, preserve_modification_time: bool) -> &mut Self {
+        self.preserve_modification_time = preserve_modification_time;
+        self
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -436,13 +846,58 @@ impl SparseEdit {
     ///
     /// If the type does not carry sparse changes, the function will return an
     /// [`EditOutcome::Unchanged`] and the complete image needs to be rewritten.
+    ///
+    /// Before changing the file, the current values are compared to the values
+    /// the editor has seen. If they differ, the file is not changed and an
+    /// error for which [`Error::is_file_changed()`] is true is returned.
+    ///
+    /// The original values are written to a journal next to the file before
+    /// changing it. If applying the changes is interrupted, the original file
+    /// can be restored via [`SparseEdit::recover()`]. If the directory does not
+    /// allow creating the journal, for example since it is read-only, the
+    /// changes are written without a journal and can't be recovered. If the
+    /// journal of an interrupted edit still exists, the file is not changed and
+    /// an error for which [`Error::is_journal_pending()`] is true is returned.
     pub async fn apply_to(&self, file: gio::File) -> Result<EditOutcome, Error> {
         match self {
             Self::Sparse(bit_changes) => {
                 let bit_changes = bit_changes.clone();
                 util::spawn_blocking(move || {
                     let stream = file.open_readwrite(gio::Cancellable::NONE)?;
+                    let input_stream = stream.input_stream();
                     let output_stream = stream.output_stream();
+
+                    let mut original_values = Vec::with_capacity(bit_changes.changes.len());
+                    for (i, change) in bit_changes.changes.iter().enumerate() {
+                        stream.seek(
+                            change.offset.try_i64()?,
+                            glib::SeekType::Set,
+                            gio::Cancellable::NONE,
+                        )?;
+                        let mut value = [0];
+                        let (n_read, err) =
+                            input_stream.read_all(&mut value, gio::Cancellable::NONE)?;
+
+                        if let Some(err) = err {
+                            return Err(err.into());
+                        }
+
+                        let expected_value = bit_changes
+                            .original_values
+                            .as_ref()
+                            .map(|original_values| original_values.get(i));
+
+                        if n_read != 1
+                            || expected_value.is_some_and(|expected| expected != Some(&value[0]))
+                        {
+                            return Err(ErrorKind::SparseEditMismatch(change.offset).err());
+                        }
+
+                        original_values.push((change.offset, value[0]));
+                    }
+
+                    let has_journal = journal::write(&file, &original_values)?;
+
                     for change in bit_changes.changes {
                         stream.seek(
                             change.offset.try_i64()?,
@@ -456,6 +911,14 @@ impl SparseEdit {
                             return Err(err.into());
                         }
                     }
+
+                    stream.close(gio::Cancellable::NONE)?;
+                    util::sync(&file)?;
+
+                    if has_journal {
+                        journal::remove(&file)?;
+                    }
+
                     Ok(EditOutcome::Changed)
                 })
                 .await?
@@ -463,6 +926,23 @@ impl SparseEdit {
             Self::Complete(_) => Ok(EditOutcome::Unchanged),
         }
     }
+
+    /// Restore the original file after an interrupted
+    /// [`apply_to()`](Self::apply_to())
+    ///
+    /// Returns [`EditOutcome::Changed`] if a journal of an interrupted edit
+    /// existed and the original values were restored. Returns
+    /// [`EditOutcome::Unchanged`] if there was nothing to recover.
+    pub async fn recover(file: gio::File) -> Result<EditOutcome, Error> {
+        util::spawn_blocking(move || {
+            if journal::recover(&file)? {
+                Ok(EditOutcome::Changed)
+            } else {
+                Ok(EditOutcome::Unchanged)
+            }
+        })
+        .await?
+    }
 }
 
 impl TryFrom<SparseEditorOutput<FungibleMemory>> for SparseEdit {
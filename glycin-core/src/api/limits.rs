@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Limits {
     pub(crate) inner: glycin_utils::Limits,
 }
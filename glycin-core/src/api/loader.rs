@@ -363,6 +363,7 @@ impl Loader {
         "image/tiff",
         "image/x-tga",
         "image/x-dds",
+        "image/vnd.ms-dds",
         "image/bmp",
         "image/x-win-bitmap",
         "image/vnd.microsoft.icon",
@@ -500,6 +501,66 @@ impl Image {
         }
     }
 
+    /// Reconstruct the original JPEG
+    ///
+    /// This is only possible for images that were losslessly recompressed
+    /// from a JPEG, for example via [`Creator::set_source_jpeg()`]. The
+    /// returned data are bit-identical to the original JPEG file.
+    pub fn reconstruct_jpeg<'a>(
+        &'a self,
+    ) -> Pin<Box<dyn Future<Output = Result<EncodedImage, Error>> + 'a + Send>> {
+        Box::pin(async move {
+            let cancellable = self.loader.cancellable.clone();
+
+            self.reconstruct_jpeg_internal()
+                .make_cancellable(cancellable)
+                .enforce_timeout(self.loader.limits.inner.timeout)
+                .await
+        })
+    }
+
+    async fn reconstruct_jpeg_internal(&self) -> Result<EncodedImage, Error> {
+        match &self.image_loader {
+            #[cfg(feature = "external")]
+            ImageLoader::Binary(image_loader) => {
+                let process = image_loader.process.use_();
+
+                let encoded_image = process.reconstruct_jpeg(self).await.err_context(&process)?;
+
+                EncodedImage::new(encoded_image.into_fungible()).await
+            }
+            #[cfg(feature = "builtin")]
+            ImageLoader::Builtin(builtin) => {
+                let loader_function: Box<dyn FnOnce() -> _ + Send>;
+
+                match builtin {
+                    #[cfg(feature = "builtin-image-rs")]
+                    ImageBuiltinLoader::ImageRs(loader) => {
+                        let loader = loader.to_owned();
+                        loader_function = Box::new(move || {
+                            loader.lock().unwrap().reconstruct_jpeg::<FungibleMemory>()
+                        });
+                    }
+                    #[cfg(feature = "builtin-test")]
+                    ImageBuiltinLoader::Test(loader) => {
+                        let loader = loader.to_owned();
+                        loader_function = Box::new(move || {
+                            loader.lock().unwrap().reconstruct_jpeg::<FungibleMemory>()
+                        });
+                    }
+                }
+
+                let data = gio::spawn_blocking(|| {
+                    loader_function().map_err(|e| Error::from(e.into_loader_error()))
+                })
+                .await
+                .map_err(|e| ErrorKind::panic(e))??;
+
+                EncodedImage::new(glycin_utils::EncodedImage::new(data)).await
+            }
+        }
+    }
+
     /// Returns already obtained info
     pub fn details(&self) -> ImageDetails {
         ImageDetails::new(self.details.clone())
@@ -782,6 +843,47 @@ impl Frame {
             frame.into_fungible()
         };
 
+        Self::from_validated(
+            frame,
+            image.loader.color_convert_icc_srgb,
+            image.loader.memory_format_selection,
+            image.details(),
+        )
+        .await
+    }
+
+    /// Frame from an editor preview
+    ///
+    /// Since the operations are already applied by the editor, no orientation
+    /// is applied. ICC profiles are always converted to sRGB.
+    pub(crate) async fn from_editor<B: ByteData>(
+        mut frame: glycin_utils::Frame<B>,
+        limits: &Limits,
+    ) -> Result<Self, Error> {
+        frame.initial_seal().await?;
+
+        validate_frame(&frame, limits)?;
+
+        let image_details = ImageDetails::new(Arc::new(glycin_utils::ImageDetails::new(
+            frame.width,
+            frame.height,
+        )));
+
+        Self::from_validated(
+            frame.into_fungible(),
+            true,
+            MemoryFormatSelection::all(),
+            image_details,
+        )
+        .await
+    }
+
+    async fn from_validated(
+        frame: glycin_utils::Frame<FungibleMemory>,
+        color_convert_icc_srgb: bool,
+        memory_format_selection: MemoryFormatSelection,
+        image_details: ImageDetails,
+    ) -> Result<Self, Error> {
         let mut color_state = ColorState::Srgb;
 
         let cicp = frame
@@ -801,7 +903,7 @@ impl Frame {
             color_state = ColorState::Cicp(cicp);
             frame
         } else if let Some(icc_profile) = icc_profile {
-            if image.loader.color_convert_icc_srgb {
+            if color_convert_icc_srgb {
                 let (frame, icc_result) =
                     spawn_blocking(move || icc::apply_transformation(&icc_profile, frame)).await?;
 
@@ -825,10 +927,7 @@ impl Frame {
 
         let mut frame = frame.into_fungible();
 
-        if let Some(target_format) = image
-            .loader
-            .memory_format_selection
-            .best_format_for(frame.memory_format)
+        if let Some(target_format) = memory_format_selection.best_format_for(frame.memory_format)
             && frame.memory_format != target_format
         {
             frame = util::spawn_blocking(move || {
@@ -848,7 +947,7 @@ impl Frame {
             memory_format: frame.memory_format,
             delay: frame.delay.into(),
             details: Arc::new(frame.details.into_other()?),
-            image_details: image.details(),
+            image_details,
             color_state,
         })
     }
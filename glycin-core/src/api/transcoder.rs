@@ -0,0 +1,358 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use gio::glib;
+use gio::prelude::*;
+use glycin_common::MemoryFormatSelection;
+use gufo_common::field;
+use gufo_common::orientation::{Orientation, Rotation};
+use gufo_common::physical_dimension::PixelDensity;
+
+use crate::error::ErrorKind;
+use crate::util::{self, CancellableFuture, TimeoutFuture};
+use crate::{
+    Creator, CreatorCapabilities, EncodedImage, Error, Limits, Loader, MimeType, Pool,
+    SandboxSelector,
+};
+
+/// Start of every JPEG file
+const JPEG_MAGIC: &[u8] = &[0xFF, 0xD8, 0xFF];
+
+/// Builder pattern for converting images to another format
+///
+/// The image is loaded and encoded again in the respective sandboxes. The
+/// orientation is applied to the texture. Metadata and color information
+/// are carried over as far as the target format supports them.
+///
+/// JPEGs are losslessly recompressed instead if the target format supports
+/// it, see [`Transcoder::jpeg_recompression()`].
+#[derive(Debug)]
+pub struct Transcoder {
+    loader: Loader,
+    /// Source data if the source isn't a file or stream
+    source_bytes: Option<glib::Bytes>,
+    jpeg_recompression: bool,
+    mime_type: MimeType,
+    pool: Arc<Pool>,
+    cancellable: gio::Cancellable,
+    sandbox_selector: SandboxSelector,
+    limits: Limits,
+}
+
+static_assertions::assert_impl_all!(Transcoder: Send, Sync);
+
+impl Transcoder {
+    /// Create a transcoder with a [`gio::File`] as source
+    pub fn new(file: gio::File, mime_type: MimeType) -> Self {
+        Self::new_loader(Loader::new(file), None, mime_type)
+    }
+
+    /// Create a transcoder with a [`gio::InputStream`] as source
+    ///
+    /// # Safety
+    ///
+    /// The provided stream must no longer be used after being passed to glycin.
+    pub unsafe fn new_stream(stream: impl IsA<gio::InputStream>, mime_type: MimeType) -> Self {
+        unsafe { Self::new_loader(Loader::new_stream(stream), None, mime_type) }
+    }
+
+    /// Create a transcoder with [`glib::Bytes`] as source
+    pub fn new_bytes(bytes: glib::Bytes, mime_type: MimeType) -> Self {
+        Self::new_loader(Loader::new_bytes(bytes.clone()), Some(bytes), mime_type)
+    }
+
+    /// Create a transcoder with [`Vec<u8>`] as source
+    pub fn new_vec(buf: Vec<u8>, mime_type: MimeType) -> Self {
+        Self::new_bytes(glib::Bytes::from_owned(buf), mime_type)
+    }
+
+    fn new_loader(loader: Loader, source_bytes: Option<glib::Bytes>, mime_type: MimeType) -> Self {
+        Self {
+            loader,
+            source_bytes,
+            jpeg_recompression: true,
+            mime_type,
+            pool: Pool::global(),
+            cancellable: gio::Cancellable::new(),
+            sandbox_selector: SandboxSelector::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Sets the method by which the sandbox mechanism is selected.
+    ///
+    /// The default without calling this function is [`SandboxSelector::Auto`].
+    pub fn sandbox_selector(&mut self, sandbox_selector: SandboxSelector) -> &mut Self {
+        self.sandbox_selector = sandbox_selector;
+        self
+    }
+
+    /// Set [`Cancellable`](gio::Cancellable) to cancel loading and encoding
+    pub fn cancellable(&mut self, cancellable: impl IsA<gio::Cancellable>) -> &mut Self {
+        self.cancellable = cancellable.upcast();
+        self
+    }
+
+    pub fn pool(&mut self, pool: Arc<Pool>) -> &mut Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Limits for loading and creating the image
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Losslessly recompress JPEGs if the target format supports it
+    ///
+    /// The JPEG is stored without decoding it, such that the original file
+    /// can be reconstructed via [`Image::reconstruct_jpeg()`]. The image is
+    /// encoded from its pixels for sources that are streams.
+    ///
+    /// The default is `true`. See
+    /// [`CreatorCapabilities::supports_jpeg_recompression()`].
+    ///
+    /// [`Image::reconstruct_jpeg()`]: crate::Image::reconstruct_jpeg
+    pub fn jpeg_recompression(&mut self, jpeg_recompression: bool) -> &mut Self {
+        self.jpeg_recompression = jpeg_recompression;
+        self
+    }
+
+    /// Load the source into a [`Creator`] for the target format
+    ///
+    /// This allows to set encoding options like the quality before encoding
+    /// the image via [`Creator::create()`] or [`Creator::create_to_file()`].
+    pub fn creator(self) -> Pin<Box<dyn Future<Output = Result<Creator, Error>> + Send>> {
+        Box::pin(self.creator_internal())
+    }
+
+    /// Load the source and encode it in the target format
+    pub fn transcode(self) -> Pin<Box<dyn Future<Output = Result<EncodedImage, Error>> + Send>> {
+        Box::pin(async move { self.creator_internal().await?.create().await })
+    }
+
+    async fn creator_internal(self) -> Result<Creator, Error> {
+        let capabilities = Creator::capabilities(self.mime_type.clone()).await?;
+
+        let mut creator = Creator::new(self.mime_type.clone()).await?;
+        creator
+            .sandbox_selector(self.sandbox_selector)
+            .cancellable(self.cancellable.clone())
+            .pool(self.pool.clone())
+            .limits(self.limits.clone());
+
+        if self.jpeg_recompression
+            && capabilities.supports_jpeg_recompression()
+            && let Some(source_jpeg) = self.source_jpeg().await?
+        {
+            creator.set_source_jpeg(source_jpeg)?;
+            return Ok(creator);
+        }
+
+        let mut loader = self.loader;
+        loader
+            .sandbox_selector(self.sandbox_selector)
+            .cancellable(self.cancellable)
+            .pool(self.pool)
+            .limits(self.limits)
+            .apply_transformations(true)
+            // Only convert to sRGB if the profile can't be stored
+            .color_convert_icc_srgb(!capabilities.supports_color_icc_profile());
+
+        if !capabilities.memory_formats().is_empty() {
+            loader.accepted_memory_formats(MemoryFormatSelection::from_memory_formats(
+                capabilities.memory_formats(),
+            ));
+        }
+
+        let mut image = loader.load().await?;
+
+        let details = image.details();
+        let orientation = image.transformation_orientation();
+
+        if capabilities.supports_metadata_exif() {
+            let exif = details.metadata_exif().map(reset_exif_orientation);
+            creator.set_metadata_exif(exif)?;
+        }
+
+        if capabilities.supports_metadata_xmp() {
+            creator.set_metadata_xmp(details.metadata_xmp().map(|x| x.to_vec()))?;
+        }
+
+        if capabilities.supports_metadata_key_value()
+            && let Some(key_value) = details.metadata_key_value()
+        {
+            creator.set_metadata_key_value(key_value.clone())?;
+        }
+
+        let mut first_n_frame = None;
+        let mut has_frames = false;
+
+        loop {
+            let frame = match image.next_frame().await {
+                Ok(frame) => frame,
+                Err(err) if err.has_no_more_frames() && has_frames => break,
+                Err(err) => return Err(err),
+            };
+
+            let n_frame = frame.details().n_frame();
+
+            // Animations start over after the last frame
+            if has_frames && n_frame == first_n_frame {
+                break;
+            } else if !has_frames {
+                first_n_frame = n_frame;
+                has_frames = true;
+            }
+
+            add_frame(&mut creator, &capabilities, &frame, orientation)?;
+
+            let next_frame = match (frame.delay(), n_frame) {
+                // Without frame numbers the end of an animation can't be detected
+                (Some(_), None) => false,
+                (Some(_), Some(_)) => capabilities.supports_animation(),
+                // Pages are read until there are no more frames
+                (None, _) => capabilities.supports_multi_page(),
+            };
+
+            if !next_frame {
+                break;
+            }
+        }
+
+        Ok(creator)
+    }
+
+    /// Source data if the source is a JPEG that can be read without consuming
+    /// the source
+    ///
+    /// Files are only read completely if they start like a JPEG. Reading them
+    /// is subject to the cancellable and the timeout of the limits.
+    async fn source_jpeg(&self) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(bytes) = &self.source_bytes {
+            return Ok(bytes.starts_with(JPEG_MAGIC).then(|| bytes.to_vec()));
+        }
+
+        let Some(file) = self.loader.source.file() else {
+            return Ok(None);
+        };
+
+        let read_cancellable = gio::Cancellable::new();
+        let result = {
+            let cancellable = read_cancellable.clone();
+            async move {
+                util::spawn_blocking(move || read_jpeg(&file, &cancellable))
+                    .await?
+                    .map_err(|err| ErrorKind::ImageSource(err).err())
+            }
+        }
+        .make_cancellable(self.cancellable.clone())
+        .enforce_timeout(self.limits.inner.timeout)
+        .await;
+
+        if result.is_err() {
+            // Stop reading if canceled or timed out
+            read_cancellable.cancel();
+        }
+
+        result
+    }
+}
+
+/// Content of `file` if it starts like a JPEG
+fn read_jpeg(
+    file: &gio::File,
+    cancellable: &gio::Cancellable,
+) -> Result<Option<Vec<u8>>, glib::Error> {
+    let stream = file.read(Some(cancellable))?;
+
+    let mut header = [0; JPEG_MAGIC.len()];
+    let (n, err) = stream.read_all(&mut header, Some(cancellable))?;
+    if let Some(err) = err {
+        return Err(err);
+    }
+
+    if header[..n] != *JPEG_MAGIC {
+        return Ok(None);
+    }
+
+    let output = gio::MemoryOutputStream::new_resizable();
+    output.write_all(&header, Some(cancellable))?;
+    output.splice(
+        &stream,
+        gio::OutputStreamSpliceFlags::CLOSE_SOURCE | gio::OutputStreamSpliceFlags::CLOSE_TARGET,
+        Some(cancellable),
+    )?;
+
+    Ok(Some(output.steal_as_bytes().to_vec()))
+}
+
+fn add_frame(
+    creator: &mut Creator,
+    capabilities: &CreatorCapabilities,
+    frame: &crate::Frame,
+    orientation: Orientation,
+) -> Result<(), Error> {
+    let frame_details = frame.details();
+
+    let new_frame = creator.add_frame_with_stride(
+        frame.width(),
+        frame.height(),
+        frame.stride(),
+        frame.memory_format(),
+        frame.buf_slice().to_vec(),
+    )?;
+
+    if capabilities.supports_color_icc_profile() {
+        new_frame.set_color_icc_profile(frame_details.color_icc_profile().map(|x| x.to_vec()))?;
+    }
+
+    if capabilities.supports_color_cicp() {
+        new_frame.set_color_cicp(frame_details.color_cicp())?;
+    } else if frame_details.color_cicp().is_some() {
+        tracing::warn!("Target format doesn't support CICP. Color information will be lost.");
+    }
+
+    if capabilities.supports_pixel_density() {
+        // The texture has been rotated by the loader
+        let pixel_density =
+            frame_details
+                .pixel_density()
+                .map(|density| match orientation.rotate() {
+                    Rotation::_90 | Rotation::_270 => PixelDensity::new(density.y(), density.x()),
+                    Rotation::_0 | Rotation::_180 => density,
+                });
+        new_frame.set_pixel_density(pixel_density)?;
+    }
+
+    if capabilities.supports_animation() {
+        new_frame.set_delay(frame.delay())?;
+    }
+
+    Ok(())
+}
+
+/// Since the orientation is applied to the texture, the Exif orientation has
+/// to be reset
+fn reset_exif_orientation(exif_data: &[u8]) -> Vec<u8> {
+    let mut exif_data = exif_data.to_vec();
+
+    let diff = gufo_exif::Exif::for_vec(exif_data.clone())
+        .ok()
+        .and_then(|mut exif| {
+            exif.update_entry_diff(
+                field::Orientation.into(),
+                gufo_exif::Typed::Short(vec![Orientation::Id as u16]),
+            )
+            .ok()
+        });
+
+    for (pos, value) in diff.unwrap_or_default() {
+        if let Some(byte) = exif_data.get_mut(pos) {
+            *byte = value;
+        }
+    }
+
+    exif_data
+}
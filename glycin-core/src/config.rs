@@ -57,14 +57,16 @@ impl Ord for MimeType {
 
 impl MimeType {
     pub const BMP: Self = Self::new_static("image/bmp");
-    /// No encoding
-    pub const DDS: Self = Self::new_static("image/x-dds");
+    pub const CUR: Self = Self::new_static("image/x-win-bitmap");
+    /// Formerly `image/x-dds`, which is still accepted
+    pub const DDS: Self = Self::new_static("image/vnd.ms-dds");
     pub const GIF: Self = Self::new_static("image/gif");
     pub const ICO: Self = Self::new_static("image/vnd.microsoft.icon");
     pub const JPEG: Self = Self::new_static("image/jpeg");
     pub const OPEN_EXR: Self = Self::new_static("image/x-exr");
     pub const PNG: Self = Self::new_static("image/png");
     pub const QOI: Self = Self::new_static("image/qoi");
+    pub const RADIANCE_HDR: Self = Self::new_static("image/vnd.radiance");
     pub const TGA: Self = Self::new_static("image/x-tga");
     pub const TIFF: Self = Self::new_static("image/tiff");
     pub const WEBP: Self = Self::new_static("image/webp");
@@ -77,6 +79,7 @@ impl MimeType {
     const EXTENSIONS: &[(Self, &'static str)] = &[
         (Self::AVIF, "avif"),
         (Self::BMP, "bmp"),
+        (Self::CUR, "cur"),
         (Self::DDS, "dds"),
         (Self::GIF, "gif"),
         (Self::HEIC, "heic"),
@@ -86,6 +89,7 @@ impl MimeType {
         (Self::OPEN_EXR, "exr"),
         (Self::PNG, "png"),
         (Self::QOI, "qoi"),
+        (Self::RADIANCE_HDR, "hdr"),
         (Self::TGA, "tga"),
         (Self::TIFF, "tiff"),
         (Self::WEBP, "webp"),
@@ -223,10 +227,30 @@ pub struct EditorConfig {
     pub(crate) fontconfig: bool,
     pub(crate) operations: BTreeSet<OperationId>,
     pub(crate) creator: bool,
+    pub(crate) creator_animation: bool,
     pub(crate) creator_color_icc_profile: bool,
+    pub(crate) creator_color_cicp: bool,
     pub(crate) creator_encoding_quality: bool,
+    pub(crate) creator_encoding_alpha_quality: bool,
     pub(crate) creator_encoding_compression: bool,
+    pub(crate) creator_encoding_lossless: bool,
+    pub(crate) creator_encoding_chroma_subsampling: bool,
+    pub(crate) creator_encoding_progressive: bool,
+    pub(crate) creator_encoding_effort: bool,
+    pub(crate) creator_encoding_bit_depth: bool,
+    pub(crate) creator_encoding_palette_size: bool,
+    pub(crate) creator_encoding_texture_compression: bool,
+    pub(crate) creator_encoding_mipmaps: bool,
+    pub(crate) creator_encoding_exr_compression: bool,
+    pub(crate) creator_encoding_cmyk_icc_profile: bool,
+    pub(crate) creator_multi_page: bool,
+    pub(crate) creator_icon_standard_sizes: bool,
+    pub(crate) creator_cursor_hotspot: bool,
+    pub(crate) creator_layer_name: bool,
+    pub(crate) creator_jpeg_recompression: bool,
+    pub(crate) creator_metadata_exif: bool,
     pub(crate) creator_metadata_key_value: bool,
+    pub(crate) creator_metadata_xmp: bool,
     pub(crate) creator_pixel_density: bool,
     pub(crate) creator_memory_formats: BTreeSet<MemoryFormat>,
 }
@@ -545,18 +569,80 @@ impl Config {
 
             let creator = Self::handle_and_default(keyfile.boolean(&group, "Creator"))?;
 
+            let creator_animation =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorAnimation"))?;
+
             let creator_color_icc_profile =
                 Self::handle_and_default(keyfile.boolean(&group, "CreatorColorIccProfile"))?;
 
+            let creator_color_cicp =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorColorCicp"))?;
+
             let creator_encoding_compression =
                 Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingCompression"))?;
 
             let creator_encoding_quality =
                 Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingQuality"))?;
 
+            let creator_encoding_alpha_quality =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingAlphaQuality"))?;
+
+            let creator_encoding_lossless =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingLossless"))?;
+
+            let creator_encoding_chroma_subsampling = Self::handle_and_default(
+                keyfile.boolean(&group, "CreatorEncodingChromaSubsampling"),
+            )?;
+
+            let creator_encoding_progressive =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingProgressive"))?;
+
+            let creator_encoding_effort =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingEffort"))?;
+
+            let creator_encoding_bit_depth =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingBitDepth"))?;
+
+            let creator_encoding_palette_size =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingPaletteSize"))?;
+
+            let creator_encoding_texture_compression = Self::handle_and_default(
+                keyfile.boolean(&group, "CreatorEncodingTextureCompression"),
+            )?;
+
+            let creator_encoding_mipmaps =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingMipmaps"))?;
+
+            let creator_encoding_exr_compression =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingExrCompression"))?;
+
+            let creator_encoding_cmyk_icc_profile =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorEncodingCmykIccProfile"))?;
+
+            let creator_multi_page =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorMultiPage"))?;
+
+            let creator_icon_standard_sizes =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorIconStandardSizes"))?;
+
+            let creator_cursor_hotspot =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorCursorHotspot"))?;
+
+            let creator_layer_name =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorLayerName"))?;
+
+            let creator_jpeg_recompression =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorJpegRecompression"))?;
+
+            let creator_metadata_exif =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorMetadataExif"))?;
+
             let creator_metadata_key_value =
                 Self::handle_and_default(keyfile.boolean(&group, "CreatorMetadataKeyValue"))?;
 
+            let creator_metadata_xmp =
+                Self::handle_and_default(keyfile.boolean(&group, "CreatorMetadataXmp"))?;
+
             let creator_pixel_density =
                 Self::handle_and_default(keyfile.boolean(&group, "CreatorPixelDensity"))?;
 
@@ -581,10 +667,30 @@ impl Config {
                 fontconfig,
                 operations,
                 creator,
+                creator_animation,
                 creator_color_icc_profile,
+                creator_color_cicp,
                 creator_encoding_compression,
                 creator_encoding_quality,
+                creator_encoding_alpha_quality,
+                creator_encoding_lossless,
+                creator_encoding_chroma_subsampling,
+                creator_encoding_progressive,
+                creator_encoding_effort,
+                creator_encoding_bit_depth,
+                creator_encoding_palette_size,
+                creator_encoding_texture_compression,
+                creator_encoding_mipmaps,
+                creator_encoding_exr_compression,
+                creator_encoding_cmyk_icc_profile,
+                creator_multi_page,
+                creator_icon_standard_sizes,
+                creator_cursor_hotspot,
+                creator_layer_name,
+                creator_jpeg_recompression,
+                creator_metadata_exif,
                 creator_metadata_key_value,
+                creator_metadata_xmp,
                 creator_pixel_density,
                 creator_memory_formats,
             };
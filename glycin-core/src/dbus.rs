@@ -15,8 +15,8 @@ use gio::prelude::*;
 use glycin_common::Operations;
 use glycin_utils::{
     CompleteEditorOutput, EditRequest, EncodedImage, EncodingOptions, FrameRequest, InitRequest,
-    InitializationDetails, NewImage, RemoteEditableImage, RemoteError, RemoteImage, SharedMemory,
-    SparseEditorOutput,
+    InitializationDetails, Limits, NewImage, RemoteEditableImage, RemoteError, RemoteImage,
+    SharedMemory, SparseEditorOutput,
 };
 use nix::sys::signal;
 use zbus::zvariant::{self, OwnedObjectPath};
@@ -296,6 +296,21 @@ impl RemoteProcess<LoaderProxy<'static>> {
 
         loader_proxy.frame(frame_request).await.map_err(Into::into)
     }
+
+    pub async fn reconstruct_jpeg(
+        &self,
+        image: &Image,
+    ) -> Result<glycin_utils::EncodedImage<SharedMemory>, Error> {
+        let frame_request_path = image.frame_request_path();
+
+        let loader_proxy = LoaderStateProxy::builder(&self.dbus_connection)
+            .destination("org.gnome.glycin")?
+            .path(frame_request_path)?
+            .build()
+            .await?;
+
+        loader_proxy.reconstruct_jpeg().await.map_err(Into::into)
+    }
 }
 
 impl RemoteProcess<EditorProxy<'static>> {
@@ -311,12 +326,44 @@ impl RemoteProcess<EditorProxy<'static>> {
             .map_err(Into::into)
     }
 
+    /// Let the editor write the encoded image to `fd`
+    ///
+    /// Returns `None` if the editor doesn't support this method.
+    pub async fn create_to_fd(
+        &self,
+        mime_type: &MimeType,
+        new_image: &NewImage<SharedMemory>,
+        encoding_options: &EncodingOptions,
+        fd: OwnedFd,
+    ) -> Result<Option<()>, Error> {
+        let result = self
+            .proxy
+            .create_to_fd(
+                mime_type.to_string(),
+                new_image,
+                encoding_options,
+                fd.into(),
+            )
+            .await;
+
+        match result {
+            Err(RemoteError::ZBus(zbus::Error::MethodError(name, _, _)))
+                if name.as_str() == "org.freedesktop.DBus.Error.UnknownMethod" =>
+            {
+                Ok(None)
+            }
+            result => result.map(Some).map_err(Into::into),
+        }
+    }
+
     pub async fn edit(
         &self,
         external_reader: OwnedFd,
         mime_type: &MimeType,
+        limits: &Limits,
     ) -> Result<RemoteEditableImage, Error> {
-        let init_request = self.init_request(mime_type, external_reader)?;
+        let mut init_request = self.init_request(mime_type, external_reader)?;
+        init_request.details.limits = limits.clone();
 
         self.proxy.edit(init_request).await.map_err(Into::into)
     }
@@ -359,6 +406,26 @@ impl RemoteProcess<EditorProxy<'static>> {
             .map_err(Into::into)
     }
 
+    pub async fn editor_preview(
+        &self,
+        operations: &Operations,
+        frame_request: FrameRequest,
+        editable_image: &EditableImage,
+    ) -> Result<glycin_utils::Frame<SharedMemory>, Error> {
+        let editor_proxy = EditableImageProxy::builder(&self.dbus_connection)
+            .destination("org.gnome.glycin")?
+            .path(editable_image.edit_request_path())?
+            .build()
+            .await?;
+
+        let edit_request = EditRequest::for_operations(operations)?;
+
+        editor_proxy
+            .preview(edit_request, frame_request)
+            .await
+            .map_err(Into::into)
+    }
+
     pub fn done_background(self: Arc<Self>, image: &EditableImage) {
         let edit_request_path = image.edit_request_path();
         let arc = self.clone();
@@ -391,6 +458,9 @@ pub trait LoaderState {
         &self,
         frame_request: FrameRequest,
     ) -> Result<glycin_utils::Frame<SharedMemory>, RemoteError>;
+    async fn reconstruct_jpeg(
+        &self,
+    ) -> Result<glycin_utils::EncodedImage<SharedMemory>, RemoteError>;
     async fn done(&self) -> Result<(), RemoteError>;
 }
 
@@ -406,6 +476,14 @@ pub trait Editor {
         encoding_options: EncodingOptions,
     ) -> Result<EncodedImage<SharedMemory>, RemoteError>;
 
+    async fn create_to_fd(
+        &self,
+        mime_type: String,
+        new_image: &NewImage<SharedMemory>,
+        encoding_options: &EncodingOptions,
+        fd: zvariant::OwnedFd,
+    ) -> Result<(), RemoteError>;
+
     async fn edit(&self, init_request: InitRequest) -> Result<RemoteEditableImage, RemoteError>;
 }
 
@@ -421,6 +499,12 @@ pub trait EditableImage {
         edit_request: EditRequest,
     ) -> Result<CompleteEditorOutput<SharedMemory>, RemoteError>;
 
+    async fn preview(
+        &self,
+        edit_request: EditRequest,
+        frame_request: FrameRequest,
+    ) -> Result<glycin_utils::Frame<SharedMemory>, RemoteError>;
+
     async fn done(&self) -> Result<(), RemoteError>;
 }
 
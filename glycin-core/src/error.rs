@@ -66,6 +66,7 @@ impl<T, E: Into<Error>> ResultExt<T> for Result<T, E> {
 pub struct Error {
     kind: Box<ErrorKind>,
     context: Option<ErrorContext>,
+    partial_output_size: Option<u64>,
 }
 
 impl Display for Error {
@@ -81,6 +82,7 @@ impl Error {
         Self {
             kind: Box::new(kind),
             context: None,
+            partial_output_size: None,
         }
     }
 
@@ -88,6 +90,7 @@ impl Error {
         Self {
             kind: Box::new(ErrorKind::Other(msg.to_string())),
             context: None,
+            partial_output_size: None,
         }
     }
 
@@ -103,6 +106,16 @@ impl Error {
         }
     }
 
+    /// Returns if the requested feature is not supported by the format or
+    /// its implementation
+    pub fn is_feature_not_supported(&self) -> bool {
+        matches!(
+            *self.kind,
+            ErrorKind::FeatureNotSupported(_)
+                | ErrorKind::RemoteError(RemoteError::UnsupportedFeature(_))
+        )
+    }
+
     pub fn failed_image_source(&self) -> Option<glib::Error> {
         if let ErrorKind::ImageSource(err) = &*self.kind {
             Some(err.clone())
@@ -149,6 +162,50 @@ impl Error {
     pub fn is_timeout(&self) -> bool {
         matches!(*self.kind, ErrorKind::Timeout(_))
     }
+
+    /// Returns if sparse changes were refused since the file differs from the
+    /// data the edit was created for
+    pub fn is_file_changed(&self) -> bool {
+        matches!(*self.kind, ErrorKind::SparseEditMismatch(_))
+    }
+
+    /// Returns if sparse changes were refused since the journal of an
+    /// interrupted edit still exists
+    ///
+    /// The file has to be restored via
+    /// [`SparseEdit::recover()`](crate::SparseEdit::recover) first.
+    pub fn is_journal_pending(&self) -> bool {
+        matches!(*self.kind, ErrorKind::PendingJournal(_))
+    }
+
+    /// Returns if the edited image did not match the expected result
+    ///
+    /// See [`Editor::verify_output()`](crate::Editor::verify_output).
+    pub fn is_verification_failed(&self) -> bool {
+        matches!(*self.kind, ErrorKind::VerificationFailed(_))
+    }
+
+    /// Number of bytes that were written to the stream before the error
+    /// occurred
+    ///
+    /// Only set for errors of
+    /// [`Creator::create_to()`](crate::Creator::create_to). The written data
+    /// are not removed from the stream.
+    pub fn partial_output_size(&self) -> Option<u64> {
+        self.partial_output_size
+    }
+
+    pub(crate) fn with_partial_output_size(mut self, size: u64) -> Self {
+        self.partial_output_size = Some(size);
+        self
+    }
+
+    /// Returns if the image could not be encoded within the target size
+    ///
+    /// See [`Creator::set_target_size()`](crate::Creator::set_target_size).
+    pub fn is_target_size_exceeded(&self) -> bool {
+        matches!(*self.kind, ErrorKind::TargetSizeExceeded { .. })
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -234,6 +291,20 @@ pub(crate) enum ErrorKind {
     ThreadPanic(Option<String>),
     #[error("Feature not supported: {0}")]
     FeatureNotSupported(#[from] FeatureNotSupported),
+    #[error("File content at offset {0} differs from the data the edit was created for")]
+    SparseEditMismatch(u64),
+    #[error("Sparse edit journal: {0}")]
+    InvalidJournal(String),
+    #[error("Journal '{0}' of an interrupted edit has to be recovered first")]
+    PendingJournal(String),
+    #[error("File '{0}' has no parent directory")]
+    NoParentDirectory(String),
+    #[error("Verification of the edited image failed: {0}")]
+    VerificationFailed(String),
+    #[error("Output verification requires an editor created from a file or bytes")]
+    VerificationUnavailable,
+    #[error("Encoded image has {size} bytes which exceeds the target size of {target_size} bytes")]
+    TargetSizeExceeded { size: usize, target_size: u64 },
     #[error("Operation did not complete in supplied limit of {0:?}")]
     Timeout(Duration),
     #[error("This state should never have been reached: {0}:{1}")]
@@ -1,5 +1,7 @@
 pub mod color_mode;
 pub mod creator;
+pub mod creator_capabilities;
+pub mod editor;
 pub mod encoded_image;
 pub mod frame;
 pub mod frame_details;
@@ -12,7 +14,9 @@ pub mod pixel_density;
 use std::sync::OnceLock;
 
 pub use color_mode::GlyColorMode;
-pub use creator::GlyCreator;
+pub use creator::{GlyChromaSubsampling, GlyCreator, GlyExrCompression, GlyTextureCompression};
+pub use creator_capabilities::GlyCreatorCapabilities;
+pub use editor::{GlyEditor, GlySaveFlags};
 pub use encoded_image::GlyEncodedImage;
 pub use frame::{GlyCicp, GlyFrame};
 pub use frame_details::GlyFrameDetails;
@@ -6,14 +6,89 @@ use glib::prelude::*;
 use glib::subclass::prelude::*;
 use glycin_utils::MemoryFormat;
 
+use crate::api::GOutputStreamSend;
 use crate::error::ErrorKind;
 use crate::gobject::GlyNewFrame;
 use crate::util::AsyncMutex;
-use crate::{Creator, Error, MimeType, SandboxSelector, gobject};
+use crate::{
+    ChromaSubsampling, Creator, Error, ExrCompression, MimeType, SandboxSelector,
+    TextureCompression, gobject,
+};
 
 static_assertions::assert_impl_all!(GlyCreator: Send, Sync);
 use super::init;
 
+#[derive(Debug, Copy, Clone, gio::glib::Enum, Default)]
+#[enum_type(name = "GlyChromaSubsampling")]
+#[repr(i32)]
+#[non_exhaustive]
+pub enum GlyChromaSubsampling {
+    #[default]
+    Yuv444 = 0,
+    Yuv422 = 1,
+    Yuv420 = 2,
+}
+
+impl From<GlyChromaSubsampling> for ChromaSubsampling {
+    fn from(value: GlyChromaSubsampling) -> Self {
+        match value {
+            GlyChromaSubsampling::Yuv444 => Self::Yuv444,
+            GlyChromaSubsampling::Yuv422 => Self::Yuv422,
+            GlyChromaSubsampling::Yuv420 => Self::Yuv420,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, gio::glib::Enum, Default)]
+#[enum_type(name = "GlyTextureCompression")]
+#[repr(i32)]
+#[non_exhaustive]
+pub enum GlyTextureCompression {
+    #[default]
+    Bc1 = 0,
+    Bc3 = 1,
+    Bc4 = 2,
+    Bc5 = 3,
+    Bc7 = 4,
+}
+
+impl From<GlyTextureCompression> for TextureCompression {
+    fn from(value: GlyTextureCompression) -> Self {
+        match value {
+            GlyTextureCompression::Bc1 => Self::Bc1,
+            GlyTextureCompression::Bc3 => Self::Bc3,
+            GlyTextureCompression::Bc4 => Self::Bc4,
+            GlyTextureCompression::Bc5 => Self::Bc5,
+            GlyTextureCompression::Bc7 => Self::Bc7,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, gio::glib::Enum, Default)]
+#[enum_type(name = "GlyExrCompression")]
+#[repr(i32)]
+#[non_exhaustive]
+pub enum GlyExrCompression {
+    #[default]
+    Uncompressed = 0,
+    Rle = 1,
+    Zip = 2,
+    Piz = 3,
+    Dwaa = 4,
+}
+
+impl From<GlyExrCompression> for ExrCompression {
+    fn from(value: GlyExrCompression) -> Self {
+        match value {
+            GlyExrCompression::Uncompressed => Self::Uncompressed,
+            GlyExrCompression::Rle => Self::Rle,
+            GlyExrCompression::Zip => Self::Zip,
+            GlyExrCompression::Piz => Self::Piz,
+            GlyExrCompression::Dwaa => Self::Dwaa,
+        }
+    }
+}
+
 pub mod imp {
     use super::*;
 
@@ -72,6 +147,12 @@ impl GlyCreator {
         Ok(obj)
     }
 
+    pub async fn capabilities(mime_type: String) -> Result<gobject::GlyCreatorCapabilities, Error> {
+        let capabilities = Creator::capabilities(MimeType::new(mime_type)).await?;
+
+        Ok(gobject::GlyCreatorCapabilities::new(capabilities))
+    }
+
     pub fn inner(&self) -> &AsyncMutex<Option<crate::Creator>> {
         &self.imp().creator
     }
@@ -108,6 +189,23 @@ impl GlyCreator {
             .set_encoding_quality(quality)
     }
 
+    pub fn set_encoding_alpha_quality(
+        &self,
+        alpha_quality: u8,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_alpha_quality(alpha_quality)
+    }
+
+    pub fn set_target_size(&self, target_size: u64) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_target_size(target_size)
+    }
+
     pub fn set_encoding_compression(
         &self,
         compression: u8,
@@ -118,6 +216,132 @@ impl GlyCreator {
             .set_encoding_compression(compression)
     }
 
+    pub fn set_encoding_lossless(&self, lossless: bool) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_lossless(lossless)
+    }
+
+    pub fn set_encoding_chroma_subsampling(
+        &self,
+        chroma_subsampling: GlyChromaSubsampling,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_chroma_subsampling(chroma_subsampling.into())
+    }
+
+    pub fn set_encoding_progressive(
+        &self,
+        progressive: bool,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_progressive(progressive)
+    }
+
+    pub fn set_encoding_effort(&self, effort: u8) -> Result<(), crate::FeatureNotSupported> {
+        self.creator().as_mut().unwrap().set_encoding_effort(effort)
+    }
+
+    pub fn set_encoding_bit_depth(&self, bit_depth: u8) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_bit_depth(bit_depth)
+    }
+
+    pub fn set_encoding_palette_size(
+        &self,
+        palette_size: u16,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_palette_size(palette_size)
+    }
+
+    pub fn set_encoding_texture_compression(
+        &self,
+        texture_compression: GlyTextureCompression,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_texture_compression(texture_compression.into())
+    }
+
+    pub fn set_encoding_mipmaps(&self, mipmaps: bool) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_mipmaps(mipmaps)
+    }
+
+    pub fn set_encoding_exr_compression(
+        &self,
+        exr_compression: GlyExrCompression,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_exr_compression(exr_compression.into())
+    }
+
+    pub fn set_encoding_cmyk_icc_profile(
+        &self,
+        icc_profile: glib::Bytes,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_encoding_cmyk_icc_profile(icc_profile.to_vec())
+    }
+
+    pub fn set_icon_standard_sizes(
+        &self,
+        icon_standard_sizes: bool,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_icon_standard_sizes(icon_standard_sizes)
+    }
+
+    pub fn set_source_jpeg(&self, jpeg: glib::Bytes) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_source_jpeg(jpeg.to_vec())
+    }
+
+    pub fn set_metadata_exif(
+        &self,
+        exif: Option<glib::Bytes>,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_metadata_exif(exif.map(|x| x.to_vec()))
+    }
+
+    pub fn set_metadata_xmp(
+        &self,
+        xmp: Option<glib::Bytes>,
+    ) -> Result<(), crate::FeatureNotSupported> {
+        self.creator()
+            .as_mut()
+            .unwrap()
+            .set_metadata_xmp(xmp.map(|x| x.to_vec()))
+    }
+
+    pub fn set_loop_count(&self, loop_count: u32) -> Result<(), crate::FeatureNotSupported> {
+        self.creator().as_mut().unwrap().set_loop_count(loop_count)
+    }
+
     pub async fn add_frame(
         &self,
         width: u32,
@@ -149,14 +373,49 @@ impl GlyCreator {
     }
 
     pub async fn create(&self) -> Result<gobject::GlyEncodedImage, crate::Error> {
+        let creator = self.prepare_creator().await?;
+        let encoded_image: crate::EncodedImage = creator.create().await?;
+        Ok(gobject::GlyEncodedImage::new(encoded_image))
+    }
+
+    /// Encode the image and write it to `stream`
+    ///
+    /// # Safety
+    ///
+    /// The stream must not be used until the returned future has completed.
+    pub unsafe fn create_to(
+        &self,
+        stream: gio::OutputStream,
+    ) -> impl Future<Output = Result<(), crate::Error>> + Send + use<> {
+        let stream = unsafe { GOutputStreamSend::new(stream) };
+        let obj = self.clone();
+
+        async move {
+            let creator = obj.prepare_creator().await?;
+            creator.create_to_send(stream).await
+        }
+    }
+
+    pub fn create_to_file(
+        &self,
+        file: gio::File,
+    ) -> impl Future<Output = Result<(), crate::Error>> + Send + use<> {
+        let obj = self.clone();
+
+        async move {
+            let creator = obj.prepare_creator().await?;
+            creator.create_to_file(file).await
+        }
+    }
+
+    async fn prepare_creator(&self) -> Result<Creator, crate::Error> {
         if let Some(mut creator) = std::mem::take(&mut *self.imp().creator.lock_blocking()) {
             for frame in &*self.imp().frames.lock().await {
                 frame.build(&mut creator).await?;
             }
 
             creator.sandbox_selector(self.sandbox_selector());
-            let encoded_image: crate::EncodedImage = creator.create().await?;
-            Ok(gobject::GlyEncodedImage::new(encoded_image))
+            Ok(creator)
         } else {
             Err(ErrorKind::LoaderUsedTwice.err())
         }
@@ -0,0 +1,108 @@
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use gio::glib;
+use glib::prelude::*;
+use glib::subclass::prelude::*;
+use glycin_common::MemoryFormatSelection;
+
+use crate::CreatorCapabilities;
+
+static_assertions::assert_impl_all!(GlyCreatorCapabilities: Send, Sync);
+
+pub mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, glib::Properties)]
+    #[properties(wrapper_type = super::GlyCreatorCapabilities)]
+    pub struct GlyCreatorCapabilities {
+        #[property(get = Self::memory_formats)]
+        memory_formats: PhantomData<MemoryFormatSelection>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_animation())]
+        supports_animation: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_multi_page())]
+        supports_multi_page: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_quality())]
+        supports_encoding_quality: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_alpha_quality())]
+        supports_encoding_alpha_quality: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_target_size())]
+        supports_target_size: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_compression())]
+        supports_encoding_compression: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_lossless())]
+        supports_encoding_lossless: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_chroma_subsampling())]
+        supports_encoding_chroma_subsampling: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_progressive())]
+        supports_encoding_progressive: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_effort())]
+        supports_encoding_effort: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_bit_depth())]
+        supports_encoding_bit_depth: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_palette_size())]
+        supports_encoding_palette_size: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_texture_compression())]
+        supports_encoding_texture_compression: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_mipmaps())]
+        supports_encoding_mipmaps: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_exr_compression())]
+        supports_encoding_exr_compression: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_encoding_cmyk_icc_profile())]
+        supports_encoding_cmyk_icc_profile: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_icon_standard_sizes())]
+        supports_icon_standard_sizes: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_jpeg_recompression())]
+        supports_jpeg_recompression: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_metadata_exif())]
+        supports_metadata_exif: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_metadata_xmp())]
+        supports_metadata_xmp: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_metadata_key_value())]
+        supports_metadata_key_value: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_color_icc_profile())]
+        supports_color_icc_profile: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_color_cicp())]
+        supports_color_cicp: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_pixel_density())]
+        supports_pixel_density: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_cursor_hotspot())]
+        supports_cursor_hotspot: PhantomData<bool>,
+        #[property(get = |imp: &Self| imp.capabilities().supports_layer_name())]
+        supports_layer_name: PhantomData<bool>,
+
+        pub(super) capabilities: OnceLock<CreatorCapabilities>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for GlyCreatorCapabilities {
+        const NAME: &'static str = "GlyCreatorCapabilities";
+        type Type = super::GlyCreatorCapabilities;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for GlyCreatorCapabilities {}
+
+    impl GlyCreatorCapabilities {
+        fn capabilities(&self) -> &CreatorCapabilities {
+            self.capabilities.get().unwrap()
+        }
+
+        fn memory_formats(&self) -> MemoryFormatSelection {
+            MemoryFormatSelection::from_memory_formats(self.capabilities().memory_formats())
+        }
+    }
+}
+
+glib::wrapper! {
+    /// GObject wrapper for [`CreatorCapabilities`]
+    pub struct GlyCreatorCapabilities(ObjectSubclass<imp::GlyCreatorCapabilities>);
+}
+
+impl GlyCreatorCapabilities {
+    pub fn new(capabilities: CreatorCapabilities) -> Self {
+        let obj = glib::Object::new::<Self>();
+        obj.imp().capabilities.set(capabilities).unwrap();
+        obj
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::{Mutex, OnceLock};
+
+use gio::glib;
+use glib::prelude::*;
+use glib::subclass::prelude::*;
+use glycin_common::{Operation, Operations};
+
+use super::init;
+use crate::{Editor, Error, SandboxSelector, SaveOptions};
+
+static_assertions::assert_impl_all!(GlyEditor: Send, Sync);
+
+/// Options for saving edited images
+#[glib::flags(name = "GlySaveFlags")]
+pub enum GlySaveFlags {
+    Backup = (1 << 0),
+    DiscardAttributes = (1 << 1),
+    PreserveModificationTime = (1 << 2),
+}
+
+impl From<GlySaveFlags> for SaveOptions {
+    fn from(flags: GlySaveFlags) -> Self {
+        let mut options = SaveOptions::new();
+
+        options
+            .backup(flags.contains(GlySaveFlags::Backup))
+            .preserve_attributes(!flags.contains(GlySaveFlags::DiscardAttributes))
+            .preserve_modification_time(flags.contains(GlySaveFlags::PreserveModificationTime));
+
+        options
+    }
+}
+
+pub mod imp {
+    use super::*;
+
+    #[derive(Default, Debug, glib::Properties)]
+    #[properties(wrapper_type = super::GlyEditor)]
+    pub struct GlyEditor {
+        #[property(get, construct_only)]
+        file: OnceLock<gio::File>,
+        #[property(get, set, builder(SandboxSelector::default()))]
+        pub(super) sandbox_selector: Mutex<SandboxSelector>,
+
+        pub(super) operations: Mutex<Vec<Operation>>,
+        pub(super) cancellable: gio::Cancellable,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for GlyEditor {
+        const NAME: &'static str = "GlyEditor";
+        type Type = super::GlyEditor;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for GlyEditor {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            init();
+        }
+    }
+}
+
+glib::wrapper! {
+    /// GObject wrapper for [`Editor`]
+    pub struct GlyEditor(ObjectSubclass<imp::GlyEditor>);
+}
+
+impl GlyEditor {
+    pub fn new(file: gio::File) -> Self {
+        glib::Object::builder().property("file", file).build()
+    }
+
+    pub fn cancellable(&self) -> gio::Cancellable {
+        self.imp().cancellable.clone()
+    }
+
+    pub fn add_operation(&self, operation: Operation) {
+        self.imp().operations.lock().unwrap().push(operation);
+    }
+
+    /// Operations added so far
+    pub fn operations(&self) -> Operations {
+        Operations::new(self.imp().operations.lock().unwrap().clone())
+    }
+
+    pub async fn apply_and_save(&self, file: gio::File, flags: GlySaveFlags) -> Result<(), Error> {
+        let mut editor = Editor::new(self.file());
+        editor.sandbox_selector(self.sandbox_selector());
+        editor.cancellable(self.cancellable());
+
+        let editable_image = editor.edit().await?;
+
+        editable_image
+            .apply_and_save(&self.operations(), file, flags.into())
+            .await
+    }
+}
@@ -17,6 +17,8 @@ pub mod imp {
     pub struct GlyEncodedImage {
         #[property(get=Self::data, nullable)]
         data: PhantomData<glib::Bytes>,
+        #[property(get=Self::quality, minimum = -1, maximum = 100, default = -1)]
+        quality: PhantomData<i32>,
 
         pub(super) encoded_image: OnceLock<EncodedImage>,
     }
@@ -34,6 +36,14 @@ pub mod imp {
         fn data(&self) -> glib::Bytes {
             glib::Bytes::from_owned(self.encoded_image.get().unwrap().data_full())
         }
+
+        fn quality(&self) -> i32 {
+            self.encoded_image
+                .get()
+                .unwrap()
+                .quality()
+                .map_or(-1, i32::from)
+        }
     }
 }
 
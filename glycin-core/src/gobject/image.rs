@@ -4,7 +4,7 @@ use futures_util::lock::{MappedMutexGuard, Mutex, MutexGuard};
 use gio::{Cancellable, glib};
 use glib::subclass::prelude::*;
 
-use super::GlyFrame;
+use super::{GlyEncodedImage, GlyFrame};
 use crate::main_context::ProvidesMainContext;
 use crate::{Error, FrameRequest, Image, ImageDetails, MainContextSelector, util};
 
@@ -69,6 +69,25 @@ impl GlyImage {
         ))
     }
 
+    pub fn reconstruct_jpeg(&self) -> Result<GlyEncodedImage, Error> {
+        util::block_on(async {
+            let mut image = self.image();
+
+            let mut main_context = MainContextSelector::Managed;
+            std::mem::swap(&mut main_context, &mut image.loader.main_context_selector);
+
+            let encoded_image = image.reconstruct_jpeg().await?;
+
+            image.loader.main_context_selector = main_context;
+
+            Ok(GlyEncodedImage::new(encoded_image))
+        })
+    }
+
+    pub async fn reconstruct_jpeg_future(&self) -> Result<GlyEncodedImage, Error> {
+        Ok(GlyEncodedImage::new(self.image().reconstruct_jpeg().await?))
+    }
+
     pub fn cancellable(&self) -> Cancellable {
         self.image().cancellable()
     }
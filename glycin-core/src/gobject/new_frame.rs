@@ -1,4 +1,5 @@
 use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 use gio::glib;
 use glib::prelude::*;
@@ -7,6 +8,7 @@ use glycin_utils::MemoryFormat;
 use gufo_common::physical_dimension::PixelDensity;
 
 use super::init;
+use crate::Cicp;
 use crate::gobject::GlyPixelDensity;
 
 static_assertions::assert_impl_all!(GlyNewFrame: Send, Sync);
@@ -47,7 +49,11 @@ pub mod imp {
         #[property(get, set, nullable)]
         color_icc_profile: Mutex<Option<glib::Bytes>>,
 
+        pub(crate) color_cicp: Mutex<Option<Cicp>>,
         pub(crate) pixel_density: Mutex<Option<PixelDensity>>,
+        pub(crate) delay: Mutex<Option<Duration>>,
+        pub(crate) cursor_hotspot: Mutex<Option<(u16, u16)>>,
+        pub(crate) layer_name: Mutex<Option<String>>,
     }
 
     #[glib::object_subclass]
@@ -88,11 +94,27 @@ impl GlyNewFrame {
             .build()
     }
 
+    pub fn set_color_cicp(&self, cicp: Option<Cicp>) {
+        *self.imp().color_cicp.lock().unwrap() = cicp;
+    }
+
     pub fn set_pixel_density(&self, pixel_density: Option<GlyPixelDensity>) {
         *self.imp().pixel_density.lock().unwrap() =
             pixel_density.map(|x| x.inner().to_owned().unwrap());
     }
 
+    pub fn set_delay(&self, delay: Option<Duration>) {
+        *self.imp().delay.lock().unwrap() = delay;
+    }
+
+    pub fn set_cursor_hotspot(&self, cursor_hotspot: Option<(u16, u16)>) {
+        *self.imp().cursor_hotspot.lock().unwrap() = cursor_hotspot;
+    }
+
+    pub fn set_layer_name(&self, layer_name: Option<String>) {
+        *self.imp().layer_name.lock().unwrap() = layer_name;
+    }
+
     pub async fn build(&self, creator: &mut crate::Creator) -> Result<(), crate::Error> {
         let frame = if self.stride() == 0 {
             creator.add_frame(
@@ -113,7 +135,11 @@ impl GlyNewFrame {
 
         // TODO: Errors here should be handled earlier
         frame.set_color_icc_profile(self.color_icc_profile().map(|x| x.into_data().to_vec()))?;
+        frame.set_color_cicp(*self.imp().color_cicp.lock().unwrap())?;
         frame.set_pixel_density(self.imp().pixel_density.lock().unwrap().clone())?;
+        frame.set_delay(*self.imp().delay.lock().unwrap())?;
+        frame.set_cursor_hotspot(*self.imp().cursor_hotspot.lock().unwrap())?;
+        frame.set_layer_name(self.imp().layer_name.lock().unwrap().clone())?;
 
         Ok(())
     }
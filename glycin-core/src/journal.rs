@@ -0,0 +1,226 @@
+//! Undo journal for sparse edits
+//!
+//! Before sparse changes are written into a file, the original values are
+//! stored in a journal next to the file. If writing the changes is
+//! interrupted, the journal allows to restore the original file.
+//!
+//! The journal consists of a signature followed by entries of a big endian
+//! `u64` offset and the original byte at that offset.
+//!
+//! If the directory does not allow creating the journal, the changes are
+//! written without a journal. An existing journal is never replaced, since it
+//! holds the only copy of the original values of an interrupted edit.
+
+use gio::glib;
+use gio::prelude::*;
+use glycin_utils::safe_math::SafeConversion;
+
+use crate::Error;
+use crate::error::ErrorKind;
+use crate::util::sync;
+
+const SIGNATURE: &[u8] = b"glycin-sparse-edit-journal-1\n";
+const ENTRY_SIZE: usize = 9;
+
+/// Location of the journal for `file`
+pub(crate) fn journal_file(file: &gio::File) -> Result<gio::File, Error> {
+    let (Some(parent), Some(basename)) = (file.parent(), file.basename()) else {
+        return Err(ErrorKind::NoParentDirectory(file.uri().to_string()).err());
+    };
+
+    let mut name = std::ffi::OsString::from(".");
+    name.push(basename);
+    name.push(".glycin-journal");
+
+    Ok(parent.child(name))
+}
+
+/// Write the original values and make sure they are stored on disk
+///
+/// Returns `false` if the journal could not be created because the directory
+/// is not writable. Fails if the journal of an interrupted edit exists.
+pub(crate) fn write(file: &gio::File, original_values: &[(u64, u8)]) -> Result<bool, Error> {
+    let mut content = Vec::with_capacity(SIGNATURE.len() + original_values.len() * ENTRY_SIZE);
+    content.extend_from_slice(SIGNATURE);
+    for (offset, value) in original_values {
+        content.extend_from_slice(&offset.to_be_bytes());
+        content.push(*value);
+    }
+
+    let journal = journal_file(file)?;
+    let stream = match journal.create(gio::FileCreateFlags::PRIVATE, gio::Cancellable::NONE) {
+        Ok(stream) => stream,
+        Err(err) if err.matches(gio::IOErrorEnum::Exists) => {
+            return Err(ErrorKind::PendingJournal(journal.uri().to_string()).err());
+        }
+        Err(err)
+            if err.matches(gio::IOErrorEnum::PermissionDenied)
+                || err.matches(gio::IOErrorEnum::ReadOnly) =>
+        {
+            tracing::warn!(
+                "Can't create journal '{}', changing file without journal: {err}",
+                journal.uri()
+            );
+            return Ok(false);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let (_, err) = stream.write_all(&content, gio::Cancellable::NONE)?;
+    if let Some(err) = err {
+        return Err(err.into());
+    }
+    stream.close(gio::Cancellable::NONE)?;
+
+    sync(&journal)?;
+    // The directory entry of the new journal has to be on disk as well
+    sync_parent(&journal)?;
+
+    Ok(true)
+}
+
+/// Remove the journal after all changes have been written
+pub(crate) fn remove(file: &gio::File) -> Result<(), Error> {
+    let journal = journal_file(file)?;
+    journal.delete(gio::Cancellable::NONE)?;
+    sync_parent(&journal)
+}
+
+fn sync_parent(journal: &gio::File) -> Result<(), Error> {
+    match journal.parent() {
+        Some(parent) => sync(&parent),
+        None => Ok(()),
+    }
+}
+
+/// Restore original values from an existing journal
+///
+/// Returns `false` if no journal exists.
+pub(crate) fn recover(file: &gio::File) -> Result<bool, Error> {
+    let journal = journal_file(file)?;
+
+    let content = match journal.load_contents(gio::Cancellable::NONE) {
+        Ok((content, _)) => content,
+        Err(err) if err.matches(gio::IOErrorEnum::NotFound) => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+
+    let Some(entries) = content.strip_prefix(SIGNATURE) else {
+        return Err(ErrorKind::InvalidJournal(format!(
+            "Unknown journal format in '{}'",
+            journal.uri()
+        ))
+        .err());
+    };
+
+    let stream = file.open_readwrite(gio::Cancellable::NONE)?;
+    let output_stream = stream.output_stream();
+
+    // An incomplete last entry can only exist if writing the journal was
+    // interrupted. In that case, the file has not been changed yet.
+    for entry in entries.chunks_exact(ENTRY_SIZE) {
+        let (offset, value) = entry.split_at(8);
+        let offset = u64::from_be_bytes(offset.try_into().map_err(|_| ErrorKind::unreachable())?);
+
+        stream.seek(
+            offset.try_i64()?,
+            glib::SeekType::Set,
+            gio::Cancellable::NONE,
+        )?;
+        let (_, err) = output_stream.write_all(value, gio::Cancellable::NONE)?;
+        if let Some(err) = err {
+            return Err(err.into());
+        }
+    }
+
+    stream.close(gio::Cancellable::NONE)?;
+    sync(file)?;
+
+    remove(file)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tmp_file(name: &str, content: &[u8]) -> gio::File {
+        let dir = std::env::temp_dir().join(format!("glycin-journal-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        gio::File::for_path(path)
+    }
+
+    fn content(file: &gio::File) -> Vec<u8> {
+        std::fs::read(file.path().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn recover_interrupted_edit() {
+        let original = b"0123456789".to_vec();
+        let file = tmp_file("interrupted", &original);
+
+        assert!(write(&file, &[(2, b'2'), (5, b'5'), (7, b'7')]).unwrap());
+
+        // Only the first changes were written before the interruption
+        std::fs::write(file.path().unwrap(), b"01x34y6789").unwrap();
+
+        assert!(recover(&file).unwrap());
+        assert_eq!(content(&file), original);
+        assert!(
+            !journal_file(&file)
+                .unwrap()
+                .query_exists(gio::Cancellable::NONE)
+        );
+
+        // Nothing left to recover
+        assert!(!recover(&file).unwrap());
+    }
+
+    #[test]
+    fn recover_incomplete_journal() {
+        let original = b"0123456789".to_vec();
+        let file = tmp_file("incomplete", &original);
+
+        assert!(write(&file, &[(2, b'2'), (5, b'5')]).unwrap());
+
+        // Writing the journal was interrupted within the last entry
+        let journal_path = journal_file(&file).unwrap().path().unwrap();
+        let journal = std::fs::read(&journal_path).unwrap();
+        std::fs::write(&journal_path, &journal[..journal.len() - 3]).unwrap();
+
+        assert!(recover(&file).unwrap());
+        assert_eq!(content(&file), original);
+    }
+
+    #[test]
+    fn keep_pending_journal() {
+        let original = b"0123456789".to_vec();
+        let file = tmp_file("pending", &original);
+
+        assert!(write(&file, &[(2, b'2'), (5, b'5')]).unwrap());
+
+        // Only the first change was written before the interruption
+        std::fs::write(file.path().unwrap(), b"01x3456789").unwrap();
+
+        // A new edit must not replace the journal
+        let edit = crate::SparseEdit::Sparse(glycin_utils::ByteChanges::from_slice(&[(7, b'z')]));
+        let err = crate::util::block_on(edit.apply_to(file.clone())).unwrap_err();
+        assert!(err.is_journal_pending());
+        assert_eq!(content(&file), b"01x3456789");
+
+        assert!(recover(&file).unwrap());
+        assert_eq!(content(&file), original);
+    }
+
+    #[test]
+    fn recover_invalid_journal() {
+        let file = tmp_file("invalid", b"0123456789");
+        std::fs::write(journal_file(&file).unwrap().path().unwrap(), b"invalid").unwrap();
+
+        assert!(recover(&file).is_err());
+        assert_eq!(content(&file), b"0123456789");
+    }
+}
@@ -48,6 +48,7 @@ mod error;
 #[cfg(feature = "external")]
 mod fontconfig;
 mod icc;
+mod journal;
 mod main_context;
 mod orientation;
 #[cfg(feature = "external")]
@@ -56,6 +57,7 @@ mod pool;
 mod pool_shim;
 #[cfg(feature = "external")]
 mod sandbox;
+mod save;
 mod source;
 mod util;
 
@@ -74,7 +76,9 @@ pub use error::Error;
 pub use glycin_common::{
     ColorProfilePreference, MemoryFormat, MemoryFormatSelection, Operation, OperationId, Operations,
 };
+pub use glycin_utils::{ChromaSubsampling, ExrCompression, TextureCompression};
 pub use gufo_common::cicp::Cicp;
+pub use gufo_common::orientation::Rotation;
 pub use main_context::MainContextSelector;
 pub use pool::{Pool, PoolConfig};
 #[cfg(not(feature = "external"))]
@@ -0,0 +1,206 @@
+//! Atomic replacement of files
+//!
+//! The new content is written to a temporary file in the same directory which
+//! is then renamed to the destination. This way, the destination always
+//! contains either the old or the new content, even if writing is
+//! interrupted.
+
+use gio::glib;
+use gio::prelude::*;
+
+use crate::error::ErrorKind;
+use crate::{Error, SaveOptions, util};
+
+/// Attributes that are copied from the replaced file
+const ATTRIBUTES: &str = "unix::mode,unix::uid,unix::gid";
+/// Attributes that are only copied if the file system supports them
+const OPTIONAL_ATTRIBUTES: &str = "xattr::*,selinux::context";
+const MODIFICATION_TIME_ATTRIBUTES: &str = "time::modified,time::modified-usec";
+
+/// Replace the content of `file` with `data`
+pub(crate) fn replace(file: &gio::File, data: &[u8], options: &SaveOptions) -> Result<(), Error> {
+    // Replace the target of symlinks instead of the link itself
+    let file = match file.path().map(std::fs::canonicalize) {
+        Some(Ok(path)) => gio::File::for_path(path),
+        _ => file.clone(),
+    };
+
+    let (Some(parent), Some(basename)) = (file.parent(), file.basename()) else {
+        return Err(ErrorKind::NoParentDirectory(file.uri().to_string()).err());
+    };
+
+    let mut name = std::ffi::OsString::from(".");
+    name.push(&basename);
+    name.push(format!(".{}.glycin-tmp", glib::uuid_string_random()));
+    let tmp = parent.child(name);
+
+    let result = replace_with_tmp(&file, &tmp, data, options);
+
+    if result.is_err()
+        && let Err(err) = tmp.delete(gio::Cancellable::NONE)
+        && !err.matches(gio::IOErrorEnum::NotFound)
+    {
+        tracing::warn!("Failed to remove temporary file '{}': {err}", tmp.uri());
+    }
+
+    result?;
+
+    util::sync(&parent)
+}
+
+fn replace_with_tmp(
+    file: &gio::File,
+    tmp: &gio::File,
+    data: &[u8],
+    options: &SaveOptions,
+) -> Result<(), Error> {
+    let exists = file.query_exists(gio::Cancellable::NONE);
+
+    // Don't expose the content before the permissions of the replaced file are
+    // copied
+    let flags = if exists && options.preserve_attributes {
+        gio::FileCreateFlags::PRIVATE
+    } else {
+        gio::FileCreateFlags::NONE
+    };
+
+    let stream = tmp.create(flags, gio::Cancellable::NONE)?;
+
+    let (_, err) = stream.write_all(data, gio::Cancellable::NONE)?;
+    if let Some(err) = err {
+        return Err(err.into());
+    }
+    stream.close(gio::Cancellable::NONE)?;
+
+    if exists {
+        copy_attributes(file, tmp, options)?;
+    }
+
+    util::sync(tmp)?;
+
+    if exists && options.backup {
+        backup(file)?;
+    }
+
+    tmp.move_(
+        file,
+        gio::FileCopyFlags::OVERWRITE | gio::FileCopyFlags::NOFOLLOW_SYMLINKS,
+        gio::Cancellable::NONE,
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// Copy attributes of `file` to `tmp`
+///
+/// Fails if permissions, ownership, or the modification time can't be kept.
+/// Extended attributes and the SELinux context are copied on a best effort
+/// basis.
+fn copy_attributes(file: &gio::File, tmp: &gio::File, options: &SaveOptions) -> Result<(), Error> {
+    let mut attributes = Vec::new();
+
+    if options.preserve_attributes {
+        attributes.push(ATTRIBUTES);
+    }
+
+    if options.preserve_modification_time {
+        attributes.push(MODIFICATION_TIME_ATTRIBUTES);
+    }
+
+    if !attributes.is_empty() {
+        let info = file.query_info(
+            &attributes.join(","),
+            gio::FileQueryInfoFlags::NONE,
+            gio::Cancellable::NONE,
+        )?;
+        tmp.set_attributes_from_info(
+            &info,
+            gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+            gio::Cancellable::NONE,
+        )?;
+    }
+
+    if options.preserve_attributes {
+        let result = file
+            .query_info(
+                OPTIONAL_ATTRIBUTES,
+                gio::FileQueryInfoFlags::NONE,
+                gio::Cancellable::NONE,
+            )
+            .and_then(|info| {
+                tmp.set_attributes_from_info(
+                    &info,
+                    gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
+                    gio::Cancellable::NONE,
+                )
+            });
+
+        if let Err(err) = result {
+            tracing::warn!(
+                "Failed to keep extended attributes of '{}': {err}",
+                file.uri()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Keep a copy of `file` with a `~` appended to the name
+pub(crate) fn backup(file: &gio::File) -> Result<(), Error> {
+    file.copy(
+        &backup_file(file)?,
+        gio::FileCopyFlags::OVERWRITE | gio::FileCopyFlags::ALL_METADATA,
+        gio::Cancellable::NONE,
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// Modification time as seconds and microseconds
+pub(crate) fn modification_time(file: &gio::File) -> Result<(u64, u32), Error> {
+    let info = file.query_info(
+        MODIFICATION_TIME_ATTRIBUTES,
+        gio::FileQueryInfoFlags::NONE,
+        gio::Cancellable::NONE,
+    )?;
+
+    Ok((
+        info.attribute_uint64(gio::FILE_ATTRIBUTE_TIME_MODIFIED),
+        info.attribute_uint32(gio::FILE_ATTRIBUTE_TIME_MODIFIED_USEC),
+    ))
+}
+
+pub(crate) fn set_modification_time(
+    file: &gio::File,
+    (secs, usecs): (u64, u32),
+) -> Result<(), Error> {
+    file.set_attribute_uint64(
+        gio::FILE_ATTRIBUTE_TIME_MODIFIED,
+        secs,
+        gio::FileQueryInfoFlags::NONE,
+        gio::Cancellable::NONE,
+    )?;
+    file.set_attribute_uint32(
+        gio::FILE_ATTRIBUTE_TIME_MODIFIED_USEC,
+        usecs,
+        gio::FileQueryInfoFlags::NONE,
+        gio::Cancellable::NONE,
+    )?;
+
+    Ok(())
+}
+
+/// Location of the backup for `file`
+fn backup_file(file: &gio::File) -> Result<gio::File, Error> {
+    let (Some(parent), Some(basename)) = (file.parent(), file.basename()) else {
+        return Err(ErrorKind::NoParentDirectory(file.uri().to_string()).err());
+    };
+
+    let mut name = basename.into_os_string();
+    name.push("~");
+
+    Ok(parent.child(name))
+}
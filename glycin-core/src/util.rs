@@ -65,6 +65,17 @@ pub trait TimeoutFuture<T>: Future<Output = Result<T, crate::Error>> + Sized {
 
 impl<T, F: Future<Output = Result<T, crate::Error>>> TimeoutFuture<T> for F {}
 
+/// Flush file content to disk
+///
+/// Only local files can be synced.
+pub fn sync(file: &gio::File) -> Result<(), crate::Error> {
+    if let Some(path) = file.path() {
+        std::fs::File::open(path)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
 #[cfg(feature = "gdk4")]
 pub const fn gdk_memory_format(format: MemoryFormat) -> gdk::MemoryFormat {
     match format {
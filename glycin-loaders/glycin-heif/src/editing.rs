@@ -1,35 +1,214 @@
+mod isobmff;
+
 use std::io::Read;
 
-use glycin_utils::{ByteData, EditorImplementation, GenericContexts, MemoryFormatInfo};
+use glycin_utils::editing::EditingFrame;
+use glycin_utils::safe_math::*;
+use glycin_utils::{
+    ByteChanges, ByteData, ChromaSubsampling, CompleteEditorOutput, EditorImplementation,
+    ExtendedMemoryFormat, Frame, FrameRequest, FungibleMemory, GenericContexts, MemoryFormatInfo,
+    Operations, ProcessError, SparseEditorOutput, editing,
+};
 use libheif_rs::{
-    Channel, ColorProfileRaw, ColorSpace, CompressionFormat, EncoderQuality, HeifContext, Image,
-    LibHeif, RgbChroma,
+    Channel, ColorProfileNCLX, ColorProfileRaw, ColorSpace, CompressionFormat, Encoder,
+    EncoderParameterValue, EncoderQuality, HeifContext, Image, LibHeif, RgbChroma,
 };
 
+use self::isobmff::{Heif, Transformations};
+
 pub struct ImgEditor {
     mime_type: String,
+    data: Vec<u8>,
+}
+
+impl ImgEditor {
+    /// Transformations of the primary image after applying the operations
+    ///
+    /// Returns `None` if the operations can't be expressed via transformation
+    /// properties.
+    fn transformations(
+        heif: &Heif,
+        operations: &Operations,
+    ) -> Result<Option<Transformations>, ProcessError> {
+        let Some(transformations) = heif.transformations()? else {
+            return Ok(None);
+        };
+
+        isobmff::apply_operations(transformations, operations)
+    }
+
+    /// Decode image, apply operations to the pixels, and encode it again
+    fn apply_reencode<B: ByteData>(
+        &self,
+        operations: &Operations,
+    ) -> Result<CompleteEditorOutput<B>, ProcessError> {
+        let context = HeifContext::read_from_bytes(&self.data).expected_error()?;
+        let handle = context.primary_image_handle().expected_error()?;
+
+        let rgb_chroma = crate::rgb_chroma(&handle);
+        let memory_format = crate::memory_format(&handle, rgb_chroma);
+        let bit_depth = handle.luma_bits_per_pixel();
+
+        let lib_heif = LibHeif::new();
+        let image = lib_heif
+            .decode(&handle, ColorSpace::Rgb(rgb_chroma), None)
+            .expected_error()?;
+        let plane = image.planes().interleaved.expected_error()?;
+
+        let editing_frame = EditingFrame {
+            width: plane.width,
+            height: plane.height,
+            stride: plane.stride.try_u32()?,
+            memory_format: ExtendedMemoryFormat::Basic(memory_format),
+            texture: FungibleMemory::from_vec(plane.data.to_vec()),
+        };
+
+        let editing_frame =
+            editing::apply_operations(editing_frame, operations).expected_error()?;
+
+        let width = editing_frame.width;
+        let height = editing_frame.height;
+
+        let mut new_image =
+            Image::new(width, height, ColorSpace::Rgb(rgb_chroma)).expected_error()?;
+        new_image
+            .create_plane(Channel::Interleaved, width, height, bit_depth)
+            .expected_error()?;
+        new_image.set_premultiplied_alpha(handle.is_premultiplied_alpha());
+
+        if let Some(icc_profile) = handle.color_profile_raw() {
+            new_image
+                .set_color_profile_raw(&icc_profile)
+                .expected_error()?;
+        } else if let Some(nclx) = handle.color_profile_nclx() {
+            new_image.set_color_profile_nclx(&nclx).expected_error()?;
+        }
+
+        let new_plane = new_image.planes_mut().interleaved.internal_error()?;
+        let row_len = (width as usize).smul(memory_format.n_bytes().usize())?;
+        let stride = editing_frame.stride as usize;
+
+        for y in 0..height as usize {
+            let src = editing_frame
+                .texture
+                .get(y.smul(stride)?..y.smul(stride)?.sadd(row_len)?)
+                .expected_error()?;
+            new_plane
+                .data
+                .get_mut(y.smul(new_plane.stride)?..y.smul(new_plane.stride)?.sadd(row_len)?)
+                .expected_error()?
+                .copy_from_slice(src);
+        }
+
+        let mut new_context = HeifContext::new().expected_error()?;
+        let mut encoder = lib_heif
+            .encoder_for_format(compression_format(&self.mime_type)?)
+            .expected_error()?;
+        encoder
+            .set_quality(reencode_quality(&handle))
+            .expected_error()?;
+
+        let new_handle = new_context
+            .encode_image(&new_image, &mut encoder, None)
+            .expected_error()?;
+
+        if let Some(exif) = crate::exif(&handle) {
+            new_context
+                .add_exif_metadata(&new_handle, &exif)
+                .expected_error()?;
+        }
+
+        let mut meta_ids = vec![0; handle.number_of_metadata_blocks(b"mime").try_usize()?];
+        handle.metadata_block_ids(&mut meta_ids, b"mime");
+        for meta_id in meta_ids {
+            if handle.metadata_content_type(meta_id) == Some("application/rdf+xml")
+                && let Ok(xmp) = handle.metadata(meta_id)
+            {
+                new_context
+                    .add_xmp_metadata(&new_handle, &xmp)
+                    .expected_error()?;
+            }
+        }
+
+        let bytes = new_context.write_to_bytes().expected_error()?;
+        let data = B::try_from_vec(bytes).expected_error()?;
+
+        Ok(CompleteEditorOutput::new(data))
+    }
 }
 
 impl EditorImplementation for ImgEditor {
     fn edit<S: Read>(
-        _stream: S,
+        mut stream: S,
         mime_type: String,
         _details: glycin_utils::InitializationDetails,
     ) -> Result<Self, glycin_utils::ProcessError> {
-        Err(glycin_utils::RemoteError::UnsupportedImageFormat(
-            mime_type.clone(),
-        ))
-        .expected_error()
+        // Fail early for unsupported formats
+        compression_format(&mime_type)?;
+
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).internal_error()?;
+
+        Ok(Self { mime_type, data })
+    }
+
+    fn apply_sparse<B: ByteData>(
+        &self,
+        operations: Operations,
+    ) -> Result<SparseEditorOutput<B>, ProcessError> {
+        let heif = Heif::new(self.data.clone())?;
+
+        if let Some(transformations) = Self::transformations(&heif, &operations)?
+            && let Some(byte_changes) = heif.set_transformations_sparse(transformations)?
+        {
+            return Ok(SparseEditorOutput::byte_changes(
+                ByteChanges::from_slice(&byte_changes).with_original_values(&self.data)?,
+            ));
+        }
+
+        Ok(SparseEditorOutput::from(self.apply_complete(operations)?))
     }
 
     fn apply_complete<B: ByteData>(
         &self,
-        _operations: glycin_utils::Operations,
-    ) -> Result<glycin_utils::CompleteEditorOutput<B>, glycin_utils::ProcessError> {
-        Err(glycin_utils::RemoteError::UnsupportedImageFormat(
-            self.mime_type.clone(),
-        ))
-        .expected_error()
+        operations: Operations,
+    ) -> Result<CompleteEditorOutput<B>, ProcessError> {
+        let heif = Heif::new(self.data.clone())?;
+
+        if let Some(transformations) = Self::transformations(&heif, &operations)? {
+            if let Some(byte_changes) = heif.set_transformations_sparse(transformations)? {
+                let mut data = heif.into_inner();
+                ByteChanges::from_slice(&byte_changes)
+                    .apply(&mut data)
+                    .internal_error()?;
+                return CompleteEditorOutput::new_lossless(data);
+            }
+
+            if let Some(data) = heif.set_transformations(transformations)? {
+                return CompleteEditorOutput::new_lossless(data);
+            }
+        }
+
+        // Only the primary image would be kept
+        if heif.has_sequence() {
+            return Err(ProcessError::UnsupportedFeature(String::from(
+                "Re-encoding files with image sequences",
+            )));
+        }
+
+        self.apply_reencode(&operations)
+    }
+
+    fn preview<B: ByteData>(
+        &self,
+        operations: Operations,
+        frame_request: FrameRequest,
+    ) -> Result<Frame<B>, ProcessError> {
+        // Decoding already applies the transformation properties
+        let context = HeifContext::read_from_bytes(&self.data).expected_error()?;
+        let frame = crate::decode::<FungibleMemory>(context, &self.mime_type)?;
+
+        editing::preview_frame(frame, &operations, &frame_request)
     }
 
     fn create<B: ByteData>(
@@ -37,16 +216,61 @@ impl EditorImplementation for ImgEditor {
         mut new_image: glycin_utils::NewImage<B>,
         encoding_options: glycin_utils::EncodingOptions,
     ) -> Result<glycin_utils::EncodedImage<B>, glycin_utils::ProcessError> {
-        let frame = new_image.frames.remove(0);
+        let mut frame = new_image.frames.remove(0).into_fungible();
+
+        // Float data is stored with the highest bit depth the codecs support
+        let target_format = match frame.memory_format {
+            glycin_utils::MemoryFormat::R16g16b16Float
+            | glycin_utils::MemoryFormat::R32g32b32Float => {
+                Some(glycin_utils::MemoryFormat::R16g16b16)
+            }
+            glycin_utils::MemoryFormat::R16g16b16a16Float
+            | glycin_utils::MemoryFormat::R32g32b32a32Float => {
+                Some(glycin_utils::MemoryFormat::R16g16b16a16)
+            }
+            _ => None,
+        };
+
+        if let Some(target_format) = target_format {
+            editing::change_memory_format(&mut frame, target_format).expected_error()?;
+        }
 
         let width = frame.width;
         let height = frame.height;
 
+        let is_hdr = matches!(
+            frame.memory_format,
+            glycin_utils::MemoryFormat::R16g16b16 | glycin_utils::MemoryFormat::R16g16b16a16
+        );
+
+        let bit_depth = match (is_hdr, encoding_options.bit_depth) {
+            (_, Some(8)) | (false, None) => 8,
+            (true, None) => 10,
+            (true, Some(bit_depth @ (10 | 12))) => bit_depth,
+            (_, Some(bit_depth)) => {
+                return Err(ProcessError::expected(&format!(
+                    "Unsupported bit depth {bit_depth} for {:?}",
+                    frame.memory_format
+                )));
+            }
+        };
+
+        // Reduce 16-bit data for 8-bit output
+        if is_hdr && bit_depth == 8 {
+            let target_format = if frame.memory_format.has_alpha() {
+                glycin_utils::MemoryFormat::R8g8b8a8
+            } else {
+                glycin_utils::MemoryFormat::R8g8b8
+            };
+            editing::change_memory_format(&mut frame, target_format).expected_error()?;
+        }
+        let is_hdr = is_hdr && bit_depth > 8;
+
         let heif_chroma = heif_chroma(frame.memory_format).internal_error()?;
         let mut image = Image::new(width, height, ColorSpace::Rgb(heif_chroma)).expected_error()?;
 
         image
-            .create_plane(Channel::Interleaved, width, height, 8)
+            .create_plane(Channel::Interleaved, width, height, bit_depth)
             .expected_error()?;
 
         if let Some(icc_profile) = &frame.details.color_icc_profile {
@@ -58,12 +282,35 @@ impl EditorImplementation for ImgEditor {
                 .expected_error()?;
         }
 
+        let cicp = frame.details.color_cicp;
+        let mut heif_encoding_options = None;
+
+        if cicp.is_some() {
+            // Writes an `nclx` box that is updated with the CICP after encoding
+            image
+                .set_color_profile_nclx(&ColorProfileNCLX::new().internal_error()?)
+                .expected_error()?;
+
+            let mut options = libheif_rs::EncodingOptions::new().expected_error()?;
+            options.set_save_two_colr_boxes_when_icc_and_nclx_available(true);
+            heif_encoding_options = Some(options);
+        }
+
         let plane = image.planes_mut().interleaved.internal_error()?;
-        let new_stride = width as usize * frame.memory_format.n_bytes().usize();
+        let row_len = width as usize * frame.memory_format.n_bytes().usize();
 
         for y in 0..height as usize {
-            for x in 0..new_stride {
-                plane.data[plane.stride * y + x] = frame.texture[y * new_stride + x];
+            let src = &frame.texture[y * frame.stride as usize..][..row_len];
+            let dst = &mut plane.data[plane.stride * y..][..row_len];
+
+            if is_hdr {
+                // Values have to be in the range of the bit depth
+                for (dst, src) in dst.chunks_exact_mut(2).zip(src.chunks_exact(2)) {
+                    let value = u16::from_ne_bytes([src[0], src[1]]) >> (16 - bit_depth);
+                    dst.copy_from_slice(&value.to_ne_bytes());
+                }
+            } else {
+                dst.copy_from_slice(src);
             }
         }
 
@@ -71,38 +318,156 @@ impl EditorImplementation for ImgEditor {
         let lib_heif = LibHeif::new();
         let mut context = HeifContext::new().expected_error()?;
 
-        let format = match mime_type.as_str() {
-            "image/heif" => CompressionFormat::Hevc,
-            "image/avif" => CompressionFormat::Av1,
-            _ => {
-                return Err(glycin_utils::ProcessError::UnsupportedImageFormat(
-                    mime_type,
-                ));
-            }
-        };
+        let format = compression_format(&mime_type)?;
         let mut encoder = lib_heif.encoder_for_format(format).expected_error()?;
 
-        encoder
-            .set_quality(EncoderQuality::Lossy(
-                encoding_options.quality.unwrap_or(90),
-            ))
-            .expected_error()?;
+        let quality = if encoding_options.lossless == Some(true) {
+            EncoderQuality::LossLess
+        } else {
+            EncoderQuality::Lossy(encoding_options.quality.unwrap_or(90))
+        };
+        encoder.set_quality(quality).expected_error()?;
+
+        if let Some(chroma_subsampling) = encoding_options.chroma_subsampling {
+            let chroma = match chroma_subsampling {
+                ChromaSubsampling::Yuv444 => "444",
+                ChromaSubsampling::Yuv422 => "422",
+                ChromaSubsampling::Yuv420 => "420",
+                chroma_subsampling => {
+                    return Err(ProcessError::UnsupportedFeature(format!(
+                        "Chroma subsampling {chroma_subsampling:?}"
+                    )));
+                }
+            };
+            set_encoder_parameter(
+                &encoder,
+                "chroma",
+                EncoderParameterValue::String(chroma.to_string()),
+            )?;
+        }
+
+        if let Some(effort) = encoding_options.effort {
+            let effort = i32::from(effort.min(100));
+            match format {
+                // AV1 encoders use a speed from 0 (slowest) to 9 (fastest)
+                CompressionFormat::Av1 => set_encoder_parameter(
+                    &encoder,
+                    "speed",
+                    EncoderParameterValue::Int(9 - effort * 9 / 100),
+                )?,
+                _ => {
+                    let preset = X265_PRESETS[(effort * 9 / 100) as usize];
+                    set_encoder_parameter(
+                        &encoder,
+                        "preset",
+                        EncoderParameterValue::String(preset.to_string()),
+                    )?;
+                }
+            }
+        }
 
-        context
-            .encode_image(&image, &mut encoder, None)
+        let handle = context
+            .encode_image(&image, &mut encoder, heif_encoding_options)
             .expected_error()?;
 
-        let bytes = context.write_to_bytes().expected_error()?;
+        if let Some(exif) = &new_image.image_info.metadata_exif {
+            context.add_exif_metadata(&handle, exif).expected_error()?;
+        }
+
+        if let Some(xmp) = &new_image.image_info.metadata_xmp {
+            context.add_xmp_metadata(&handle, xmp).expected_error()?;
+        }
+
+        let mut bytes = context.write_to_bytes().expected_error()?;
+
+        if let Some(cicp) = cicp {
+            // The frame's matrix coefficients and range describe the RGB data. The coded
+            // image uses the ones the encoder has chosen for the YCbCr conversion.
+            let nclx = handle.color_profile_nclx().internal_error()?;
+            let cicp = [
+                cicp[0],
+                cicp[1],
+                nclx.matrix_coefficients() as u8,
+                nclx.full_range_flag(),
+            ];
+
+            let mut heif = Heif::new(bytes)?;
+            heif.set_color_cicp(cicp)?;
+            bytes = heif.into_inner();
+        }
+
         let data = B::try_from_vec(bytes).expected_error()?;
 
         Ok(glycin_utils::EncodedImage::new(data))
     }
 }
 
+const X265_PRESETS: [&str; 10] = [
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+];
+
+/// Sets parameter, fails if the encoder plugin doesn't support it
+fn set_encoder_parameter(
+    encoder: &Encoder,
+    name: &str,
+    value: EncoderParameterValue,
+) -> Result<(), ProcessError> {
+    if !encoder.parameters_names().iter().any(|x| x == name) {
+        return Err(ProcessError::UnsupportedFeature(format!(
+            "Encoder parameter '{name}'"
+        )));
+    }
+
+    encoder.set_parameter_value(name, value).expected_error()?;
+
+    Ok(())
+}
+
 fn heif_chroma(memory_format: glycin_utils::MemoryFormat) -> Option<RgbChroma> {
     Some(match memory_format {
         glycin_utils::MemoryFormat::R8g8b8 => RgbChroma::Rgb,
         glycin_utils::MemoryFormat::R8g8b8a8 => RgbChroma::Rgba,
+        #[cfg(target_endian = "little")]
+        glycin_utils::MemoryFormat::R16g16b16 => RgbChroma::HdrRgbLe,
+        #[cfg(target_endian = "big")]
+        glycin_utils::MemoryFormat::R16g16b16 => RgbChroma::HdrRgbBe,
+        #[cfg(target_endian = "little")]
+        glycin_utils::MemoryFormat::R16g16b16a16 => RgbChroma::HdrRgbaLe,
+        #[cfg(target_endian = "big")]
+        glycin_utils::MemoryFormat::R16g16b16a16 => RgbChroma::HdrRgbaBe,
         _ => return None,
     })
 }
+
+/// Quality for re-encoding the primary image
+///
+/// Images that store RGB without a YCbCr transform have been encoded
+/// losslessly and are kept lossless.
+fn reencode_quality(handle: &libheif_rs::ImageHandle) -> EncoderQuality {
+    let is_lossless = handle
+        .color_profile_nclx()
+        .is_some_and(|nclx| nclx.matrix_coefficients() == libheif_rs::MatrixCoefficients::RGB_GBR);
+
+    if is_lossless {
+        EncoderQuality::LossLess
+    } else {
+        EncoderQuality::Lossy(90)
+    }
+}
+
+fn compression_format(mime_type: &str) -> Result<CompressionFormat, ProcessError> {
+    match mime_type {
+        "image/heif" => Ok(CompressionFormat::Hevc),
+        "image/avif" => Ok(CompressionFormat::Av1),
+        _ => Err(ProcessError::UnsupportedImageFormat(mime_type.to_string())),
+    }
+}
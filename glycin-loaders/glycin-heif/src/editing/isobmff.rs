@@ -0,0 +1,902 @@
+//! Minimal ISOBMFF handling for changing HEIF transformation properties
+//!
+//! Only the boxes needed to find and rewrite the `clap`, `irot`, `imir`, and
+//! `colr` properties of the primary item are parsed. Everything else is copied
+//! verbatim.
+
+use std::ops::Range;
+
+use glycin_utils::{GenericContexts, Operation, Operations, ProcessError};
+use gufo_common::orientation::{Orientation, Rotation};
+
+/// Geometric transformations of the primary item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transformations {
+    /// Dimensions of the coded image as given in `ispe`
+    pub image_size: (u32, u32),
+    /// Clean aperture in coordinates of the coded image
+    pub clip: (u32, u32, u32, u32),
+    /// Orientation applied after the clean aperture
+    pub orientation: Orientation,
+}
+
+impl Transformations {
+    pub fn has_clip(&self) -> bool {
+        self.clip != (0, 0, self.image_size.0, self.image_size.1)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BoxInfo {
+    box_type: [u8; 4],
+    /// Complete box including header
+    range: Range<usize>,
+    /// Content after the header
+    content: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Association {
+    essential: bool,
+    property_index: u16,
+}
+
+#[derive(Debug, Clone)]
+struct IpmaEntry {
+    item_id: u32,
+    associations: Vec<Association>,
+}
+
+pub struct Heif {
+    data: Vec<u8>,
+    meta: BoxInfo,
+    iprp: BoxInfo,
+    ipco: BoxInfo,
+    ipma: BoxInfo,
+    iloc: Option<BoxInfo>,
+    properties: Vec<BoxInfo>,
+    ipma_version: u8,
+    ipma_flags: u32,
+    ipma_entries: Vec<IpmaEntry>,
+    primary_item: u32,
+    has_sequence: bool,
+}
+
+const TRANSFORMATIVE: [&[u8; 4]; 3] = [b"clap", b"irot", b"imir"];
+
+impl Heif {
+    pub fn new(data: Vec<u8>) -> Result<Self, ProcessError> {
+        let top_level = boxes(&data, 0..data.len())?;
+
+        let meta = find(&top_level, b"meta").expected_error()?.clone();
+        let has_sequence = find(&top_level, b"moov").is_some();
+
+        // `meta` is a full box
+        let meta_children = boxes(&data, skip(&meta.content, 4)?)?;
+
+        let pitm = find(&meta_children, b"pitm").expected_error()?;
+        let pitm_version = *data.get(pitm.content.start).expected_error()?;
+        let primary_item = if pitm_version == 0 {
+            read_u16(&data, pitm.content.start + 4)? as u32
+        } else {
+            read_u32(&data, pitm.content.start + 4)?
+        };
+
+        let iloc = find(&meta_children, b"iloc").cloned();
+
+        let iprp = find(&meta_children, b"iprp").expected_error()?.clone();
+        let iprp_children = boxes(&data, iprp.content.clone())?;
+        let ipco = find(&iprp_children, b"ipco").expected_error()?.clone();
+        let ipma = find(&iprp_children, b"ipma").expected_error()?.clone();
+
+        let properties = boxes(&data, ipco.content.clone())?;
+
+        let (ipma_version, ipma_flags, ipma_entries) = parse_ipma(&data, &ipma)?;
+
+        Ok(Self {
+            data,
+            meta,
+            iprp,
+            ipco,
+            ipma,
+            iloc,
+            properties,
+            ipma_version,
+            ipma_flags,
+            ipma_entries,
+            primary_item,
+            has_sequence,
+        })
+    }
+
+    /// Whether the file contains an image sequence besides the items
+    pub fn has_sequence(&self) -> bool {
+        self.has_sequence
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn primary_associations(&self) -> Result<&[Association], ProcessError> {
+        Ok(&self
+            .ipma_entries
+            .iter()
+            .find(|x| x.item_id == self.primary_item)
+            .expected_error()?
+            .associations)
+    }
+
+    fn property(&self, index: u16) -> Option<&BoxInfo> {
+        self.properties.get(usize::from(index).checked_sub(1)?)
+    }
+
+    /// Transformative properties of the primary item in the order they are
+    /// applied
+    fn transformative_properties(&self) -> Result<Vec<(u16, &BoxInfo)>, ProcessError> {
+        let mut properties = Vec::new();
+        for association in self.primary_associations()? {
+            let property = self.property(association.property_index).expected_error()?;
+            if TRANSFORMATIVE.contains(&&property.box_type) {
+                properties.push((association.property_index, property));
+            }
+        }
+
+        Ok(properties)
+    }
+
+    fn is_shared(&self, property_index: u16) -> bool {
+        self.ipma_entries.iter().any(|entry| {
+            entry.item_id != self.primary_item
+                && entry
+                    .associations
+                    .iter()
+                    .any(|x| x.property_index == property_index)
+        })
+    }
+
+    /// Current transformations of the primary item
+    ///
+    /// Returns `None` if the transformations can't be represented, for example
+    /// because they are not in the canonical `clap`, `irot`, `imir` order.
+    pub fn transformations(&self) -> Result<Option<Transformations>, ProcessError> {
+        let mut image_size = None;
+        for association in self.primary_associations()? {
+            if let Some(property) = self.property(association.property_index)
+                && &property.box_type == b"ispe"
+            {
+                // Full box with version and flags
+                let width = read_u32(&self.data, property.content.start + 4)?;
+                let height = read_u32(&self.data, property.content.start + 8)?;
+                image_size = Some((width, height));
+            }
+        }
+
+        let Some(image_size) = image_size else {
+            return Ok(None);
+        };
+
+        let mut transformations = Transformations {
+            image_size,
+            clip: (0, 0, image_size.0, image_size.1),
+            orientation: Orientation::Id,
+        };
+
+        let properties = self.transformative_properties()?;
+        let order = properties
+            .iter()
+            .map(|(_, x)| TRANSFORMATIVE.iter().position(|t| *t == &x.box_type))
+            .collect::<Vec<_>>();
+        if !order.is_sorted() || order.windows(2).any(|x| x[0] == x[1]) {
+            return Ok(None);
+        }
+
+        for (_, property) in properties {
+            match &property.box_type {
+                b"clap" => {
+                    let Some(clip) = self.read_clap(property, image_size)? else {
+                        return Ok(None);
+                    };
+                    transformations.clip = clip;
+                }
+                b"irot" => {
+                    let angle = *self.data.get(property.content.start).expected_error()? & 0b11;
+                    transformations.orientation = transformations
+                        .orientation
+                        .add_rotation(rotation_from_angle(angle));
+                }
+                b"imir" => {
+                    // Axis 0 is the vertical axis, mirroring left and right
+                    let axis = *self.data.get(property.content.start).expected_error()? & 0b1;
+                    let operation = if axis == 0 {
+                        Operation::MirrorHorizontally
+                    } else {
+                        Operation::MirrorVertically
+                    };
+                    let mut operations = Operations::new(vec![operation]);
+                    operations.prepend(Operations::new_orientation(transformations.orientation));
+                    transformations.orientation = operations.orientation().internal_error()?;
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Ok(Some(transformations))
+    }
+
+    /// Clean aperture as integer rectangle
+    fn read_clap(
+        &self,
+        clap: &BoxInfo,
+        (image_width, image_height): (u32, u32),
+    ) -> Result<Option<(u32, u32, u32, u32)>, ProcessError> {
+        let start = clap.content.start;
+        let mut values = [0; 8];
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = read_u32(&self.data, start + i * 4)?;
+        }
+
+        let [w_n, w_d, h_n, h_d, x_n, x_d, y_n, y_d] = values;
+
+        let (Some(width), Some(height)) = (
+            integer(i64::from(w_n), i64::from(w_d)),
+            integer(i64::from(h_n), i64::from(h_d)),
+        ) else {
+            return Ok(None);
+        };
+
+        // Offsets are signed, left = horizOff + (imageWidth - clapWidth) / 2
+        let (x_n, x_d) = (i64::from(x_n as i32), i64::from(x_d));
+        let (y_n, y_d) = (i64::from(y_n as i32), i64::from(y_d));
+        let (Some(x), Some(y)) = (
+            integer(2 * x_n + (i64::from(image_width) - width) * x_d, 2 * x_d),
+            integer(2 * y_n + (i64::from(image_height) - height) * y_d, 2 * y_d),
+        ) else {
+            return Ok(None);
+        };
+
+        if x < 0
+            || y < 0
+            || width <= 0
+            || height <= 0
+            || x + width > i64::from(image_width)
+            || y + height > i64::from(image_height)
+        {
+            return Ok(None);
+        }
+
+        Ok(Some((x as u32, y as u32, width as u32, height as u32)))
+    }
+
+    /// Byte changes that set the transformations without changing the file
+    /// size
+    ///
+    /// Returns `None` if properties would have to be added or removed.
+    pub fn set_transformations_sparse(
+        &self,
+        transformations: Transformations,
+    ) -> Result<Option<Vec<(u64, u8)>>, ProcessError> {
+        let mut clap = None;
+        let mut irot = None;
+        let mut imir = None;
+        for (index, property) in self.transformative_properties()? {
+            if self.is_shared(index) {
+                return Ok(None);
+            }
+            match &property.box_type {
+                b"clap" => clap = Some(property),
+                b"irot" => irot = Some(property),
+                b"imir" => imir = Some(property),
+                _ => unreachable!(),
+            }
+        }
+
+        let mut changes = Vec::new();
+
+        if let Some(clap) = clap {
+            let new_clap = clap_content(transformations);
+            for (i, value) in new_clap.into_iter().enumerate() {
+                changes.push(((clap.content.start + i) as u64, value));
+            }
+        } else if transformations.has_clip() {
+            return Ok(None);
+        }
+
+        let (angle, axis) = match (transformations.orientation.mirror(), imir.is_some()) {
+            (false, false) => (angle(transformations.orientation.rotate()), None),
+            (true, true) => {
+                let rotation = transformations.orientation.rotate();
+                // Mirroring on the horizontal axis is the same as on the
+                // vertical axis combined with a 180° rotation
+                if irot.is_some() || rotation == Rotation::_0 {
+                    (angle(Rotation::_0 - rotation), Some(0))
+                } else {
+                    (angle(Rotation::_180 - rotation), Some(1))
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        match irot {
+            Some(irot) => changes.push((irot.content.start as u64, angle)),
+            None if angle == 0 => {}
+            None => return Ok(None),
+        }
+
+        if let (Some(imir), Some(axis)) = (imir, axis) {
+            changes.push((imir.content.start as u64, axis));
+        }
+
+        Ok(Some(changes))
+    }
+
+    /// Rewrite the file with new transformation properties for the primary
+    /// item
+    ///
+    /// Returns `None` if the file layout doesn't allow rewriting it. Files
+    /// that contain an image sequence are refused with
+    /// [`ProcessError::UnsupportedFeature`] since re-encoding would drop the
+    /// sequence.
+    pub fn set_transformations(
+        &self,
+        transformations: Transformations,
+    ) -> Result<Option<Vec<u8>>, ProcessError> {
+        // Track sample offsets would have to be changed as well
+        if self.has_sequence {
+            return Err(ProcessError::UnsupportedFeature(String::from(
+                "Changing the layout of files with image sequences",
+            )));
+        }
+
+        // The rewritten `meta` box always uses a compact header
+        if self.meta.content.start - self.meta.range.start != 8 {
+            return Ok(None);
+        }
+
+        let data = &self.data;
+
+        let mut new_properties = Vec::new();
+
+        if transformations.has_clip() {
+            new_properties.push(new_box(b"clap", &clap_content(transformations)));
+        }
+
+        let orientation = transformations.orientation;
+        let rotation = if orientation.mirror() {
+            Rotation::_0 - orientation.rotate()
+        } else {
+            orientation.rotate()
+        };
+        if rotation != Rotation::_0 {
+            new_properties.push(new_box(b"irot", &[angle(rotation)]));
+        }
+        if orientation.mirror() {
+            new_properties.push(new_box(b"imir", &[0]));
+        }
+
+        // Properties are referenced with one based indices
+        let first_new_index = self.properties.len() + 1;
+        let last_new_index = first_new_index + new_properties.len();
+        if last_new_index > 0x7fff {
+            return Ok(None);
+        }
+
+        let mut ipma_flags = self.ipma_flags;
+        if last_new_index > 0x7f {
+            ipma_flags |= 1;
+        }
+
+        let mut ipma_entries = self.ipma_entries.clone();
+        let primary = ipma_entries
+            .iter_mut()
+            .find(|x| x.item_id == self.primary_item)
+            .expected_error()?;
+
+        // Drop the old transformations and append the new ones at the end since
+        // transformative properties have to follow descriptive ones
+        let properties = &self.properties;
+        primary.associations.retain(|x| {
+            usize::from(x.property_index)
+                .checked_sub(1)
+                .and_then(|i| properties.get(i))
+                .is_none_or(|x| !TRANSFORMATIVE.contains(&&x.box_type))
+        });
+        for i in 0..new_properties.len() {
+            primary.associations.push(Association {
+                essential: true,
+                property_index: (first_new_index + i) as u16,
+            });
+        }
+
+        if primary.associations.len() > u8::MAX as usize {
+            return Ok(None);
+        }
+
+        let mut ipco_content = data[self.ipco.content.clone()].to_vec();
+        for property in new_properties {
+            ipco_content.extend_from_slice(&property);
+        }
+        let new_ipco = new_box(b"ipco", &ipco_content);
+
+        let new_ipma = ipma_box(self.ipma_version, ipma_flags, &ipma_entries);
+
+        let new_iprp = replace_children(
+            data,
+            &self.iprp,
+            0,
+            &[(&self.ipco.range, new_ipco), (&self.ipma.range, new_ipma)],
+        )?;
+
+        let delta = new_iprp.len() as i64 - self.iprp.range.len() as i64;
+
+        let mut replacements = vec![(&self.iprp.range, new_iprp)];
+
+        if let Some(iloc) = &self.iloc {
+            let Some(new_iloc) = shift_iloc(data, iloc, self.meta.range.end, delta)? else {
+                return Ok(None);
+            };
+            replacements.push((&iloc.range, new_iloc));
+        }
+
+        let new_meta = replace_children(data, &self.meta, 4, &replacements)?;
+
+        let mut new_data = Vec::with_capacity(data.len() + new_meta.len());
+        new_data.extend_from_slice(&data[..self.meta.range.start]);
+        new_data.extend_from_slice(&new_meta);
+        new_data.extend_from_slice(&data[self.meta.range.end..]);
+
+        Ok(Some(new_data))
+    }
+
+    /// Overwrite the `nclx` color information of the primary item
+    ///
+    /// The matrix coefficients and the range have to match the coded image.
+    pub fn set_color_cicp(&mut self, cicp: [u8; 4]) -> Result<(), ProcessError> {
+        let content = self
+            .primary_associations()?
+            .iter()
+            .filter_map(|x| self.property(x.property_index))
+            .find(|x| {
+                &x.box_type == b"colr"
+                    && self.data.get(x.content.start..x.content.start + 4)
+                        == Some(b"nclx".as_slice())
+            })
+            .expected_error()?
+            .content
+            .clone();
+
+        let pos = skip(&content, 4)?.start;
+        if content.end < pos + 7 {
+            return Err(ProcessError::expected(&"Invalid size for box colr"));
+        }
+
+        self.data[pos..pos + 2].copy_from_slice(&u16::from(cicp[0]).to_be_bytes());
+        self.data[pos + 2..pos + 4].copy_from_slice(&u16::from(cicp[1]).to_be_bytes());
+        self.data[pos + 4..pos + 6].copy_from_slice(&u16::from(cicp[2]).to_be_bytes());
+        // Full range flag followed by 7 reserved bits
+        self.data[pos + 6] = cicp[3] << 7;
+
+        Ok(())
+    }
+}
+
+/// Apply operations to existing transformations
+///
+/// Returns `None` if an operation is not supported.
+pub fn apply_operations(
+    transformations: Transformations,
+    operations: &Operations,
+) -> Result<Option<Transformations>, ProcessError> {
+    let mut all_operations = operations.clone();
+    all_operations.prepend(Operations::new_orientation(transformations.orientation));
+    all_operations.prepend(Operations::new(vec![Operation::Clip(transformations.clip)]));
+
+    let normalized = all_operations
+        .normalize(transformations.image_size)
+        .expected_error()?;
+
+    let (clip, orientation_operations) = match normalized.operations() {
+        [Operation::Clip(clip), rest @ ..] => (*clip, rest),
+        rest => (
+            (
+                0,
+                0,
+                transformations.image_size.0,
+                transformations.image_size.1,
+            ),
+            rest,
+        ),
+    };
+
+    let Some(orientation) = Operations::new(orientation_operations.to_vec()).orientation() else {
+        return Ok(None);
+    };
+
+    Ok(Some(Transformations {
+        clip,
+        orientation,
+        ..transformations
+    }))
+}
+
+fn rotation_from_angle(angle: u8) -> Rotation {
+    match angle {
+        1 => Rotation::_90,
+        2 => Rotation::_180,
+        3 => Rotation::_270,
+        _ => Rotation::_0,
+    }
+}
+
+fn angle(rotation: Rotation) -> u8 {
+    (rotation.degrees() / 90) as u8
+}
+
+/// Content of a `clap` box for an integer rectangle
+fn clap_content(transformations: Transformations) -> Vec<u8> {
+    let (image_width, image_height) = transformations.image_size;
+    let (x, y, width, height) = transformations.clip;
+
+    // horizOff = x + (clapWidth - imageWidth) / 2
+    let horizontal_offset = 2 * x as i64 + width as i64 - image_width as i64;
+    let vertical_offset = 2 * y as i64 + height as i64 - image_height as i64;
+
+    let mut content = Vec::with_capacity(32);
+    content.extend_from_slice(&width.to_be_bytes());
+    content.extend_from_slice(&1_u32.to_be_bytes());
+    content.extend_from_slice(&height.to_be_bytes());
+    content.extend_from_slice(&1_u32.to_be_bytes());
+    content.extend_from_slice(&(horizontal_offset as i32).to_be_bytes());
+    content.extend_from_slice(&2_u32.to_be_bytes());
+    content.extend_from_slice(&(vertical_offset as i32).to_be_bytes());
+    content.extend_from_slice(&2_u32.to_be_bytes());
+    content
+}
+
+fn integer(numerator: i64, denominator: i64) -> Option<i64> {
+    if denominator == 0 || numerator % denominator != 0 {
+        None
+    } else {
+        Some(numerator / denominator)
+    }
+}
+
+fn find<'a>(boxes: &'a [BoxInfo], box_type: &[u8; 4]) -> Option<&'a BoxInfo> {
+    boxes.iter().find(|x| &x.box_type == box_type)
+}
+
+fn skip(range: &Range<usize>, n: usize) -> Result<Range<usize>, ProcessError> {
+    let start = range.start.checked_add(n).expected_error()?;
+    if start > range.end {
+        return Err(ProcessError::expected(&"Box too short"));
+    }
+    Ok(start..range.end)
+}
+
+fn boxes(data: &[u8], range: Range<usize>) -> Result<Vec<BoxInfo>, ProcessError> {
+    let mut boxes = Vec::new();
+    let mut pos = range.start;
+
+    while pos < range.end {
+        let size = read_u32(data, pos)? as u64;
+        let box_type: [u8; 4] = data
+            .get(pos + 4..pos + 8)
+            .expected_error()?
+            .try_into()
+            .internal_error()?;
+
+        let (size, header_len) = match size {
+            0 => ((range.end - pos) as u64, 8),
+            1 => (read_u64(data, pos + 8)?, 16),
+            size => (size, 8),
+        };
+
+        let end = pos
+            .checked_add(usize::try_from(size).expected_error()?)
+            .expected_error()?;
+        if end > range.end || size < header_len {
+            return Err(ProcessError::expected(&format!(
+                "Invalid size for box {}",
+                String::from_utf8_lossy(&box_type)
+            )));
+        }
+
+        boxes.push(BoxInfo {
+            box_type,
+            range: pos..end,
+            content: pos + header_len as usize..end,
+        });
+
+        pos = end;
+    }
+
+    Ok(boxes)
+}
+
+fn parse_ipma(data: &[u8], ipma: &BoxInfo) -> Result<(u8, u32, Vec<IpmaEntry>), ProcessError> {
+    let mut pos = ipma.content.start;
+    let version_flags = read_u32(data, pos)?;
+    let version = (version_flags >> 24) as u8;
+    let flags = version_flags & 0xffffff;
+    pos += 4;
+
+    if version > 1 {
+        return Err(ProcessError::expected(&format!(
+            "Unsupported ipma version {version}"
+        )));
+    }
+
+    let entry_count = read_u32(data, pos)?;
+    pos += 4;
+
+    let mut entries = Vec::new();
+    for _ in 0..entry_count {
+        let item_id = if version < 1 {
+            pos += 2;
+            read_u16(data, pos - 2)? as u32
+        } else {
+            pos += 4;
+            read_u32(data, pos - 4)?
+        };
+
+        let association_count = *data.get(pos).expected_error()?;
+        pos += 1;
+
+        let mut associations = Vec::new();
+        for _ in 0..association_count {
+            let association = if flags & 1 == 1 {
+                pos += 2;
+                let value = read_u16(data, pos - 2)?;
+                Association {
+                    essential: value & 0x8000 != 0,
+                    property_index: value & 0x7fff,
+                }
+            } else {
+                pos += 1;
+                let value = *data.get(pos - 1).expected_error()?;
+                Association {
+                    essential: value & 0x80 != 0,
+                    property_index: u16::from(value & 0x7f),
+                }
+            };
+            associations.push(association);
+        }
+
+        entries.push(IpmaEntry {
+            item_id,
+            associations,
+        });
+    }
+
+    Ok((version, flags, entries))
+}
+
+fn ipma_box(version: u8, flags: u32, entries: &[IpmaEntry]) -> Vec<u8> {
+    let mut content = Vec::new();
+    content.extend_from_slice(&((u32::from(version) << 24) | flags).to_be_bytes());
+    content.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+
+    for entry in entries {
+        if version < 1 {
+            content.extend_from_slice(&(entry.item_id as u16).to_be_bytes());
+        } else {
+            content.extend_from_slice(&entry.item_id.to_be_bytes());
+        }
+
+        content.push(entry.associations.len() as u8);
+
+        for association in &entry.associations {
+            if flags & 1 == 1 {
+                let value = (u16::from(association.essential) << 15) | association.property_index;
+                content.extend_from_slice(&value.to_be_bytes());
+            } else {
+                let value =
+                    (u8::from(association.essential) << 7) | association.property_index as u8;
+                content.push(value);
+            }
+        }
+    }
+
+    new_box(b"ipma", &content)
+}
+
+/// Shift file offsets in `iloc` that point behind the `meta` box
+///
+/// Returns `None` if offsets can't be represented.
+fn shift_iloc(
+    data: &[u8],
+    iloc: &BoxInfo,
+    meta_end: usize,
+    delta: i64,
+) -> Result<Option<Vec<u8>>, ProcessError> {
+    let mut new_iloc = data[iloc.range.clone()].to_vec();
+
+    let mut pos = iloc.content.start;
+    let version = *data.get(pos).expected_error()?;
+    pos += 4;
+
+    if version > 2 {
+        return Err(ProcessError::expected(&format!(
+            "Unsupported iloc version {version}"
+        )));
+    }
+
+    let sizes = read_u16(data, pos)?;
+    pos += 2;
+    let offset_size = usize::from(sizes >> 12);
+    let length_size = usize::from((sizes >> 8) & 0xf);
+    let base_offset_size = usize::from((sizes >> 4) & 0xf);
+    let index_size = if version > 0 {
+        usize::from(sizes & 0xf)
+    } else {
+        0
+    };
+
+    let item_count = if version < 2 {
+        pos += 2;
+        read_u16(data, pos - 2)? as u32
+    } else {
+        pos += 4;
+        read_u32(data, pos - 4)?
+    };
+
+    let meta_end = meta_end as u64;
+
+    for _ in 0..item_count {
+        // Item ID
+        pos += if version < 2 { 2 } else { 4 };
+
+        let construction_method = if version > 0 {
+            pos += 2;
+            read_u16(data, pos - 2)? & 0xf
+        } else {
+            0
+        };
+
+        let data_reference_index = read_u16(data, pos)?;
+        pos += 2;
+
+        let base_offset_pos = pos;
+        let base_offset = read_sized(data, pos, base_offset_size)?;
+        pos += base_offset_size;
+
+        // Only offsets into this file are affected
+        let in_file = construction_method == 0 && data_reference_index == 0;
+        let shift_base = in_file && base_offset_size > 0 && base_offset >= meta_end;
+        if shift_base {
+            let Some(value) = shifted(base_offset, delta, base_offset_size) else {
+                return Ok(None);
+            };
+            write_sized(
+                &mut new_iloc,
+                base_offset_pos - iloc.range.start,
+                base_offset_size,
+                value,
+            );
+        }
+
+        let extent_count = read_u16(data, pos)?;
+        pos += 2;
+
+        for _ in 0..extent_count {
+            pos += index_size;
+
+            let extent_offset_pos = pos;
+            let extent_offset = read_sized(data, pos, offset_size)?;
+            pos += offset_size;
+            pos += length_size;
+
+            if in_file && !shift_base && base_offset + extent_offset >= meta_end {
+                let Some(value) = shifted(extent_offset, delta, offset_size) else {
+                    return Ok(None);
+                };
+                if offset_size == 0 {
+                    return Ok(None);
+                }
+                write_sized(
+                    &mut new_iloc,
+                    extent_offset_pos - iloc.range.start,
+                    offset_size,
+                    value,
+                );
+            }
+        }
+    }
+
+    if pos > iloc.range.end {
+        return Err(ProcessError::expected(&"Invalid iloc box"));
+    }
+
+    Ok(Some(new_iloc))
+}
+
+fn shifted(value: u64, delta: i64, size: usize) -> Option<u64> {
+    let value = value.checked_add_signed(delta)?;
+    if size == 4 && value > u32::MAX as u64 {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Rebuild a box with some of its children replaced
+fn replace_children(
+    data: &[u8],
+    parent: &BoxInfo,
+    full_box_header: usize,
+    replacements: &[(&Range<usize>, Vec<u8>)],
+) -> Result<Vec<u8>, ProcessError> {
+    let children_range = skip(&parent.content, full_box_header)?;
+
+    let mut content = data[parent.content.start..children_range.start].to_vec();
+    for child in boxes(data, children_range)? {
+        if let Some((_, new_child)) = replacements.iter().find(|(x, _)| **x == child.range) {
+            content.extend_from_slice(new_child);
+        } else {
+            content.extend_from_slice(&data[child.range]);
+        }
+    }
+
+    Ok(new_box(&parent.box_type, &content))
+}
+
+fn new_box(box_type: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut new_box = Vec::with_capacity(content.len() + 16);
+    let size = content.len() + 8;
+    if let Ok(size) = u32::try_from(size) {
+        new_box.extend_from_slice(&size.to_be_bytes());
+        new_box.extend_from_slice(box_type);
+    } else {
+        new_box.extend_from_slice(&1_u32.to_be_bytes());
+        new_box.extend_from_slice(box_type);
+        new_box.extend_from_slice(&(size as u64 + 8).to_be_bytes());
+    }
+    new_box.extend_from_slice(content);
+    new_box
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, ProcessError> {
+    Ok(u16::from_be_bytes(
+        data.get(pos..pos + 2)
+            .expected_error()?
+            .try_into()
+            .internal_error()?,
+    ))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, ProcessError> {
+    Ok(u32::from_be_bytes(
+        data.get(pos..pos + 4)
+            .expected_error()?
+            .try_into()
+            .internal_error()?,
+    ))
+}
+
+fn read_u64(data: &[u8], pos: usize) -> Result<u64, ProcessError> {
+    Ok(u64::from_be_bytes(
+        data.get(pos..pos + 8)
+            .expected_error()?
+            .try_into()
+            .internal_error()?,
+    ))
+}
+
+fn read_sized(data: &[u8], pos: usize, size: usize) -> Result<u64, ProcessError> {
+    match size {
+        0 => Ok(0),
+        4 => read_u32(data, pos).map(u64::from),
+        8 => read_u64(data, pos),
+        size => Err(ProcessError::expected(&format!(
+            "Unsupported field size {size}"
+        ))),
+    }
+}
+
+fn write_sized(data: &mut [u8], pos: usize, size: usize, value: u64) {
+    match size {
+        4 => data[pos..pos + 4].copy_from_slice(&(value as u32).to_be_bytes()),
+        8 => data[pos..pos + 8].copy_from_slice(&value.to_be_bytes()),
+        _ => {}
+    }
+}
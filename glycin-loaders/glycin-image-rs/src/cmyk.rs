@@ -0,0 +1,238 @@
+//! Conversion between RGB and CMYK via ICC profiles
+//!
+//! The image-rs decoders only invert CMYK values, which does not resemble the
+//! printed colors. CMYK images with an embedded profile are therefore decoded
+//! here and converted to sRGB.
+//!
+//! CMYK textures use the same layout as `R8g8b8a8`, with C, M, Y, and K in
+//! place of the four channels.
+
+use std::io::Cursor;
+
+use glycin_utils::safe_math::{SafeConversion, SafeMath};
+use glycin_utils::*;
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use tiff::decoder::DecodingResult;
+use zune_jpeg::zune_core::colorspace::ColorSpace;
+use zune_jpeg::zune_core::options::DecoderOptions;
+
+fn profile(icc_profile: &[u8], color_space: DataColorSpace) -> Option<ColorProfile> {
+    ColorProfile::new_from_slice(icc_profile)
+        .ok()
+        .filter(|x| x.color_space == color_space)
+}
+
+fn cmyk_profile(icc_profile: &[u8]) -> Result<ColorProfile, ProcessError> {
+    profile(icc_profile, DataColorSpace::Cmyk)
+        .ok_or_else(|| ProcessError::expected(&"ICC profile does not describe a CMYK color space"))
+}
+
+/// Convert the frame to 8-bit CMYK with `icc_profile` as target
+///
+/// The frame's ICC profile is used as source if it describes an RGB color
+/// space, otherwise the frame is assumed to be sRGB. The target profile is
+/// attached to the returned frame.
+pub fn from_rgb<B: ByteData>(
+    frame: &Frame<B>,
+    icc_profile: &[u8],
+) -> Result<Frame<FungibleMemory>, ProcessError> {
+    let target_profile = cmyk_profile(icc_profile)?;
+
+    let src_profile = frame
+        .details
+        .color_icc_profile
+        .as_ref()
+        .and_then(|x| profile(x, DataColorSpace::Rgb))
+        .unwrap_or_else(ColorProfile::new_srgb);
+
+    let mut rgb_frame = Frame::new(
+        frame.width,
+        frame.height,
+        frame.memory_format,
+        FungibleMemory::try_from_slice(&frame.texture).expected_error()?,
+    )
+    .expected_error()?;
+    rgb_frame.stride = frame.stride;
+    editing::change_memory_format(&mut rgb_frame, MemoryFormat::R8g8b8).expected_error()?;
+
+    let transform = src_profile
+        .create_transform_8bit(
+            Layout::Rgb,
+            &target_profile,
+            Layout::Rgba,
+            TransformOptions::default(),
+        )
+        .expected_error()?;
+
+    let width = rgb_frame.width.try_usize()?;
+    let mut cmyk = vec![0; width.smul(4)?.smul(rgb_frame.height.try_usize()?)?];
+
+    for (y, cmyk_row) in cmyk.chunks_exact_mut(width.smul(4)?).enumerate() {
+        let start = y.smul(rgb_frame.stride.try_usize()?)?;
+        let row = rgb_frame
+            .texture
+            .get(start..start.sadd(width.smul(3)?)?)
+            .expected_error()?;
+
+        transform.transform(row, cmyk_row).expected_error()?;
+    }
+
+    let mut cmyk_frame = Frame::new(
+        frame.width,
+        frame.height,
+        MemoryFormat::R8g8b8a8,
+        FungibleMemory::try_from_vec(cmyk).expected_error()?,
+    )
+    .expected_error()?;
+
+    cmyk_frame.details.pixel_density = frame.details.pixel_density.clone();
+    cmyk_frame.details.color_icc_profile =
+        Some(FungibleMemory::try_from_slice(icc_profile).expected_error()?);
+
+    Ok(cmyk_frame)
+}
+
+fn jpeg_colorspace(data: &[u8]) -> Option<ColorSpace> {
+    let options = DecoderOptions::new_fast()
+        .set_max_width(usize::MAX)
+        .set_max_height(usize::MAX);
+    let mut decoder = zune_jpeg::JpegDecoder::new_with_options(Cursor::new(data), options);
+    decoder.decode_headers().ok()?;
+    decoder.input_colorspace()
+}
+
+/// Whether the image stores CMYK that is described by the ICC profile
+pub fn is_cmyk(
+    mime_type: &str,
+    data: &[u8],
+    original_color_type: image::ExtendedColorType,
+    icc_profile: &[u8],
+) -> bool {
+    let stores_cmyk = match mime_type {
+        "image/jpeg" => matches!(
+            jpeg_colorspace(data),
+            Some(ColorSpace::CMYK | ColorSpace::YCCK)
+        ),
+        "image/tiff" => matches!(
+            original_color_type,
+            image::ExtendedColorType::Cmyk8 | image::ExtendedColorType::Cmyk16
+        ),
+        _ => false,
+    };
+
+    stores_cmyk && profile(icc_profile, DataColorSpace::Cmyk).is_some()
+}
+
+/// Decode the CMYK image and convert it to sRGB via the ICC profile
+pub fn frame<B: ByteData>(
+    mime_type: &str,
+    data: &[u8],
+    icc_profile: &[u8],
+) -> Result<Frame<B>, ProcessError> {
+    let src_profile = cmyk_profile(icc_profile)?;
+
+    let (width, height, texture, memory_format) = match mime_type {
+        "image/jpeg" => {
+            let (width, height, cmyk) = jpeg_cmyk(data)?;
+            let texture = to_srgb_8bit(&src_profile, &cmyk)?;
+            (width, height, texture, MemoryFormat::R8g8b8)
+        }
+        "image/tiff" => {
+            let mut decoder = tiff::decoder::Decoder::new(Cursor::new(data))
+                .expected_error()?
+                .with_limits(tiff::decoder::Limits::unlimited());
+            let (width, height) = decoder.dimensions().expected_error()?;
+
+            match decoder.read_image().expected_error()? {
+                DecodingResult::U8(cmyk) => (
+                    width,
+                    height,
+                    to_srgb_8bit(&src_profile, &cmyk)?,
+                    MemoryFormat::R8g8b8,
+                ),
+                DecodingResult::U16(cmyk) => (
+                    width,
+                    height,
+                    to_srgb_16bit(&src_profile, &cmyk)?,
+                    MemoryFormat::R16g16b16,
+                ),
+                _ => return Err(ProcessError::expected(&"Unsupported CMYK sample type")),
+            }
+        }
+        mime_type => return Err(ProcessError::UnsupportedImageFormat(mime_type.to_string())),
+    };
+
+    Frame::new(
+        width,
+        height,
+        memory_format,
+        B::try_from_vec(texture).expected_error()?,
+    )
+    .expected_error()
+}
+
+/// Decode the JPEG into CMYK ink values
+fn jpeg_cmyk(data: &[u8]) -> Result<(u32, u32, Vec<u8>), ProcessError> {
+    let colorspace = jpeg_colorspace(data).expected_error()?;
+
+    // Decode the stored values without any conversion
+    let options = DecoderOptions::new_fast()
+        .jpeg_set_out_colorspace(colorspace)
+        .set_max_width(usize::MAX)
+        .set_max_height(usize::MAX);
+    let mut decoder = zune_jpeg::JpegDecoder::new_with_options(Cursor::new(data), options);
+    let mut pixels = decoder.decode().expected_error()?;
+    let info = decoder.info().expected_error()?;
+
+    // Adobe applications store inverted CMYK values, which is what other
+    // applications follow as well
+    for pixel in pixels.chunks_exact_mut(4) {
+        if colorspace == ColorSpace::YCCK {
+            let [y, cb, cr] = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+            pixel[0] = (y + 1.402 * (cr - 128.)).round().clamp(0., 255.) as u8;
+            pixel[1] = (y - 0.344136 * (cb - 128.) - 0.714136 * (cr - 128.))
+                .round()
+                .clamp(0., 255.) as u8;
+            pixel[2] = (y + 1.772 * (cb - 128.)).round().clamp(0., 255.) as u8;
+            pixel[3] = 255 - pixel[3];
+        } else {
+            for value in pixel {
+                *value = 255 - *value;
+            }
+        }
+    }
+
+    Ok((u32::from(info.width), u32::from(info.height), pixels))
+}
+
+fn to_srgb_8bit(src_profile: &ColorProfile, cmyk: &[u8]) -> Result<Vec<u8>, ProcessError> {
+    let transform = src_profile
+        .create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .expected_error()?;
+
+    let mut rgb = vec![0; (cmyk.len() / 4).smul(3)?];
+    transform.transform(cmyk, &mut rgb).expected_error()?;
+
+    Ok(rgb)
+}
+
+fn to_srgb_16bit(src_profile: &ColorProfile, cmyk: &[u16]) -> Result<Vec<u8>, ProcessError> {
+    let transform = src_profile
+        .create_transform_16bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .expected_error()?;
+
+    let mut rgb = vec![0_u16; (cmyk.len() / 4).smul(3)?];
+    transform.transform(cmyk, &mut rgb).expected_error()?;
+
+    Ok(bytemuck::cast_slice(&rgb).to_vec())
+}
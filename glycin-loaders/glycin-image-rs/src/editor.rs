@@ -1,26 +1,50 @@
+mod animated;
+mod dds;
+mod exr;
+mod gif;
+mod ico;
 mod jpeg;
 mod png;
 mod tiff;
+mod vp8;
+mod webp;
 
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
 
 use glycin_utils::*;
-use image::{ExtendedColorType, ImageFormat};
+use image::{ExtendedColorType, ImageEncoder, ImageFormat};
 
 pub enum ImgEditor {
     Png(png::EditorPng),
     Jpeg(jpeg::EditJpeg),
+    Gif(gif::EditGif),
+    WebP(webp::EditWebP),
+    Animated(animated::EditAnimated),
 }
 
 impl EditorImplementation for ImgEditor {
     fn edit<S: Read>(
-        stream: S,
+        mut stream: S,
         mime_type: String,
         _details: InitializationDetails,
     ) -> Result<Self, ProcessError> {
         Ok(match mime_type.as_str() {
-            "image/png" => Self::Png(png::load(stream)?),
             "image/jpeg" => Self::Jpeg(jpeg::load(stream)?),
+            "image/png" | "image/gif" | "image/webp" => {
+                let image_format = image_format(&mime_type)?;
+                let mut data = Vec::new();
+                stream.read_to_end(&mut data).internal_error()?;
+
+                if animated::is_animated(image_format, &data)? {
+                    Self::Animated(animated::load(image_format, data))
+                } else {
+                    match image_format {
+                        ImageFormat::Gif => Self::Gif(gif::load(data)),
+                        ImageFormat::WebP => Self::WebP(webp::load(data)),
+                        _ => Self::Png(png::load(Cursor::new(data))?),
+                    }
+                }
+            }
             mime_type => return Err(ProcessError::UnsupportedImageFormat(mime_type.to_string())),
         })
     }
@@ -44,6 +68,23 @@ impl EditorImplementation for ImgEditor {
         match self {
             Self::Png(png) => png::apply(png, operations),
             Self::Jpeg(jpeg) => jpeg::apply_complete(jpeg, operations),
+            Self::Gif(gif) => gif::apply(gif, operations),
+            Self::WebP(webp) => webp::apply(webp, operations),
+            Self::Animated(animated) => animated::apply(animated, operations),
+        }
+    }
+
+    fn preview<B: ByteData>(
+        &self,
+        operations: Operations,
+        frame_request: FrameRequest,
+    ) -> Result<Frame<B>, ProcessError> {
+        match self {
+            Self::Png(png) => png::preview(png, operations, frame_request),
+            Self::Jpeg(jpeg) => jpeg::preview(jpeg, operations, frame_request),
+            Self::Gif(gif) => gif::preview(gif, operations, frame_request),
+            Self::WebP(webp) => webp::preview(webp, operations, frame_request),
+            Self::Animated(animated) => animated::preview(animated, operations, frame_request),
         }
     }
 
@@ -55,10 +96,61 @@ impl EditorImplementation for ImgEditor {
         if new_image.frames.is_empty() {
             return Err(ProcessError::expected(&"No frames passed."));
         }
-        let frame = new_image.frames.remove(0);
+
+        // Icons and cursors store every frame as a separate resolution
+        let ico_kind = match mime_type.as_str() {
+            "image/vnd.microsoft.icon" => Some(ico::Kind::Icon),
+            "image/x-win-bitmap" => Some(ico::Kind::Cursor),
+            _ => None,
+        };
+        if let Some(kind) = ico_kind {
+            let data = ico::create(kind, new_image, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
+
+        // Textures are compressed in blocks without an image-rs encoder
+        if matches!(mime_type.as_str(), "image/vnd.ms-dds" | "image/x-dds") {
+            let data = dds::create(new_image, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
 
         let image_format = image_format(&mime_type)?;
 
+        if new_image.frames.len() > 1
+            && matches!(
+                image_format,
+                ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP
+            )
+        {
+            let data = animated::create(image_format, new_image, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
+
+        // TIFF stores each frame as a separate page
+        if image_format == ImageFormat::Tiff {
+            let data = tiff::create(&new_image, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
+
+        // OpenEXR stores each frame as a separate layer
+        if image_format == ImageFormat::OpenExr {
+            let data = exr::create(&new_image, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
+
+        let frame = new_image.frames.remove(0);
+
+        // WebP is encoded here to support all metadata and GIF to quantize the colors
+        if image_format == ImageFormat::Gif {
+            let data = gif::create(frame, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
+
+        if image_format == ImageFormat::WebP {
+            let data = webp::create(&new_image.image_info, frame, &encoding_options)?;
+            return Ok(EncodedImage::new(B::try_from_vec(data).expected_error()?));
+        }
+
         let frame = frame.into_fungible();
 
         let memory_format = image_memory_format(frame.memory_format)?;
@@ -73,27 +165,116 @@ impl EditorImplementation for ImgEditor {
                 memory_format,
                 icc_profile,
             )?,
-            ImageFormat::Jpeg => jpeg::create(frame, encoding_options, icc_profile)?,
-            ImageFormat::Tiff => tiff::create(frame)?,
+            ImageFormat::Jpeg => {
+                jpeg::create(&new_image.image_info, frame, encoding_options, icc_profile)?
+            }
             _ => {
-                let mut cur = Cursor::new(Vec::new());
-                image::write_buffer_with_format(
-                    &mut cur,
-                    &frame.texture,
-                    frame.width,
-                    frame.height,
-                    memory_format,
-                    image_format,
-                )
-                .expected_error()?;
-
-                cur.into_inner()
+                let mut buf = Vec::new();
+                encode_pixels(&frame, memory_format, image_format, &mut buf)?;
+                buf
             }
         };
 
         let data = B::try_from_vec(image_buf).expected_error()?;
         Ok(EncodedImage::new(data))
     }
+
+    fn create_to<B: ByteData, W: Write>(
+        mime_type: String,
+        new_image: NewImage<B>,
+        encoding_options: EncodingOptions,
+        writer: &mut W,
+    ) -> Result<(), ProcessError> {
+        match (image_format(&mime_type), new_image.frames.as_slice()) {
+            // Formats that only store the pixels are written while encoding. The
+            // TIFF and OpenEXR encoders seek in the output and therefore use the
+            // default implementation, which encodes the complete image first.
+            (
+                Ok(
+                    image_format @ (ImageFormat::Bmp
+                    | ImageFormat::Farbfeld
+                    | ImageFormat::Hdr
+                    | ImageFormat::Qoi
+                    | ImageFormat::Tga),
+                ),
+                [frame],
+            ) => {
+                let memory_format = image_memory_format(frame.memory_format)?;
+                encode_pixels(frame, memory_format, image_format, writer)
+            }
+            _ => {
+                let encoded_image = Self::create(mime_type, new_image, encoding_options)?;
+                writer.write_all(&encoded_image.data).internal_error()
+            }
+        }
+    }
+}
+
+/// Encode formats that only store the pixels via image-rs
+fn encode_pixels<W: Write>(
+    frame: &Frame<impl ByteData>,
+    memory_format: ExtendedColorType,
+    image_format: ImageFormat,
+    writer: &mut W,
+) -> Result<(), ProcessError> {
+    let (buf, width, height) = (&frame.texture[..], frame.width, frame.height);
+
+    match image_format {
+        ImageFormat::Bmp => image::codecs::bmp::BmpEncoder::new(writer).write_image(
+            buf,
+            width,
+            height,
+            memory_format,
+        ),
+        ImageFormat::Farbfeld => image::codecs::farbfeld::FarbfeldEncoder::new(writer).write_image(
+            buf,
+            width,
+            height,
+            memory_format,
+        ),
+        ImageFormat::Hdr => image::codecs::hdr::HdrEncoder::new(writer).write_image(
+            buf,
+            width,
+            height,
+            memory_format,
+        ),
+        ImageFormat::Qoi => image::codecs::qoi::QoiEncoder::new(writer).write_image(
+            buf,
+            width,
+            height,
+            memory_format,
+        ),
+        ImageFormat::Tga => image::codecs::tga::TgaEncoder::new(writer).write_image(
+            buf,
+            width,
+            height,
+            memory_format,
+        ),
+        image_format => {
+            return Err(ProcessError::UnsupportedImageFormat(format!(
+                "{image_format:?}"
+            )));
+        }
+    }
+    .expected_error()
+}
+
+/// Decode the image and create a preview with the operations applied
+fn decoder_preview<B: ByteData>(
+    mut decoder: impl image::ImageDecoder,
+    operations: &Operations,
+    frame_request: &FrameRequest,
+) -> Result<Frame<B>, ProcessError> {
+    let icc_profile = decoder.icc_profile().ok().flatten();
+    let editing_frame = image_rs::Handler::default().editing_frame(decoder)?;
+
+    let mut frame = editing::preview(editing_frame, operations, frame_request)?;
+    frame.details.color_icc_profile = icc_profile
+        .map(B::try_from_vec)
+        .transpose()
+        .expected_error()?;
+
+    Ok(frame)
 }
 
 fn image_format(mime_type: &str) -> Result<ImageFormat, ProcessError> {
@@ -108,6 +289,7 @@ fn image_format(mime_type: &str) -> Result<ImageFormat, ProcessError> {
         "image/qoi" => ImageFormat::Qoi,
         "image/x-tga" => ImageFormat::Tga,
         "image/tiff" => ImageFormat::Tiff,
+        "image/vnd.radiance" => ImageFormat::Hdr,
         "image/webp" => ImageFormat::WebP,
         _ => return Err(ProcessError::UnsupportedImageFormat(mime_type.to_string())),
     })
@@ -0,0 +1,426 @@
+//! Editing and creation of animated GIF, APNG, and WebP images
+//!
+//! All frames are decoded as complete canvases, such that frame offsets and
+//! disposal are already resolved. The operations are applied to every frame
+//! and the frames are encoded again with the original delays and loop count.
+//! Frames are decoded, edited, and encoded one after another, such that only
+//! one canvas is kept in memory at a time.
+//!
+//! Still GIF and WebP images are handled in [`super::gif`] and [`super::webp`].
+
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use editing::EditingFrame;
+use glycin_utils::quantize::Quantizer;
+use glycin_utils::safe_math::{SafeConversion, SafeMath};
+use glycin_utils::*;
+use gufo_common::field;
+use gufo_common::orientation::Orientation;
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::metadata::LoopCount;
+use image::{AnimationDecoder, Delay, ImageFormat, RgbaImage};
+
+use super::webp::{WebPChunks, WebPCompression};
+
+pub struct EditAnimated {
+    format: ImageFormat,
+    data: Vec<u8>,
+}
+
+struct Animation<'a> {
+    frames: image::Frames<'a>,
+    loop_count: LoopCount,
+}
+
+/// Frames that all have the canvas size of the first frame
+pub struct CanvasFrames<I> {
+    first: Option<image::Frame>,
+    frames: I,
+    canvas_size: (u32, u32),
+}
+
+impl<I: Iterator<Item = Result<image::Frame, ProcessError>>> CanvasFrames<I> {
+    pub fn new(frames: impl IntoIterator<IntoIter = I>) -> Result<Self, ProcessError> {
+        let mut frames = frames.into_iter();
+        let first = frames
+            .next()
+            .ok_or_else(|| ProcessError::expected(&"Image contains no frames"))??;
+        let canvas_size = first.buffer().dimensions();
+
+        Ok(Self {
+            first: Some(first),
+            frames,
+            canvas_size,
+        })
+    }
+
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.canvas_size
+    }
+}
+
+impl<I: Iterator<Item = Result<image::Frame, ProcessError>>> Iterator for CanvasFrames<I> {
+    type Item = Result<image::Frame, ProcessError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = self.first.take() {
+            return Some(Ok(first));
+        }
+
+        Some(self.frames.next()?.and_then(|frame| {
+            if frame.buffer().dimensions() == self.canvas_size {
+                Ok(frame)
+            } else {
+                Err(ProcessError::expected(&"Frames have different sizes"))
+            }
+        }))
+    }
+}
+
+/// Whether the image has to be edited as an animation
+pub fn is_animated(format: ImageFormat, data: &[u8]) -> Result<bool, ProcessError> {
+    Ok(match format {
+        ImageFormat::Gif => {
+            // Only reads the frame headers and skips the image data
+            let mut decoder = gif::DecodeOptions::new()
+                .read_info(Cursor::new(data))
+                .expected_error()?;
+            let mut n_frames = 0;
+            while n_frames < 2 && decoder.next_frame_info().expected_error()?.is_some() {
+                n_frames += 1;
+            }
+            n_frames > 1
+        }
+        ImageFormat::Png => PngDecoder::new(Cursor::new(data))
+            .expected_error()?
+            .is_apng()
+            .expected_error()?,
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(data))
+            .expected_error()?
+            .has_animation(),
+        _ => false,
+    })
+}
+
+pub fn load(format: ImageFormat, data: Vec<u8>) -> EditAnimated {
+    EditAnimated { format, data }
+}
+
+pub fn apply<B: ByteData>(
+    edit_animated: &EditAnimated,
+    mut operations: Operations,
+) -> Result<CompleteEditorOutput<B>, ProcessError> {
+    if let Some(orientation) = edit_animated.orientation() {
+        operations.prepend(Operations::new_orientation(orientation));
+    }
+
+    if edit_animated.format == ImageFormat::WebP
+        && let Some(data) = super::webp::apply_orientation_only(&edit_animated.data, &operations)
+    {
+        return CompleteEditorOutput::new_lossless(data);
+    }
+
+    let animation = edit_animated.decode()?;
+    let loop_count = animation.loop_count;
+
+    let frames = animation
+        .frames
+        .map(|frame| apply_to_frame(frame.expected_error()?, &operations));
+
+    let data = match edit_animated.format {
+        ImageFormat::Gif => super::gif::encode(frames, Some(loop_count), &Quantizer::default())?,
+        ImageFormat::Png => {
+            let info = png_info(&edit_animated.data)?;
+            let n_frames = info
+                .animation_control
+                .map(|x| x.num_frames)
+                .expected_error()?;
+            encode_apng(frames, n_frames, loop_count, info)?
+        }
+        ImageFormat::WebP => super::webp::encode(
+            frames,
+            Some(loop_count),
+            WebPChunks::from_old(&edit_animated.data)?,
+            WebPCompression::from_old(&edit_animated.data),
+        )?,
+        format => {
+            return Err(ProcessError::UnsupportedImageFormat(format!("{format:?}")));
+        }
+    };
+
+    let data = B::try_from_vec(data).expected_error()?;
+
+    Ok(CompleteEditorOutput::new(data))
+}
+
+/// Encode all frames of a new image as animation
+pub fn create<B: ByteData>(
+    format: ImageFormat,
+    new_image: NewImage<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let loop_count = match new_image
+        .image_info
+        .animation_loop_count
+        .and_then(NonZeroU32::new)
+    {
+        Some(n) => LoopCount::Finite(n),
+        None => LoopCount::Infinite,
+    };
+
+    let first_frame = new_image.frames.first().expected_error()?;
+    let icc_profile = first_frame
+        .details
+        .color_icc_profile
+        .as_ref()
+        .map(|x| x.to_vec());
+    let mut frame_details = FrameDetails::<B>::default();
+    frame_details.pixel_density = first_frame.details.pixel_density.clone();
+
+    let n_frames = new_image.frames.len().try_u32()?;
+    let frames = new_image.frames.into_iter().map(new_frame);
+
+    match format {
+        ImageFormat::Gif => super::gif::encode(
+            frames,
+            Some(loop_count),
+            &super::gif::quantizer(encoding_options),
+        ),
+        ImageFormat::Png => {
+            let mut info = png::Info::default();
+            info.icc_profile = icc_profile.map(Cow::Owned);
+
+            let data = encode_apng(frames, n_frames, loop_count, info)?;
+            Ok(super::png::add_metadata(
+                data,
+                &new_image.image_info,
+                &frame_details,
+            ))
+        }
+        ImageFormat::WebP => super::webp::encode(
+            frames,
+            Some(loop_count),
+            WebPChunks::new(icc_profile, &new_image.image_info),
+            WebPCompression::new(encoding_options),
+        ),
+        format => Err(ProcessError::UnsupportedImageFormat(format!("{format:?}"))),
+    }
+}
+
+/// Preview of the first frame with the operations applied
+pub fn preview<B: ByteData>(
+    edit_animated: &EditAnimated,
+    mut operations: Operations,
+    frame_request: FrameRequest,
+) -> Result<Frame<B>, ProcessError> {
+    if let Some(orientation) = edit_animated.orientation() {
+        operations.prepend(Operations::new_orientation(orientation));
+    }
+
+    let cursor = Cursor::new(edit_animated.data.as_slice());
+
+    match edit_animated.format {
+        ImageFormat::Gif => super::decoder_preview(
+            GifDecoder::new(cursor).expected_error()?,
+            &operations,
+            &frame_request,
+        ),
+        ImageFormat::Png => super::decoder_preview(
+            PngDecoder::new(cursor).expected_error()?,
+            &operations,
+            &frame_request,
+        ),
+        ImageFormat::WebP => super::decoder_preview(
+            WebPDecoder::new(cursor).expected_error()?,
+            &operations,
+            &frame_request,
+        ),
+        format => Err(ProcessError::UnsupportedImageFormat(format!("{format:?}"))),
+    }
+}
+
+pub fn apply_to_frame(
+    frame: image::Frame,
+    operations: &Operations,
+) -> Result<image::Frame, ProcessError> {
+    let delay = frame.delay();
+    let buffer = frame.into_buffer();
+
+    let (width, height) = buffer.dimensions();
+    let editing_frame = EditingFrame {
+        width,
+        height,
+        stride: width.smul(4)?,
+        memory_format: ExtendedMemoryFormat::Basic(MemoryFormat::R8g8b8a8),
+        texture: FungibleMemory::from_vec(buffer.into_raw()),
+    };
+
+    let editing_frame = editing::apply_operations(editing_frame, operations).expected_error()?;
+
+    let buffer = RgbaImage::from_raw(
+        editing_frame.width,
+        editing_frame.height,
+        editing_frame.texture.to_vec(),
+    )
+    .internal_error()?;
+
+    Ok(image::Frame::from_parts(buffer, 0, 0, delay))
+}
+
+pub fn new_frame<B: ByteData>(frame: Frame<B>) -> Result<image::Frame, ProcessError> {
+    let mut frame = frame.into_fungible();
+    editing::change_memory_format(&mut frame, MemoryFormat::R8g8b8a8).expected_error()?;
+
+    if frame.stride != frame.width.smul(4)? {
+        return Err(ProcessError::expected(&"Frame has unsupported stride"));
+    }
+
+    let delay: Option<Duration> = frame.delay.into();
+    let delay = Delay::from_saturating_duration(delay.unwrap_or_default());
+
+    let buffer =
+        RgbaImage::from_raw(frame.width, frame.height, frame.texture.to_vec()).expected_error()?;
+
+    Ok(image::Frame::from_parts(buffer, 0, 0, delay))
+}
+
+impl EditAnimated {
+    /// Decoder for the frames, which are only decoded when iterating
+    fn decode(&self) -> Result<Animation<'_>, ProcessError> {
+        let cursor = Cursor::new(self.data.as_slice());
+
+        Ok(match self.format {
+            ImageFormat::Gif => animation(GifDecoder::new(cursor).expected_error()?),
+            ImageFormat::Png => animation(
+                PngDecoder::new(cursor)
+                    .expected_error()?
+                    .apng()
+                    .expected_error()?,
+            ),
+            ImageFormat::WebP => animation(WebPDecoder::new(cursor).expected_error()?),
+            format => return Err(ProcessError::UnsupportedImageFormat(format!("{format:?}"))),
+        })
+    }
+
+    fn orientation(&self) -> Option<Orientation> {
+        match self.format {
+            ImageFormat::Png | ImageFormat::WebP => super::webp::orientation(&self.data),
+            _ => None,
+        }
+    }
+}
+
+fn animation<'a>(decoder: impl AnimationDecoder<'a>) -> Animation<'a> {
+    let loop_count = decoder.loop_count();
+    let frames = decoder.into_frames();
+
+    Animation { frames, loop_count }
+}
+
+/// Ancillary chunks like color information and text from the original image
+fn png_info(old_data: &[u8]) -> Result<png::Info<'static>, ProcessError> {
+    let decoder = png::Decoder::new(Cursor::new(old_data));
+    let reader = decoder.read_info().expected_error()?;
+
+    Ok(reader.info().clone())
+}
+
+fn encode_apng(
+    frames: impl IntoIterator<Item = Result<image::Frame, ProcessError>>,
+    n_frames: u32,
+    loop_count: LoopCount,
+    mut info: png::Info<'static>,
+) -> Result<Vec<u8>, ProcessError> {
+    let frames = CanvasFrames::new(frames)?;
+    let (width, height) = frames.canvas_size();
+
+    info.width = width;
+    info.height = height;
+    info.color_type = png::ColorType::Rgba;
+    info.bit_depth = png::BitDepth::Eight;
+    info.interlaced = false;
+    info.palette = None;
+    info.trns = None;
+    info.sbit = None;
+    info.bkgd = None;
+    info.animation_control = None;
+    info.frame_control = None;
+    info.exif_metadata = info
+        .exif_metadata
+        .map(|exif| reset_exif_orientation(exif.to_vec()).into());
+
+    let mut out_buf = Vec::new();
+    let mut encoder = png::Encoder::with_info(&mut out_buf, info).expected_error()?;
+    encoder
+        .set_animated(
+            n_frames,
+            match loop_count {
+                LoopCount::Infinite => 0,
+                LoopCount::Finite(n) => n.get(),
+            },
+        )
+        .expected_error()?;
+
+    let mut writer = encoder.write_header().expected_error()?;
+
+    for frame in frames {
+        let frame = frame?;
+        let delay_ms = delay_ms(frame.delay());
+        let (delay_num, delay_den) = match u16::try_from(delay_ms) {
+            Ok(delay_ms) => (delay_ms, 1000),
+            Err(_) => (u16::try_from(delay_ms / 1000).unwrap_or(u16::MAX), 1),
+        };
+
+        writer
+            .set_frame_delay(delay_num, delay_den)
+            .expected_error()?;
+        writer.set_blend_op(png::BlendOp::Source).expected_error()?;
+        writer
+            .set_dispose_op(png::DisposeOp::None)
+            .expected_error()?;
+        writer
+            .write_image_data(frame.buffer().as_raw())
+            .expected_error()?;
+    }
+
+    writer.finish().expected_error()?;
+
+    Ok(out_buf)
+}
+
+pub fn delay_ms(delay: Delay) -> u32 {
+    let (numer, denom) = delay.numer_denom_ms();
+
+    if denom == 0 {
+        0
+    } else {
+        f64::round(numer as f64 / denom as f64) as u32
+    }
+}
+
+/// Since the orientation is applied to all frames, the Exif orientation has to
+/// be reset
+pub fn reset_exif_orientation(mut exif_data: Vec<u8>) -> Vec<u8> {
+    let diff = gufo_exif::Exif::for_vec(exif_data.clone())
+        .ok()
+        .and_then(|mut exif| {
+            exif.update_entry_diff(
+                field::Orientation.into(),
+                gufo_exif::Typed::Short(vec![Orientation::Id as u16]),
+            )
+            .ok()
+        });
+
+    for (pos, value) in diff.unwrap_or_default() {
+        if let Some(byte) = exif_data.get_mut(pos) {
+            *byte = value;
+        }
+    }
+
+    exif_data
+}
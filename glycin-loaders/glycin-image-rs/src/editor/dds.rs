@@ -0,0 +1,200 @@
+//! Creation of DirectDraw Surface textures with block compression
+//!
+//! BC1 and BC3 are stored with the legacy FourCC codes for compatibility with
+//! older readers. All other formats use the DX10 header extension.
+
+mod bcn;
+
+use glycin_utils::safe_math::SafeMath;
+use glycin_utils::*;
+use image::RgbaImage;
+use image::imageops::FilterType;
+
+const HEADER_SIZE: u32 = 124;
+const PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+
+const DDPF_FOURCC: u32 = 0x4;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+const DXGI_FORMAT_BC4_UNORM: u32 = 80;
+const DXGI_FORMAT_BC5_UNORM: u32 = 83;
+const DXGI_FORMAT_BC7_UNORM: u32 = 98;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+
+enum PixelFormat {
+    FourCc(&'static [u8; 4]),
+    Dxgi(u32),
+}
+
+type BlockEncoder = fn(&bcn::Block) -> Vec<u8>;
+
+fn format(compression: TextureCompression) -> Result<(PixelFormat, BlockEncoder), ProcessError> {
+    Ok(match compression {
+        TextureCompression::Bc1 => (PixelFormat::FourCc(b"DXT1"), |x| bcn::bc1(x, true).to_vec()),
+        TextureCompression::Bc3 => (PixelFormat::FourCc(b"DXT5"), |x| bcn::bc3(x).to_vec()),
+        TextureCompression::Bc4 => (PixelFormat::Dxgi(DXGI_FORMAT_BC4_UNORM), |x| {
+            bcn::bc4(&x.map(|x| x[0])).to_vec()
+        }),
+        TextureCompression::Bc5 => (PixelFormat::Dxgi(DXGI_FORMAT_BC5_UNORM), |x| {
+            bcn::bc5(x).to_vec()
+        }),
+        TextureCompression::Bc7 => (PixelFormat::Dxgi(DXGI_FORMAT_BC7_UNORM), |x| {
+            bcn::bc7(x).to_vec()
+        }),
+        compression => {
+            return Err(ProcessError::expected(&format!(
+                "Unsupported texture compression: {compression:?}"
+            )));
+        }
+    })
+}
+
+/// Compress all 4x4 blocks, repeating the last row and column for partial
+/// blocks
+fn encode_level(image: &RgbaImage, encode_block: BlockEncoder) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut data = Vec::new();
+
+    for block_y in (0..height).step_by(4) {
+        for block_x in (0..width).step_by(4) {
+            let block: bcn::Block = std::array::from_fn(|i| {
+                let x = (block_x + i as u32 % 4).min(width - 1);
+                let y = (block_y + i as u32 / 4).min(height - 1);
+                image.get_pixel(x, y).0
+            });
+
+            data.extend_from_slice(&encode_block(&block));
+        }
+    }
+
+    data
+}
+
+/// Successively halved images down to one pixel, starting with `image`
+fn mipmaps(image: RgbaImage) -> Vec<RgbaImage> {
+    let mut levels = vec![image];
+
+    while let Some(last) = levels.last().filter(|x| x.width() > 1 || x.height() > 1) {
+        let width = (last.width() / 2).max(1);
+        let height = (last.height() / 2).max(1);
+        levels.push(image::imageops::resize(
+            last,
+            width,
+            height,
+            FilterType::Triangle,
+        ));
+    }
+
+    levels
+}
+
+pub fn create<B: ByteData>(
+    mut new_image: NewImage<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let mut frame = new_image.frames.remove(0).into_fungible();
+    editing::change_memory_format(&mut frame, MemoryFormat::R8g8b8a8).expected_error()?;
+
+    if frame.stride != frame.width.smul(4)? {
+        return Err(ProcessError::expected(&"Frame has unsupported stride"));
+    }
+
+    let image =
+        RgbaImage::from_raw(frame.width, frame.height, frame.texture.to_vec()).expected_error()?;
+
+    // Only spend bits on alpha if it's used
+    let compression = encoding_options.texture_compression.unwrap_or_else(|| {
+        if image.pixels().any(|x| x.0[3] < u8::MAX) {
+            TextureCompression::Bc3
+        } else {
+            TextureCompression::Bc1
+        }
+    });
+
+    let levels = if encoding_options.mipmaps == Some(true) {
+        mipmaps(image)
+    } else {
+        vec![image]
+    };
+
+    let (pixel_format, encode_block) = format(compression)?;
+
+    let encoded = levels
+        .iter()
+        .map(|x| encode_level(x, encode_block))
+        .collect::<Vec<_>>();
+
+    let n_levels = u32::try_from(levels.len()).expected_error()?;
+    let linear_size = u32::try_from(encoded[0].len()).expected_error()?;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_LINEARSIZE;
+    let mut caps = DDSCAPS_TEXTURE;
+    if n_levels > 1 {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+
+    let four_cc = match pixel_format {
+        PixelFormat::FourCc(four_cc) => four_cc,
+        PixelFormat::Dxgi(_) => b"DX10",
+    };
+
+    let mut buf = Vec::new();
+
+    buf.extend_from_slice(b"DDS ");
+    for value in [
+        HEADER_SIZE,
+        flags,
+        frame.height,
+        frame.width,
+        linear_size,
+        // Depth
+        0,
+        n_levels,
+    ] {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+    // Reserved
+    buf.extend_from_slice(&[0; 11 * 4]);
+
+    buf.extend_from_slice(&PIXEL_FORMAT_SIZE.to_le_bytes());
+    buf.extend_from_slice(&DDPF_FOURCC.to_le_bytes());
+    buf.extend_from_slice(four_cc);
+    // Bit count and masks are only used for uncompressed formats
+    buf.extend_from_slice(&[0; 5 * 4]);
+
+    buf.extend_from_slice(&caps.to_le_bytes());
+    // Caps 2 to 4 and reserved
+    buf.extend_from_slice(&[0; 4 * 4]);
+
+    if let PixelFormat::Dxgi(dxgi_format) = pixel_format {
+        for value in [
+            dxgi_format,
+            D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+            // Misc flags
+            0,
+            // Array size
+            1,
+            // Misc flags 2
+            0,
+        ] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    for data in encoded {
+        buf.extend_from_slice(&data);
+    }
+
+    Ok(buf)
+}
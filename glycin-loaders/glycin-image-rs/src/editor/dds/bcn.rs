@@ -0,0 +1,501 @@
+//! Block compression of 4x4 pixels as used by GPUs
+//!
+//! The endpoints are found along the principal axis of the block's colors
+//! and then refined once with a least squares fit for the chosen indices.
+
+/// Pixels of a block in row-major order
+pub type Block = [[u8; 4]; 16];
+
+type Point<const N: usize> = [f32; N];
+
+/// Interpolation weights for 4-bit indices in BC7
+const BC7_WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Encode RGB as BC1
+///
+/// If `transparency` is set, pixels with an alpha below 128 are stored as
+/// transparent.
+pub fn bc1(block: &Block, transparency: bool) -> [u8; 8] {
+    let is_transparent = |pixel: &[u8; 4]| transparency && pixel[3] < 128;
+
+    let points = block
+        .iter()
+        .filter(|x| !is_transparent(x))
+        .map(|x| [x[0], x[1], x[2]].map(f32::from))
+        .collect::<Vec<_>>();
+
+    if points.is_empty() {
+        // Equal endpoints select the mode with transparency
+        return [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+    }
+
+    let transparent = block.map(|x| is_transparent(&x));
+    let three_colors = transparent.contains(&true);
+
+    let (a, b) = range_fit(&points);
+    let mut best = Bc1Candidate::new(block, &transparent, three_colors, a, b);
+
+    let weights = best
+        .indices
+        .iter()
+        .zip(transparent)
+        .filter(|(_, transparent)| !transparent)
+        .map(|(index, _)| match (three_colors, index) {
+            (_, 0) => 0.,
+            (_, 1) => 1.,
+            (true, _) => 1. / 2.,
+            (false, 2) => 1. / 3.,
+            (false, _) => 2. / 3.,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some((a, b)) = least_squares(&points, &weights) {
+        let candidate = Bc1Candidate::new(block, &transparent, three_colors, a, b);
+        if candidate.error < best.error {
+            best = candidate;
+        }
+    }
+
+    let mut data = [0; 8];
+    data[0..2].copy_from_slice(&best.endpoints[0].to_le_bytes());
+    data[2..4].copy_from_slice(&best.endpoints[1].to_le_bytes());
+    let indices = best
+        .indices
+        .iter()
+        .enumerate()
+        .fold(0_u32, |bits, (i, index)| {
+            bits | u32::from(*index) << (i * 2)
+        });
+    data[4..8].copy_from_slice(&indices.to_le_bytes());
+
+    data
+}
+
+/// Encode RGBA as BC3
+pub fn bc3(block: &Block) -> [u8; 16] {
+    let mut data = [0; 16];
+    data[..8].copy_from_slice(&bc4(&block.map(|x| x[3])));
+    data[8..].copy_from_slice(&bc1(block, false));
+    data
+}
+
+/// Encode a single channel as BC4
+pub fn bc4(values: &[u8; 16]) -> [u8; 8] {
+    let max = values.iter().copied().max().unwrap_or_default();
+    let min = values.iter().copied().min().unwrap_or_default();
+
+    let mut data = [0; 8];
+    data[0] = max;
+    data[1] = min;
+
+    if max == min {
+        return data;
+    }
+
+    // With the first endpoint being larger, six values are interpolated
+    let mut palette = [0; 8];
+    palette[0] = u32::from(max);
+    palette[1] = u32::from(min);
+    for (i, value) in palette.iter_mut().enumerate().skip(2) {
+        *value = ((8 - i as u32) * u32::from(max) + (i as u32 - 1) * u32::from(min)) / 7;
+    }
+
+    let indices = values.iter().enumerate().fold(0_u64, |bits, (i, value)| {
+        let index = nearest(&palette, |entry| u32::from(*value).abs_diff(*entry));
+        bits | (index as u64) << (i * 3)
+    });
+    data[2..].copy_from_slice(&indices.to_le_bytes()[..6]);
+
+    data
+}
+
+/// Encode the red and green channels as BC5
+pub fn bc5(block: &Block) -> [u8; 16] {
+    let mut data = [0; 16];
+    data[..8].copy_from_slice(&bc4(&block.map(|x| x[0])));
+    data[8..].copy_from_slice(&bc4(&block.map(|x| x[1])));
+    data
+}
+
+/// Encode RGBA as BC7
+///
+/// Only mode 6 is used, which stores one pair of RGBA endpoints with 4-bit
+/// indices.
+pub fn bc7(block: &Block) -> [u8; 16] {
+    let points = block.map(|x| x.map(f32::from));
+
+    let (a, b) = range_fit(&points);
+    let mut best = Bc7Candidate::new(block, a, b);
+
+    let weights = best
+        .indices
+        .map(|x| BC7_WEIGHTS[usize::from(x)] as f32 / 64.);
+    if let Some((a, b)) = least_squares(&points, &weights) {
+        let candidate = Bc7Candidate::new(block, a, b);
+        if candidate.error < best.error {
+            best = candidate;
+        }
+    }
+
+    // The most significant bit of the first index is implied to be zero
+    if best.indices[0] >= 8 {
+        best.endpoints.swap(0, 1);
+        best.p_bits.swap(0, 1);
+        best.indices = best.indices.map(|x| 15 - x);
+    }
+
+    let mut bits = BitWriter::default();
+    // Mode 6
+    bits.put(1 << 6, 7);
+    for channel in 0..4 {
+        for endpoint in &best.endpoints {
+            bits.put(u128::from(endpoint[channel]), 7);
+        }
+    }
+    for p_bit in best.p_bits {
+        bits.put(u128::from(p_bit), 1);
+    }
+    for (i, index) in best.indices.iter().enumerate() {
+        bits.put(u128::from(*index), if i == 0 { 3 } else { 4 });
+    }
+
+    bits.value.to_le_bytes()
+}
+
+struct Bc1Candidate {
+    endpoints: [u16; 2],
+    indices: [u8; 16],
+    error: u32,
+}
+
+impl Bc1Candidate {
+    fn new(
+        block: &Block,
+        transparent: &[bool; 16],
+        three_colors: bool,
+        a: Point<3>,
+        b: Point<3>,
+    ) -> Self {
+        let mut endpoints = [to_rgb565(a), to_rgb565(b)];
+
+        // The order of the endpoints selects the mode
+        if three_colors == (endpoints[0] > endpoints[1]) {
+            endpoints.swap(0, 1);
+        }
+
+        let c0 = from_rgb565(endpoints[0]);
+        let c1 = from_rgb565(endpoints[1]);
+        let mix = |w0: u32, w1: u32| {
+            [0, 1, 2].map(|i| (w0 * c0[i] + w1 * c1[i] + (w0 + w1) / 2) / (w0 + w1))
+        };
+
+        let palette = if three_colors {
+            vec![c0, c1, mix(1, 1)]
+        } else if endpoints[0] == endpoints[1] {
+            vec![c0]
+        } else {
+            vec![c0, c1, mix(2, 1), mix(1, 2)]
+        };
+
+        let mut indices = [0; 16];
+        let mut error = 0;
+        for ((index, pixel), transparent) in indices.iter_mut().zip(block).zip(transparent) {
+            if *transparent {
+                *index = 3;
+                continue;
+            }
+
+            let pixel_error = |color: &[u32; 3]| {
+                (0..3)
+                    .map(|i| u32::from(pixel[i]).abs_diff(color[i]).pow(2))
+                    .sum::<u32>()
+            };
+            let i = nearest(&palette, pixel_error);
+            *index = i as u8;
+            error += pixel_error(&palette[i]);
+        }
+
+        Self {
+            endpoints,
+            indices,
+            error,
+        }
+    }
+}
+
+struct Bc7Candidate {
+    /// Endpoints with 7 bits per channel
+    endpoints: [[u8; 4]; 2],
+    p_bits: [u8; 2],
+    indices: [u8; 16],
+    error: u32,
+}
+
+impl Bc7Candidate {
+    fn new(block: &Block, a: Point<4>, b: Point<4>) -> Self {
+        let (e0, p0) = quantize_with_p_bit(a);
+        let (e1, p1) = quantize_with_p_bit(b);
+
+        let c0 = e0.map(|x| u32::from(x) << 1 | u32::from(p0));
+        let c1 = e1.map(|x| u32::from(x) << 1 | u32::from(p1));
+        let palette =
+            BC7_WEIGHTS.map(|w| [0, 1, 2, 3].map(|i| ((64 - w) * c0[i] + w * c1[i] + 32) >> 6));
+
+        let mut indices = [0; 16];
+        let mut error = 0;
+        for (index, pixel) in indices.iter_mut().zip(block) {
+            let pixel_error = |color: &[u32; 4]| {
+                (0..4)
+                    .map(|i| u32::from(pixel[i]).abs_diff(color[i]).pow(2))
+                    .sum::<u32>()
+            };
+            let i = nearest(&palette, pixel_error);
+            *index = i as u8;
+            error += pixel_error(&palette[i]);
+        }
+
+        Self {
+            endpoints: [e0, e1],
+            p_bits: [p0, p1],
+            indices,
+            error,
+        }
+    }
+}
+
+/// 7-bit channels and the shared lowest bit that represent `point` best
+fn quantize_with_p_bit(point: Point<4>) -> ([u8; 4], u8) {
+    [0, 1]
+        .map(|p| {
+            let values = point.map(|x| ((x - p as f32) / 2.).round().clamp(0., 127.) as u8);
+            let error = (0..4)
+                .map(|i| (f32::from(values[i] << 1 | p) - point[i]).powi(2))
+                .sum::<f32>();
+            (values, p, error)
+        })
+        .into_iter()
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(values, p, _)| (values, p))
+        .unwrap_or_default()
+}
+
+fn to_rgb565(point: Point<3>) -> u16 {
+    let [r, g, b] = point.map(|x| x.clamp(0., 255.));
+    let r = (r * 31. / 255.).round() as u16;
+    let g = (g * 63. / 255.).round() as u16;
+    let b = (b * 31. / 255.).round() as u16;
+    r << 11 | g << 5 | b
+}
+
+fn from_rgb565(color: u16) -> [u32; 3] {
+    let r = u32::from(color >> 11);
+    let g = u32::from(color >> 5 & 0x3F);
+    let b = u32::from(color & 0x1F);
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+}
+
+/// Index of the palette entry with the smallest error
+fn nearest<T>(palette: &[T], error: impl Fn(&T) -> u32) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| error(entry))
+        .map(|(i, _)| i)
+        .unwrap_or_default()
+}
+
+/// Endpoints at the extremes of the points along their principal axis
+fn range_fit<const N: usize>(points: &[Point<N>]) -> (Point<N>, Point<N>) {
+    let n = points.len() as f32;
+    let mean: Point<N> = std::array::from_fn(|i| points.iter().map(|p| p[i]).sum::<f32>() / n);
+
+    let mut covariance = [[0.; N]; N];
+    for p in points {
+        for i in 0..N {
+            for j in 0..N {
+                covariance[i][j] += (p[i] - mean[i]) * (p[j] - mean[j]);
+            }
+        }
+    }
+
+    // Power iteration for the eigenvector with the largest eigenvalue
+    let mut axis = [1.; N];
+    for _ in 0..8 {
+        let next: Point<N> =
+            std::array::from_fn(|i| (0..N).map(|j| covariance[i][j] * axis[j]).sum());
+        let length = next.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if length < f32::EPSILON {
+            break;
+        }
+        axis = next.map(|x| x / length);
+    }
+
+    let projections = points
+        .iter()
+        .map(|p| (0..N).map(|i| (p[i] - mean[i]) * axis[i]).sum::<f32>());
+    let min = projections.clone().fold(f32::INFINITY, f32::min);
+    let max = projections.fold(f32::NEG_INFINITY, f32::max);
+
+    let endpoint = |t: f32| std::array::from_fn(|i| (mean[i] + t * axis[i]).clamp(0., 255.));
+
+    (endpoint(min), endpoint(max))
+}
+
+/// Endpoints with the least squared error for interpolation weights between
+/// 0 (first endpoint) and 1 (second endpoint)
+fn least_squares<const N: usize>(
+    points: &[Point<N>],
+    weights: &[f32],
+) -> Option<(Point<N>, Point<N>)> {
+    let mut aa = 0.;
+    let mut ab = 0.;
+    let mut bb = 0.;
+    let mut ax = [0.; N];
+    let mut bx = [0.; N];
+
+    for (p, w) in points.iter().zip(weights) {
+        let a = 1. - w;
+        aa += a * a;
+        ab += a * w;
+        bb += w * w;
+        for i in 0..N {
+            ax[i] += a * p[i];
+            bx[i] += w * p[i];
+        }
+    }
+
+    let det = aa * bb - ab * ab;
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let a = std::array::from_fn(|i| ((bb * ax[i] - ab * bx[i]) / det).clamp(0., 255.));
+    let b = std::array::from_fn(|i| ((aa * bx[i] - ab * ax[i]) / det).clamp(0., 255.));
+
+    Some((a, b))
+}
+
+/// Writes values starting with the least significant bit
+#[derive(Default)]
+struct BitWriter {
+    value: u128,
+    position: u32,
+}
+
+impl BitWriter {
+    fn put(&mut self, value: u128, bits: u32) {
+        self.value |= value << self.position;
+        self.position += bits;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Reference decoder for BC4
+    fn decode_bc4(data: &[u8]) -> [u8; 16] {
+        let (a, b) = (u32::from(data[0]), u32::from(data[1]));
+
+        let palette: [u32; 8] = std::array::from_fn(|i| {
+            let i = i as u32;
+            match i {
+                0 => a,
+                1 => b,
+                _ if a > b => ((8 - i) * a + (i - 1) * b) / 7,
+                2..=5 => ((6 - i) * a + (i - 1) * b) / 5,
+                6 => 0,
+                _ => 255,
+            }
+        });
+
+        let mut indices = [0; 8];
+        indices[..6].copy_from_slice(&data[2..8]);
+        let indices = u64::from_le_bytes(indices);
+
+        std::array::from_fn(|i| palette[(indices >> (i * 3) & 0b111) as usize] as u8)
+    }
+
+    /// Reference decoder for BC7 blocks in mode 6
+    fn decode_bc7_mode6(data: &[u8; 16]) -> Block {
+        let value = u128::from_le_bytes(*data);
+        let mut position = 0;
+        let mut take = |bits: u32| {
+            let x = (value >> position) & ((1 << bits) - 1);
+            position += bits;
+            x as u32
+        };
+
+        assert_eq!(take(7), 1 << 6, "Not mode 6");
+
+        let mut endpoints = [[0; 4]; 2];
+        for channel in 0..4 {
+            for endpoint in &mut endpoints {
+                endpoint[channel] = take(7);
+            }
+        }
+
+        for endpoint in &mut endpoints {
+            let p_bit = take(1);
+            *endpoint = endpoint.map(|x| x << 1 | p_bit);
+        }
+
+        std::array::from_fn(|i| {
+            let weight = BC7_WEIGHTS[take(if i == 0 { 3 } else { 4 }) as usize];
+            std::array::from_fn(|c| {
+                (((64 - weight) * endpoints[0][c] + weight * endpoints[1][c] + 32) >> 6) as u8
+            })
+        })
+    }
+
+    fn max_error(a: impl IntoIterator<Item = u8>, b: impl IntoIterator<Item = u8>) -> u8 {
+        a.into_iter()
+            .zip(b)
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn bc4_gradient() {
+        // Values from 0 to 180
+        let values = std::array::from_fn(|i| (i % 4 * 50 + i / 4 * 10) as u8);
+        let decoded = decode_bc4(&bc4(&values));
+
+        // Half the distance between the eight palette entries
+        assert!(max_error(values, decoded) <= 13, "{values:?} {decoded:?}");
+
+        let flat = [77; 16];
+        assert_eq!(decode_bc4(&bc4(&flat)), flat);
+    }
+
+    #[test]
+    fn bc5_gradient() {
+        let block: Block =
+            std::array::from_fn(|i| [(i % 4 * 60) as u8, (i / 4 * 60 + 20) as u8, 0, 255]);
+        let data = bc5(&block);
+
+        let red = decode_bc4(&data[..8]);
+        let green = decode_bc4(&data[8..]);
+
+        assert!(max_error(block.map(|x| x[0]), red) <= 13, "{red:?}");
+        assert!(max_error(block.map(|x| x[1]), green) <= 13, "{green:?}");
+    }
+
+    #[test]
+    fn bc7_gradient() {
+        // Gradients in both directions to cover swapping the endpoints
+        for reverse in [false, true] {
+            let block: Block = std::array::from_fn(|i| {
+                let t = if reverse { 15 - i as u8 } else { i as u8 };
+                [t * 16, 255 - t * 16, 40 + t * 8, 255 - t * 4]
+            });
+            let decoded = decode_bc7_mode6(&bc7(&block));
+
+            assert!(
+                max_error(block.concat(), decoded.concat()) <= 4,
+                "{block:?} {decoded:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Creation of OpenEXR images
+//!
+//! Every frame is stored as a separate layer. Half-float textures are stored
+//! as 16-bit and all other textures as 32-bit floating point channels.
+
+use std::io::Cursor;
+
+use exr::image::write::WritableImage;
+use exr::image::{AnyChannel, AnyChannels, Blocks, Encoding, FlatSamples, Image, Layer};
+use exr::meta::attribute::{Compression, LineOrder, Text};
+use exr::meta::header::{ImageAttributes, LayerAttributes};
+use exr::prelude::f16;
+use glycin_utils::safe_math::{SafeConversion, SafeMath};
+use glycin_utils::*;
+
+const CHANNEL_NAMES: [&str; 4] = ["R", "G", "B", "A"];
+
+fn encoding(exr_compression: Option<ExrCompression>) -> Result<Encoding, ProcessError> {
+    let compression = match exr_compression {
+        None => return Ok(Encoding::default()),
+        Some(ExrCompression::Uncompressed) => Compression::Uncompressed,
+        Some(ExrCompression::Rle) => Compression::RLE,
+        Some(ExrCompression::Zip) => Compression::ZIP16,
+        Some(ExrCompression::Piz) => Compression::PIZ,
+        Some(ExrCompression::Dwaa) => Compression::DWAA(None),
+        Some(compression) => {
+            return Err(ProcessError::expected(&format!(
+                "Unsupported EXR compression: {compression:?}"
+            )));
+        }
+    };
+
+    Ok(Encoding {
+        compression,
+        blocks: Blocks::ScanLines,
+        line_order: LineOrder::Increasing,
+    })
+}
+
+fn layer_attributes(name: Option<&str>) -> Result<LayerAttributes, ProcessError> {
+    let Some(name) = name else {
+        return Ok(LayerAttributes::default());
+    };
+
+    let name = Text::new_or_none(name).ok_or_else(|| {
+        ProcessError::expected(&format!(
+            "Layer name '{name}' contains unsupported characters"
+        ))
+    })?;
+
+    Ok(LayerAttributes::named(name))
+}
+
+/// Split the texture into one list of samples per channel
+fn channels<B: ByteData>(frame: &Frame<B>) -> Result<AnyChannels<FlatSamples>, ProcessError> {
+    let (n_channels, half_float) = match frame.memory_format {
+        MemoryFormat::R16g16b16Float => (3, true),
+        MemoryFormat::R16g16b16a16Float => (4, true),
+        MemoryFormat::R32g32b32Float => (3, false),
+        MemoryFormat::R32g32b32a32Float => (4, false),
+        memory_format => {
+            return Err(ProcessError::expected(&format!(
+                "Unsupported memory format: {memory_format:?}"
+            )));
+        }
+    };
+
+    let sample_size = if half_float { 2 } else { 4 };
+    let row_size = frame
+        .width
+        .try_usize()?
+        .smul(n_channels)?
+        .smul(sample_size)?;
+
+    let mut samples = vec![Vec::new(); n_channels];
+    for y in 0..frame.height.try_usize()? {
+        let start = y.smul(frame.stride.try_usize()?)?;
+        let row = frame
+            .texture
+            .get(start..start.sadd(row_size)?)
+            .expected_error()?;
+
+        for (i, sample) in row.chunks_exact(sample_size).enumerate() {
+            samples[i % n_channels].push(sample);
+        }
+    }
+
+    let list = CHANNEL_NAMES
+        .into_iter()
+        .zip(samples)
+        .map(|(name, samples)| {
+            let sample_data = if half_float {
+                FlatSamples::F16(
+                    samples
+                        .into_iter()
+                        .map(|x| f16::from_ne_bytes([x[0], x[1]]))
+                        .collect(),
+                )
+            } else {
+                FlatSamples::F32(
+                    samples
+                        .into_iter()
+                        .map(|x| f32::from_ne_bytes([x[0], x[1], x[2], x[3]]))
+                        .collect(),
+                )
+            };
+
+            AnyChannel::new(name, sample_data)
+        })
+        .collect::<Vec<_>>();
+
+    Ok(AnyChannels::sort(list.into()))
+}
+
+pub fn create<B: ByteData>(
+    new_image: &NewImage<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let encoding = encoding(encoding_options.exr_compression)?;
+    let multi_layer = new_image.frames.len() > 1;
+
+    let layers = new_image
+        .frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            // Layers in files with multiple layers require a unique name
+            let default_name = multi_layer.then(|| format!("layer{}", i + 1));
+            let name = frame.details.layer_name.clone().or(default_name);
+
+            Ok(Layer::new(
+                (frame.width.try_usize()?, frame.height.try_usize()?),
+                layer_attributes(name.as_deref())?,
+                encoding,
+                channels(frame)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, ProcessError>>()?;
+
+    let width = new_image.frames.iter().map(|x| x.width).max();
+    let height = new_image.frames.iter().map(|x| x.height).max();
+    let size = (
+        width.unwrap_or_default().try_usize()?,
+        height.unwrap_or_default().try_usize()?,
+    );
+
+    let image = Image::from_layers(ImageAttributes::with_size(size), layers);
+
+    let mut buf = Cursor::new(Vec::new());
+    image.write().to_buffered(&mut buf).expected_error()?;
+
+    Ok(buf.into_inner())
+}
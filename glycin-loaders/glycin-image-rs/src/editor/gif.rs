@@ -0,0 +1,119 @@
+//! Editing and creation of still GIF images
+//!
+//! The colors are quantized to a palette via [`Quantizer`]. Animated GIFs are
+//! handled in [`super::animated`], which uses the same encoder.
+
+use std::io::Cursor;
+
+use glycin_utils::quantize::Quantizer;
+use glycin_utils::*;
+use image::codecs::gif::GifDecoder;
+use image::metadata::LoopCount;
+
+use super::animated;
+
+pub struct EditGif {
+    data: Vec<u8>,
+}
+
+pub fn load(data: Vec<u8>) -> EditGif {
+    EditGif { data }
+}
+
+pub fn apply<B: ByteData>(
+    edit_gif: &EditGif,
+    operations: Operations,
+) -> Result<CompleteEditorOutput<B>, ProcessError> {
+    let decoder = GifDecoder::new(Cursor::new(edit_gif.data.as_slice())).expected_error()?;
+    let image = image::DynamicImage::from_decoder(decoder).expected_error()?;
+
+    let frame = animated::apply_to_frame(image::Frame::new(image.into_rgba8()), &operations)?;
+    let data = encode([Ok(frame)], None, &Quantizer::default())?;
+
+    let data = B::try_from_vec(data).expected_error()?;
+
+    Ok(CompleteEditorOutput::new(data))
+}
+
+pub fn preview<B: ByteData>(
+    edit_gif: &EditGif,
+    operations: Operations,
+    frame_request: FrameRequest,
+) -> Result<Frame<B>, ProcessError> {
+    super::decoder_preview(
+        GifDecoder::new(Cursor::new(edit_gif.data.as_slice())).expected_error()?,
+        &operations,
+        &frame_request,
+    )
+}
+
+pub fn create<B: ByteData>(
+    frame: Frame<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let frame = animated::new_frame(frame)?;
+
+    encode([Ok(frame)], None, &quantizer(encoding_options))
+}
+
+pub fn quantizer(encoding_options: &EncodingOptions) -> Quantizer {
+    encoding_options
+        .palette_size
+        .map(Quantizer::new)
+        .unwrap_or_default()
+}
+
+/// Encode the frames as GIF
+///
+/// The loop count is only written for animations.
+pub fn encode(
+    frames: impl IntoIterator<Item = Result<image::Frame, ProcessError>>,
+    loop_count: Option<LoopCount>,
+    quantizer: &Quantizer,
+) -> Result<Vec<u8>, ProcessError> {
+    let frames = animated::CanvasFrames::new(frames)?;
+    let (width, height) = frames.canvas_size();
+
+    let mut out_buf = Vec::new();
+    let mut encoder = gif::Encoder::new(
+        &mut out_buf,
+        u16::try_from(width).expected_error()?,
+        u16::try_from(height).expected_error()?,
+        &[],
+    )
+    .expected_error()?;
+
+    if let Some(loop_count) = loop_count {
+        encoder
+            .set_repeat(match loop_count {
+                LoopCount::Infinite => gif::Repeat::Infinite,
+                LoopCount::Finite(n) => {
+                    gif::Repeat::Finite(u16::try_from(n.get()).unwrap_or(u16::MAX))
+                }
+            })
+            .expected_error()?;
+    }
+
+    for frame in frames {
+        let frame = frame?;
+        let delay = frame.delay();
+        let indexed = quantizer.quantize(frame.buffer().as_raw(), width);
+        let mut gif_frame = gif::Frame::from_palette_pixels(
+            u16::try_from(width).expected_error()?,
+            u16::try_from(height).expected_error()?,
+            indexed.indices.as_slice(),
+            indexed.palette_rgb(),
+            indexed.transparent,
+        );
+        gif_frame.delay = u16::try_from(animated::delay_ms(delay) / 10).unwrap_or(u16::MAX);
+        // Every frame covers the complete canvas, transparent areas must not show the
+        // previous frame
+        gif_frame.dispose = gif::DisposalMethod::Background;
+
+        encoder.write_frame(&gif_frame).expected_error()?;
+    }
+
+    drop(encoder);
+
+    Ok(out_buf)
+}
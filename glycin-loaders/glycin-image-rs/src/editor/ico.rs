@@ -0,0 +1,250 @@
+//! Creation of icons and cursors with multiple resolutions
+//!
+//! Every frame is stored as a separate entry. Entries with a size of 256
+//! pixels are stored as PNG and smaller entries as 32-bit BMP, as recommended
+//! for compatibility with older readers.
+
+use glycin_utils::safe_math::SafeMath;
+use glycin_utils::*;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{ExtendedColorType, ImageEncoder, RgbaImage};
+
+/// Largest size that can be stored in an entry
+const MAX_SIZE: u32 = 256;
+/// Sizes added with [`EncodingOptions::icon_standard_sizes`]
+const STANDARD_SIZES: [u32; 7] = [16, 24, 32, 48, 64, 128, 256];
+
+const ICON_DIR_SIZE: usize = 6;
+const ICON_DIR_ENTRY_SIZE: usize = 16;
+const BITMAP_INFO_HEADER_SIZE: u32 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Icon,
+    Cursor,
+}
+
+impl Kind {
+    fn resource_type(self) -> u16 {
+        match self {
+            Self::Icon => 1,
+            Self::Cursor => 2,
+        }
+    }
+}
+
+struct Entry {
+    image: RgbaImage,
+    hotspot: (u16, u16),
+}
+
+impl Entry {
+    fn size(&self) -> u32 {
+        self.image.width().max(self.image.height())
+    }
+
+    /// Scale down such that the larger side has `size` pixels
+    fn scaled(&self, size: u32) -> Result<Self, ProcessError> {
+        let scale =
+            |x: u32| -> Result<u32, ProcessError> { Ok((x.smul(size)? / self.size()).max(1)) };
+
+        let width = scale(self.image.width())?;
+        let height = scale(self.image.height())?;
+
+        let image = image::imageops::resize(&self.image, width, height, FilterType::Lanczos3);
+
+        let (x, y) = self.hotspot;
+        let hotspot = (
+            scale(x.into())?.min(width - 1) as u16,
+            scale(y.into())?.min(height - 1) as u16,
+        );
+
+        Ok(Self { image, hotspot })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, ProcessError> {
+        if self.size() >= MAX_SIZE {
+            self.encode_png()
+        } else {
+            self.encode_bmp()
+        }
+    }
+
+    fn encode_png(&self) -> Result<Vec<u8>, ProcessError> {
+        let mut buf = Vec::new();
+
+        PngEncoder::new(&mut buf)
+            .write_image(
+                self.image.as_raw(),
+                self.image.width(),
+                self.image.height(),
+                ExtendedColorType::Rgba8,
+            )
+            .expected_error()?;
+
+        Ok(buf)
+    }
+
+    /// Encode as BMP without file header
+    ///
+    /// The height is doubled since the color data is followed by a 1-bit
+    /// transparency mask.
+    fn encode_bmp(&self) -> Result<Vec<u8>, ProcessError> {
+        let width = self.image.width();
+        let height = self.image.height();
+
+        // Rows of the mask are padded to 32 bits
+        let mask_stride = width.div_ceil(32).smul(4)?;
+        let color_size = width.smul(height)?.smul(4)?;
+        let image_size = color_size.sadd(mask_stride.smul(height)?)?;
+
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&BITMAP_INFO_HEADER_SIZE.to_le_bytes());
+        buf.extend_from_slice(&width.to_le_bytes());
+        buf.extend_from_slice(&height.smul(2)?.to_le_bytes());
+        // Planes
+        buf.extend_from_slice(&1_u16.to_le_bytes());
+        // Bits per pixel
+        buf.extend_from_slice(&32_u16.to_le_bytes());
+        // Uncompressed
+        buf.extend_from_slice(&0_u32.to_le_bytes());
+        buf.extend_from_slice(&image_size.to_le_bytes());
+        // Resolution and palette are unused
+        buf.extend_from_slice(&[0; 16]);
+
+        // Rows are stored bottom-up
+        for row in self.image.rows().rev() {
+            for pixel in row {
+                let [r, g, b, a] = pixel.0;
+                buf.extend_from_slice(&[b, g, r, a]);
+            }
+        }
+
+        for row in self.image.rows().rev() {
+            let mut mask_row = vec![0; mask_stride as usize];
+            for (x, pixel) in row.enumerate() {
+                if pixel.0[3] == 0 {
+                    mask_row[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            buf.extend_from_slice(&mask_row);
+        }
+
+        Ok(buf)
+    }
+}
+
+fn new_entry<B: ByteData>(frame: Frame<B>) -> Result<Entry, ProcessError> {
+    let mut frame = frame.into_fungible();
+    editing::change_memory_format(&mut frame, MemoryFormat::R8g8b8a8).expected_error()?;
+
+    if frame.stride != frame.width.smul(4)? {
+        return Err(ProcessError::expected(&"Frame has unsupported stride"));
+    }
+
+    let hotspot = frame.details.cursor_hotspot.unwrap_or_default();
+    if u32::from(hotspot.0) >= frame.width || u32::from(hotspot.1) >= frame.height {
+        return Err(ProcessError::expected(&format!(
+            "Cursor hotspot {hotspot:?} is outside of the {}x{} image",
+            frame.width, frame.height
+        )));
+    }
+
+    let image =
+        RgbaImage::from_raw(frame.width, frame.height, frame.texture.to_vec()).expected_error()?;
+
+    Ok(Entry { image, hotspot })
+}
+
+/// Add standard sizes that are missing, using the largest frame as source
+///
+/// Frames that are too large to be stored are only used as source.
+fn add_standard_sizes(entries: &mut Vec<Entry>) -> Result<(), ProcessError> {
+    let Some(largest) = entries.iter().max_by_key(|x| x.size()) else {
+        return Ok(());
+    };
+
+    let new_entries = STANDARD_SIZES
+        .into_iter()
+        .filter(|size| *size <= largest.size())
+        .filter(|size| !entries.iter().any(|x| x.size() == *size))
+        .map(|size| largest.scaled(size))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    entries.extend(new_entries);
+    entries.retain(|x| x.size() <= MAX_SIZE);
+
+    Ok(())
+}
+
+pub fn create<B: ByteData>(
+    kind: Kind,
+    new_image: NewImage<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let mut entries = new_image
+        .frames
+        .into_iter()
+        .map(new_entry)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if encoding_options.icon_standard_sizes == Some(true) {
+        add_standard_sizes(&mut entries)?;
+    }
+
+    if let Some(entry) = entries.iter().find(|x| x.size() > MAX_SIZE) {
+        return Err(ProcessError::expected(&format!(
+            "Size {}x{} is larger than the maximum of {MAX_SIZE} pixels",
+            entry.image.width(),
+            entry.image.height()
+        )));
+    }
+
+    // Largest entry first
+    entries.sort_by_key(|x| std::cmp::Reverse(x.size()));
+
+    let n_entries = u16::try_from(entries.len()).expected_error()?;
+    let encoded = entries
+        .iter()
+        .map(Entry::encode)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut buf = Vec::new();
+
+    // Reserved
+    buf.extend_from_slice(&0_u16.to_le_bytes());
+    buf.extend_from_slice(&kind.resource_type().to_le_bytes());
+    buf.extend_from_slice(&n_entries.to_le_bytes());
+
+    let mut offset = ICON_DIR_SIZE.sadd(ICON_DIR_ENTRY_SIZE.smul(encoded.len())?)?;
+
+    for (entry, data) in entries.iter().zip(&encoded) {
+        // A value of zero means 256 pixels
+        buf.push(entry.image.width() as u8);
+        buf.push(entry.image.height() as u8);
+        // No palette
+        buf.push(0);
+        // Reserved
+        buf.push(0);
+
+        let (planes_or_x, bit_count_or_y) = match kind {
+            Kind::Icon => (1, 32),
+            Kind::Cursor => entry.hotspot,
+        };
+        buf.extend_from_slice(&planes_or_x.to_le_bytes());
+        buf.extend_from_slice(&bit_count_or_y.to_le_bytes());
+
+        buf.extend_from_slice(&u32::try_from(data.len()).expected_error()?.to_le_bytes());
+        buf.extend_from_slice(&u32::try_from(offset).expected_error()?.to_le_bytes());
+
+        offset = offset.sadd(data.len())?;
+    }
+
+    for data in encoded {
+        buf.extend_from_slice(&data);
+    }
+
+    Ok(buf)
+}
@@ -9,11 +9,14 @@ use gufo_jpeg::Jpeg;
 use zune_jpeg::zune_core::options::DecoderOptions;
 use zune_jpeg::zune_core::{self};
 
+use crate::cmyk;
+
 pub struct EditJpeg {
     buf: Vec<u8>,
 }
 
-pub fn create(
+pub fn create<B: ByteData>(
+    image_info: &ImageDetails<B>,
     frame: Frame<FungibleMemory>,
     encoding_options: EncodingOptions,
     icc_profile: Option<Vec<u8>>,
@@ -27,20 +30,64 @@ pub fn create(
             .unwrap_or(90),
     );
 
-    let color_type = match frame.memory_format {
-        MemoryFormat::R8g8b8 => jpeg_encoder::ColorType::Rgb,
-        MemoryFormat::G8 => jpeg_encoder::ColorType::Luma,
-        format => {
-            return Err(ProcessError::expected(&format!(
-                "Unsupported memory format: {format:?}"
-            )));
-        }
-    };
+    // CMYK is stored with the CMYK profile instead of the frame's profile
+    let (frame, color_type, icc_profile) =
+        if let Some(cmyk_icc_profile) = &encoding_options.cmyk_icc_profile {
+            (
+                cmyk::from_rgb(&frame, cmyk_icc_profile)?,
+                jpeg_encoder::ColorType::Cmyk,
+                Some(cmyk_icc_profile.clone()),
+            )
+        } else {
+            let color_type = match frame.memory_format {
+                MemoryFormat::R8g8b8 => jpeg_encoder::ColorType::Rgb,
+                MemoryFormat::G8 => jpeg_encoder::ColorType::Luma,
+                format => {
+                    return Err(ProcessError::expected(&format!(
+                        "Unsupported memory format: {format:?}"
+                    )));
+                }
+            };
+
+            (frame, color_type, icc_profile)
+        };
+
+    if let Some(chroma_subsampling) = encoding_options.chroma_subsampling {
+        encoder.set_sampling_factor(match chroma_subsampling {
+            ChromaSubsampling::Yuv444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+            chroma_subsampling => {
+                return Err(ProcessError::UnsupportedFeature(format!(
+                    "Chroma subsampling {chroma_subsampling:?}"
+                )));
+            }
+        });
+    }
+
+    if let Some(progressive) = encoding_options.progressive {
+        encoder.set_progressive(progressive);
+    }
+
+    // Optimized Huffman tables require an additional pass over the image data
+    if encoding_options.effort.is_some_and(|x| x >= 50) {
+        encoder.set_optimized_huffman_tables(true);
+    }
 
     if let Some(icc_profile) = icc_profile {
         let _ = encoder.add_icc_profile(&icc_profile);
     }
 
+    if let Some(exif) = &image_info.metadata_exif {
+        encoder.add_exif_metadata(exif).expected_error()?;
+    }
+
+    if let Some(xmp) = &image_info.metadata_xmp {
+        let mut segment = gufo_jpeg::XMP_IDENTIFIER_STRING.to_vec();
+        segment.extend_from_slice(xmp);
+        encoder.add_app_segment(1, segment).expected_error()?;
+    }
+
     if let Some(pixel_density) = frame.details.pixel_density {
         let (unit, unit_jpeg) = match pixel_density.x().unit() {
             PhysicalDimensionUnit::Centimeter => (
@@ -97,7 +144,9 @@ pub fn apply_sparse<B: ByteData>(
     if let Some(orientation) = operations.orientation()
         && let Some(byte_changes) = rotate_sparse(orientation, &jpeg)?
     {
-        return Ok(SparseEditorOutput::byte_changes(byte_changes));
+        return Ok(SparseEditorOutput::byte_changes(
+            byte_changes.with_original_values(&edit_jpeg.buf)?,
+        ));
     }
 
     Ok(SparseEditorOutput::from(apply_non_sparse(
@@ -129,6 +178,23 @@ pub fn apply_complete<B: ByteData>(
     apply_non_sparse(jpeg, operations)
 }
 
+pub fn preview<B: ByteData>(
+    edit_jpeg: &EditJpeg,
+    mut operations: Operations,
+    frame_request: FrameRequest,
+) -> Result<Frame<B>, glycin_utils::ProcessError> {
+    let jpeg = gufo::jpeg::Jpeg::new(edit_jpeg.buf.clone()).expected_error()?;
+    let metadata = gufo::Metadata::for_jpeg(&jpeg);
+    if let Some(orientation) = metadata.orientation() {
+        operations.prepend(Operations::new_orientation(orientation));
+    }
+
+    let decoder =
+        image::codecs::jpeg::JpegDecoder::new(Cursor::new(&edit_jpeg.buf)).expected_error()?;
+
+    super::decoder_preview(decoder, &operations, &frame_request)
+}
+
 fn apply_non_sparse<B: ByteData>(
     jpeg: Jpeg,
     operations: Operations,
@@ -1,7 +1,9 @@
 use std::io::{Cursor, Read};
 
+use glycin_utils::quantize::{AlphaMode, IndexedImage, Quantizer};
+use glycin_utils::safe_math::SafeMath;
 use glycin_utils::{image_rs, *};
-use gufo::png::NewChunk;
+use gufo::png::{ChunkType, NewChunk};
 use gufo_common::error::ErrorWithData;
 use gufo_common::physical_dimension::PhysicalDimensionUnit;
 use gufo_common::{field, orientation};
@@ -12,11 +14,12 @@ pub struct EditorPng {
     png: gufo::png::Png,
     metadata: gufo::Metadata,
     editing_frame: glycin_utils::editing::EditingFrame<LocalMemory>,
+    icc_profile: Option<Vec<u8>>,
 }
 
 pub fn create<B: ByteData>(
     new_image: NewImage<B>,
-    frame: Frame<FungibleMemory>,
+    mut frame: Frame<FungibleMemory>,
     encoding_options: EncodingOptions,
     memory_format: ExtendedColorType,
     icc_profile: Option<Vec<u8>>,
@@ -33,6 +36,11 @@ pub fn create<B: ByteData>(
         image::codecs::png::CompressionType::Default
     };
 
+    if let Some(palette_size) = encoding_options.palette_size {
+        let out_buf = encode_indexed(&mut frame, palette_size, compression, icc_profile)?;
+        return Ok(add_metadata(out_buf, &new_image.image_info, &frame.details));
+    }
+
     let mut out_buf = Vec::new();
     let mut encoder = image::codecs::png::PngEncoder::new_with_quality(
         &mut out_buf,
@@ -51,12 +59,93 @@ pub fn create<B: ByteData>(
     Ok(add_metadata(out_buf, &new_image.image_info, &frame.details))
 }
 
+/// Encode with a palette of at most `palette_size` colors
+fn encode_indexed(
+    frame: &mut Frame<FungibleMemory>,
+    palette_size: u16,
+    compression: image::codecs::png::CompressionType,
+    icc_profile: Option<Vec<u8>>,
+) -> Result<Vec<u8>, ProcessError> {
+    editing::change_memory_format(frame, MemoryFormat::R8g8b8a8).expected_error()?;
+
+    if frame.stride != frame.width.smul(4)? {
+        return Err(ProcessError::expected(&"Frame has unsupported stride"));
+    }
+
+    let indexed = Quantizer::new(palette_size)
+        .alpha_mode(AlphaMode::Full)
+        .quantize(&frame.texture, frame.width);
+
+    let bit_depth = match indexed.palette.len() {
+        0..=2 => png::BitDepth::One,
+        3..=4 => png::BitDepth::Two,
+        5..=16 => png::BitDepth::Four,
+        _ => png::BitDepth::Eight,
+    };
+
+    let mut info = png::Info::with_size(frame.width, frame.height);
+    info.color_type = png::ColorType::Indexed;
+    info.bit_depth = bit_depth;
+    info.palette = Some(indexed.palette_rgb().into());
+    info.icc_profile = icc_profile.map(Into::into);
+
+    // Trailing opaque entries can be omitted
+    let mut alpha = indexed.palette_alpha();
+    while alpha.last() == Some(&255) {
+        alpha.pop();
+    }
+    if !alpha.is_empty() {
+        info.trns = Some(alpha.into());
+    }
+
+    let mut out_buf = Vec::new();
+    let mut encoder = png::Encoder::with_info(&mut out_buf, info).expected_error()?;
+    encoder.set_compression(match compression {
+        image::codecs::png::CompressionType::Fast => png::Compression::Fast,
+        image::codecs::png::CompressionType::Best => png::Compression::High,
+        _ => png::Compression::Balanced,
+    });
+
+    let mut writer = encoder.write_header().expected_error()?;
+    writer
+        .write_image_data(&pack_indices(&indexed, bit_depth as u8))
+        .expected_error()?;
+    writer.finish().expected_error()?;
+
+    Ok(out_buf)
+}
+
+/// Pack indices into rows with `bits` bits per pixel
+fn pack_indices(indexed: &IndexedImage, bits: u8) -> Vec<u8> {
+    if bits == 8 {
+        return indexed.indices.clone();
+    }
+
+    let pixels_per_byte = usize::from(8 / bits);
+    let width = (indexed.width as usize).max(1);
+
+    indexed
+        .indices
+        .chunks(width)
+        .flat_map(|row| {
+            row.chunks(pixels_per_byte).map(|pixels| {
+                pixels.iter().enumerate().fold(0, |byte, (i, index)| {
+                    byte | index << (8 - bits as usize * (i + 1))
+                })
+            })
+        })
+        .collect()
+}
+
 pub fn load<S: Read>(mut stream: S) -> Result<EditorPng, glycin_utils::ProcessError> {
     let mut old_png_data: Vec<u8> = Vec::new();
     stream.read_to_end(&mut old_png_data).internal_error()?;
     let cursor = Cursor::new(&old_png_data);
 
-    let decoder = image::codecs::png::PngDecoder::new(cursor).expected_error()?;
+    let mut decoder = image::codecs::png::PngDecoder::new(cursor).expected_error()?;
+    let icc_profile = image::ImageDecoder::icc_profile(&mut decoder)
+        .ok()
+        .flatten();
 
     let editing_frame = image_rs::Handler::default()
         .editing_frame(decoder)
@@ -69,6 +158,7 @@ pub fn load<S: Read>(mut stream: S) -> Result<EditorPng, glycin_utils::ProcessEr
         png,
         metadata,
         editing_frame,
+        icc_profile,
     })
 }
 
@@ -118,6 +208,27 @@ pub fn apply<B: ByteData>(
     Ok(CompleteEditorOutput::new(data))
 }
 
+pub fn preview<B: ByteData>(
+    img_editor: &EditorPng,
+    mut operations: Operations,
+    frame_request: FrameRequest,
+) -> Result<Frame<B>, glycin_utils::ProcessError> {
+    if let Some(orientation) = img_editor.metadata.orientation() {
+        operations.prepend(Operations::new_orientation(orientation));
+    }
+
+    let editing_frame = img_editor.editing_frame.clone().into_funglible();
+    let mut frame = editing::preview(editing_frame, &operations, &frame_request)?;
+    frame.details.color_icc_profile = img_editor
+        .icc_profile
+        .clone()
+        .map(B::try_from_vec)
+        .transpose()
+        .expected_error()?;
+
+    Ok(frame)
+}
+
 fn reset_exif_orientation(mut png: gufo::png::Png) -> Result<Vec<u8>, glycin_utils::ProcessError> {
     let ornt = png
         .chunks()
@@ -219,6 +330,14 @@ fn add_metadata_internal<B: ByteData, C: ByteData>(
         }
     }
 
+    if let Some(exif) = &image_info.metadata_exif {
+        new_chunks.push(NewChunk::new(ChunkType::eXIf, exif.to_vec()));
+    }
+
+    if let Some(xmp) = &image_info.metadata_xmp {
+        new_chunks.push(NewChunk::new(ChunkType::iTXt, xmp_itxt(xmp)));
+    }
+
     if let Some(pixel_density) = &frame_details.pixel_density {
         let pixel_density = pixel_density.convert(PhysicalDimensionUnit::Meter);
         new_chunks.push(NewChunk::phys_meter(
@@ -235,3 +354,13 @@ fn add_metadata_internal<B: ByteData, C: ByteData>(
 
     Ok(png.into_inner())
 }
+
+/// Uncompressed iTXt chunk data with the XMP keyword
+fn xmp_itxt(xmp: &[u8]) -> Vec<u8> {
+    let mut data = b"XML:com.adobe.xmp\0".to_vec();
+    // No compression, empty language tag, and empty translated keyword
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(xmp);
+
+    data
+}
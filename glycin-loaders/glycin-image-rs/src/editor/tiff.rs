@@ -1,26 +1,56 @@
-use std::io::Cursor;
+use std::borrow::Cow;
+use std::io::{Cursor, Seek, Write};
 
 use glycin_utils::*;
+use gufo_common::exif::IfdId;
 use gufo_common::physical_dimension::PhysicalDimensionUnit;
-use tiff::encoder::{Rational, TiffValue, colortype};
-use tiff::tags::ResolutionUnit;
+use gufo_exif::Typed;
+use tiff::Directory;
+use tiff::encoder::{
+    Compression, DeflateLevel, Rational, SRational, TiffEncoder, TiffKindStandard, TiffValue,
+    colortype,
+};
+use tiff::tags::{ResolutionUnit, Tag, Type};
 
-fn write_tiff<B: ByteData, C: colortype::ColorType<Inner: bytemuck::AnyBitPattern>>(
-    frame: Frame<B>,
-) -> Result<Vec<u8>, ProcessError>
+use crate::cmyk;
+
+/// XMP packet as defined in the XMP specification part 3
+const TAG_XMP: u16 = 700;
+/// Pointer to the interoperability IFD inside the Exif IFD
+const TAG_INTEROPERABILITY_IFD: u16 = 0xA005;
+
+/// Maps the compression level from 0 to 100 to a TIFF compression method
+fn compression(encoding_options: &EncodingOptions) -> Compression {
+    match encoding_options.compression {
+        None | Some(0) => Compression::Uncompressed,
+        Some(..30) => Compression::Packbits,
+        Some(..60) => Compression::Lzw,
+        Some(..80) => Compression::Deflate(DeflateLevel::Fast),
+        Some(..95) => Compression::Deflate(DeflateLevel::Balanced),
+        Some(_) => Compression::Deflate(DeflateLevel::Best),
+    }
+}
+
+/// Write the frame as a new image file directory (page)
+fn write_page<W: Write + Seek, B: ByteData, C: colortype::ColorType<Inner: bytemuck::Pod>>(
+    tiff_encoder: &mut TiffEncoder<W, TiffKindStandard>,
+    frame: &Frame<B>,
+    xmp: Option<&[u8]>,
+    exif: Option<&[u8]>,
+) -> Result<(), ProcessError>
 where
     [C::Inner]: TiffValue,
 {
-    let mut buf = Vec::new();
-
-    let mut tiff_encoder =
-        tiff::encoder::TiffEncoder::new(Cursor::new(&mut buf)).expected_error()?;
+    let exif_directory = exif
+        .map(|exif| write_exif_directory(tiff_encoder, exif))
+        .transpose()?
+        .flatten();
 
     let mut image_encoder = tiff_encoder
         .new_image::<C>(frame.width, frame.height)
         .expected_error()?;
 
-    if let Some(pixel_density) = frame.details.pixel_density {
+    if let Some(pixel_density) = &frame.details.pixel_density {
         let (pixel_density, unit) =
             if matches!(pixel_density.x().unit(), PhysicalDimensionUnit::Centimeter) {
                 // Make sure that both are using the same unit
@@ -36,7 +66,7 @@ where
             };
 
         let x_rational = pixel_density.x().value_rational();
-        let y_rational = pixel_density.x().value_rational();
+        let y_rational = pixel_density.y().value_rational();
 
         image_encoder.x_resolution(Rational {
             n: x_rational.numerator,
@@ -51,20 +81,184 @@ where
         image_encoder.resolution_unit(unit);
     }
 
-    let data = bytemuck::try_cast_slice(&frame.texture).unwrap();
-    image_encoder.write_data(data).expected_error()?;
+    if let Some(icc_profile) = &frame.details.color_icc_profile {
+        image_encoder
+            .encoder()
+            .write_tag(Tag::IccProfile, &icc_profile[..])
+            .expected_error()?;
+    }
 
-    Ok(buf)
+    if let Some(xmp) = xmp {
+        image_encoder
+            .encoder()
+            .write_tag(Tag::Unknown(TAG_XMP), xmp)
+            .expected_error()?;
+    }
+
+    if let Some(exif_directory) = exif_directory {
+        image_encoder
+            .encoder()
+            .write_tag(Tag::ExifDirectory, exif_directory)
+            .expected_error()?;
+    }
+
+    // Textures that are not aligned for the sample type are copied
+    let data: Cow<[C::Inner]> = match bytemuck::try_cast_slice(&frame.texture) {
+        Ok(data) => Cow::Borrowed(data),
+        Err(_) if frame.texture.len().is_multiple_of(size_of::<C::Inner>()) => {
+            Cow::Owned(bytemuck::pod_collect_to_vec(&frame.texture))
+        }
+        Err(err) => {
+            return Err(ProcessError::expected(&format!(
+                "Texture doesn't consist of whole samples: {err}"
+            )));
+        }
+    };
+    image_encoder.write_data(&data).expected_error()?;
+
+    Ok(())
+}
+
+/// Write the Exif IFD of the Exif data as a directory that is not part of the
+/// page sequence
+///
+/// Tags of the primary IFD describe the original image data and are not
+/// copied. Returns the offset of the directory, which is stored as `LONG` since
+/// not all readers support the `IFD` type, or `None` if the Exif data don't
+/// contain an Exif IFD.
+fn write_exif_directory<W: Write + Seek>(
+    tiff_encoder: &mut TiffEncoder<W, TiffKindStandard>,
+    exif: &[u8],
+) -> Result<Option<u32>, ProcessError> {
+    let mut exif = exif.to_vec();
+    let mut document = gufo_exif::structure::Document::for_mut_slice(&mut exif).expected_error()?;
+    let Some(entries) = document.entries().expected_error()?.remove(&IfdId::Exif) else {
+        return Ok(None);
+    };
+
+    let mut directory = tiff_encoder.extra_directory().expected_error()?;
+
+    for (tag, entry) in entries {
+        // Nested IFDs would point to offsets in the original Exif data
+        if tag.0 == TAG_INTEROPERABILITY_IFD {
+            continue;
+        }
+
+        let Ok(value) = entry.data else {
+            continue;
+        };
+
+        let tag = Tag::from_u16_exhaustive(tag.0);
+        let result = match value {
+            Typed::Byte(x) => directory.write_tag(tag, &x[..]),
+            Typed::Ascii(mut x) => {
+                x.push(0);
+                write_tag_bytes(&mut directory, tag, Type::ASCII, &x)
+            }
+            Typed::Short(x) => directory.write_tag(tag, &x[..]),
+            Typed::Long(x) => directory.write_tag(tag, &x[..]),
+            Typed::Rational(x) => directory.write_tag(
+                tag,
+                &x.iter()
+                    .map(|x| Rational {
+                        n: x.numerator,
+                        d: x.denominator,
+                    })
+                    .collect::<Vec<_>>()[..],
+            ),
+            Typed::Undefined(x) => write_tag_bytes(&mut directory, tag, Type::UNDEFINED, &x),
+            Typed::SLong(x) => directory.write_tag(tag, &x[..]),
+            Typed::SRational(x) => directory.write_tag(
+                tag,
+                &x.iter()
+                    .map(|x| SRational {
+                        n: x.numerator,
+                        d: x.denominator,
+                    })
+                    .collect::<Vec<_>>()[..],
+            ),
+            Typed::Utf8(_) | Typed::Unknown(..) => continue,
+        };
+
+        result.expected_error()?;
+    }
+
+    let offset = directory.finish_with_offsets().expected_error()?;
+
+    Ok(Some(offset.offset))
 }
 
-pub fn create<B: ByteData>(frame: Frame<B>) -> Result<Vec<u8>, ProcessError> {
-    match frame.memory_format {
-        MemoryFormat::R8g8b8 => write_tiff::<B, colortype::RGB8>(frame),
-        MemoryFormat::R8g8b8a8 => write_tiff::<B, colortype::RGBA8>(frame),
-        MemoryFormat::R16g16b16 => write_tiff::<B, colortype::RGB16>(frame),
-        MemoryFormat::R16g16b16a16 => write_tiff::<B, colortype::RGBA16>(frame),
-        MemoryFormat::R32g32b32Float => write_tiff::<B, colortype::RGB32Float>(frame),
-        MemoryFormat::R32g32b32a32Float => write_tiff::<B, colortype::RGB32Float>(frame),
-        _ => todo!(),
+/// Write a tag with data that don't depend on the byte order
+fn write_tag_bytes<W: Write + Seek>(
+    directory: &mut tiff::encoder::DirectoryEncoder<'_, W, TiffKindStandard>,
+    tag: Tag,
+    type_: Type,
+    data: &[u8],
+) -> tiff::TiffResult<()> {
+    let entry = directory.write_entry_bytes(type_, data)?;
+    directory.extend_from(&Directory::from_iter([(tag, entry)]));
+
+    Ok(())
+}
+
+/// Create a TIFF file with one page per frame
+pub fn create<B: ByteData>(
+    new_image: &NewImage<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let mut buf = Vec::new();
+
+    let mut tiff_encoder = TiffEncoder::new(Cursor::new(&mut buf))
+        .expected_error()?
+        .with_compression(compression(encoding_options));
+
+    for (i, frame) in new_image.frames.iter().enumerate() {
+        let encoder = &mut tiff_encoder;
+        // Metadata applies to the whole document and is only stored with the first page
+        let xmp = new_image
+            .image_info
+            .metadata_xmp
+            .as_ref()
+            .filter(|_| i == 0)
+            .map(|x| &x[..]);
+        let exif = new_image
+            .image_info
+            .metadata_exif
+            .as_ref()
+            .filter(|_| i == 0)
+            .map(|x| &x[..]);
+
+        // CMYK pages are stored with the CMYK profile instead of the frame's profile
+        if let Some(icc_profile) = &encoding_options.cmyk_icc_profile {
+            let frame = cmyk::from_rgb(frame, icc_profile)?;
+            write_page::<_, _, colortype::CMYK8>(encoder, &frame, xmp, exif)?;
+            continue;
+        }
+
+        match frame.memory_format {
+            MemoryFormat::R8g8b8 => write_page::<_, B, colortype::RGB8>(encoder, frame, xmp, exif)?,
+            MemoryFormat::R8g8b8a8 => {
+                write_page::<_, B, colortype::RGBA8>(encoder, frame, xmp, exif)?
+            }
+            MemoryFormat::R16g16b16 => {
+                write_page::<_, B, colortype::RGB16>(encoder, frame, xmp, exif)?
+            }
+            MemoryFormat::R16g16b16a16 => {
+                write_page::<_, B, colortype::RGBA16>(encoder, frame, xmp, exif)?
+            }
+            MemoryFormat::R32g32b32Float => {
+                write_page::<_, B, colortype::RGB32Float>(encoder, frame, xmp, exif)?
+            }
+            MemoryFormat::R32g32b32a32Float => {
+                write_page::<_, B, colortype::RGBA32Float>(encoder, frame, xmp, exif)?
+            }
+            memory_format => {
+                return Err(ProcessError::expected(&format!(
+                    "Unsupported memory format for TIFF: {memory_format:?}"
+                )));
+            }
+        }
     }
+
+    Ok(buf)
 }
@@ -0,0 +1,1337 @@
+//! Lossy WebP encoding
+//!
+//! The color channels are written as a VP8 key frame (RFC 6386) with 4:2:0
+//! chroma subsampling. Only the 16x16 intra prediction modes are used, which
+//! keeps the encoder small while still giving reasonable compression. The
+//! alpha channel is stored separately in an `ALPH` chunk that is compressed
+//! with WebP lossless.
+
+mod tables;
+
+use glycin_utils::*;
+use gufo::webp::FourCC;
+use image::ExtendedColorType;
+use image::codecs::webp::WebPEncoder;
+use tables::*;
+
+/// Largest width and height that VP8 can store
+const MAX_DIMENSION: u32 = 0x3FFF;
+/// Largest size of the first partition, stored with 19 bits
+const MAX_FIRST_PARTITION_SIZE: usize = (1 << 19) - 1;
+
+/// Quality that is used if none is specified
+pub const DEFAULT_QUALITY: u8 = 75;
+
+/// Payloads of the chunks for a lossy WebP frame
+pub struct LossyFrame {
+    /// `VP8 ` chunk
+    pub vp8: Vec<u8>,
+    /// `ALPH` chunk, if the frame has transparent pixels
+    pub alpha: Option<Vec<u8>>,
+}
+
+/// Encode RGBA pixels as lossy WebP frame
+///
+/// The `quality` controls the quantization of the color channels and
+/// `alpha_quality` the number of different alpha levels. Both are given from 0
+/// to 100, with 100 being the best quality.
+pub fn encode(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+    alpha_quality: u8,
+) -> Result<LossyFrame, ProcessError> {
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(ProcessError::expected(&format!(
+            "Image too large for lossy WebP: {width}x{height}"
+        )));
+    }
+
+    let width = usize::try_from(width).internal_error()?;
+    let height = usize::try_from(height).internal_error()?;
+
+    if width == 0 || height == 0 || rgba.len() != width * height * 4 {
+        return Err(ProcessError::expected(&"Invalid frame dimensions"));
+    }
+
+    let vp8 = Vp8Encoder::new(rgba, width, height, quality).encode()?;
+    let alpha = encode_alpha(rgba, width, height, alpha_quality)?;
+
+    Ok(LossyFrame { vp8, alpha })
+}
+
+/// Quality that corresponds to the quantizer of an existing `VP8 ` chunk
+///
+/// This is the inverse of the mapping used by [`encode()`]. Quantizer deltas
+/// and segment quantizers are ignored.
+pub fn quality(vp8: &[u8]) -> Option<u8> {
+    let frame_tag = u32::from_le_bytes([*vp8.first()?, *vp8.get(1)?, *vp8.get(2)?, 0]);
+    let is_key_frame = frame_tag & 1 == 0;
+    let first_partition_size = usize::try_from(frame_tag >> 5).ok()?;
+
+    if !is_key_frame {
+        return None;
+    }
+
+    let mut r = BoolReader::new(vp8.get(10..10 + first_partition_size)?)?;
+
+    // Color space and clamping type
+    r.literal(2);
+
+    // Segmentation (9.3)
+    if r.flag() {
+        let update_map = r.flag();
+        if r.flag() {
+            // Segment feature mode
+            r.literal(1);
+            // Quantizer and loop filter level per segment
+            for bits in [7, 7, 7, 7, 6, 6, 6, 6] {
+                r.optional_signed(bits);
+            }
+        }
+        if update_map {
+            for _ in 0..3 {
+                if r.flag() {
+                    r.literal(8);
+                }
+            }
+        }
+    }
+
+    // Filter type, loop filter level, and sharpness (9.4)
+    r.literal(1 + 6 + 3);
+
+    // Loop filter adjustments
+    if r.flag() && r.flag() {
+        for _ in 0..8 {
+            r.optional_signed(6);
+        }
+    }
+
+    // Number of token partitions (9.5)
+    r.literal(2);
+
+    // Quantizer index (9.6)
+    let quantizer_index = r.literal(7);
+
+    Some((100 - (quantizer_index * 100 + 63) / 127) as u8)
+}
+
+/// Intra prediction modes for 16x16 luma and 8x8 chroma blocks
+///
+/// The values correspond to the order of the modes in the mode trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Dc,
+    V,
+    H,
+    Tm,
+}
+
+impl Mode {
+    const ALL: [Self; 4] = [Self::Dc, Self::V, Self::H, Self::Tm];
+}
+
+/// Block types as used for the token probabilities
+#[derive(Debug, Clone, Copy)]
+enum BlockType {
+    /// Luma block without DC, which is stored in the Y2 block
+    YAfterY2 = 0,
+    /// Block of the luma DCs
+    Y2 = 1,
+    Chroma = 2,
+}
+
+/// Decisions for a macroblock that are stored in the first partition
+struct MacroblockHeader {
+    luma_mode: Mode,
+    chroma_mode: Mode,
+    skip: bool,
+}
+
+/// Quantizer step sizes for DC and AC coefficients
+#[derive(Debug, Clone, Copy)]
+struct Steps {
+    dc: i32,
+    ac: i32,
+}
+
+/// Quantizer step sizes for all block types (14.1)
+struct Quantizer {
+    y1: Steps,
+    y2: Steps,
+    uv: Steps,
+}
+
+impl Quantizer {
+    fn new(index: usize) -> Self {
+        let dc = DC_QUANT[index];
+        let ac = AC_QUANT[index];
+
+        Self {
+            y1: Steps { dc, ac },
+            y2: Steps {
+                dc: dc * 2,
+                ac: (ac * 155 / 100).max(8),
+            },
+            uv: Steps {
+                dc: dc.min(132),
+                ac,
+            },
+        }
+    }
+}
+
+/// One plane of the image, padded to full macroblocks
+struct Plane {
+    data: Vec<u8>,
+    stride: usize,
+}
+
+impl Plane {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            data: vec![0; width * height],
+            stride: width,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.stride + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.data[y * self.stride + x] = value;
+    }
+
+    /// Neighboring pixels of a block, used for the prediction
+    ///
+    /// Pixels outside of the image are 127 above and 129 left of the image.
+    fn edges(&self, x0: usize, y0: usize, size: usize) -> Edges {
+        let mut above = [127; 16];
+        let mut left = [129; 16];
+
+        if y0 > 0 {
+            for (x, value) in above.iter_mut().enumerate().take(size) {
+                *value = self.get(x0 + x, y0 - 1);
+            }
+        }
+
+        if x0 > 0 {
+            for (y, value) in left.iter_mut().enumerate().take(size) {
+                *value = self.get(x0 - 1, y0 + y);
+            }
+        }
+
+        let top_left = match (x0, y0) {
+            (_, 0) => 127,
+            (0, _) => 129,
+            _ => self.get(x0 - 1, y0 - 1),
+        };
+
+        Edges {
+            above,
+            left,
+            top_left,
+            has_above: y0 > 0,
+            has_left: x0 > 0,
+            size,
+        }
+    }
+}
+
+/// Reconstructed pixels around a block
+struct Edges {
+    above: [u8; 16],
+    left: [u8; 16],
+    top_left: u8,
+    has_above: bool,
+    has_left: bool,
+    size: usize,
+}
+
+impl Edges {
+    /// Predicted block with `size * size` pixels
+    fn predict(&self, mode: Mode) -> [u8; 256] {
+        let size = self.size;
+        let shift = size.trailing_zeros();
+        let mut block = [0; 256];
+
+        let sum = |values: &[u8; 16]| values[..size].iter().map(|x| u32::from(*x)).sum::<u32>();
+
+        for y in 0..size {
+            for x in 0..size {
+                block[y * size + x] = match mode {
+                    Mode::Dc => match (self.has_above, self.has_left) {
+                        (true, true) => {
+                            ((sum(&self.above) + sum(&self.left) + size as u32) >> (shift + 1))
+                                as u8
+                        }
+                        (true, false) => ((sum(&self.above) + size as u32 / 2) >> shift) as u8,
+                        (false, true) => ((sum(&self.left) + size as u32 / 2) >> shift) as u8,
+                        (false, false) => 128,
+                    },
+                    Mode::V => self.above[x],
+                    Mode::H => self.left[y],
+                    Mode::Tm => (i32::from(self.left[y]) + i32::from(self.above[x])
+                        - i32::from(self.top_left))
+                    .clamp(0, 255) as u8,
+                };
+            }
+        }
+
+        block
+    }
+}
+
+struct Vp8Encoder {
+    width: usize,
+    height: usize,
+    mb_width: usize,
+    mb_height: usize,
+    quantizer_index: usize,
+    quantizer: Quantizer,
+    /// Source planes Y, U, and V
+    source: [Plane; 3],
+    /// Planes as the decoder will reconstruct them, used for the prediction
+    recon: [Plane; 3],
+    /// Whether the blocks above have non-zero coefficients, per macroblock
+    /// column
+    ///
+    /// Entries are the four luma columns, two columns for each chroma channel,
+    /// and the Y2 block.
+    top_non_zero: Vec<[bool; 9]>,
+    /// Same as `top_non_zero` for the macroblock to the left
+    left_non_zero: [bool; 9],
+    tokens: BoolWriter,
+}
+
+impl Vp8Encoder {
+    fn new(rgba: &[u8], width: usize, height: usize, quality: u8) -> Self {
+        let mb_width = width.div_ceil(16);
+        let mb_height = height.div_ceil(16);
+        let quantizer_index = (usize::from(100 - quality.min(100)) * 127 + 50) / 100;
+
+        Self {
+            width,
+            height,
+            mb_width,
+            mb_height,
+            quantizer_index,
+            quantizer: Quantizer::new(quantizer_index),
+            source: to_yuv420(rgba, width, height, mb_width, mb_height),
+            recon: [
+                Plane::new(mb_width * 16, mb_height * 16),
+                Plane::new(mb_width * 8, mb_height * 8),
+                Plane::new(mb_width * 8, mb_height * 8),
+            ],
+            top_non_zero: vec![[false; 9]; mb_width],
+            left_non_zero: [false; 9],
+            tokens: BoolWriter::new(),
+        }
+    }
+
+    fn encode(mut self) -> Result<Vec<u8>, ProcessError> {
+        let mut headers = Vec::with_capacity(self.mb_width * self.mb_height);
+
+        for mb_y in 0..self.mb_height {
+            self.left_non_zero = [false; 9];
+            for mb_x in 0..self.mb_width {
+                headers.push(self.encode_macroblock(mb_x, mb_y));
+            }
+        }
+
+        let first_partition = self.first_partition(&headers);
+        let tokens = std::mem::take(&mut self.tokens).finish();
+
+        if first_partition.len() > MAX_FIRST_PARTITION_SIZE {
+            return Err(ProcessError::expected(&"Image too large for lossy WebP"));
+        }
+
+        // Key frame, version 0, shown
+        let frame_tag = (first_partition.len() as u32) << 5 | 1 << 4;
+
+        let mut data = Vec::with_capacity(10 + first_partition.len() + tokens.len());
+        data.extend_from_slice(&frame_tag.to_le_bytes()[..3]);
+        data.extend_from_slice(&[0x9d, 0x01, 0x2a]);
+        // Dimensions without upscaling
+        data.extend_from_slice(&(self.width as u16).to_le_bytes());
+        data.extend_from_slice(&(self.height as u16).to_le_bytes());
+        data.extend_from_slice(&first_partition);
+        data.extend_from_slice(&tokens);
+
+        Ok(data)
+    }
+
+    /// Frame header and macroblock headers (19.2 and 19.3)
+    fn first_partition(&self, headers: &[MacroblockHeader]) -> Vec<u8> {
+        let mut w = BoolWriter::new();
+
+        // Color space and clamping type
+        w.put_literal(0, 1);
+        w.put_literal(0, 1);
+        // No segmentation
+        w.put_literal(0, 1);
+        // Normal loop filter with level depending on the quantizer
+        w.put_literal(0, 1);
+        w.put_literal((self.quantizer_index / 3).min(63) as u32, 6);
+        w.put_literal(0, 3);
+        // No loop filter adjustments
+        w.put_literal(0, 1);
+        // One token partition
+        w.put_literal(0, 2);
+        // Quantizer index without deltas
+        w.put_literal(self.quantizer_index as u32, 7);
+        for _ in 0..5 {
+            w.put_literal(0, 1);
+        }
+        // Refresh entropy probabilities
+        w.put_literal(0, 1);
+
+        // Keep the default token probabilities
+        for prob in COEFF_UPDATE_PROBS
+            .as_flattened()
+            .as_flattened()
+            .as_flattened()
+        {
+            w.put(*prob, false);
+        }
+
+        let skipped = headers.iter().filter(|x| x.skip).count();
+        let prob_skip_false = ((headers.len() - skipped) * 255 / headers.len()).clamp(1, 255) as u8;
+
+        w.put_literal(1, 1);
+        w.put_literal(u32::from(prob_skip_false), 8);
+
+        for header in headers {
+            w.put(prob_skip_false, header.skip);
+
+            // Keyframe luma mode tree without B_PRED
+            let luma_mode = header.luma_mode as u8;
+            w.put(145, true);
+            w.put(156, luma_mode >= 2);
+            if luma_mode < 2 {
+                w.put(163, luma_mode == 1);
+            } else {
+                w.put(128, luma_mode == 3);
+            }
+
+            // Keyframe chroma mode tree
+            let chroma_mode = header.chroma_mode as u8;
+            w.put(142, chroma_mode >= 1);
+            if chroma_mode >= 1 {
+                w.put(114, chroma_mode >= 2);
+                if chroma_mode >= 2 {
+                    w.put(183, chroma_mode == 3);
+                }
+            }
+        }
+
+        w.finish()
+    }
+
+    fn encode_macroblock(&mut self, mb_x: usize, mb_y: usize) -> MacroblockHeader {
+        let (luma_mode, luma_pred) = self.best_prediction(0, mb_x * 16, mb_y * 16, 16);
+        let (chroma_mode, u_pred) = self.best_prediction(1, mb_x * 8, mb_y * 8, 8);
+        let v_pred = self.recon[2]
+            .edges(mb_x * 8, mb_y * 8, 8)
+            .predict(chroma_mode);
+
+        // Luma blocks with their DCs moved into the Y2 block
+        let mut y_levels = [[0; 16]; 16];
+        let mut y_dc = [0; 16];
+        for (b, levels) in y_levels.iter_mut().enumerate() {
+            let x0 = mb_x * 16 + b % 4 * 4;
+            let y0 = mb_y * 16 + b / 4 * 4;
+            let mut coeffs = residual(&self.source[0], &luma_pred, 16, x0, y0, b % 4, b / 4);
+            fdct4x4(&mut coeffs);
+            y_dc[b] = coeffs[0];
+            *levels = quantize_block(&coeffs, self.quantizer.y1, 1);
+        }
+
+        fwht4x4(&mut y_dc);
+        let y2_levels = quantize_block(&y_dc, self.quantizer.y2, 0);
+
+        let mut uv_levels = [[[0; 16]; 4]; 2];
+        for (c, pred) in [&u_pred, &v_pred].into_iter().enumerate() {
+            for (b, levels) in uv_levels[c].iter_mut().enumerate() {
+                let x0 = mb_x * 8 + b % 2 * 4;
+                let y0 = mb_y * 8 + b / 2 * 4;
+                let mut coeffs = residual(&self.source[c + 1], pred, 8, x0, y0, b % 2, b / 2);
+                fdct4x4(&mut coeffs);
+                *levels = quantize_block(&coeffs, self.quantizer.uv, 0);
+            }
+        }
+
+        let skip = y2_levels.iter().all(|x| *x == 0)
+            && y_levels.as_flattened().iter().all(|x| *x == 0)
+            && uv_levels
+                .as_flattened()
+                .as_flattened()
+                .iter()
+                .all(|x| *x == 0);
+
+        if skip {
+            self.top_non_zero[mb_x] = [false; 9];
+            self.left_non_zero = [false; 9];
+        } else {
+            self.write_tokens(mb_x, &y2_levels, &y_levels, &uv_levels);
+        }
+
+        self.reconstruct(mb_x, mb_y, &luma_pred, &y2_levels, &y_levels);
+        for (c, pred) in [&u_pred, &v_pred].into_iter().enumerate() {
+            for (b, levels) in uv_levels[c].iter().enumerate() {
+                let mut coeffs = dequantize_block(levels, self.quantizer.uv);
+                idct4x4(&mut coeffs);
+                add_residual(
+                    &mut self.recon[c + 1],
+                    pred,
+                    8,
+                    &coeffs,
+                    (mb_x * 8, mb_y * 8),
+                    (b % 2, b / 2),
+                );
+            }
+        }
+
+        MacroblockHeader {
+            luma_mode,
+            chroma_mode,
+            skip,
+        }
+    }
+
+    /// Prediction mode with the smallest squared error
+    ///
+    /// For chroma, the error of the U and V channels is combined.
+    fn best_prediction(
+        &self,
+        plane: usize,
+        x0: usize,
+        y0: usize,
+        size: usize,
+    ) -> (Mode, [u8; 256]) {
+        let planes = if plane == 0 { 0..1 } else { 1..3 };
+
+        let edges = planes
+            .clone()
+            .map(|p| self.recon[p].edges(x0, y0, size))
+            .collect::<Vec<_>>();
+
+        Mode::ALL
+            .into_iter()
+            .map(|mode| {
+                let error = planes
+                    .clone()
+                    .zip(&edges)
+                    .map(|(p, edges)| {
+                        let pred = edges.predict(mode);
+                        let mut error = 0;
+                        for y in 0..size {
+                            for x in 0..size {
+                                let diff = i32::from(self.source[p].get(x0 + x, y0 + y))
+                                    - i32::from(pred[y * size + x]);
+                                error += (diff * diff) as u64;
+                            }
+                        }
+                        error
+                    })
+                    .sum::<u64>();
+                (error, mode)
+            })
+            .min_by_key(|(error, _)| *error)
+            .map(|(_, mode)| (mode, edges[0].predict(mode)))
+            .unwrap_or((Mode::Dc, edges[0].predict(Mode::Dc)))
+    }
+
+    fn write_tokens(
+        &mut self,
+        mb_x: usize,
+        y2_levels: &[i32; 16],
+        y_levels: &[[i32; 16]; 16],
+        uv_levels: &[[[i32; 16]; 4]; 2],
+    ) {
+        let top = &mut self.top_non_zero[mb_x];
+        let left = &mut self.left_non_zero;
+
+        let context = usize::from(top[8]) + usize::from(left[8]);
+        let non_zero = self
+            .tokens
+            .put_coefficients(BlockType::Y2, 0, context, y2_levels);
+        top[8] = non_zero;
+        left[8] = non_zero;
+
+        for (b, levels) in y_levels.iter().enumerate() {
+            let (x, y) = (b % 4, b / 4);
+            let context = usize::from(top[x]) + usize::from(left[y]);
+            let non_zero = self
+                .tokens
+                .put_coefficients(BlockType::YAfterY2, 1, context, levels);
+            top[x] = non_zero;
+            left[y] = non_zero;
+        }
+
+        for (c, blocks) in uv_levels.iter().enumerate() {
+            for (b, levels) in blocks.iter().enumerate() {
+                let x = 4 + c * 2 + b % 2;
+                let y = 4 + c * 2 + b / 2;
+                let context = usize::from(top[x]) + usize::from(left[y]);
+                let non_zero = self
+                    .tokens
+                    .put_coefficients(BlockType::Chroma, 0, context, levels);
+                top[x] = non_zero;
+                left[y] = non_zero;
+            }
+        }
+    }
+
+    /// Store the luma pixels of a macroblock as the decoder will see them
+    fn reconstruct(
+        &mut self,
+        mb_x: usize,
+        mb_y: usize,
+        pred: &[u8; 256],
+        y2_levels: &[i32; 16],
+        y_levels: &[[i32; 16]; 16],
+    ) {
+        let mut y_dc = dequantize_block(y2_levels, self.quantizer.y2);
+        iwht4x4(&mut y_dc);
+
+        for (b, levels) in y_levels.iter().enumerate() {
+            let mut coeffs = dequantize_block(levels, self.quantizer.y1);
+            coeffs[0] = y_dc[b];
+            idct4x4(&mut coeffs);
+            add_residual(
+                &mut self.recon[0],
+                pred,
+                16,
+                &coeffs,
+                (mb_x * 16, mb_y * 16),
+                (b % 4, b / 4),
+            );
+        }
+    }
+}
+
+/// Difference between source and prediction for a 4x4 block
+fn residual(
+    source: &Plane,
+    pred: &[u8; 256],
+    size: usize,
+    x0: usize,
+    y0: usize,
+    block_x: usize,
+    block_y: usize,
+) -> [i32; 16] {
+    let mut block = [0; 16];
+    for y in 0..4 {
+        for x in 0..4 {
+            let pred = pred[(block_y * 4 + y) * size + block_x * 4 + x];
+            block[y * 4 + x] = i32::from(source.get(x0 + x, y0 + y)) - i32::from(pred);
+        }
+    }
+    block
+}
+
+/// Add the decoded residual of a 4x4 block to the prediction
+fn add_residual(
+    recon: &mut Plane,
+    pred: &[u8; 256],
+    size: usize,
+    residual: &[i32; 16],
+    (mb_x0, mb_y0): (usize, usize),
+    (block_x, block_y): (usize, usize),
+) {
+    for y in 0..4 {
+        for x in 0..4 {
+            let pred_x = block_x * 4 + x;
+            let pred_y = block_y * 4 + y;
+            let value = i32::from(pred[pred_y * size + pred_x]) + residual[y * 4 + x];
+            recon.set(mb_x0 + pred_x, mb_y0 + pred_y, value.clamp(0, 255) as u8);
+        }
+    }
+}
+
+/// Quantize coefficients starting at `first` in natural order
+fn quantize_block(coeffs: &[i32; 16], steps: Steps, first: usize) -> [i32; 16] {
+    let mut levels = [0; 16];
+    for (i, (level, coeff)) in levels.iter_mut().zip(coeffs).enumerate().skip(first) {
+        // Round the DC to the nearest level and use a dead zone for the AC
+        // coefficients, since small AC coefficients are expensive to store
+        let (step, rounding) = if i == 0 {
+            (steps.dc, steps.dc / 2)
+        } else {
+            (steps.ac, steps.ac * 3 / 8)
+        };
+        let abs = ((coeff.abs() + rounding) / step).min(2047);
+        *level = if *coeff < 0 { -abs } else { abs };
+    }
+    levels
+}
+
+fn dequantize_block(levels: &[i32; 16], steps: Steps) -> [i32; 16] {
+    let mut coeffs = [0; 16];
+    for (i, (coeff, level)) in coeffs.iter_mut().zip(levels).enumerate() {
+        *coeff = level * if i == 0 { steps.dc } else { steps.ac };
+    }
+    coeffs
+}
+
+/// Forward DCT, as in libvpx
+fn fdct4x4(block: &mut [i32; 16]) {
+    let mut tmp = [0; 16];
+    for i in 0..4 {
+        let row = &block[i * 4..][..4];
+        let a1 = (row[0] + row[3]) * 8;
+        let b1 = (row[1] + row[2]) * 8;
+        let c1 = (row[1] - row[2]) * 8;
+        let d1 = (row[0] - row[3]) * 8;
+
+        tmp[i * 4] = a1 + b1;
+        tmp[i * 4 + 2] = a1 - b1;
+        tmp[i * 4 + 1] = (c1 * 2217 + d1 * 5352 + 14500) >> 12;
+        tmp[i * 4 + 3] = (d1 * 2217 - c1 * 5352 + 7500) >> 12;
+    }
+
+    for i in 0..4 {
+        let a1 = tmp[i] + tmp[12 + i];
+        let b1 = tmp[4 + i] + tmp[8 + i];
+        let c1 = tmp[4 + i] - tmp[8 + i];
+        let d1 = tmp[i] - tmp[12 + i];
+
+        block[i] = (a1 + b1 + 7) >> 4;
+        block[8 + i] = (a1 - b1 + 7) >> 4;
+        block[4 + i] = ((c1 * 2217 + d1 * 5352 + 12000) >> 16) + i32::from(d1 != 0);
+        block[12 + i] = (d1 * 2217 - c1 * 5352 + 51000) >> 16;
+    }
+}
+
+/// Inverse DCT (14.3)
+fn idct4x4(block: &mut [i32; 16]) {
+    const C1: i64 = 20091;
+    const C2: i64 = 35468;
+
+    let mut tmp = [0i64; 16];
+    for i in 0..4 {
+        let [i0, i4, i8, i12] = [i, 4 + i, 8 + i, 12 + i].map(|x| i64::from(block[x]));
+        let a1 = i0 + i8;
+        let b1 = i0 - i8;
+        let c1 = ((i4 * C2) >> 16) - (i12 + ((i12 * C1) >> 16));
+        let d1 = (i4 + ((i4 * C1) >> 16)) + ((i12 * C2) >> 16);
+
+        tmp[i] = a1 + d1;
+        tmp[12 + i] = a1 - d1;
+        tmp[4 + i] = b1 + c1;
+        tmp[8 + i] = b1 - c1;
+    }
+
+    for i in 0..4 {
+        let [i0, i1, i2, i3] = [0, 1, 2, 3].map(|x| tmp[i * 4 + x]);
+        let a1 = i0 + i2;
+        let b1 = i0 - i2;
+        let c1 = ((i1 * C2) >> 16) - (i3 + ((i3 * C1) >> 16));
+        let d1 = (i1 + ((i1 * C1) >> 16)) + ((i3 * C2) >> 16);
+
+        block[i * 4] = ((a1 + d1 + 4) >> 3) as i32;
+        block[i * 4 + 3] = ((a1 - d1 + 4) >> 3) as i32;
+        block[i * 4 + 1] = ((b1 + c1 + 4) >> 3) as i32;
+        block[i * 4 + 2] = ((b1 - c1 + 4) >> 3) as i32;
+    }
+}
+
+/// Forward Walsh-Hadamard transform, as in libvpx
+fn fwht4x4(block: &mut [i32; 16]) {
+    let mut tmp = [0; 16];
+    for i in 0..4 {
+        let row = &block[i * 4..][..4];
+        let a1 = (row[0] + row[2]) * 4;
+        let d1 = (row[1] + row[3]) * 4;
+        let c1 = (row[1] - row[3]) * 4;
+        let b1 = (row[0] - row[2]) * 4;
+
+        tmp[i * 4] = a1 + d1 + i32::from(a1 != 0);
+        tmp[i * 4 + 1] = b1 + c1;
+        tmp[i * 4 + 2] = b1 - c1;
+        tmp[i * 4 + 3] = a1 - d1;
+    }
+
+    for i in 0..4 {
+        let a1 = tmp[i] + tmp[8 + i];
+        let d1 = tmp[4 + i] + tmp[12 + i];
+        let c1 = tmp[4 + i] - tmp[12 + i];
+        let b1 = tmp[i] - tmp[8 + i];
+
+        let values = [a1 + d1, b1 + c1, b1 - c1, a1 - d1];
+        for (k, value) in values.into_iter().enumerate() {
+            let value = value + i32::from(value < 0);
+            block[k * 4 + i] = (value + 3) >> 3;
+        }
+    }
+}
+
+/// Inverse Walsh-Hadamard transform (14.3)
+fn iwht4x4(block: &mut [i32; 16]) {
+    for i in 0..4 {
+        let a1 = block[i] + block[12 + i];
+        let b1 = block[4 + i] + block[8 + i];
+        let c1 = block[4 + i] - block[8 + i];
+        let d1 = block[i] - block[12 + i];
+
+        block[i] = a1 + b1;
+        block[4 + i] = c1 + d1;
+        block[8 + i] = a1 - b1;
+        block[12 + i] = d1 - c1;
+    }
+
+    for row in block.chunks_exact_mut(4) {
+        let a1 = row[0] + row[3];
+        let b1 = row[1] + row[2];
+        let c1 = row[1] - row[2];
+        let d1 = row[0] - row[3];
+
+        row[0] = (a1 + b1 + 3) >> 3;
+        row[1] = (c1 + d1 + 3) >> 3;
+        row[2] = (a1 - b1 + 3) >> 3;
+        row[3] = (d1 - c1 + 3) >> 3;
+    }
+}
+
+/// Convert to BT.601 YUV with 4:2:0 subsampling
+///
+/// The planes are padded to full macroblocks by repeating the last row and
+/// column.
+fn to_yuv420(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    mb_width: usize,
+    mb_height: usize,
+) -> [Plane; 3] {
+    let pixel = |x: usize, y: usize| {
+        let i = (y.min(height - 1) * width + x.min(width - 1)) * 4;
+        [rgba[i], rgba[i + 1], rgba[i + 2]].map(i32::from)
+    };
+
+    let mut y_plane = Plane::new(mb_width * 16, mb_height * 16);
+    for y in 0..mb_height * 16 {
+        for x in 0..mb_width * 16 {
+            let [r, g, b] = pixel(x, y);
+            let luma = (16839 * r + 33059 * g + 6420 * b + (16 << 16) + (1 << 15)) >> 16;
+            y_plane.set(x, y, luma as u8);
+        }
+    }
+
+    let mut u_plane = Plane::new(mb_width * 8, mb_height * 8);
+    let mut v_plane = Plane::new(mb_width * 8, mb_height * 8);
+    for y in 0..mb_height * 8 {
+        for x in 0..mb_width * 8 {
+            // Sum of 2x2 pixels
+            let [r, g, b] = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .map(|(dx, dy)| pixel(x * 2 + dx, y * 2 + dy))
+                .into_iter()
+                .fold([0; 3], |sum, p| {
+                    [sum[0] + p[0], sum[1] + p[1], sum[2] + p[2]]
+                });
+
+            let chroma = |value: i32| ((value + (128 << 18) + (1 << 17)) >> 18).clamp(0, 255) as u8;
+            u_plane.set(x, y, chroma(-9719 * r - 19081 * g + 28800 * b));
+            v_plane.set(x, y, chroma(28800 * r - 24116 * g - 4684 * b));
+        }
+    }
+
+    [y_plane, u_plane, v_plane]
+}
+
+/// Boolean entropy encoder (7.3)
+struct BoolWriter {
+    data: Vec<u8>,
+    range: u32,
+    bottom: u32,
+    bit_count: u32,
+}
+
+impl Default for BoolWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoolWriter {
+    fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            range: 255,
+            bottom: 0,
+            bit_count: 24,
+        }
+    }
+
+    fn put(&mut self, prob: u8, value: bool) {
+        let split = 1 + (((self.range - 1) * u32::from(prob)) >> 8);
+
+        if value {
+            self.bottom += split;
+            self.range -= split;
+        } else {
+            self.range = split;
+        }
+
+        while self.range < 128 {
+            self.range <<= 1;
+
+            if self.bottom & (1 << 31) != 0 {
+                self.add_one_to_output();
+            }
+
+            self.bottom <<= 1;
+            self.bit_count -= 1;
+
+            if self.bit_count == 0 {
+                self.data.push((self.bottom >> 24) as u8);
+                self.bottom &= (1 << 24) - 1;
+                self.bit_count = 8;
+            }
+        }
+    }
+
+    /// Unsigned value with the most significant bit first
+    fn put_literal(&mut self, value: u32, bits: u32) {
+        for bit in (0..bits).rev() {
+            self.put(128, (value >> bit) & 1 == 1);
+        }
+    }
+
+    /// Propagate a carry into the already written bytes
+    fn add_one_to_output(&mut self) {
+        for byte in self.data.iter_mut().rev() {
+            if *byte == 255 {
+                *byte = 0;
+            } else {
+                *byte += 1;
+                break;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let mut c = self.bit_count;
+        let mut v = self.bottom;
+
+        if c < 32 && v & (1 << (32 - c)) != 0 {
+            self.add_one_to_output();
+        }
+
+        v <<= c & 7;
+        c >>= 3;
+        for _ in 0..c {
+            v <<= 8;
+        }
+
+        for _ in 0..4 {
+            self.data.push((v >> 24) as u8);
+            v <<= 8;
+        }
+
+        self.data
+    }
+
+    /// Write the tokens for the quantized coefficients of a block (13)
+    ///
+    /// Returns whether the block has non-zero coefficients.
+    fn put_coefficients(
+        &mut self,
+        block_type: BlockType,
+        first: usize,
+        context: usize,
+        levels: &[i32; 16],
+    ) -> bool {
+        let probs = &COEFF_PROBS[block_type as usize];
+
+        let Some(last) = (first..16).rev().find(|i| levels[ZIGZAG[*i]] != 0) else {
+            self.put(probs[COEFF_BANDS[first]][context][0], false);
+            return false;
+        };
+
+        let mut context = context;
+        let mut after_zero = false;
+
+        for i in first..=last {
+            let p = &probs[COEFF_BANDS[i]][context];
+            let level = levels[ZIGZAG[i]];
+            let abs = level.abs();
+
+            // No end of block directly after a zero
+            if !after_zero {
+                self.put(p[0], true);
+            }
+
+            if abs == 0 {
+                self.put(p[1], false);
+                after_zero = true;
+                context = 0;
+                continue;
+            }
+
+            self.put(p[1], true);
+            self.put_token_value(p, abs);
+            self.put(128, level < 0);
+
+            after_zero = false;
+            context = if abs == 1 { 1 } else { 2 };
+        }
+
+        if last < 15 {
+            self.put(probs[COEFF_BANDS[last + 1]][context][0], false);
+        }
+
+        true
+    }
+
+    /// Token for a non-zero absolute value and the extra bits of its category
+    fn put_token_value(&mut self, p: &[u8; 11], abs: i32) {
+        if abs == 1 {
+            self.put(p[2], false);
+            return;
+        }
+        self.put(p[2], true);
+
+        if abs <= 4 {
+            self.put(p[3], false);
+            if abs == 2 {
+                self.put(p[4], false);
+            } else {
+                self.put(p[4], true);
+                self.put(p[5], abs == 4);
+            }
+            return;
+        }
+        self.put(p[3], true);
+
+        let category = match abs {
+            5..=6 => 0,
+            7..=10 => 1,
+            11..=18 => 2,
+            19..=34 => 3,
+            35..=66 => 4,
+            _ => 5,
+        };
+
+        if category < 2 {
+            self.put(p[6], false);
+            self.put(p[7], category == 1);
+        } else {
+            self.put(p[6], true);
+            if category < 4 {
+                self.put(p[8], false);
+                self.put(p[9], category == 3);
+            } else {
+                self.put(p[8], true);
+                self.put(p[10], category == 5);
+            }
+        }
+
+        let extra = abs - DCT_CAT_BASE[category];
+        let extra_probs = PROB_DCT_CAT[category];
+        let bits = extra_probs.iter().take_while(|x| **x != 0).count();
+        for (k, prob) in extra_probs[..bits].iter().enumerate() {
+            self.put(*prob, (extra >> (bits - 1 - k)) & 1 == 1);
+        }
+    }
+}
+
+/// Boolean entropy decoder (7.3), only used to read frame headers
+struct BoolReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    value: u32,
+    range: u32,
+    bit_count: u32,
+}
+
+impl<'a> BoolReader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let value = u32::from(u16::from_be_bytes([*data.first()?, *data.get(1)?]));
+
+        Some(Self {
+            data,
+            pos: 2,
+            value,
+            range: 255,
+            bit_count: 0,
+        })
+    }
+
+    fn read(&mut self, prob: u8) -> bool {
+        let split = 1 + (((self.range - 1) * u32::from(prob)) >> 8);
+        let big_split = split << 8;
+
+        let value = if self.value >= big_split {
+            self.range -= split;
+            self.value -= big_split;
+            true
+        } else {
+            self.range = split;
+            false
+        };
+
+        while self.range < 128 {
+            self.value <<= 1;
+            self.range <<= 1;
+            self.bit_count += 1;
+
+            if self.bit_count == 8 {
+                self.bit_count = 0;
+                // Missing data are read as zeros
+                self.value |= u32::from(self.data.get(self.pos).copied().unwrap_or(0));
+                self.pos += 1;
+            }
+        }
+
+        value
+    }
+
+    fn flag(&mut self) -> bool {
+        self.read(128)
+    }
+
+    fn literal(&mut self, bits: u32) -> u32 {
+        (0..bits).fold(0, |value, _| value << 1 | u32::from(self.flag()))
+    }
+
+    /// Value that is only present if a flag is set, followed by a sign
+    fn optional_signed(&mut self, bits: u32) {
+        if self.flag() {
+            self.literal(bits + 1);
+        }
+    }
+}
+
+/// Payload of an `ALPH` chunk, if any pixel is not opaque
+///
+/// Below an `alpha_quality` of 100, the number of alpha levels is reduced
+/// before the lossless compression, unless keeping all levels results in a
+/// smaller chunk. The values are stored uncompressed if that is even smaller.
+fn encode_alpha(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    alpha_quality: u8,
+) -> Result<Option<Vec<u8>>, ProcessError> {
+    /// Header bit for reduced alpha levels
+    const LEVEL_REDUCTION: u8 = 1 << 4;
+    /// Header value for lossless compression
+    const LOSSLESS: u8 = 1;
+
+    let alpha = rgba.chunks_exact(4).map(|x| x[3]).collect::<Vec<_>>();
+
+    if alpha.iter().all(|x| *x == u8::MAX) {
+        return Ok(None);
+    }
+
+    // No filtering, lossless compression
+    let mut data = vec![LOSSLESS];
+    data.extend_from_slice(&compress_alpha(&alpha, width, height)?);
+
+    let alpha_quality = u32::from(alpha_quality.min(100));
+    if alpha_quality < 100 {
+        let levels = if alpha_quality <= 70 {
+            2 + alpha_quality / 5
+        } else {
+            16 + (alpha_quality - 70) * 8
+        };
+
+        let reduced_alpha = alpha
+            .iter()
+            .map(|value| {
+                let level = (u32::from(*value) * (levels - 1) + 127) / 255;
+                ((level * 255 + (levels - 1) / 2) / (levels - 1)) as u8
+            })
+            .collect::<Vec<_>>();
+
+        let mut reduced = vec![LEVEL_REDUCTION | LOSSLESS];
+        reduced.extend_from_slice(&compress_alpha(&reduced_alpha, width, height)?);
+
+        if reduced.len() < data.len() {
+            data = reduced;
+        }
+    }
+
+    if alpha.len() + 1 < data.len() {
+        // No filtering, no compression
+        data = vec![0];
+        data.extend_from_slice(&alpha);
+    }
+
+    Ok(Some(data))
+}
+
+/// Alpha values compressed as WebP lossless image stream
+fn compress_alpha(alpha: &[u8], width: usize, height: usize) -> Result<Vec<u8>, ProcessError> {
+    // The alpha values are stored in the green channel of a lossless image
+    let mut lossless = Vec::new();
+    WebPEncoder::new_lossless(&mut lossless)
+        .encode(
+            alpha,
+            width.try_into().internal_error()?,
+            height.try_into().internal_error()?,
+            ExtendedColorType::L8,
+        )
+        .expected_error()?;
+
+    let vp8l = gufo::webp::WebP::new(lossless)
+        .expected_error()?
+        .chunks()
+        .into_iter()
+        .find(|x| x.four_cc() == FourCC::VP8L)
+        .map(|x| x.payload().to_vec())
+        .internal_error()?;
+
+    // Without the signature and image header, since the dimensions are implied
+    Ok(vp8l.get(5..).internal_error()?.to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use image::codecs::webp::WebPDecoder;
+
+    use super::*;
+
+    const SIZES: [(usize, usize); 4] = [(1, 1), (16, 16), (17, 33), (64, 48)];
+    const QUALITIES: [u8; 4] = [10, 50, 90, 100];
+
+    /// Gradients with a checkerboard pattern and an optional alpha gradient
+    fn test_image(width: usize, height: usize, alpha: bool) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                let detail = if (x / 4 + y / 4) % 2 == 0 { 0 } else { 24 };
+                [
+                    (x * 255 / width) as u8,
+                    (y * 255 / height) as u8,
+                    (96 + detail + (x + y) % 32) as u8,
+                    if alpha {
+                        ((x + y) * 255 / (width + height)) as u8
+                    } else {
+                        u8::MAX
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// Decode the frame with image-webp
+    fn decode(frame: &LossyFrame, width: usize, height: usize) -> Vec<u8> {
+        fn chunk(data: &mut Vec<u8>, four_cc: &[u8], payload: &[u8]) {
+            data.extend_from_slice(four_cc);
+            data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            data.extend_from_slice(payload);
+            if payload.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+
+        let flags = if frame.alpha.is_some() { 1 << 4 } else { 0 };
+        let mut vp8x = vec![flags, 0, 0, 0];
+        vp8x.extend_from_slice(&(width as u32 - 1).to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&(height as u32 - 1).to_le_bytes()[..3]);
+
+        let mut webp = b"WEBP".to_vec();
+        chunk(&mut webp, b"VP8X", &vp8x);
+        if let Some(alpha) = &frame.alpha {
+            chunk(&mut webp, b"ALPH", alpha);
+        }
+        chunk(&mut webp, b"VP8 ", &frame.vp8);
+
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(webp.len() as u32).to_le_bytes());
+        data.extend_from_slice(&webp);
+
+        let decoder = WebPDecoder::new(Cursor::new(data)).unwrap();
+        let image = image::DynamicImage::from_decoder(decoder).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (width as u32, height as u32)
+        );
+
+        image.into_rgba8().into_raw()
+    }
+
+    /// Peak signal-to-noise ratio of the selected channels in dB
+    fn psnr(a: &[u8], b: &[u8], channels: std::ops::Range<usize>) -> f64 {
+        let (sum, n) = a
+            .chunks_exact(4)
+            .zip(b.chunks_exact(4))
+            .flat_map(|(a, b)| a[channels.clone()].iter().zip(&b[channels.clone()]))
+            .fold((0., 0.), |(sum, n), (a, b)| {
+                (sum + (f64::from(*a) - f64::from(*b)).powi(2), n + 1.)
+            });
+
+        10. * (255. * 255. / (sum / n)).log10()
+    }
+
+    /// Lowest expected PSNR of the color channels
+    fn min_psnr(quality: u8) -> f64 {
+        match quality {
+            0..=10 => 18.,
+            11..=50 => 26.,
+            51..=90 => 33.,
+            _ => 34.,
+        }
+    }
+
+    #[test]
+    fn encode_decodable() {
+        for (width, height) in SIZES {
+            for alpha in [false, true] {
+                let rgba = test_image(width, height, alpha);
+
+                for quality in QUALITIES {
+                    let frame =
+                        encode(&rgba, width as u32, height as u32, quality, quality).unwrap();
+                    let decoded = decode(&frame, width, height);
+                    let info = format!("{width}x{height} alpha {alpha} quality {quality}");
+
+                    let color_psnr = psnr(&rgba, &decoded, 0..3);
+                    assert!(
+                        color_psnr >= min_psnr(quality),
+                        "{info}: {color_psnr:.1} dB"
+                    );
+
+                    // The alpha channel is only stored for images with transparency
+                    assert_eq!(frame.alpha.is_some(), alpha, "{info}");
+
+                    let alpha_psnr = psnr(&rgba, &decoded, 3..4);
+                    if quality == 100 {
+                        assert_eq!(alpha_psnr, f64::INFINITY, "{info}");
+                    } else {
+                        assert!(alpha_psnr >= 18., "{info}: alpha {alpha_psnr:.1} dB");
+                    }
+
+                    assert_eq!(super::quality(&frame.vp8), Some(quality), "{info}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encode_quality_order() {
+        for alpha in [false, true] {
+            let rgba = test_image(64, 48, alpha);
+
+            let psnrs = QUALITIES
+                .iter()
+                .map(|quality| {
+                    let frame = encode(&rgba, 64, 48, *quality, *quality).unwrap();
+                    let decoded = decode(&frame, 64, 48);
+                    (psnr(&rgba, &decoded, 0..3), psnr(&rgba, &decoded, 3..4))
+                })
+                .collect::<Vec<_>>();
+
+            assert!(psnrs.windows(2).all(|x| x[0].0 < x[1].0), "{psnrs:?}");
+            assert!(psnrs.windows(2).all(|x| x[0].1 <= x[1].1), "{psnrs:?}");
+        }
+    }
+}
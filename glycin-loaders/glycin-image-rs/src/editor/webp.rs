@@ -0,0 +1,403 @@
+//! Editing and creation of still WebP images
+//!
+//! The extended file format is always written to store ICC profile, Exif, and
+//! XMP. New images are encoded lossy if a quality is set, see [`super::vp8`].
+//! Edited images keep lossy compression at a comparable quality. If only the
+//! orientation changes, the encoded frames are kept and the orientation is
+//! stored in the Exif data instead. Animated WebPs are handled in
+//! [`super::animated`], which uses the same encoder.
+
+use std::io::Cursor;
+use std::ops::Range;
+
+use glycin_utils::safe_math::SafeConversion;
+use glycin_utils::*;
+use gufo::webp::FourCC;
+use gufo_common::field;
+use gufo_common::orientation::Orientation;
+use image::codecs::webp::{WebPDecoder, WebPEncoder};
+use image::metadata::LoopCount;
+use image::{ExtendedColorType, RgbaImage};
+
+use super::animated;
+
+pub struct EditWebP {
+    data: Vec<u8>,
+}
+
+pub fn load(data: Vec<u8>) -> EditWebP {
+    EditWebP { data }
+}
+
+pub fn apply<B: ByteData>(
+    edit_webp: &EditWebP,
+    mut operations: Operations,
+) -> Result<CompleteEditorOutput<B>, ProcessError> {
+    if let Some(orientation) = orientation(&edit_webp.data) {
+        operations.prepend(Operations::new_orientation(orientation));
+    }
+
+    if let Some(data) = apply_orientation_only(&edit_webp.data, &operations) {
+        return CompleteEditorOutput::new_lossless(data);
+    }
+
+    let decoder = WebPDecoder::new(Cursor::new(edit_webp.data.as_slice())).expected_error()?;
+    let image = image::DynamicImage::from_decoder(decoder).expected_error()?;
+
+    let frame = animated::apply_to_frame(image::Frame::new(image.into_rgba8()), &operations)?;
+    let data = encode(
+        [Ok(frame)],
+        None,
+        WebPChunks::from_old(&edit_webp.data)?,
+        WebPCompression::from_old(&edit_webp.data),
+    )?;
+
+    let data = B::try_from_vec(data).expected_error()?;
+
+    Ok(CompleteEditorOutput::new(data))
+}
+
+pub fn preview<B: ByteData>(
+    edit_webp: &EditWebP,
+    mut operations: Operations,
+    frame_request: FrameRequest,
+) -> Result<Frame<B>, ProcessError> {
+    if let Some(orientation) = orientation(&edit_webp.data) {
+        operations.prepend(Operations::new_orientation(orientation));
+    }
+
+    super::decoder_preview(
+        WebPDecoder::new(Cursor::new(edit_webp.data.as_slice())).expected_error()?,
+        &operations,
+        &frame_request,
+    )
+}
+
+pub fn create<B: ByteData>(
+    image_info: &ImageDetails<B>,
+    frame: Frame<B>,
+    encoding_options: &EncodingOptions,
+) -> Result<Vec<u8>, ProcessError> {
+    let icc_profile = frame.details.color_icc_profile.as_ref().map(|x| x.to_vec());
+    let frame = animated::new_frame(frame)?;
+
+    encode(
+        [Ok(frame)],
+        None,
+        WebPChunks::new(icc_profile, image_info),
+        WebPCompression::new(encoding_options),
+    )
+}
+
+/// Orientation stored in the Exif data
+pub fn orientation(data: &[u8]) -> Option<Orientation> {
+    gufo::Metadata::for_guessed(data.to_vec())
+        .ok()?
+        .orientation()
+}
+
+/// Keep the encoded frames if the operations only change the orientation
+///
+/// The `operations` have to include the orientation from the Exif data. The
+/// resulting orientation is written to the Exif data. Returns `None` if the
+/// frames have to be encoded again.
+pub fn apply_orientation_only(data: &[u8], operations: &Operations) -> Option<Vec<u8>> {
+    let orientation = operations.orientation()?;
+    let mut data = data.to_vec();
+
+    let exif = chunks(&data, 12).find(|(four_cc, _)| *four_cc == FourCC::EXIF.bytes());
+
+    let diff = exif.clone().and_then(|(_, range)| {
+        gufo_exif::Exif::for_vec(data[range].to_vec())
+            .ok()?
+            .update_entry_diff(
+                field::Orientation.into(),
+                gufo_exif::Typed::Short(vec![orientation as u16]),
+            )
+            .ok()
+    });
+
+    match (exif, diff) {
+        (Some((_, range)), Some(diff)) => {
+            for (pos, value) in diff {
+                *data.get_mut(range.start.checked_add(pos)?)? = value;
+            }
+        }
+        // Without an orientation entry the image is already correctly oriented
+        _ if orientation == Orientation::Id => {}
+        _ => return None,
+    }
+
+    Some(data)
+}
+
+/// FourCC and payload range of the chunks in `data` starting at `pos`
+fn chunks(data: &[u8], mut pos: usize) -> impl Iterator<Item = ([u8; 4], Range<usize>)> + '_ {
+    std::iter::from_fn(move || {
+        let four_cc = data.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+        let size = data.get(pos.checked_add(4)?..pos.checked_add(8)?)?;
+        let size = usize::try_from(u32::from_le_bytes(size.try_into().ok()?)).ok()?;
+
+        let start = pos.checked_add(8)?;
+        let end = start.checked_add(size)?;
+        data.get(start..end)?;
+
+        // Chunks are padded to an even size
+        pos = end.checked_add(size % 2)?;
+
+        Some((four_cc, start..end))
+    })
+}
+
+/// Chunks that are stored next to the image data in WebP
+#[derive(Default)]
+pub struct WebPChunks {
+    icc_profile: Option<Vec<u8>>,
+    exif: Option<Vec<u8>>,
+    xmp: Option<Vec<u8>>,
+    background_color: Option<Vec<u8>>,
+}
+
+impl WebPChunks {
+    pub fn new<B: ByteData>(icc_profile: Option<Vec<u8>>, image_info: &ImageDetails<B>) -> Self {
+        Self {
+            icc_profile,
+            exif: image_info.metadata_exif.as_ref().map(|x| x.to_vec()),
+            xmp: image_info.metadata_xmp.as_ref().map(|x| x.to_vec()),
+            background_color: None,
+        }
+    }
+
+    pub fn from_old(old_data: &[u8]) -> Result<Self, ProcessError> {
+        let old_webp = gufo::webp::WebP::new(old_data.to_vec()).expected_error()?;
+        let old_chunk = |four_cc| {
+            old_webp
+                .chunks()
+                .into_iter()
+                .find(|x| x.four_cc() == four_cc)
+                .map(|x| x.payload().to_vec())
+        };
+
+        Ok(Self {
+            icc_profile: old_chunk(FourCC::ICCP),
+            exif: old_chunk(FourCC::EXIF).map(animated::reset_exif_orientation),
+            xmp: old_chunk(FourCC::XMP),
+            background_color: old_chunk(FourCC::ANIM).and_then(|x| x.get(..4).map(|x| x.to_vec())),
+        })
+    }
+}
+
+/// Compression of the WebP frames
+#[derive(Debug, Clone, Copy)]
+pub enum WebPCompression {
+    Lossless,
+    Lossy { quality: u8, alpha_quality: u8 },
+}
+
+impl WebPCompression {
+    /// Lossy if the frames of an existing image are stored lossy
+    ///
+    /// The quality is taken from the quantizer of the first lossy frame. Alpha
+    /// is kept without reducing its levels.
+    pub fn from_old(old_data: &[u8]) -> Self {
+        let vp8 = chunks(old_data, 12).find_map(|(four_cc, range)| {
+            let payload = &old_data[range];
+
+            if four_cc == FourCC::VP8.bytes() {
+                Some(payload)
+            } else if four_cc == FourCC::ANMF.bytes() {
+                // Frame chunks follow the frame header
+                chunks(payload, 16)
+                    .find(|(four_cc, _)| *four_cc == FourCC::VP8.bytes())
+                    .map(|(_, range)| &payload[range])
+            } else {
+                None
+            }
+        });
+
+        match vp8 {
+            Some(vp8) => Self::Lossy {
+                quality: super::vp8::quality(vp8).unwrap_or(super::vp8::DEFAULT_QUALITY),
+                alpha_quality: 100,
+            },
+            None => Self::Lossless,
+        }
+    }
+
+    /// Lossy if a quality is set, unless lossless is requested explicitly
+    pub fn new(encoding_options: &EncodingOptions) -> Self {
+        let lossless = encoding_options
+            .lossless
+            .unwrap_or(encoding_options.quality.is_none());
+
+        if lossless {
+            Self::Lossless
+        } else {
+            Self::Lossy {
+                quality: encoding_options
+                    .quality
+                    .unwrap_or(super::vp8::DEFAULT_QUALITY),
+                alpha_quality: encoding_options.alpha_quality.unwrap_or(100),
+            }
+        }
+    }
+}
+
+/// Encode the frames as WebP
+///
+/// The `ANIM` chunk and frame chunks are only written for animations.
+pub fn encode(
+    frames: impl IntoIterator<Item = Result<image::Frame, ProcessError>>,
+    loop_count: Option<LoopCount>,
+    webp_chunks: WebPChunks,
+    compression: WebPCompression,
+) -> Result<Vec<u8>, ProcessError> {
+    const FLAG_ANIMATION: u8 = 1 << 1;
+    const FLAG_XMP: u8 = 1 << 2;
+    const FLAG_EXIF: u8 = 1 << 3;
+    const FLAG_ALPHA: u8 = 1 << 4;
+    const FLAG_ICC: u8 = 1 << 5;
+
+    let frames = animated::CanvasFrames::new(frames)?;
+    let (width, height) = frames.canvas_size();
+
+    let WebPChunks {
+        icc_profile,
+        exif,
+        xmp,
+        background_color,
+    } = webp_chunks;
+    let background_color = background_color.unwrap_or(vec![0; 4]);
+
+    let mut flags = 0;
+    let mut chunks = Vec::new();
+
+    if let Some(icc_profile) = &icc_profile {
+        flags |= FLAG_ICC;
+        write_chunk(&mut chunks, FourCC::ICCP, icc_profile)?;
+    }
+
+    if let Some(loop_count) = loop_count {
+        flags |= FLAG_ANIMATION;
+
+        let loop_count = match loop_count {
+            LoopCount::Infinite => 0,
+            LoopCount::Finite(n) => u16::try_from(n.get()).unwrap_or(u16::MAX),
+        };
+
+        let mut anim = background_color;
+        anim.extend_from_slice(&loop_count.to_le_bytes());
+        write_chunk(&mut chunks, FourCC::ANIM, &anim)?;
+    }
+
+    for frame in frames {
+        let frame = frame?;
+        if frame.buffer().pixels().any(|x| x.0[3] != u8::MAX) {
+            flags |= FLAG_ALPHA;
+        }
+
+        let frame_chunks = encode_frame(frame.buffer(), compression)?;
+
+        if loop_count.is_some() {
+            let mut anmf = Vec::new();
+            // Frame position
+            anmf.extend_from_slice(&u24(0)?);
+            anmf.extend_from_slice(&u24(0)?);
+            anmf.extend_from_slice(&u24(width.checked_sub(1).internal_error()?)?);
+            anmf.extend_from_slice(&u24(height.checked_sub(1).internal_error()?)?);
+            anmf.extend_from_slice(&u24(animated::delay_ms(frame.delay()).min(0xFF_FFFF))?);
+            // Don't blend with the previous frame, don't dispose
+            anmf.push(1 << 1);
+            anmf.extend_from_slice(&frame_chunks);
+
+            write_chunk(&mut chunks, FourCC::ANMF, &anmf)?;
+        } else {
+            chunks.extend_from_slice(&frame_chunks);
+        }
+    }
+
+    if let Some(exif) = &exif {
+        flags |= FLAG_EXIF;
+        write_chunk(&mut chunks, FourCC::EXIF, exif)?;
+    }
+
+    if let Some(xmp) = &xmp {
+        flags |= FLAG_XMP;
+        write_chunk(&mut chunks, FourCC::XMP, xmp)?;
+    }
+
+    let mut vp8x = vec![flags, 0, 0, 0];
+    vp8x.extend_from_slice(&u24(width.checked_sub(1).internal_error()?)?);
+    vp8x.extend_from_slice(&u24(height.checked_sub(1).internal_error()?)?);
+
+    let mut webp_content = gufo::webp::WEBP_MAGIC_BYTES.to_vec();
+    write_chunk(&mut webp_content, FourCC::VP8X, &vp8x)?;
+    webp_content.extend_from_slice(&chunks);
+
+    let mut out_buf = gufo::webp::RIFF_MAGIC_BYTES.to_vec();
+    out_buf.extend_from_slice(&webp_content.len().try_u32()?.to_le_bytes());
+    out_buf.extend_from_slice(&webp_content);
+
+    Ok(out_buf)
+}
+
+/// Image data chunks for one frame
+fn encode_frame(buffer: &RgbaImage, compression: WebPCompression) -> Result<Vec<u8>, ProcessError> {
+    let (width, height) = buffer.dimensions();
+    let mut chunks = Vec::new();
+
+    match compression {
+        WebPCompression::Lossless => {
+            let mut frame_data = Vec::new();
+            WebPEncoder::new_lossless(&mut frame_data)
+                .encode(buffer.as_raw(), width, height, ExtendedColorType::Rgba8)
+                .expected_error()?;
+
+            let frame_webp = gufo::webp::WebP::new(frame_data).expected_error()?;
+            let vp8l = frame_webp
+                .chunks()
+                .into_iter()
+                .find(|x| x.four_cc() == FourCC::VP8L)
+                .map(|x| x.payload().to_vec())
+                .internal_error()?;
+
+            write_chunk(&mut chunks, FourCC::VP8L, &vp8l)?;
+        }
+        WebPCompression::Lossy {
+            quality,
+            alpha_quality,
+        } => {
+            let frame = super::vp8::encode(buffer.as_raw(), width, height, quality, alpha_quality)?;
+
+            if let Some(alpha) = &frame.alpha {
+                write_chunk(&mut chunks, FourCC::ALPH, alpha)?;
+            }
+            write_chunk(&mut chunks, FourCC::VP8, &frame.vp8)?;
+        }
+    }
+
+    Ok(chunks)
+}
+
+fn write_chunk(buf: &mut Vec<u8>, four_cc: FourCC, payload: &[u8]) -> Result<(), ProcessError> {
+    buf.extend_from_slice(&four_cc.bytes());
+    buf.extend_from_slice(&payload.len().try_u32()?.to_le_bytes());
+    buf.extend_from_slice(payload);
+
+    // Chunks are padded to an even size
+    if payload.len() % 2 == 1 {
+        buf.push(0);
+    }
+
+    Ok(())
+}
+
+/// 24-bit little endian value as used by WebP
+fn u24(value: u32) -> Result<[u8; 3], ProcessError> {
+    match value.to_le_bytes() {
+        [a, b, c, 0] => Ok([a, b, c]),
+        _ => Err(ProcessError::expected(&format!(
+            "Value too large for WebP: {value}"
+        ))),
+    }
+}
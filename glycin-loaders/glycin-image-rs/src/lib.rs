@@ -1,6 +1,7 @@
 #![allow(clippy::large_enum_variant)]
 
 mod animated;
+mod cmyk;
 mod editor;
 mod exr;
 
@@ -48,6 +49,11 @@ pub enum Decoder {
         frame_receiver: FrameReceiver,
     },
     Exr(Vec<u8>),
+    Cmyk {
+        data: Vec<u8>,
+        mime_type: String,
+        icc_profile: Vec<u8>,
+    },
 }
 
 impl LoaderImplementation for ImgLoader {
@@ -74,6 +80,8 @@ impl LoaderImplementation for ImgLoader {
 
         let data = Cursor::new(buf);
         let mut format = ImageRsFormat::create(data.clone(), &mime_type)?;
+        // Read before setting limits since TIFF limits tags to the size of the image
+        let icc_profile = format.icc_profile().ok().flatten();
         if let Err(err) = format.set_no_limits() {
             eprint!("Failed to unset decoder limits: {err}");
         }
@@ -153,7 +161,21 @@ impl LoaderImplementation for ImgLoader {
             });
         }
 
-        if format.decoder.is_animated() {
+        // CMYK is converted via the embedded profile instead of the decoder's inversion
+        if let Some(icc_profile) = icc_profile
+            && cmyk::is_cmyk(
+                &mime_type,
+                data.get_ref(),
+                format.original_color_type(),
+                &icc_profile,
+            )
+        {
+            *loader_impelementation.decoder.lock().unwrap() = Some(Decoder::Cmyk {
+                data: data.into_inner(),
+                mime_type,
+                icc_profile,
+            });
+        } else if format.decoder.is_animated() {
             let (send, recv) = channel();
             let thread =
                 std::thread::spawn(move || animated::worker(format, data, mime_type, send));
@@ -203,6 +225,11 @@ impl LoaderImplementation for ImgLoader {
                 frame
             }
             Decoder::Exr(data) => exr::frame(&data)?,
+            Decoder::Cmyk {
+                data,
+                mime_type,
+                icc_profile,
+            } => cmyk::frame(&mime_type, &data, &icc_profile)?,
         };
 
         frame.details.color_cicp = cicp.map(|x| {
@@ -261,7 +288,7 @@ impl ImageRsFormat<Reader> {
             .format_name("BMP")
             .default_bit_depth(8),
 
-            "image/vnd.ms-dds" => Self::new(ImageRsDecoder::Dds(
+            "image/vnd.ms-dds" | "image/x-dds" => Self::new(ImageRsDecoder::Dds(
                 codecs::dds::DdsDecoder::new(data).expected_error()?,
             ))
             .format_name("DDS")
@@ -437,6 +464,14 @@ impl<T: std::io::BufRead + std::io::Seek> ImageRsFormat<T> {
         self.visit(|x| image::ImageDecoder::xmp_metadata(*x))
     }
 
+    fn icc_profile(&mut self) -> Result<Option<Vec<u8>>, image::ImageError> {
+        self.visit(|x| image::ImageDecoder::icc_profile(*x))
+    }
+
+    fn original_color_type(&mut self) -> image::ExtendedColorType {
+        self.visit(|x| image::ImageDecoder::original_color_type(*x))
+    }
+
     fn info<B: ByteData>(&mut self) -> ImageDetails<B> {
         match self.decoder {
             ImageRsDecoder::Bmp(ref mut d) => self.handler.info(d),
@@ -1,35 +1,222 @@
+pub(crate) mod container;
+
 use std::io::Read;
 
 use glycin_common::ChannelType;
+use glycin_utils::editing::EditingFrame;
+use glycin_utils::safe_math::{SafeConversion, SafeMath};
 use glycin_utils::{
-    ByteData, EditorImplementation, GenericContexts, MemoryFormat, MemoryFormatInfo, ProcessError,
+    ByteChanges, ByteData, CompleteEditorOutput, EditorImplementation, EncodedImage,
+    EncodingOptions, ExtendedMemoryFormat, Frame, FrameRequest, FungibleMemory, GenericContexts,
+    LoaderImplementation, MemoryFormat, MemoryFormatInfo, Operations, ProcessError,
+    SparseEditorOutput, editing,
 };
-use jpegxl_rs::encode::{EncoderFrame, Metadata};
+use gufo_common::cicp::Cicp;
+use jpegxl_rs::common::PixelType;
+use jpegxl_rs::encode::{ColorEncoding, EncoderFrame, EncoderSpeed, JxlEncoder, Metadata};
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+use self::container::OrientationField;
 
 pub struct ImgEditor {
-    mime_type: String,
+    data: Vec<u8>,
+}
+
+impl ImgEditor {
+    /// Byte changes to the orientation field if the operations only change the
+    /// orientation
+    fn orientation_changes(
+        &self,
+        operations: &Operations,
+    ) -> Result<Option<Vec<(u64, u8)>>, ProcessError> {
+        let Some(field) = OrientationField::find(&self.data)? else {
+            return Ok(None);
+        };
+
+        let mut operations = operations.clone();
+        operations.prepend(Operations::new_orientation(field.orientation));
+
+        operations
+            .orientation()
+            .map(|orientation| field.byte_changes(&self.data, orientation))
+            .transpose()
+    }
+
+    /// Decode image, apply operations to the pixels, and encode it again
+    ///
+    /// Images that use the original color profile are assumed to be lossless
+    /// and are encoded losslessly again. JPEG reconstruction data are dropped
+    /// since they don't match the new image data anymore. The result is
+    /// therefore never considered lossless for recompressed JPEGs.
+    fn apply_reencode<B: ByteData>(
+        &self,
+        operations: &Operations,
+    ) -> Result<CompleteEditorOutput<B>, ProcessError> {
+        let info = crate::basic_info(&self.data).info.expected_error()?;
+        let lossless = info.uses_original_profile == jpegxl_sys::common::types::JxlBool::True;
+
+        let jpeg_reconstruction = container::boxes(&self.data)?
+            .iter()
+            .any(|x| &x.box_type == b"jbrd");
+        if jpeg_reconstruction {
+            eprintln!("Re-encoding drops the JPEG reconstruction data");
+        }
+
+        let data = if info.exponent_bits_per_sample > 0 {
+            self.reencode::<f32>(operations, lossless)?
+        } else if info.bits_per_sample > 8 {
+            self.reencode::<u16>(operations, lossless)?
+        } else {
+            self.reencode::<u8>(operations, lossless)?
+        };
+
+        if lossless && !jpeg_reconstruction {
+            CompleteEditorOutput::new_lossless(data)
+        } else {
+            Ok(CompleteEditorOutput::new(
+                B::try_from_vec(data).expected_error()?,
+            ))
+        }
+    }
+
+    fn reencode<T: PixelType + FromBytes + IntoBytes + Immutable>(
+        &self,
+        operations: &Operations,
+        lossless: bool,
+    ) -> Result<Vec<u8>, ProcessError> {
+        let decoder = jpegxl_rs::decoder_builder().build().expected_error()?;
+        let (metadata, pixels) = decoder.decode_with::<T>(&self.data).expected_error()?;
+
+        let n_channels = metadata.num_color_channels + u32::from(metadata.has_alpha_channel);
+        let memory_format =
+            memory_format::<T>(metadata.num_color_channels, metadata.has_alpha_channel)
+                .expected_error()?;
+
+        let editing_frame = EditingFrame {
+            width: metadata.width,
+            height: metadata.height,
+            stride: metadata.width.smul(memory_format.n_bytes().u32())?,
+            memory_format: ExtendedMemoryFormat::Basic(memory_format),
+            texture: FungibleMemory::from_vec(pixels.as_bytes().to_vec()),
+        };
+
+        let editing_frame =
+            editing::apply_operations(editing_frame, operations).expected_error()?;
+
+        let row_len = editing_frame
+            .width
+            .smul(memory_format.n_bytes().u32())?
+            .try_usize()?;
+        let pixels = pixels::<T>(
+            &editing_frame.texture,
+            editing_frame.stride,
+            editing_frame.height,
+            row_len,
+        )?;
+
+        let mut encoder = jpegxl_rs::encoder_builder().build().internal_error()?;
+        encoder.has_alpha = metadata.has_alpha_channel;
+        encoder.use_container = true;
+        if metadata.num_color_channels == 1 {
+            encoder.color_encoding = Some(ColorEncoding::SrgbLuma);
+        }
+        if lossless {
+            encoder.lossless = Some(true);
+            encoder.uses_original_profile = true;
+        }
+
+        // Keep metadata boxes. JPEG reconstruction data are dropped since they
+        // don't match the new image data anymore.
+        for jxl_box in container::boxes(&self.data)? {
+            match &jxl_box.box_type {
+                b"Exif" => {
+                    // The decoder applied the orientation to the pixels
+                    let content = container::reset_exif_orientation(jxl_box.content);
+                    encoder
+                        .add_metadata(&Metadata::Custom(*b"Exif", &content), false)
+                        .expected_error()?;
+                }
+                b"xml " | b"jumb" => {
+                    encoder
+                        .add_metadata(&Metadata::Custom(jxl_box.box_type, jxl_box.content), false)
+                        .expected_error()?;
+                }
+                _ => {}
+            }
+        }
+
+        let encoder_result = encoder
+            .encode_frame::<T, T>(
+                &EncoderFrame::new(&pixels).num_channels(n_channels),
+                editing_frame.width,
+                editing_frame.height,
+            )
+            .expected_error()?;
+
+        Ok(encoder_result.data)
+    }
 }
 
 impl EditorImplementation for ImgEditor {
     fn edit<S: Read>(
-        _stream: S,
+        mut stream: S,
         mime_type: String,
         _details: glycin_utils::InitializationDetails,
     ) -> Result<Self, glycin_utils::ProcessError> {
-        Err(glycin_utils::RemoteError::UnsupportedImageFormat(
-            mime_type.clone(),
-        ))
-        .expected_error()
+        if mime_type != "image/jxl" {
+            return Err(ProcessError::UnsupportedImageFormat(mime_type));
+        }
+
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data).internal_error()?;
+
+        Ok(Self { data })
+    }
+
+    fn apply_sparse<B: ByteData>(
+        &self,
+        operations: Operations,
+    ) -> Result<SparseEditorOutput<B>, ProcessError> {
+        if let Some(byte_changes) = self.orientation_changes(&operations)? {
+            return Ok(SparseEditorOutput::byte_changes(
+                ByteChanges::from_slice(&byte_changes).with_original_values(&self.data)?,
+            ));
+        }
+
+        Ok(SparseEditorOutput::from(self.apply_reencode(&operations)?))
     }
 
     fn apply_complete<B: ByteData>(
         &self,
-        _operations: glycin_utils::Operations,
-    ) -> Result<glycin_utils::CompleteEditorOutput<B>, glycin_utils::ProcessError> {
-        Err(glycin_utils::RemoteError::UnsupportedImageFormat(
-            self.mime_type.clone(),
-        ))
-        .expected_error()
+        operations: Operations,
+    ) -> Result<CompleteEditorOutput<B>, ProcessError> {
+        if let Some(byte_changes) = self.orientation_changes(&operations)? {
+            let mut data = self.data.clone();
+            ByteChanges::from_slice(&byte_changes)
+                .apply(&mut data)
+                .internal_error()?;
+            return CompleteEditorOutput::new_lossless(data);
+        }
+
+        self.apply_reencode(&operations)
+    }
+
+    fn preview<B: ByteData>(
+        &self,
+        operations: Operations,
+        frame_request: FrameRequest,
+    ) -> Result<Frame<B>, ProcessError> {
+        let basic_info = crate::basic_info(&self.data);
+        let mut decoder = crate::ImgDecoder {
+            data: self.data.clone(),
+            icc_profile: basic_info.icc_profile,
+            cicp: basic_info.cicp,
+        };
+
+        // The decoder already applies the orientation
+        let frame = decoder.specific_frame::<FungibleMemory>(FrameRequest::default())?;
+
+        editing::preview_frame(frame, &operations, &frame_request)
     }
 
     fn create<B: ByteData>(
@@ -37,15 +224,37 @@ impl EditorImplementation for ImgEditor {
         mut new_image: glycin_utils::NewImage<B>,
         encoding_options: glycin_utils::EncodingOptions,
     ) -> Result<glycin_utils::EncodedImage<B>, glycin_utils::ProcessError> {
+        if let Some(source_jpeg) = new_image.source_jpeg {
+            return recompress_jpeg(&source_jpeg, &encoding_options);
+        }
+
         let frame = new_image.frames.remove(0);
 
         let mut encoder = jpegxl_rs::encoder_builder().build().internal_error()?;
+        encoder.has_alpha = frame.memory_format.has_alpha();
+
+        if let Some(cicp) = frame.details.color_cicp {
+            let color_encoding = Cicp::from_bytes(&cicp)
+                .ok()
+                .and_then(crate::cicp_to_color_encoding)
+                .ok_or_else(|| ProcessError::expected(&"Unsupported CICP"))?;
+            encoder.color_encoding = Some(ColorEncoding::Custom(color_encoding));
+        }
 
         // You can change the settings after initialization
         if let Some(quality) = encoding_options.quality {
             encoder.quality = quality as f32 / 100. * 15.;
         }
 
+        if encoding_options.lossless == Some(true) {
+            encoder.lossless = Some(true);
+            encoder.uses_original_profile = true;
+        }
+
+        if let Some(effort) = encoding_options.effort {
+            encoder.speed = encoder_speed(effort);
+        }
+
         if let Some(exif) = new_image.image_info.metadata_exif {
             encoder
                 .add_metadata(&Metadata::Exif(&exif), true)
@@ -60,7 +269,12 @@ impl EditorImplementation for ImgEditor {
 
         if !matches!(
             frame.memory_format,
-            MemoryFormat::R8g8b8 | MemoryFormat::R8g8b8a8
+            MemoryFormat::R8g8b8
+                | MemoryFormat::R8g8b8a8
+                | MemoryFormat::R16g16b16
+                | MemoryFormat::R16g16b16a16
+                | MemoryFormat::R32g32b32Float
+                | MemoryFormat::R32g32b32a32Float
         ) {
             return Err(ProcessError::expected(&format!(
                 "Unsupported memory format: {:?}",
@@ -68,28 +282,148 @@ impl EditorImplementation for ImgEditor {
             )));
         }
 
-        /*
-        TODO:
-        | MemoryFormatSelection::R16g16b16
-        | MemoryFormatSelection::R16g16b16a16
-        | MemoryFormatSelection::R32g32b32Float
-        | MemoryFormatSelection::R32g32b32a32Float
-         */
+        let data = match (
+            frame.memory_format.channel_type(),
+            encoding_options.bit_depth,
+        ) {
+            (ChannelType::U8, None | Some(8)) => {
+                let pixels = frame_pixels::<u8>(&frame)?;
+                encode_frame(&mut encoder, &frame, &pixels)?
+            }
+            (ChannelType::U8, Some(16)) => {
+                // Scale to the full 16-bit range instead of only using the lower bits
+                let pixels = frame_pixels::<u8>(&frame)?
+                    .into_iter()
+                    .map(|x| u16::from(x) * 257)
+                    .collect::<Vec<_>>();
+                encode_frame(&mut encoder, &frame, &pixels)?
+            }
+            (ChannelType::U16, None | Some(16)) => {
+                let pixels = frame_pixels::<u16>(&frame)?;
+                encode_frame(&mut encoder, &frame, &pixels)?
+            }
+            (ChannelType::F32, None | Some(32)) => {
+                let pixels = frame_pixels::<f32>(&frame)?;
+                encode_frame(&mut encoder, &frame, &pixels)?
+            }
+            (_, bit_depth) => {
+                return Err(ProcessError::expected(&format!(
+                    "Unsupported bit depth {bit_depth:?} for {:?}",
+                    frame.memory_format
+                )));
+            }
+        };
 
-        let num_channels = frame.memory_format.n_channels() as u32;
+        let data = B::try_from_vec(data).expected_error()?;
 
-        let encoder_result = match frame.memory_format.channel_type() {
-            ChannelType::U8 => encoder.encode_frame::<u8, u8>(
-                &EncoderFrame::new(&frame.texture).num_channels(num_channels),
-                frame.width,
-                frame.height,
-            ),
-            _ => unreachable!(),
-        }
-        .expected_error()?;
+        Ok(glycin_utils::EncodedImage::new(data))
+    }
+}
 
-        let data = B::try_from_vec(encoder_result.data).expected_error()?;
+/// Losslessly recompress a JPEG, storing the data for reconstructing the
+/// original file
+///
+/// Metadata are taken from the JPEG since they are part of the reconstruction
+/// data.
+fn recompress_jpeg<B: ByteData>(
+    source_jpeg: &[u8],
+    encoding_options: &EncodingOptions,
+) -> Result<EncodedImage<B>, ProcessError> {
+    let mut encoder = jpegxl_rs::encoder_builder().build().internal_error()?;
+    encoder.use_container = true;
+    encoder.uses_original_profile = true;
 
-        Ok(glycin_utils::EncodedImage::new(data))
+    if let Some(effort) = encoding_options.effort {
+        encoder.speed = encoder_speed(effort);
+    }
+
+    let encoder_result = encoder.encode_jpeg(source_jpeg).expected_error()?;
+    let data = B::try_from_vec(encoder_result.data).expected_error()?;
+
+    Ok(EncodedImage::new(data))
+}
+
+fn encoder_speed(effort: u8) -> EncoderSpeed {
+    match effort.min(100) / 10 {
+        0 => EncoderSpeed::Lightning,
+        1 => EncoderSpeed::Thunder,
+        2 => EncoderSpeed::Falcon,
+        3 => EncoderSpeed::Cheetah,
+        4 => EncoderSpeed::Hare,
+        5 => EncoderSpeed::Wombat,
+        6 => EncoderSpeed::Squirrel,
+        7 => EncoderSpeed::Kitten,
+        8 => EncoderSpeed::Tortoise,
+        _ => EncoderSpeed::Glacier,
     }
 }
+
+/// Pixel values of the frame without the padding at the end of rows
+fn frame_pixels<T: FromBytes + Immutable>(
+    frame: &Frame<impl ByteData>,
+) -> Result<Vec<T>, ProcessError> {
+    let row_len = frame
+        .width
+        .smul(frame.memory_format.n_bytes().u32())?
+        .try_usize()?;
+
+    pixels::<T>(&frame.texture, frame.stride, frame.height, row_len)
+}
+
+/// Encode the pixels with the bit depth of their type
+fn encode_frame<T: PixelType>(
+    encoder: &mut JxlEncoder,
+    frame: &Frame<impl ByteData>,
+    pixels: &[T],
+) -> Result<Vec<u8>, ProcessError> {
+    let num_channels = u32::from(frame.memory_format.n_channels());
+
+    let encoder_result = encoder
+        .encode_frame::<T, T>(
+            &EncoderFrame::new(pixels).num_channels(num_channels),
+            frame.width,
+            frame.height,
+        )
+        .expected_error()?;
+
+    Ok(encoder_result.data)
+}
+
+/// Pixel values without the padding at the end of rows
+fn pixels<T: FromBytes + Immutable>(
+    texture: &[u8],
+    stride: u32,
+    height: u32,
+    row_len: usize,
+) -> Result<Vec<T>, ProcessError> {
+    texture
+        .chunks(stride.try_usize()?)
+        .take(height.try_usize()?)
+        .flat_map(|row| {
+            row.get(..row_len)
+                .unwrap_or_default()
+                .chunks_exact(size_of::<T>())
+        })
+        .map(T::read_from_bytes)
+        .collect::<Result<Vec<T>, _>>()
+        .ok()
+        .internal_error()
+}
+
+fn memory_format<T: PixelType>(num_color_channels: u32, has_alpha: bool) -> Option<MemoryFormat> {
+    let format = match (T::bits_per_sample(), num_color_channels, has_alpha) {
+        ((32, 8), 3, false) => MemoryFormat::R32g32b32Float,
+        ((32, 8), 3, true) => MemoryFormat::R32g32b32a32Float,
+        ((16, 0), 3, false) => MemoryFormat::R16g16b16,
+        ((16, 0), 3, true) => MemoryFormat::R16g16b16a16,
+        ((16, 0), 1, false) => MemoryFormat::G16,
+        ((16, 0), 1, true) => MemoryFormat::G16a16,
+        ((8, 0), 3, false) => MemoryFormat::R8g8b8,
+        ((8, 0), 3, true) => MemoryFormat::R8g8b8a8,
+        ((8, 0), 1, false) => MemoryFormat::G8,
+        ((8, 0), 1, true) => MemoryFormat::G8a8,
+        _ => return None,
+    };
+
+    Some(format)
+}
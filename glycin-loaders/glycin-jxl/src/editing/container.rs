@@ -0,0 +1,256 @@
+//! Access to JPEG XL container boxes and codestream header fields
+
+use glycin_utils::{GenericContexts, ProcessError};
+use gufo_common::field;
+use gufo_common::orientation::Orientation;
+
+const CODESTREAM_SIGNATURE: [u8; 2] = [0xFF, 0x0A];
+const CONTAINER_SIGNATURE: [u8; 12] = [
+    0x00, 0x00, 0x00, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+];
+
+#[derive(Debug)]
+pub struct JxlBox<'a> {
+    pub box_type: [u8; 4],
+    pub content: &'a [u8],
+    /// Offset of the content within the file
+    pub content_offset: usize,
+}
+
+/// Boxes of a JPEG XL file in container format
+///
+/// Returns an empty list for bare codestreams.
+pub fn boxes(data: &[u8]) -> Result<Vec<JxlBox<'_>>, ProcessError> {
+    let mut boxes = Vec::new();
+
+    if !data.starts_with(&CONTAINER_SIGNATURE) {
+        return Ok(boxes);
+    }
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let size = u32::from_be_bytes(
+            data.get(pos..pos + 4)
+                .expected_error()?
+                .try_into()
+                .internal_error()?,
+        ) as u64;
+        let box_type: [u8; 4] = data
+            .get(pos + 4..pos + 8)
+            .expected_error()?
+            .try_into()
+            .internal_error()?;
+
+        let (size, header_len) = match size {
+            0 => ((data.len() - pos) as u64, 8),
+            1 => (
+                u64::from_be_bytes(
+                    data.get(pos + 8..pos + 16)
+                        .expected_error()?
+                        .try_into()
+                        .internal_error()?,
+                ),
+                16,
+            ),
+            size => (size, 8),
+        };
+
+        let end = pos
+            .checked_add(usize::try_from(size).expected_error()?)
+            .expected_error()?;
+        let content = data.get(pos + header_len..end).expected_error()?;
+
+        boxes.push(JxlBox {
+            box_type,
+            content,
+            content_offset: pos + header_len,
+        });
+
+        pos = end;
+    }
+
+    Ok(boxes)
+}
+
+/// Content of an `Exif` box with the orientation set to [`Orientation::Id`]
+///
+/// The content is returned unchanged if the Exif data can't be parsed.
+pub fn reset_exif_orientation(content: &[u8]) -> Vec<u8> {
+    let mut content = content.to_vec();
+
+    // The TIFF header is preceded by a four byte offset to it
+    let tiff_start = content
+        .get(..4)
+        .and_then(|x| x.try_into().ok())
+        .map(u32::from_be_bytes)
+        .and_then(|x| usize::try_from(x).ok())
+        .and_then(|x| x.checked_add(4));
+
+    let diff = tiff_start
+        .and_then(|start| content.get(start..).map(|x| (start, x.to_vec())))
+        .and_then(|(start, tiff)| {
+            let mut exif = gufo_exif::Exif::for_vec(tiff).ok()?;
+            let diff = exif
+                .update_entry_diff(
+                    field::Orientation.into(),
+                    gufo_exif::Typed::Short(vec![Orientation::Id as u16]),
+                )
+                .ok()?;
+            Some((start, diff))
+        });
+
+    if let Some((start, diff)) = diff {
+        for (pos, value) in diff {
+            if let Some(byte) = content.get_mut(start + pos) {
+                *byte = value;
+            }
+        }
+    }
+
+    content
+}
+
+/// Position of the codestream within the file
+fn codestream_offset(data: &[u8]) -> Result<usize, ProcessError> {
+    if data.starts_with(&CODESTREAM_SIGNATURE) {
+        return Ok(0);
+    }
+
+    for jxl_box in boxes(data)? {
+        match &jxl_box.box_type {
+            b"jxlc" => return Ok(jxl_box.content_offset),
+            // Partial codestream boxes start with a four byte index
+            b"jxlp" => return Ok(jxl_box.content_offset + 4),
+            _ => {}
+        }
+    }
+
+    Err(ProcessError::expected(&"No JPEG XL codestream found"))
+}
+
+/// The orientation field in the codestream's image metadata
+#[derive(Debug, Clone, Copy)]
+pub struct OrientationField {
+    /// Bit position in the file
+    bit_position: usize,
+    pub orientation: Orientation,
+}
+
+impl OrientationField {
+    /// Find the orientation field
+    ///
+    /// Returns `None` if the image metadata use default values. In this case,
+    /// the field is not present in the codestream and can't be changed without
+    /// rewriting the header.
+    pub fn find(data: &[u8]) -> Result<Option<Self>, ProcessError> {
+        let offset = codestream_offset(data)?;
+        let mut reader = BitReader::new(data, offset);
+
+        if reader.read(16)? != u16::from_le_bytes(CODESTREAM_SIGNATURE) as u32 {
+            return Err(ProcessError::expected(
+                &"Invalid JPEG XL codestream signature",
+            ));
+        }
+
+        // SizeHeader
+        let small = reader.read(1)? == 1;
+        if small {
+            reader.read(5)?;
+        } else {
+            reader.skip_size()?;
+        }
+        let ratio = reader.read(3)?;
+        if ratio == 0 {
+            if small {
+                reader.read(5)?;
+            } else {
+                reader.skip_size()?;
+            }
+        }
+
+        // ImageMetadata
+        let all_default = reader.read(1)? == 1;
+        if all_default {
+            return Ok(None);
+        }
+        let extra_fields = reader.read(1)? == 1;
+        if !extra_fields {
+            return Ok(None);
+        }
+
+        let bit_position = reader.position;
+        let value = reader.read(3)? + 1;
+        let orientation = Orientation::try_from(value as u16).expected_error()?;
+
+        Ok(Some(Self {
+            bit_position,
+            orientation,
+        }))
+    }
+
+    /// Byte changes that set the orientation field
+    pub fn byte_changes(
+        &self,
+        data: &[u8],
+        orientation: Orientation,
+    ) -> Result<Vec<(u64, u8)>, ProcessError> {
+        let value = u16::from(orientation) - 1;
+
+        // The three bits can span two bytes
+        let first_byte = self.bit_position / 8;
+        let last_byte = (self.bit_position + 2) / 8;
+        let mut bytes = data.get(first_byte..=last_byte).expected_error()?.to_vec();
+
+        for i in 0..3 {
+            let bit_position = self.bit_position + i - first_byte * 8;
+            let mask = 1 << (bit_position % 8);
+            let byte = &mut bytes[bit_position / 8];
+
+            if value & (1 << i) == 0 {
+                *byte &= !mask;
+            } else {
+                *byte |= mask;
+            }
+        }
+
+        Ok(bytes
+            .into_iter()
+            .enumerate()
+            .map(|(i, byte)| ((first_byte + i) as u64, byte))
+            .collect())
+    }
+}
+
+/// Reads bits in the least significant bit first order used by JPEG XL
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], byte_offset: usize) -> Self {
+        Self {
+            data,
+            position: byte_offset * 8,
+        }
+    }
+
+    fn read(&mut self, n_bits: usize) -> Result<u32, ProcessError> {
+        let mut value = 0;
+        for i in 0..n_bits {
+            let byte = self.data.get(self.position / 8).expected_error()?;
+            let bit = (byte >> (self.position % 8)) & 1;
+            value |= u32::from(bit) << i;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    /// Skip dimension encoded as `U32(1 + u(9), 1 + u(13), 1 + u(18), 1 + u(30))`
+    fn skip_size(&mut self) -> Result<(), ProcessError> {
+        let n_bits = [9, 13, 18, 30][self.read(2)? as usize];
+        self.read(n_bits)?;
+        Ok(())
+    }
+}
@@ -8,7 +8,8 @@ use std::mem::MaybeUninit;
 use glycin_utils::*;
 use gufo_common::cicp::{Cicp, ColorPrimaries, MatrixCoefficients, TransferCharacteristics};
 use jpegxl_sys::color::color_encoding::{
-    JxlColorEncoding, JxlColorSpace, JxlPrimaries, JxlTransferFunction, JxlWhitePoint,
+    JxlColorEncoding, JxlColorSpace, JxlPrimaries, JxlRenderingIntent, JxlTransferFunction,
+    JxlWhitePoint,
 };
 use jpegxl_sys::common::types::{JxlBool, JxlBoxType};
 use jpegxl_sys::decode::*;
@@ -157,6 +158,27 @@ impl LoaderImplementation for ImgDecoder {
 
         Ok(frame)
     }
+
+    fn reconstruct_jpeg<T: ByteData>(&mut self) -> Result<T, ProcessError> {
+        let has_reconstruction_data = editing::container::boxes(&self.data)?
+            .iter()
+            .any(|x| &x.box_type == b"jbrd");
+
+        if !has_reconstruction_data {
+            return Err(ProcessError::expected(
+                &"Image does not contain JPEG reconstruction data",
+            ));
+        }
+
+        let decoder = jpegxl_rs::decoder_builder().build().expected_error()?;
+
+        match decoder.reconstruct(&self.data).expected_error()? {
+            (_, jpegxl_rs::decode::Data::Jpeg(data)) => T::try_from_vec(data).expected_error(),
+            (_, jpegxl_rs::decode::Data::Pixels(_)) => Err(ProcessError::expected(
+                &"Failed to reconstruct JPEG from image",
+            )),
+        }
+    }
 }
 
 struct BasicInfo {
@@ -322,3 +344,36 @@ fn color_encoding_to_cicp(c: JxlColorEncoding) -> Option<Cicp> {
         video_full_range_flag: gufo_common::cicp::VideoRangeFlag::Full,
     })
 }
+
+fn cicp_to_color_encoding(cicp: Cicp) -> Option<JxlColorEncoding> {
+    let (primaries, white_point) = match cicp.color_primaries {
+        ColorPrimaries::Srgb => (JxlPrimaries::SRgb, JxlWhitePoint::D65),
+        ColorPrimaries::DisplayP3 => (JxlPrimaries::P3, JxlWhitePoint::D65),
+        ColorPrimaries::DciP3 => (JxlPrimaries::P3, JxlWhitePoint::Dci),
+        ColorPrimaries::Rec2020 => (JxlPrimaries::Rec2100, JxlWhitePoint::D65),
+        _ => return None,
+    };
+
+    let transfer_function = match cicp.transfer_characteristics {
+        TransferCharacteristics::Linear => JxlTransferFunction::Linear,
+        TransferCharacteristics::Hlg => JxlTransferFunction::HLG,
+        TransferCharacteristics::Pq => JxlTransferFunction::PQ,
+        TransferCharacteristics::Srgb => JxlTransferFunction::SRGB,
+        TransferCharacteristics::Bt709 => JxlTransferFunction::BT709,
+        TransferCharacteristics::Dci => JxlTransferFunction::DCI,
+        _ => return None,
+    };
+
+    Some(JxlColorEncoding {
+        color_space: JxlColorSpace::Rgb,
+        white_point,
+        white_point_xy: [0.; 2],
+        primaries,
+        primaries_red_xy: [0.; 2],
+        primaries_green_xy: [0.; 2],
+        primaries_blue_xy: [0.; 2],
+        transfer_function,
+        gamma: 0.,
+        rendering_intent: JxlRenderingIntent::Relative,
+    })
+}
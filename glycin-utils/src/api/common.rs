@@ -3,7 +3,7 @@ use std::time::Duration;
 #[cfg(feature = "external")]
 use zbus::zvariant::{Type, as_value};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "external",
     derive(serde::Deserialize, serde::Serialize, Type)
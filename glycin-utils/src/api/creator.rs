@@ -3,7 +3,7 @@ use zbus::zvariant::{DeserializeDict, SerializeDict, Type, as_value};
 
 use crate::{ByteData, FungibleMemory, MemoryAllocationError, api};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "external",
     derive(Type, serde::Serialize, serde::Deserialize)
@@ -22,11 +22,29 @@ pub struct NewImage<B: ByteData> {
     pub image_info: api::ImageDetails<B>,
     #[cfg_attr(feature = "external", serde(with = "as_value"))]
     pub frames: Vec<api::Frame<B>>,
+    /// Existing JPEG to recompress losslessly instead of encoding the frames
+    #[cfg_attr(
+        feature = "external",
+        serde(
+            with = "as_value::optional",
+            skip_serializing_if = "Option::is_none",
+            default
+        )
+    )]
+    pub source_jpeg: Option<B>,
+    /// Limits that are enforced before encoding
+    #[cfg_attr(feature = "external", serde(with = "as_value", default))]
+    pub limits: api::Limits,
 }
 
 impl<B: ByteData> NewImage<B> {
     pub fn new(image_info: api::ImageDetails<B>, frames: Vec<api::Frame<B>>) -> Self {
-        Self { image_info, frames }
+        Self {
+            image_info,
+            frames,
+            source_jpeg: None,
+            limits: Default::default(),
+        }
     }
 
     pub fn into_other<O: ByteData>(self) -> Result<NewImage<O>, MemoryAllocationError> {
@@ -37,6 +55,8 @@ impl<B: ByteData> NewImage<B> {
                 .into_iter()
                 .map(|x| x.into_other::<O>())
                 .collect::<Result<_, _>>()?,
+            source_jpeg: self.source_jpeg.map(|x| x.into_other()).transpose()?,
+            limits: self.limits,
         })
     }
 
@@ -45,6 +65,25 @@ impl<B: ByteData> NewImage<B> {
         for frame in &mut self.frames {
             frame.initial_seal().await?;
         }
+        if let Some(source_jpeg) = &mut self.source_jpeg {
+            source_jpeg.initial_seal().await?;
+        }
+        Ok(())
+    }
+
+    /// Ensure that all frames are within the limits
+    pub fn check_limits(&self) -> Result<(), crate::ProcessError> {
+        let (max_width, max_height) = self.limits.max_dimensions;
+
+        for frame in &self.frames {
+            if frame.width > max_width || frame.height > max_height {
+                return Err(crate::ProcessError::expected(&format!(
+                    "Frame dimensions {}x{} exceed the limit of {max_width}x{max_height}",
+                    frame.width, frame.height
+                )));
+            }
+        }
+
         Ok(())
     }
 
@@ -53,17 +92,111 @@ impl<B: ByteData> NewImage<B> {
         for frame in &mut self.frames {
             frame.final_seal().await?;
         }
+        if let Some(source_jpeg) = &mut self.source_jpeg {
+            source_jpeg.final_seal().await?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 #[cfg_attr(feature = "external", derive(DeserializeDict, SerializeDict, Type))]
 #[cfg_attr(feature = "external", zvariant(signature = "dict"))]
 #[non_exhaustive]
 pub struct EncodingOptions {
     pub quality: Option<u8>,
+    /// Quality of the alpha channel if it's stored separately
+    pub alpha_quality: Option<u8>,
     pub compression: Option<u8>,
+    /// Encode without any loss of information
+    pub lossless: Option<bool>,
+    pub chroma_subsampling: Option<ChromaSubsampling>,
+    /// Store the image such that a lower resolution can be shown early
+    pub progressive: Option<bool>,
+    /// Time spent on encoding from 0 (fastest) to 100 (smallest output)
+    pub effort: Option<u8>,
+    /// Bits per channel in the encoded image
+    pub bit_depth: Option<u8>,
+    /// Maximum number of colors for an image with a color palette
+    pub palette_size: Option<u16>,
+    /// Add the standard icon sizes, scaled down from the largest frame
+    pub icon_standard_sizes: Option<bool>,
+    /// Block compression for GPU textures
+    pub texture_compression: Option<TextureCompression>,
+    /// Store successively halved resolutions down to one pixel
+    pub mipmaps: Option<bool>,
+    /// Compression method for OpenEXR images
+    pub exr_compression: Option<ExrCompression>,
+    /// Convert to CMYK with this ICC profile, which is embedded in the image
+    pub cmyk_icc_profile: Option<Vec<u8>>,
+    /// Maximum size of the encoded image in bytes
+    ///
+    /// The highest quality that results in an image of at most this size is
+    /// used. See [`EditorImplementation::create_target_size`].
+    ///
+    /// [`EditorImplementation::create_target_size`]: crate::EditorImplementation::create_target_size
+    pub target_size: Option<u64>,
+}
+
+/// Resolution of the chroma channels relative to the luma channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "external",
+    derive(Type, serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(feature = "external", zvariant(signature = "s"))]
+#[non_exhaustive]
+pub enum ChromaSubsampling {
+    /// Full chroma resolution
+    Yuv444,
+    /// Half horizontal chroma resolution
+    Yuv422,
+    /// Half horizontal and vertical chroma resolution
+    Yuv420,
+}
+
+/// Block compression for GPU textures
+///
+/// All formats store blocks of 4x4 pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "external",
+    derive(Type, serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(feature = "external", zvariant(signature = "s"))]
+#[non_exhaustive]
+pub enum TextureCompression {
+    /// RGB with optional 1-bit alpha, also known as DXT1
+    Bc1,
+    /// RGBA with interpolated alpha, also known as DXT5
+    Bc3,
+    /// Single channel
+    Bc4,
+    /// Two channels, commonly used for normal maps
+    Bc5,
+    /// RGBA with higher quality than BC3
+    Bc7,
+}
+
+/// Compression method for OpenEXR images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "external",
+    derive(Type, serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(feature = "external", zvariant(signature = "s"))]
+#[non_exhaustive]
+pub enum ExrCompression {
+    /// No compression
+    Uncompressed,
+    /// Lossless run-length encoding
+    Rle,
+    /// Lossless Deflate compression of 16 scan lines at once
+    Zip,
+    /// Lossless wavelet compression, suited for grainy images
+    Piz,
+    /// Lossy DCT-based compression of 32 scan lines at once
+    Dwaa,
 }
 
 #[derive(Debug)]
@@ -83,11 +216,24 @@ pub struct EncodingOptions {
 pub struct EncodedImage<B: ByteData> {
     #[cfg_attr(feature = "external", serde(with = "as_value"))]
     pub data: B,
+    /// Quality that was selected to reach the target size
+    #[cfg_attr(
+        feature = "external",
+        serde(
+            with = "as_value::optional",
+            skip_serializing_if = "Option::is_none",
+            default
+        )
+    )]
+    pub quality: Option<u8>,
 }
 
 impl<B: ByteData> EncodedImage<B> {
     pub fn new(data: B) -> Self {
-        Self { data }
+        Self {
+            data,
+            quality: None,
+        }
     }
 
     pub async fn inital_seal(&mut self) -> Result<(), MemoryAllocationError> {
@@ -101,6 +247,7 @@ impl<B: ByteData> EncodedImage<B> {
     pub fn into_fungible(self) -> EncodedImage<FungibleMemory> {
         EncodedImage {
             data: self.data.into_fungible(),
+            quality: self.quality,
         }
     }
 }
@@ -7,8 +7,8 @@ use glycin_common::Operations;
 use zbus::zvariant::{self, DeserializeDict, SerializeDict, Type, as_value};
 
 use crate::{
-    ByteData, EncodedImage, EncodingOptions, FungibleMemory, GenericContexts,
-    InitializationDetails, MemoryAllocationError, NewImage, ProcessError,
+    ByteData, EncodedImage, EncodingOptions, Frame, FrameRequest, FungibleMemory, GenericContexts,
+    InitializationDetails, LocalMemory, MemoryAllocationError, NewImage, ProcessError,
 };
 
 /// Implement this trait to create an image editor
@@ -27,6 +27,77 @@ pub trait EditorImplementation: Send + Sync + Sized + RefUnwindSafe + 'static {
         encoding_options: EncodingOptions,
     ) -> Result<EncodedImage<B>, ProcessError>;
 
+    /// Encode the image and write it to `writer`
+    ///
+    /// The default implementation writes the result of [`Self::create`].
+    /// Editors with encoders that write while encoding can implement this to
+    /// avoid keeping the complete encoded image in memory.
+    fn create_to<B: ByteData, W: Write>(
+        mime_type: String,
+        new_image: NewImage<B>,
+        encoding_options: EncodingOptions,
+        writer: &mut W,
+    ) -> Result<(), ProcessError> {
+        let encoded_image = Self::create(mime_type, new_image, encoding_options)?;
+
+        writer.write_all(&encoded_image.data).internal_error()
+    }
+
+    /// Encode with the highest quality that results in at most `target_size`
+    /// bytes
+    ///
+    /// The default implementation does a binary search over
+    /// [`EncodingOptions::quality`]. If even the lowest quality exceeds the
+    /// target size, the image encoded with the lowest quality is returned.
+    fn create_target_size<B: ByteData>(
+        mime_type: String,
+        new_image: NewImage<B>,
+        mut encoding_options: EncodingOptions,
+        target_size: u64,
+    ) -> Result<EncodedImage<B>, ProcessError> {
+        let new_image = new_image.into_other::<LocalMemory>().internal_error()?;
+        encoding_options.target_size = None;
+
+        let mut fitting = None;
+        let mut smallest = None;
+
+        let mut low = 0_u8;
+        let mut high = 100_u8;
+
+        while low <= high {
+            let quality = low + (high - low) / 2;
+            encoding_options.quality = Some(quality);
+
+            let encoded_image = Self::create::<LocalMemory>(
+                mime_type.clone(),
+                new_image.clone(),
+                encoding_options.clone(),
+            )?;
+
+            if encoded_image.data.len() as u64 <= target_size {
+                fitting = Some((quality, encoded_image));
+                low = quality + 1;
+            } else {
+                // Later attempts always use a lower quality
+                smallest = Some((quality, encoded_image));
+                match quality.checked_sub(1) {
+                    Some(quality) => high = quality,
+                    None => break,
+                }
+            }
+        }
+
+        let (quality, encoded_image) = fitting
+            .or(smallest)
+            .ok_or_else(|| ProcessError::expected(&"No quality could be tried"))?;
+
+        let mut encoded_image =
+            EncodedImage::new(B::try_from_vec(encoded_image.data.into_inner()).internal_error()?);
+        encoded_image.quality = Some(quality);
+
+        Ok(encoded_image)
+    }
+
     fn apply_sparse<B: ByteData>(
         &self,
         operations: Operations,
@@ -40,6 +111,58 @@ pub trait EditorImplementation: Send + Sync + Sized + RefUnwindSafe + 'static {
         &self,
         operations: Operations,
     ) -> Result<CompleteEditorOutput<B>, ProcessError>;
+
+    /// Decoded frame with operations applied, without encoding the image
+    ///
+    /// The [`editing::preview`](crate::editing::preview) function can be used to
+    /// apply the operations and the frame request to a decoded frame.
+    fn preview<B: ByteData>(
+        &self,
+        operations: Operations,
+        frame_request: FrameRequest,
+    ) -> Result<Frame<B>, ProcessError> {
+        let _ = (operations, frame_request);
+        Err(ProcessError::expected(
+            &"Previews are not supported for this format",
+        ))
+    }
+}
+
+/// Encode an image, respecting [`EncodingOptions::target_size`] and
+/// [`NewImage::limits`]
+pub fn create<E: EditorImplementation, B: ByteData>(
+    mime_type: String,
+    new_image: NewImage<B>,
+    encoding_options: EncodingOptions,
+) -> Result<EncodedImage<B>, ProcessError> {
+    new_image.check_limits()?;
+
+    match encoding_options.target_size {
+        Some(target_size) => {
+            E::create_target_size(mime_type, new_image, encoding_options, target_size)
+        }
+        None => E::create(mime_type, new_image, encoding_options),
+    }
+}
+
+/// Encode an image and write it to `writer`, respecting
+/// [`EncodingOptions::target_size`] and [`NewImage::limits`]
+///
+/// With a target size, the image is encoded completely before writing it.
+pub fn create_to<E: EditorImplementation, B: ByteData, W: Write>(
+    mime_type: String,
+    new_image: NewImage<B>,
+    encoding_options: EncodingOptions,
+    writer: &mut W,
+) -> Result<(), ProcessError> {
+    new_image.check_limits()?;
+
+    if encoding_options.target_size.is_some() {
+        let encoded_image = create::<E, B>(mime_type, new_image, encoding_options)?;
+        return writer.write_all(&encoded_image.data).internal_error();
+    }
+
+    E::create_to(mime_type, new_image, encoding_options, writer)
 }
 
 #[cfg(feature = "external")]
@@ -149,6 +272,11 @@ impl<B: ByteData> From<CompleteEditorOutput<B>> for SparseEditorOutput<B> {
 #[non_exhaustive]
 pub struct ByteChanges {
     pub changes: Vec<ByteChange>,
+    /// Values before the changes, in the same order as `changes`
+    ///
+    /// Allows to verify that the changes are applied to the same data the
+    /// editor has seen.
+    pub original_values: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Clone)]
@@ -171,9 +299,29 @@ impl ByteChanges {
                     new_value: *new_value,
                 })
                 .collect(),
+            original_values: None,
         }
     }
 
+    /// Record the values in `data` that are replaced by the changes
+    pub fn with_original_values(mut self, data: &[u8]) -> Result<Self, ProcessError> {
+        let original_values = self
+            .changes
+            .iter()
+            .map(|change| {
+                usize::try_from(change.offset)
+                    .ok()
+                    .and_then(|offset| data.get(offset))
+                    .copied()
+            })
+            .collect::<Option<Vec<u8>>>()
+            .expected_error()?;
+
+        self.original_values = Some(original_values);
+
+        Ok(self)
+    }
+
     pub fn apply(&self, data: &mut [u8]) -> std::io::Result<()> {
         let mut cur = Cursor::new(data);
         for change in self.changes.iter() {
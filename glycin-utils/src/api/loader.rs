@@ -25,6 +25,16 @@ pub trait LoaderImplementation: Send + Sync + Sized + 'static {
         &mut self,
         frame_request: FrameRequest,
     ) -> Result<Frame<T>, ProcessError>;
+
+    /// Original JPEG of an image that was losslessly recompressed from a JPEG
+    ///
+    /// The default implementation returns an error since most formats don't
+    /// store JPEG reconstruction data.
+    fn reconstruct_jpeg<T: ByteData>(&mut self) -> Result<T, ProcessError> {
+        Err(ProcessError::expected(
+            &"Image does not contain JPEG reconstruction data",
+        ))
+    }
 }
 
 #[cfg(feature = "external")]
@@ -127,7 +137,7 @@ impl<B: ByteData> RemoteImage<B> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "external",
     derive(Type, serde::Serialize, serde::Deserialize)
@@ -220,6 +230,16 @@ pub struct ImageDetails<B: ByteData> {
         )
     )]
     pub transformation_orientation: Option<Orientation>,
+    /// Number of times an animation is played. Zero means infinitely.
+    #[cfg_attr(
+        feature = "external",
+        serde(
+            with = "as_value::optional",
+            skip_serializing_if = "Option::is_none",
+            default
+        )
+    )]
+    pub animation_loop_count: Option<u32>,
 }
 
 impl<B: ByteData> ImageDetails<B> {
@@ -236,6 +256,7 @@ impl<B: ByteData> ImageDetails<B> {
             metadata_key_value: None,
             transformation_ignore_exif: false,
             transformation_orientation: None,
+            animation_loop_count: None,
         }
     }
 
@@ -252,6 +273,7 @@ impl<B: ByteData> ImageDetails<B> {
             metadata_key_value: self.metadata_key_value,
             transformation_ignore_exif: self.transformation_ignore_exif,
             transformation_orientation: self.transformation_orientation,
+            animation_loop_count: self.animation_loop_count,
         }
     }
 
@@ -268,6 +290,7 @@ impl<B: ByteData> ImageDetails<B> {
             metadata_key_value: self.metadata_key_value,
             transformation_ignore_exif: self.transformation_ignore_exif,
             transformation_orientation: self.transformation_orientation,
+            animation_loop_count: self.animation_loop_count,
         })
     }
 
@@ -308,6 +331,8 @@ impl<B: ByteData> Default for FrameDetails<B> {
             n_frame: None,
             pixel_density: None,
             physical_size: None,
+            cursor_hotspot: None,
+            layer_name: None,
         }
     }
 }
@@ -315,7 +340,7 @@ impl<B: ByteData> Default for FrameDetails<B> {
 #[cfg(not(feature = "external"))]
 pub type Optional<T> = Option<T>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "external", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
     feature = "external",
@@ -424,7 +449,7 @@ impl<B: ByteData> Frame<B> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "external",
     derive(Type, serde::Serialize, serde::Deserialize)
@@ -534,6 +559,26 @@ pub struct FrameDetails<B: ByteData> {
         )
     )]
     pub physical_size: Option<physical_dimension::PhysicalSize>,
+    /// Position of the pointer within a cursor image
+    #[cfg_attr(
+        feature = "external",
+        serde(
+            with = "as_value::optional",
+            skip_serializing_if = "Option::is_none",
+            default
+        )
+    )]
+    pub cursor_hotspot: Option<(u16, u16)>,
+    /// Name of the layer for formats that store frames as layers
+    #[cfg_attr(
+        feature = "external",
+        serde(
+            with = "as_value::optional",
+            skip_serializing_if = "Option::is_none",
+            default
+        )
+    )]
+    pub layer_name: Option<String>,
 }
 
 impl<B: ByteData> FrameDetails<B> {
@@ -548,6 +593,8 @@ impl<B: ByteData> FrameDetails<B> {
             n_frame: self.n_frame,
             pixel_density: self.pixel_density,
             physical_size: self.physical_size,
+            cursor_hotspot: self.cursor_hotspot,
+            layer_name: self.layer_name,
         }
     }
 
@@ -562,6 +609,8 @@ impl<B: ByteData> FrameDetails<B> {
             n_frame: self.n_frame,
             pixel_density: self.pixel_density,
             physical_size: self.physical_size,
+            cursor_hotspot: self.cursor_hotspot,
+            layer_name: self.layer_name,
         })
     }
 
@@ -7,6 +7,8 @@ mod change_memory_format;
 mod clip;
 mod operations;
 mod orientation;
+mod preview;
+mod scale;
 
 pub use change_memory_format::change_memory_format;
 pub use clip::clip;
@@ -15,6 +17,8 @@ use gufo_common::math::MathError;
 use gufo_common::read::ReadError;
 pub use operations::apply_operations;
 pub use orientation::change_orientation;
+pub use preview::{preview, preview_frame};
+pub use scale::scale_down;
 
 use crate::ByteData;
 
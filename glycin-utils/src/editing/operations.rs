@@ -28,3 +28,84 @@ pub fn apply_operations(
 
     Ok(frame)
 }
+
+#[cfg(test)]
+mod test {
+    use glycin_common::{ExtendedMemoryFormat, MemoryFormat};
+
+    use super::*;
+
+    fn frame() -> EditingFrame<FungibleMemory> {
+        EditingFrame {
+            width: 5,
+            height: 3,
+            stride: 5,
+            memory_format: ExtendedMemoryFormat::Basic(MemoryFormat::G8),
+            texture: FungibleMemory::from_vec((0..15).collect()),
+        }
+    }
+
+    fn apply(operations: &Operations) -> (u32, u32, Vec<u8>) {
+        let frame = apply_operations(frame(), operations).unwrap();
+        let row_len = frame.width as usize;
+        let texture = frame
+            .texture
+            .chunks(frame.stride as usize)
+            .take(frame.height as usize)
+            .flat_map(|row| row[..row_len].to_vec())
+            .collect();
+        (frame.width, frame.height, texture)
+    }
+
+    #[test]
+    fn normalize() {
+        let sequences = [
+            vec![
+                Operation::Rotate(Rotation::_90),
+                Operation::Clip((1, 1, 2, 3)),
+            ],
+            vec![
+                Operation::Clip((1, 0, 4, 2)),
+                Operation::MirrorHorizontally,
+                Operation::Rotate(Rotation::_270),
+                Operation::Clip((0, 1, 5, 5)),
+            ],
+            vec![
+                Operation::Rotate(Rotation::_90),
+                Operation::MirrorVertically,
+                Operation::Clip((0, 2, 2, 3)),
+            ],
+            vec![
+                Operation::MirrorVertically,
+                Operation::Clip((1, 1, 3, 2)),
+                Operation::Rotate(Rotation::_180),
+                Operation::Clip((1, 0, 2, 2)),
+                Operation::MirrorHorizontally,
+            ],
+        ];
+
+        for operations in sequences {
+            let operations = Operations::new(operations);
+            let normalized = operations.normalize((5, 3)).unwrap();
+            assert!(normalized.operations().len() <= 3);
+            assert_eq!(apply(&operations), apply(&normalized), "{operations:?}");
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        for mirror in [false, true] {
+            for rotation in [Rotation::_0, Rotation::_90, Rotation::_180, Rotation::_270] {
+                let mut operations = Operations::new(vec![Operation::MirrorVertically]);
+                operations.prepend(Operations::new_orientation(Orientation::new(
+                    mirror, rotation,
+                )));
+
+                let mut roundtrip = operations.inverse().unwrap();
+                roundtrip.prepend(operations);
+
+                assert_eq!(apply(&roundtrip), apply(&Operations::new(vec![])));
+            }
+        }
+    }
+}
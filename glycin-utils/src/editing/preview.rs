@@ -0,0 +1,61 @@
+use glycin_common::{ExtendedMemoryFormat, Operations};
+
+use super::EditingFrame;
+use crate::{ByteData, FrameRequest, FungibleMemory, GenericContexts, ProcessError, editing};
+
+/// Create a preview frame by applying operations to a decoded frame
+///
+/// After applying the `operations`, the `clip` and `scale` of the
+/// `frame_request` are applied to the result. The frame is only scaled down,
+/// never up.
+pub fn preview<B: ByteData>(
+    frame: EditingFrame<FungibleMemory>,
+    operations: &Operations,
+    frame_request: &FrameRequest,
+) -> Result<crate::Frame<B>, ProcessError> {
+    let mut frame = editing::apply_operations(frame, operations).expected_error()?;
+
+    if let Some(clip) = frame_request.clip {
+        frame = editing::clip(frame, clip).expected_error()?;
+    }
+
+    if let Some(scale) = frame_request.scale {
+        frame = editing::scale_down(frame, scale).expected_error()?;
+    }
+
+    let ExtendedMemoryFormat::Basic(memory_format) = frame.memory_format else {
+        return Err(ProcessError::expected(&format!(
+            "Unsupported memory format for preview: {:?}",
+            frame.memory_format
+        )));
+    };
+
+    let texture = B::try_from_slice(&frame.texture).expected_error()?;
+    let mut new_frame =
+        crate::Frame::new(frame.width, frame.height, memory_format, texture).expected_error()?;
+    new_frame.stride = frame.stride;
+
+    Ok(new_frame)
+}
+
+/// Same as [`preview()`] but for a decoded [`Frame`](crate::Frame)
+///
+/// The frame details like color profiles are kept.
+pub fn preview_frame<B: ByteData>(
+    frame: crate::Frame<FungibleMemory>,
+    operations: &Operations,
+    frame_request: &FrameRequest,
+) -> Result<crate::Frame<B>, ProcessError> {
+    let editing_frame = EditingFrame {
+        width: frame.width,
+        height: frame.height,
+        stride: frame.stride,
+        memory_format: ExtendedMemoryFormat::Basic(frame.memory_format),
+        texture: frame.texture,
+    };
+
+    let mut new_frame = preview(editing_frame, operations, frame_request)?;
+    new_frame.details = frame.details.into_other().expected_error()?;
+
+    Ok(new_frame)
+}
@@ -0,0 +1,141 @@
+use glycin_common::{ChannelType, ExtendedMemoryFormat, MemoryFormatInfo};
+use gufo_common::math::Checked;
+
+use super::{EditingFrame, Error};
+use crate::FungibleMemory;
+
+/// Scale frame down to fit into the given dimensions
+///
+/// The aspect ratio is kept and frames are never scaled up. Pixels are
+/// averaged over the covered area of the original frame.
+pub fn scale_down(
+    frame: EditingFrame<FungibleMemory>,
+    (max_width, max_height): (u32, u32),
+) -> Result<EditingFrame<FungibleMemory>, Error> {
+    let factor = f64::min(
+        max_width as f64 / frame.width as f64,
+        max_height as f64 / frame.height as f64,
+    );
+
+    if factor >= 1. {
+        return Ok(frame);
+    }
+
+    let width = u32::max(1, (frame.width as f64 * factor).round() as u32);
+    let height = u32::max(1, (frame.height as f64 * factor).round() as u32);
+
+    let pixel_size = frame.memory_format.n_bytes().usize();
+    let n_channels = frame.memory_format.n_channels() as usize;
+    let channel_type = match frame.memory_format {
+        ExtendedMemoryFormat::Basic(memory_format) => memory_format.channel_type(),
+        ExtendedMemoryFormat::Y8Cb8Cr8 | ExtendedMemoryFormat::Y8Cb8Cr8K8 => ChannelType::U8,
+    };
+    let channel_size = channel_type.size() as usize;
+
+    let new_stride = (Checked::new(width) * frame.memory_format.n_bytes().u32()).check()?;
+    let size = (Checked::new(height as usize) * new_stride as usize).check()?;
+    let mut new = Vec::with_capacity(size);
+
+    let stride = frame.stride as usize;
+    let mut sums = vec![0.; n_channels];
+
+    for y in 0..height as u64 {
+        let y0 = (y * frame.height as u64 / height as u64) as usize;
+        let y1 = usize::max(
+            y0 + 1,
+            ((y + 1) * frame.height as u64 / height as u64) as usize,
+        );
+
+        for x in 0..width as u64 {
+            let x0 = (x * frame.width as u64 / width as u64) as usize;
+            let x1 = usize::max(
+                x0 + 1,
+                ((x + 1) * frame.width as u64 / width as u64) as usize,
+            );
+
+            // Half floats are not averaged but sampled
+            if channel_type == ChannelType::F16 {
+                let i = (Checked::new(y0) * stride + Checked::new(x0) * pixel_size).check()?;
+                new.extend_from_slice(frame.texture.get(i..i + pixel_size).unwrap_or_default());
+                continue;
+            }
+
+            sums.fill(0.);
+            for y_ in y0..y1 {
+                for x_ in x0..x1 {
+                    let i = (Checked::new(y_) * stride + Checked::new(x_) * pixel_size).check()?;
+                    let Some(pixel) = frame.texture.get(i..i + pixel_size) else {
+                        continue;
+                    };
+
+                    for (sum, channel) in sums.iter_mut().zip(pixel.chunks_exact(channel_size)) {
+                        *sum += match channel_type {
+                            ChannelType::U8 => channel[0] as f64,
+                            ChannelType::U16 => u16::from_ne_bytes([channel[0], channel[1]]) as f64,
+                            _ => {
+                                f32::from_ne_bytes([channel[0], channel[1], channel[2], channel[3]])
+                                    as f64
+                            }
+                        };
+                    }
+                }
+            }
+
+            let n_pixels = ((x1 - x0) * (y1 - y0)) as f64;
+            for sum in &sums {
+                let value = sum / n_pixels;
+                match channel_type {
+                    ChannelType::U8 => new.push(value.round() as u8),
+                    ChannelType::U16 => {
+                        new.extend_from_slice(&(value.round() as u16).to_ne_bytes())
+                    }
+                    _ => new.extend_from_slice(&(value as f32).to_ne_bytes()),
+                }
+            }
+        }
+    }
+
+    let mut frame = frame;
+    frame.width = width;
+    frame.height = height;
+    frame.stride = new_stride;
+    frame.texture = FungibleMemory::from_vec(new);
+
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MemoryFormat;
+
+    #[test]
+    fn scale_u8() {
+        let frame = EditingFrame {
+            width: 4,
+            height: 2,
+            stride: 4,
+            memory_format: ExtendedMemoryFormat::Basic(MemoryFormat::G8),
+            texture: FungibleMemory::from_vec(vec![0, 2, 10, 20, 4, 6, 30, 40]),
+        };
+
+        let frame = scale_down(frame, (2, 2)).unwrap();
+        assert_eq!((frame.width, frame.height, frame.stride), (2, 1, 2));
+        assert_eq!(&*frame.texture, &[3, 25]);
+    }
+
+    #[test]
+    fn scale_no_upscale() {
+        let frame = EditingFrame {
+            width: 1,
+            height: 1,
+            stride: 3,
+            memory_format: ExtendedMemoryFormat::Basic(MemoryFormat::R8g8b8),
+            texture: FungibleMemory::from_vec(vec![1, 2, 3]),
+        };
+
+        let frame = scale_down(frame, (10, 10)).unwrap();
+        assert_eq!((frame.width, frame.height), (1, 1));
+        assert_eq!(&*frame.texture, &[1, 2, 3]);
+    }
+}
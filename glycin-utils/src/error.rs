@@ -19,6 +19,7 @@ pub enum RemoteError {
     EditingError(String),
     InternalEditorError(String),
     UnsupportedImageFormat(String),
+    UnsupportedFeature(String),
     ConversionTooLargerError,
     OutOfMemory(String),
     Aborted,
@@ -47,6 +48,7 @@ impl ProcessError {
                 RemoteError::InternalLoaderError(err.to_string())
             }
             ProcessError::UnsupportedImageFormat(msg) => RemoteError::UnsupportedImageFormat(msg),
+            ProcessError::UnsupportedFeature(msg) => RemoteError::UnsupportedFeature(msg),
             ProcessError::ConversionTooLargerError => RemoteError::ConversionTooLargerError,
             err @ ProcessError::OutOfMemory { .. } => RemoteError::OutOfMemory(err.to_string()),
             ProcessError::NoMoreFrames => RemoteError::NoMoreFrames,
@@ -60,6 +62,7 @@ impl ProcessError {
                 RemoteError::InternalEditorError(err.to_string())
             }
             ProcessError::UnsupportedImageFormat(msg) => RemoteError::UnsupportedImageFormat(msg),
+            ProcessError::UnsupportedFeature(msg) => RemoteError::UnsupportedFeature(msg),
             ProcessError::ConversionTooLargerError => RemoteError::ConversionTooLargerError,
             err @ ProcessError::OutOfMemory { .. } => RemoteError::OutOfMemory(err.to_string()),
             ProcessError::NoMoreFrames => RemoteError::NoMoreFrames,
@@ -76,6 +79,8 @@ pub enum ProcessError {
     InternalError { err: String, location: Location },
     #[error("Unsupported image format: {0}")]
     UnsupportedImageFormat(String),
+    #[error("Feature not supported: {0}")]
+    UnsupportedFeature(String),
     #[error("Dimension too large for system")]
     ConversionTooLargerError,
     #[error("{location}: Not enough memory available")]
@@ -1,6 +1,6 @@
 // Copyright (c) 2024 GNOME Foundation Inc.
 
-use std::io::Read;
+use std::io::{BufWriter, Read, Write};
 use std::marker::PhantomData;
 use std::os::fd::OwnedFd;
 use std::os::unix::net::UnixStream;
@@ -61,7 +61,30 @@ impl<E: api::EditorImplementation> Editor<E> {
         new_image.initial_seal().await?;
         blocking::unblock(|| {
             super::catch_unwind(|| {
-                E::create(mime_type, new_image, encoding_options).map_err(|x| x.into_editor_error())
+                api::create::<E, _>(mime_type, new_image, encoding_options)
+                    .map_err(|x| x.into_editor_error())
+            })
+            .flatten()
+        })
+        .await
+    }
+
+    /// Like `create` but writes the encoded image to `fd` while encoding
+    async fn create_to_fd(
+        &self,
+        mime_type: String,
+        mut new_image: api::NewImage<SharedMemory>,
+        encoding_options: api::EncodingOptions,
+        fd: zbus::zvariant::OwnedFd,
+    ) -> Result<(), RemoteError> {
+        new_image.initial_seal().await?;
+        let mut stream = BufWriter::new(UnixStream::from(OwnedFd::from(fd)));
+
+        blocking::unblock(move || {
+            super::catch_unwind(move || {
+                api::create_to::<E, _, _>(mime_type, new_image, encoding_options, &mut stream)
+                    .and_then(|_| stream.flush().internal_error())
+                    .map_err(|x| x.into_editor_error())
             })
             .flatten()
         })
@@ -107,11 +130,7 @@ impl<E: api::EditorImplementation> Editor<E> {
             .object_server()
             .at(
                 &path,
-                EditableImage {
-                    editor_implementation: Arc::new(Box::new(editor_state)),
-                    path: path.clone(),
-                    dropped: Default::default(),
-                },
+                EditableImage::new(Arc::new(Box::new(editor_state)), path.clone()),
             )
             .await
             .internal_error()
@@ -127,6 +146,16 @@ pub struct EditableImage<E: api::EditorImplementation> {
     dropped: async_lock::OnceCell<()>,
 }
 
+impl<E: api::EditorImplementation> EditableImage<E> {
+    pub fn new(editor_implementation: Arc<Box<E>>, path: OwnedObjectPath) -> Self {
+        Self {
+            editor_implementation,
+            path,
+            dropped: Default::default(),
+        }
+    }
+}
+
 #[zbus::interface(name = "org.gnome.glycin.EditableImage")]
 impl<E: api::EditorImplementation> EditableImage<E> {
     async fn apply_sparse(
@@ -178,6 +207,32 @@ impl<E: api::EditorImplementation> EditableImage<E> {
         }
     }
 
+    /// Decoded frame with the operations applied
+    async fn preview(
+        &self,
+        mut edit_request: EditRequest,
+        frame_request: api::FrameRequest,
+    ) -> Result<api::Frame<SharedMemory>, RemoteError> {
+        edit_request.initial_seal().await?;
+        let operations = edit_request.operations()?;
+
+        let editor_implementation = self.editor_implementation.clone();
+        let mut editor_output = blocking::unblock(move || {
+            super::catch_unwind(move || {
+                editor_implementation
+                    .preview(operations, frame_request)
+                    .map_err(|x| x.into_loader_error())
+            })
+            .flatten()
+        })
+        .fuse();
+
+        futures_util::select! {
+            result = editor_output => result,
+            _ = self.dropped.wait().fuse() => Err(RemoteError::Aborted),
+        }
+    }
+
     async fn done(
         &self,
         #[zbus(object_server)] object_server: &zbus::ObjectServer,
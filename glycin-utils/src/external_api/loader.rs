@@ -115,6 +115,31 @@ impl<T: api::LoaderImplementation> Image<T> {
         }
     }
 
+    async fn reconstruct_jpeg(&self) -> Result<api::EncodedImage<SharedMemory>, RemoteError> {
+        let loader_implementation = self.loader_implementation.clone();
+        let mut reconstruct_jpeg = blocking::unblock(move || {
+            let mut loader_implementation = loader_implementation.lock().map_err(|err| {
+                RemoteError::InternalLoaderError(format!(
+                    "Failed to lock loader state for operation: {err}"
+                ))
+            })?;
+
+            super::catch_unwind(move || {
+                loader_implementation
+                    .reconstruct_jpeg()
+                    .map(api::EncodedImage::new)
+                    .map_err(|x| x.into_loader_error())
+            })
+            .flatten()
+        })
+        .fuse();
+
+        futures_util::select! {
+            result = reconstruct_jpeg => result,
+            _ = self.dropped.wait().fuse() => Err(RemoteError::Aborted),
+        }
+    }
+
     async fn done(
         &self,
         #[zbus(object_server)] object_server: &zbus::ObjectServer,
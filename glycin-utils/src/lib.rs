@@ -19,6 +19,7 @@ pub mod image_rs;
 #[cfg(all(feature = "loader-utils", feature = "external"))]
 pub mod instruction_handler;
 mod memory;
+pub mod quantize;
 pub mod safe_math;
 
 pub use api::*;
@@ -0,0 +1,492 @@
+//! Reduce the colors of an image to a palette
+//!
+//! The palette is selected via median cut and the pixels are mapped to the
+//! palette with optional Floyd–Steinberg dithering.
+
+use std::collections::HashMap;
+
+/// Image with colors reduced to a palette
+#[derive(Debug, Clone)]
+pub struct IndexedImage {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA colors with at most 256 entries
+    pub palette: Vec<[u8; 4]>,
+    /// Palette index for every pixel
+    pub indices: Vec<u8>,
+    /// Index of the fully transparent palette entry
+    pub transparent: Option<u8>,
+}
+
+impl IndexedImage {
+    /// Palette as RGB without alpha
+    pub fn palette_rgb(&self) -> Vec<u8> {
+        self.palette
+            .iter()
+            .flat_map(|x| [x[0], x[1], x[2]])
+            .collect()
+    }
+
+    /// Alpha values of the palette entries
+    pub fn palette_alpha(&self) -> Vec<u8> {
+        self.palette.iter().map(|x| x[3]).collect()
+    }
+}
+
+/// How transparency is represented in the palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// Pixels are either opaque or fully transparent, like in GIF
+    #[default]
+    Binary,
+    /// Palette entries can be partially transparent, like in PNG
+    Full,
+}
+
+/// Builder for the quantization of RGBA images
+#[derive(Debug, Clone)]
+pub struct Quantizer {
+    palette_size: usize,
+    dithering: bool,
+    alpha_mode: AlphaMode,
+}
+
+impl Default for Quantizer {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl Quantizer {
+    /// Maximum number of colors in the palette
+    ///
+    /// The value is limited to be between 2 and 256.
+    pub fn new(palette_size: u16) -> Self {
+        Self {
+            palette_size: palette_size.clamp(2, 256).into(),
+            dithering: true,
+            alpha_mode: AlphaMode::default(),
+        }
+    }
+
+    /// Distribute the color error to neighboring pixels
+    ///
+    /// Enabled by default.
+    pub fn dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Quantize tightly packed RGBA 8-bit pixels
+    pub fn quantize(&self, rgba: &[u8], width: u32) -> IndexedImage {
+        let pixels = rgba
+            .chunks_exact(4)
+            .map(|x| self.normalize([x[0], x[1], x[2], x[3]]))
+            .collect::<Vec<_>>();
+
+        let height = pixels.len().checked_div(width as usize).unwrap_or(0) as u32;
+
+        let mut histogram = HashMap::<[u8; 4], u32>::new();
+        let mut has_transparent = false;
+        for pixel in &pixels {
+            match pixel {
+                None => has_transparent = true,
+                Some(color) => *histogram.entry(*color).or_default() += 1,
+            }
+        }
+
+        // Fully transparent pixels share an extra entry
+        let n_colors = self.palette_size - usize::from(has_transparent);
+        let mut palette = median_cut(histogram.into_iter().collect(), n_colors);
+
+        let transparent = if has_transparent {
+            palette.push([0; 4]);
+            Some((palette.len() - 1) as u8)
+        } else {
+            None
+        };
+
+        let indices = self.map_pixels(&pixels, width as usize, &palette, transparent);
+
+        IndexedImage {
+            width,
+            height,
+            palette,
+            indices,
+            transparent,
+        }
+    }
+
+    /// Returns `None` for pixels that are stored as transparent index
+    fn normalize(&self, [r, g, b, a]: [u8; 4]) -> Option<[u8; 4]> {
+        match self.alpha_mode {
+            AlphaMode::Binary if a < 128 => None,
+            AlphaMode::Binary => Some([r, g, b, 255]),
+            AlphaMode::Full if a == 0 => None,
+            AlphaMode::Full => Some([r, g, b, a]),
+        }
+    }
+
+    fn map_pixels(
+        &self,
+        pixels: &[Option<[u8; 4]>],
+        width: usize,
+        palette: &[[u8; 4]],
+        transparent: Option<u8>,
+    ) -> Vec<u8> {
+        // The transparent entry is only used for transparent pixels
+        let opaque_palette = &palette[..palette.len() - usize::from(transparent.is_some())];
+
+        let tree = PaletteTree::new(opaque_palette);
+        let mut cache = HashMap::<[u8; 4], u8>::new();
+        let mut nearest =
+            |color: [u8; 4]| *cache.entry(color).or_insert_with(|| tree.nearest(color));
+
+        let mut indices = Vec::with_capacity(pixels.len());
+
+        // Errors for the current and next row with one pixel padding on each side
+        let mut errors = vec![[0_f32; 4]; width + 2];
+        let mut next_errors = vec![[0_f32; 4]; width + 2];
+
+        for row in pixels.chunks(width.max(1)) {
+            for (x, pixel) in row.iter().enumerate() {
+                let Some(color) = pixel else {
+                    indices.push(transparent.unwrap_or_default());
+                    continue;
+                };
+
+                if !self.dithering {
+                    indices.push(nearest(*color));
+                    continue;
+                }
+
+                let error = errors[x + 1];
+                let value: [f32; 4] =
+                    std::array::from_fn(|i| (f32::from(color[i]) + error[i]).clamp(0., 255.));
+
+                let index = nearest(value.map(|x| x.round() as u8));
+                indices.push(index);
+
+                let selected = palette[index as usize];
+                let error: [f32; 4] = std::array::from_fn(|i| value[i] - f32::from(selected[i]));
+
+                for i in 0..4 {
+                    errors[x + 2][i] += error[i] * 7. / 16.;
+                    next_errors[x][i] += error[i] * 3. / 16.;
+                    next_errors[x + 1][i] += error[i] * 5. / 16.;
+                    next_errors[x + 2][i] += error[i] * 1. / 16.;
+                }
+            }
+
+            std::mem::swap(&mut errors, &mut next_errors);
+            next_errors.fill([0.; 4]);
+        }
+
+        indices
+    }
+}
+
+/// Box of colors in the RGBA color space
+struct ColorBox {
+    colors: Vec<([u8; 4], u32)>,
+    /// Number of pixels
+    population: u64,
+    /// Channel with the largest range
+    widest_channel: usize,
+    /// Range of the widest channel
+    range: u8,
+}
+
+impl ColorBox {
+    fn new(colors: Vec<([u8; 4], u32)>) -> Self {
+        let population = colors.iter().map(|(_, n)| u64::from(*n)).sum();
+
+        let mut min = [u8::MAX; 4];
+        let mut max = [u8::MIN; 4];
+        for (color, _) in &colors {
+            for i in 0..4 {
+                min[i] = min[i].min(color[i]);
+                max[i] = max[i].max(color[i]);
+            }
+        }
+
+        let (widest_channel, range) = (0..4)
+            .map(|i| (i, max[i].saturating_sub(min[i])))
+            .max_by_key(|(_, range)| *range)
+            .unwrap_or((0, 0));
+
+        Self {
+            colors,
+            population,
+            widest_channel,
+            range,
+        }
+    }
+
+    /// Split at the median of the widest channel
+    fn split(mut self) -> (Self, Self) {
+        let channel = self.widest_channel;
+        self.colors.sort_unstable_by_key(|(c, _)| c[channel]);
+
+        let half = self.population / 2;
+        let mut sum = 0;
+        let median = self
+            .colors
+            .iter()
+            .position(|(_, n)| {
+                sum += u64::from(*n);
+                sum >= half
+            })
+            .unwrap_or(0)
+            .clamp(1, self.colors.len() - 1);
+
+        let rest = self.colors.split_off(median);
+        (Self::new(self.colors), Self::new(rest))
+    }
+
+    /// Average color weighted by the number of pixels
+    fn average(&self) -> [u8; 4] {
+        let population = self.population.max(1);
+        std::array::from_fn(|i| {
+            let sum: u64 = self
+                .colors
+                .iter()
+                .map(|(c, n)| u64::from(c[i]) * u64::from(*n))
+                .sum();
+            ((sum + population / 2) / population) as u8
+        })
+    }
+}
+
+fn median_cut(colors: Vec<([u8; 4], u32)>, n_colors: usize) -> Vec<[u8; 4]> {
+    if colors.len() <= n_colors {
+        return colors.into_iter().map(|(c, _)| c).collect();
+    }
+
+    let mut boxes = vec![ColorBox::new(colors)];
+
+    while boxes.len() < n_colors {
+        // Prefer splitting large boxes that contain many pixels
+        let Some((i, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.colors.len() > 1)
+            .max_by_key(|(_, b)| u64::from(b.range) * b.population)
+        else {
+            break;
+        };
+
+        let (a, b) = boxes.swap_remove(i).split();
+        boxes.push(a);
+        boxes.push(b);
+    }
+
+    boxes.iter().map(ColorBox::average).collect()
+}
+
+/// k-d tree of the palette for finding the nearest color
+struct PaletteTree {
+    nodes: Vec<PaletteNode>,
+}
+
+struct PaletteNode {
+    color: [u8; 4],
+    /// Index in the palette
+    index: u8,
+    /// Channel by which the subtrees are split
+    channel: usize,
+    /// Nodes with a smaller or equal value in `channel`
+    lower: Option<usize>,
+    /// Nodes with a larger or equal value in `channel`
+    upper: Option<usize>,
+}
+
+impl PaletteTree {
+    fn new(palette: &[[u8; 4]]) -> Self {
+        let mut entries = palette
+            .iter()
+            .enumerate()
+            .map(|(i, color)| (*color, i as u8))
+            .collect::<Vec<_>>();
+
+        let mut tree = Self { nodes: Vec::new() };
+        tree.build(&mut entries);
+        tree
+    }
+
+    /// Add the entries as subtree and return the index of its root
+    fn build(&mut self, entries: &mut [([u8; 4], u8)]) -> Option<usize> {
+        if entries.is_empty() {
+            return None;
+        }
+
+        let channel = (0..4)
+            .max_by_key(|i| {
+                let min = entries.iter().map(|(c, _)| c[*i]).min().unwrap_or(0);
+                let max = entries.iter().map(|(c, _)| c[*i]).max().unwrap_or(0);
+                max - min
+            })
+            .unwrap_or(0);
+
+        entries.sort_unstable_by_key(|(c, _)| c[channel]);
+        let median = entries.len() / 2;
+        let (color, index) = entries[median];
+
+        let node = self.nodes.len();
+        self.nodes.push(PaletteNode {
+            color,
+            index,
+            channel,
+            lower: None,
+            upper: None,
+        });
+
+        let (lower, rest) = entries.split_at_mut(median);
+        self.nodes[node].lower = self.build(lower);
+        self.nodes[node].upper = self.build(&mut rest[1..]);
+
+        Some(node)
+    }
+
+    /// Palette index of the color with the smallest distance
+    ///
+    /// For equal distances the smallest index is returned.
+    fn nearest(&self, color: [u8; 4]) -> u8 {
+        let mut best = (i32::MAX, 0);
+        self.search(Some(0).filter(|_| !self.nodes.is_empty()), color, &mut best);
+        best.1
+    }
+
+    fn search(&self, node: Option<usize>, color: [u8; 4], best: &mut (i32, u8)) {
+        let Some(node) = node.map(|x| &self.nodes[x]) else {
+            return;
+        };
+
+        let distance = (0..4)
+            .map(|i| (i32::from(node.color[i]) - i32::from(color[i])).pow(2))
+            .sum::<i32>();
+        *best = (*best).min((distance, node.index));
+
+        let diff = i32::from(color[node.channel]) - i32::from(node.color[node.channel]);
+        let (near, far) = if diff <= 0 {
+            (node.lower, node.upper)
+        } else {
+            (node.upper, node.lower)
+        };
+
+        self.search(near, color, best);
+
+        // Entries on the other side can only be closer if the splitting plane is
+        // within the current distance
+        if diff.pow(2) <= best.0 {
+            self.search(far, color, best);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quantize_exact() {
+        let rgba = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [255, 0, 0, 255],
+            [0, 0, 0, 0],
+        ]
+        .concat();
+
+        let image = Quantizer::new(4).quantize(&rgba, 2);
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.palette.len(), 3);
+        assert_eq!(image.transparent, Some(2));
+        assert_eq!(image.indices[0], image.indices[2]);
+        assert_eq!(image.palette[image.indices[0] as usize], [255, 0, 0, 255]);
+        assert_eq!(image.palette[image.indices[1] as usize], [0, 255, 0, 255]);
+        assert_eq!(image.indices[3], 2);
+    }
+
+    #[test]
+    fn quantize_reduce() {
+        let rgba = (0..=255_u8)
+            .flat_map(|x| [x, x, x, 255])
+            .collect::<Vec<_>>();
+
+        let image = Quantizer::new(16).dithering(false).quantize(&rgba, 16);
+        assert_eq!(image.palette.len(), 16);
+        assert_eq!(image.transparent, None);
+
+        for (pixel, index) in rgba.chunks_exact(4).zip(&image.indices) {
+            let color = image.palette[*index as usize];
+            assert!(pixel[0].abs_diff(color[0]) <= 8, "{pixel:?} {color:?}");
+        }
+    }
+
+    #[test]
+    fn quantize_dithering() {
+        // Gray between black and white
+        let pixels = vec![Some([128, 128, 128, 255]); 64];
+        let palette = [[0, 0, 0, 255], [255, 255, 255, 255]];
+
+        let indices = Quantizer::new(2).map_pixels(&pixels, 8, &palette, None);
+        let n_white = indices.iter().filter(|x| **x == 1).count();
+        assert!((24..=40).contains(&n_white), "{n_white}");
+
+        let indices = Quantizer::new(2)
+            .dithering(false)
+            .map_pixels(&pixels, 8, &palette, None);
+        assert!(indices.iter().all(|x| *x == indices[0]));
+    }
+
+    #[test]
+    fn quantize_palette_tree() {
+        // Deterministic pseudo-random colors, including duplicates
+        let mut state = 1_u32;
+        let mut next_color = || -> [u8; 4] {
+            std::array::from_fn(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8 & 0xF0
+            })
+        };
+
+        let palette = (0..200).map(|_| next_color()).collect::<Vec<_>>();
+        let tree = PaletteTree::new(&palette);
+
+        for _ in 0..1000 {
+            let color = next_color().map(|x| x | 0x07);
+
+            let expected = palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, p)| {
+                    (0..4)
+                        .map(|i| (i32::from(p[i]) - i32::from(color[i])).pow(2))
+                        .sum::<i32>()
+                })
+                .map(|(i, _)| i as u8)
+                .unwrap();
+
+            assert_eq!(tree.nearest(color), expected, "{color:?}");
+        }
+    }
+
+    #[test]
+    fn quantize_full_alpha() {
+        let rgba = [[255, 0, 0, 128], [255, 0, 0, 0], [0, 0, 255, 0]].concat();
+
+        let image = Quantizer::new(256)
+            .alpha_mode(AlphaMode::Full)
+            .quantize(&rgba, 3);
+        assert_eq!(image.palette.len(), 2);
+        assert_eq!(image.palette[image.indices[0] as usize], [255, 0, 0, 128]);
+        assert_eq!(image.indices[1], image.indices[2]);
+        assert_eq!(image.transparent, Some(image.indices[1]));
+    }
+}
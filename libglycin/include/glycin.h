@@ -216,6 +216,77 @@ typedef enum
 
 GType gly_physical_dimension_unit_get_type(void);
 
+/**************** GlyChromaSubsampling ****************/
+
+/**
+ * GlyChromaSubsampling:
+ * @GLY_CHROMA_SUBSAMPLING_YUV444: Full chroma resolution
+ * @GLY_CHROMA_SUBSAMPLING_YUV422: Half horizontal chroma resolution
+ * @GLY_CHROMA_SUBSAMPLING_YUV420: Half horizontal and vertical chroma resolution
+ *
+ * Resolution of the chroma channels relative to the luma channel
+ *
+ * Since: 2.2
+ */
+typedef enum
+{
+    GLY_CHROMA_SUBSAMPLING_YUV444,
+    GLY_CHROMA_SUBSAMPLING_YUV422,
+    GLY_CHROMA_SUBSAMPLING_YUV420,
+} GlyChromaSubsampling;
+
+GType gly_chroma_subsampling_get_type(void);
+
+/**************** GlyTextureCompression ****************/
+
+/**
+ * GlyTextureCompression:
+ * @GLY_TEXTURE_COMPRESSION_BC1: RGB with optional 1-bit alpha, also known as DXT1
+ * @GLY_TEXTURE_COMPRESSION_BC3: RGBA with interpolated alpha, also known as DXT5
+ * @GLY_TEXTURE_COMPRESSION_BC4: Single channel
+ * @GLY_TEXTURE_COMPRESSION_BC5: Two channels, commonly used for normal maps
+ * @GLY_TEXTURE_COMPRESSION_BC7: RGBA with higher quality than BC3
+ *
+ * Block compression for GPU textures
+ *
+ * Since: 2.2
+ */
+typedef enum
+{
+    GLY_TEXTURE_COMPRESSION_BC1,
+    GLY_TEXTURE_COMPRESSION_BC3,
+    GLY_TEXTURE_COMPRESSION_BC4,
+    GLY_TEXTURE_COMPRESSION_BC5,
+    GLY_TEXTURE_COMPRESSION_BC7,
+} GlyTextureCompression;
+
+GType gly_texture_compression_get_type(void);
+
+/**************** GlyExrCompression ****************/
+
+/**
+ * GlyExrCompression:
+ * @GLY_EXR_COMPRESSION_UNCOMPRESSED: No compression
+ * @GLY_EXR_COMPRESSION_RLE: Lossless run-length encoding
+ * @GLY_EXR_COMPRESSION_ZIP: Lossless Deflate compression of 16 scan lines at once
+ * @GLY_EXR_COMPRESSION_PIZ: Lossless wavelet compression, suited for grainy images
+ * @GLY_EXR_COMPRESSION_DWAA: Lossy DCT-based compression of 32 scan lines at once
+ *
+ * Compression method for OpenEXR images
+ *
+ * Since: 2.2
+ */
+typedef enum
+{
+    GLY_EXR_COMPRESSION_UNCOMPRESSED,
+    GLY_EXR_COMPRESSION_RLE,
+    GLY_EXR_COMPRESSION_ZIP,
+    GLY_EXR_COMPRESSION_PIZ,
+    GLY_EXR_COMPRESSION_DWAA,
+} GlyExrCompression;
+
+GType gly_exr_compression_get_type(void);
+
 /**************** GlyLoader ****************/
 
 /**
@@ -1061,6 +1132,67 @@ gboolean gly_new_frame_set_color_icc_profile(GlyNewFrame *new_frame, GBytes *icc
 void gly_new_frame_set_pixel_density(GlyNewFrame *new_frame,
                                      GlyPixelDensity *pixel_density);
 
+/**
+ * gly_new_frame_set_color_cicp:
+ * @new_frame:
+ * @cicp: (nullable): Coding-independent code points
+ *
+ * Tags the frame with CICP values, for example to mark PQ or HLG HDR content.
+ *
+ * If the format does not support CICP, creating the image fails.
+ *
+ * Returns: `FALSE` if the CICP values are not valid.
+ *
+ * Since: 2.2
+ */
+gboolean gly_new_frame_set_color_cicp(GlyNewFrame *new_frame,
+                                      const GlyCicp *cicp);
+
+/**
+ * gly_new_frame_set_delay:
+ * @new_frame:
+ * @delay: Duration in microseconds
+ *
+ * Duration to show the frame for animations.
+ *
+ * If the value is zero, no delay is set. Delays are only supported if the
+ * format supports animations. Otherwise, creating the image fails.
+ *
+ * Since: 2.2
+ */
+void gly_new_frame_set_delay(GlyNewFrame *new_frame, int64_t delay);
+
+/**
+ * gly_new_frame_set_cursor_hotspot:
+ * @new_frame:
+ * @x: Horizontal position from the left
+ * @y: Vertical position from the top
+ *
+ * Position of the pointer within a cursor image.
+ *
+ * If the format does not support cursor hotspots, creating the image fails.
+ *
+ * Since: 2.2
+ */
+void gly_new_frame_set_cursor_hotspot(GlyNewFrame *new_frame,
+                                      uint16_t x,
+                                      uint16_t y);
+
+/**
+ * gly_new_frame_set_layer_name:
+ * @new_frame:
+ * @layer_name: Name of the layer
+ *
+ * Name of the layer the frame is stored as. Only used by formats that store
+ * each frame as a separate layer.
+ *
+ * If the format does not support layer names, creating the image fails.
+ *
+ * Since: 2.2
+ */
+void gly_new_frame_set_layer_name(GlyNewFrame *new_frame,
+                                  const char *layer_name);
+
 /**
  * GlyEncodedImage:
  *
@@ -1080,6 +1212,67 @@ G_DECLARE_FINAL_TYPE(GlyEncodedImage, gly_encoded_image, GLY, ENCODED_IMAGE, GOb
  */
 GBytes *gly_encoded_image_get_data(GlyEncodedImage *encoded_image);
 
+/**
+ * gly_encoded_image_get_quality:
+ *
+ * Quality that was selected to reach the target size set via
+ * [method@Creator.set_target_size].
+ *
+ * Returns: The quality between 0 and 100 or -1 if no target size was set
+ *
+ * Since: 2.2
+ */
+int32_t gly_encoded_image_get_quality(GlyEncodedImage *encoded_image);
+
+/**
+ * gly_image_reconstruct_jpeg:
+ * @image:
+ * @error:
+ *
+ * Reconstructs the original JPEG of an image that was losslessly
+ * recompressed from a JPEG, for example via [method@Creator.set_source_jpeg].
+ * The returned data are identical to the original JPEG file.
+ *
+ * Returns: (transfer full): Original JPEG or `NULL` if the image does not
+ *   contain JPEG reconstruction data.
+ *
+ * Since: 2.2
+ */
+GlyEncodedImage *gly_image_reconstruct_jpeg(GlyImage *image,
+                                            GError **error);
+
+/**
+ * gly_image_reconstruct_jpeg_async:
+ * @image:
+ * @cancellable: (nullable): A [class@Gio.Cancellable] to cancel the operation
+ * @callback: A callback to call when the operation is complete
+ * @user_data: Data to pass to @callback
+ *
+ * Asynchronous version of [method@Image.reconstruct_jpeg].
+ *
+ * Since: 2.2
+ */
+void gly_image_reconstruct_jpeg_async(GlyImage *image,
+                                      GCancellable *cancellable,
+                                      GAsyncReadyCallback callback,
+                                      gpointer user_data);
+
+/**
+ * gly_image_reconstruct_jpeg_finish:
+ * @image:
+ * @result: a `GAsyncResult`
+ * @error:
+ *
+ * Finishes the [method@Image.reconstruct_jpeg_async] call.
+ *
+ * Returns: (transfer full): Original JPEG.
+ *
+ * Since: 2.2
+ */
+GlyEncodedImage *gly_image_reconstruct_jpeg_finish(GlyImage *image,
+                                                   GAsyncResult *result,
+                                                   GError **error);
+
 /**
  * GlyCreator:
  *
@@ -1227,6 +1420,115 @@ GlyEncodedImage *gly_creator_create_finish(GlyCreator *creator,
                                            GAsyncResult *result,
                                            GError **error);
 
+/**
+ * gly_creator_create_to_stream:
+ * @creator:
+ * @stream: Stream to write the encoded image to
+ * @error:
+ *
+ * Encodes the image and writes it to @stream.
+ *
+ * The encoded image is streamed from the sandbox and never kept in
+ * memory completely by the calling process. Formats whose encoders have
+ * to seek in the output, like TIFF and OpenEXR, are encoded completely
+ * inside the sandbox before they are streamed. The stream is not closed.
+ * It must not be used until the operation has completed.
+ *
+ * Returns: %TRUE if the image was written
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_create_to_stream(GlyCreator *creator,
+                                      GOutputStream *stream,
+                                      GError **error);
+
+/**
+ * gly_creator_create_to_stream_async:
+ * @creator:
+ * @stream: Stream to write the encoded image to
+ * @cancellable: (nullable): A [class@Gio.Cancellable] to cancel the operation
+ * @callback: A callback to call when the operation is complete
+ * @user_data: Data to pass to @callback
+ *
+ * Asynchronous version of [method@Creator.create_to_stream].
+ *
+ * Since: 2.2
+ */
+void gly_creator_create_to_stream_async(GlyCreator *creator,
+                                        GOutputStream *stream,
+                                        GCancellable *cancellable,
+                                        GAsyncReadyCallback callback,
+                                        gpointer user_data);
+
+/**
+ * gly_creator_create_to_stream_finish:
+ * @creator:
+ * @result: A `GAsyncResult`
+ * @error:
+ *
+ * Finishes the [method@Creator.create_to_stream_async] call.
+ *
+ * Returns: %TRUE if the image was written
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_create_to_stream_finish(GlyCreator *creator,
+                                             GAsyncResult *result,
+                                             GError **error);
+
+/**
+ * gly_creator_create_to_file:
+ * @creator:
+ * @file: File to write the encoded image to
+ * @error:
+ *
+ * Encodes the image and writes it to @file.
+ *
+ * An existing @file is only replaced after the image has been
+ * written completely.
+ *
+ * Returns: %TRUE if the image was written
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_create_to_file(GlyCreator *creator,
+                                    GFile *file,
+                                    GError **error);
+
+/**
+ * gly_creator_create_to_file_async:
+ * @creator:
+ * @file: File to write the encoded image to
+ * @cancellable: (nullable): A [class@Gio.Cancellable] to cancel the operation
+ * @callback: A callback to call when the operation is complete
+ * @user_data: Data to pass to @callback
+ *
+ * Asynchronous version of [method@Creator.create_to_file].
+ *
+ * Since: 2.2
+ */
+void gly_creator_create_to_file_async(GlyCreator *creator,
+                                      GFile *file,
+                                      GCancellable *cancellable,
+                                      GAsyncReadyCallback callback,
+                                      gpointer user_data);
+
+/**
+ * gly_creator_create_to_file_finish:
+ * @creator:
+ * @result: A `GAsyncResult`
+ * @error:
+ *
+ * Finishes the [method@Creator.create_to_file_async] call.
+ *
+ * Returns: %TRUE if the image was written
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_create_to_file_finish(GlyCreator *creator,
+                                           GAsyncResult *result,
+                                           GError **error);
+
 /**
  * gly_creator_add_metadata_key_value:
  * @creator:
@@ -1258,6 +1560,41 @@ gboolean gly_creator_add_metadata_key_value(GlyCreator *creator,
 gboolean gly_creator_set_encoding_quality(GlyCreator *creator,
                                           uint8_t quality);
 
+/**
+ * gly_creator_set_encoding_alpha_quality:
+ * @creator:
+ * @alpha_quality: Value between 0 and 100
+ *
+ * Sets the quality of the alpha channel for formats that store it
+ * separately, like lossy WebP. At 100, the alpha channel is stored
+ * without loss.
+ *
+ * Returns: `TRUE` if format supports an alpha quality setting.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_alpha_quality(GlyCreator *creator,
+                                                uint8_t alpha_quality);
+
+/**
+ * gly_creator_set_target_size:
+ * @creator:
+ * @target_size: Maximum size of the encoded image in bytes
+ *
+ * Encodes the image with the highest quality that results in at most
+ * @target_size bytes. The quality is searched for inside the sandbox.
+ * The selected quality is available via [method@EncodedImage.get_quality].
+ *
+ * If the image can't be encoded within @target_size, creating the image
+ * fails.
+ *
+ * Returns: `TRUE` if format supports a quality setting.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_target_size(GlyCreator *creator,
+                                     uint64_t target_size);
+
 /**
  * gly_creator_set_encoding_compression:
  * @creator:
@@ -1271,15 +1608,747 @@ gboolean gly_creator_set_encoding_compression(GlyCreator *creator,
                                               uint8_t compression);
 
 /**
- * gly_creator_set_sandbox_selector:
+ * gly_creator_set_encoding_lossless:
  * @creator:
- * @sandbox_selector: Method by which the sandbox mechanism is selected
+ * @lossless:
  *
- * Selects which sandbox mechanism should be used. The default without calling this function is [enum@SandboxSelector]`.AUTO`.
+ * Returns: `TRUE` if the format supports choosing lossless encoding.
  *
- * Since: 2.0
- */
-gboolean gly_creator_set_sandbox_selector(GlyCreator *creator,
-                                          GlySandboxSelector sandbox_selector);
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_lossless(GlyCreator *creator,
+                                           gboolean lossless);
+
+/**
+ * gly_creator_set_encoding_chroma_subsampling:
+ * @creator:
+ * @chroma_subsampling:
+ *
+ * Returns: `TRUE` if the format supports choosing the chroma subsampling.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_chroma_subsampling(GlyCreator *creator,
+                                                     GlyChromaSubsampling chroma_subsampling);
+
+/**
+ * gly_creator_set_encoding_progressive:
+ * @creator:
+ * @progressive:
+ *
+ * Returns: `TRUE` if the format supports progressive encoding.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_progressive(GlyCreator *creator,
+                                              gboolean progressive);
+
+/**
+ * gly_creator_set_encoding_effort:
+ * @creator:
+ * @effort: Value between 0 (fastest encoding) and 100 (smallest output)
+ *
+ * Returns: `TRUE` if the format supports an effort setting.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_effort(GlyCreator *creator,
+                                         uint8_t effort);
+
+/**
+ * gly_creator_set_encoding_bit_depth:
+ * @creator:
+ * @bit_depth: Bits per channel
+ *
+ * Returns: `TRUE` if the format supports choosing the bit depth.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_bit_depth(GlyCreator *creator,
+                                            uint8_t bit_depth);
+
+/**
+ * gly_creator_set_encoding_palette_size:
+ * @creator:
+ * @palette_size: Number of colors between 2 and 256
+ *
+ * Stores the image with a palette of at most @palette_size colors. The
+ * colors are reduced with dithering.
+ *
+ * Returns: `TRUE` if the format supports a palette.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_palette_size(GlyCreator *creator,
+                                               uint16_t palette_size);
+
+/**
+ * gly_creator_set_encoding_texture_compression:
+ * @creator:
+ * @texture_compression:
+ *
+ * Returns: `TRUE` if the format supports choosing the texture compression.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_texture_compression(GlyCreator *creator,
+                                                      GlyTextureCompression texture_compression);
+
+/**
+ * gly_creator_set_encoding_mipmaps:
+ * @creator:
+ * @mipmaps: Whether to store mipmaps
+ *
+ * Stores mipmaps that are scaled down from the first frame, halving the
+ * size for each level until both sides are one pixel.
+ *
+ * Returns: `TRUE` if the format supports mipmaps.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_mipmaps(GlyCreator *creator,
+                                          gboolean mipmaps);
+
+/**
+ * gly_creator_set_encoding_exr_compression:
+ * @creator:
+ * @exr_compression:
+ *
+ * Returns: `TRUE` if the format supports choosing the OpenEXR compression.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_exr_compression(GlyCreator *creator,
+                                                  GlyExrCompression exr_compression);
+
+/**
+ * gly_creator_set_encoding_cmyk_icc_profile:
+ * @creator:
+ * @icc_profile: ICC profile describing the CMYK color space
+ *
+ * Stores the image as CMYK for print. The frames are converted from their
+ * ICC profile, or sRGB if they don't have one, to the CMYK color space. The
+ * CMYK profile is embedded in the image.
+ *
+ * Returns: `TRUE` if the format supports CMYK output.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_encoding_cmyk_icc_profile(GlyCreator *creator,
+                                                   GBytes *icc_profile);
+
+/**
+ * gly_creator_set_icon_standard_sizes:
+ * @creator:
+ * @icon_standard_sizes: Whether to add the standard sizes
+ *
+ * Adds the standard icon sizes that are missing. They are scaled down from
+ * the largest frame.
+ *
+ * Returns: `TRUE` if the format supports generating icon sizes.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_icon_standard_sizes(GlyCreator *creator,
+                                             gboolean icon_standard_sizes);
+
+/**
+ * gly_creator_set_source_jpeg:
+ * @creator:
+ * @jpeg: Data of an existing JPEG file
+ *
+ * Losslessly recompresses an existing JPEG instead of encoding the added
+ * frames. The original file can be reconstructed via
+ * [method@Image.reconstruct_jpeg]. The metadata of the JPEG are kept and most
+ * other encoding options are ignored.
+ *
+ * Returns: `TRUE` if the format supports JPEG recompression.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_source_jpeg(GlyCreator *creator,
+                                     GBytes *jpeg);
+
+/**
+ * gly_creator_set_metadata_exif:
+ * @creator:
+ * @exif: (nullable): Exif data starting with the TIFF header
+ *
+ * Returns: `TRUE` if the format supports storing Exif data.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_metadata_exif(GlyCreator *creator, GBytes *exif);
+
+/**
+ * gly_creator_set_metadata_xmp:
+ * @creator:
+ * @xmp: (nullable): XMP data
+ *
+ * Returns: `TRUE` if the format supports storing XMP data.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_metadata_xmp(GlyCreator *creator, GBytes *xmp);
+
+/**
+ * gly_creator_set_loop_count:
+ * @creator:
+ * @loop_count: Number of times the animation is played
+ *
+ * A value of zero means that the animation is repeated infinitely, which is
+ * also the default.
+ *
+ * Returns: `TRUE` if the format supports animations.
+ *
+ * Since: 2.2
+ **/
+gboolean gly_creator_set_loop_count(GlyCreator *creator, uint32_t loop_count);
+
+/**
+ * gly_creator_set_sandbox_selector:
+ * @creator:
+ * @sandbox_selector: Method by which the sandbox mechanism is selected
+ *
+ * Selects which sandbox mechanism should be used. The default without calling this function is [enum@SandboxSelector]`.AUTO`.
+ *
+ * Since: 2.0
+ */
+gboolean gly_creator_set_sandbox_selector(GlyCreator *creator,
+                                          GlySandboxSelector sandbox_selector);
+
+/**************** GlyCreatorCapabilities ****************/
+
+/**
+ * GlyCreatorCapabilities:
+ *
+ * Features a [class@Creator] supports for an image format.
+ *
+ * Since: 2.2
+ */
+#define GLY_TYPE_CREATOR_CAPABILITIES (gly_creator_capabilities_get_type())
+G_DECLARE_FINAL_TYPE(GlyCreatorCapabilities, gly_creator_capabilities, GLY, CREATOR_CAPABILITIES, GObject)
+
+/**
+ * gly_creator_get_capabilities:
+ * @mime_type: A null-terminated string.
+ * @error:
+ *
+ * Returns the features that are supported for creating images of
+ * @mime_type without instantiating a [class@Creator].
+ *
+ * Returns: (transfer full): Supported features
+ *
+ * Since: 2.2
+ */
+GlyCreatorCapabilities *gly_creator_get_capabilities(const gchar *mime_type,
+                                                     GError **error);
+
+/**
+ * gly_creator_capabilities_get_memory_formats:
+ * @capabilities:
+ *
+ * Memory formats the image format supports for writing. Textures in
+ * other memory formats are transformed to one of these formats.
+ *
+ * Returns: Supported memory formats
+ *
+ * Since: 2.2
+ */
+GlyMemoryFormatSelection gly_creator_capabilities_get_memory_formats(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_animation:
+ * @capabilities:
+ *
+ * Whether images with more than one frame can be created.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_animation(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_multi_page:
+ * @capabilities:
+ *
+ * Whether each frame can be stored as a separate page or resolution.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_multi_page(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_quality:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_quality] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_quality(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_alpha_quality:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_alpha_quality] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_alpha_quality(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_target_size:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_target_size] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_target_size(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_compression:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_compression] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_compression(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_lossless:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_lossless] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_lossless(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_chroma_subsampling:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_chroma_subsampling] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_chroma_subsampling(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_progressive:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_progressive] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_progressive(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_effort:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_effort] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_effort(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_bit_depth:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_bit_depth] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_bit_depth(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_palette_size:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_palette_size] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_palette_size(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_texture_compression:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_texture_compression] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_texture_compression(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_mipmaps:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_mipmaps] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_mipmaps(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_exr_compression:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_exr_compression] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_exr_compression(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_encoding_cmyk_icc_profile:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_encoding_cmyk_icc_profile] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_encoding_cmyk_icc_profile(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_icon_standard_sizes:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_icon_standard_sizes] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_icon_standard_sizes(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_jpeg_recompression:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_source_jpeg] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_jpeg_recompression(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_metadata_exif:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_metadata_exif] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_metadata_exif(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_metadata_xmp:
+ * @capabilities:
+ *
+ * Whether [method@Creator.set_metadata_xmp] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_metadata_xmp(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_metadata_key_value:
+ * @capabilities:
+ *
+ * Whether [method@Creator.add_metadata_key_value] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_metadata_key_value(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_color_icc_profile:
+ * @capabilities:
+ *
+ * Whether [method@NewFrame.set_color_icc_profile] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_color_icc_profile(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_color_cicp:
+ * @capabilities:
+ *
+ * Whether [method@NewFrame.set_color_cicp] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_color_cicp(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_pixel_density:
+ * @capabilities:
+ *
+ * Whether [method@NewFrame.set_pixel_density] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_pixel_density(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_cursor_hotspot:
+ * @capabilities:
+ *
+ * Whether [method@NewFrame.set_cursor_hotspot] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_cursor_hotspot(GlyCreatorCapabilities *capabilities);
+
+/**
+ * gly_creator_capabilities_get_supports_layer_name:
+ * @capabilities:
+ *
+ * Whether [method@NewFrame.set_layer_name] is supported.
+ *
+ * Returns: `TRUE` if supported
+ *
+ * Since: 2.2
+ */
+gboolean gly_creator_capabilities_get_supports_layer_name(GlyCreatorCapabilities *capabilities);
+
+/**************** GlyEditor ****************/
+
+/**
+ * GlySaveFlags:
+ * @GLY_SAVE_FLAGS_NONE: Default behavior
+ * @GLY_SAVE_FLAGS_BACKUP: Keep a copy of the replaced file with a `~` appended to the name
+ * @GLY_SAVE_FLAGS_DISCARD_ATTRIBUTES: Don't copy permissions, ownership, and extended attributes of the replaced file
+ * @GLY_SAVE_FLAGS_PRESERVE_MODIFICATION_TIME: Keep the modification time of the replaced file
+ *
+ * Options for saving edited images
+ *
+ * Since: 2.2
+ */
+typedef enum
+{
+    GLY_SAVE_FLAGS_NONE = 0,
+    GLY_SAVE_FLAGS_BACKUP = (1 << 0),
+    GLY_SAVE_FLAGS_DISCARD_ATTRIBUTES = (1 << 1),
+    GLY_SAVE_FLAGS_PRESERVE_MODIFICATION_TIME = (1 << 2),
+} GlySaveFlags;
+
+GType gly_save_flags_get_type(void);
+
+/**
+ * GlyEditor:
+ *
+ * Image editor
+ *
+ * Operations are collected via functions like [method@Editor.add_rotation]
+ * and applied when calling [method@Editor.apply_and_save].
+ *
+ * ```c
+ * #include <glycin.h>
+ *
+ * GFile *file = g_file_new_for_path("test.jpg");
+ * GlyEditor *editor = gly_editor_new(file);
+ *
+ * // Rotate by 90 degrees counter-clockwise
+ * gly_editor_add_rotation(editor, 90);
+ *
+ * if (!gly_editor_apply_and_save(editor, file, GLY_SAVE_FLAGS_NONE, &error))
+ *   g_printerr("Failed to save: %s\n", error->message);
+ * ```
+ *
+ * Since: 2.2
+ */
+#define GLY_TYPE_EDITOR (gly_editor_get_type())
+G_DECLARE_FINAL_TYPE(GlyEditor, gly_editor, GLY, EDITOR, GObject)
+
+/**
+ * gly_editor_new:
+ * @file: A file from which the image is loaded
+ *
+ * Returns: (transfer full): a new [class@Editor]
+ *
+ * Since: 2.2
+ */
+GlyEditor *gly_editor_new(GFile *file);
+
+/**
+ * gly_editor_set_sandbox_selector:
+ * @editor:
+ * @sandbox_selector: Method by which the sandbox mechanism is selected
+ *
+ * Selects which sandbox mechanism should be used. The default without calling this function is [enum@SandboxSelector]`.AUTO`.
+ *
+ * Since: 2.2
+ */
+void gly_editor_set_sandbox_selector(GlyEditor *editor,
+                                     GlySandboxSelector sandbox_selector);
+
+/**
+ * gly_editor_add_rotation:
+ * @editor:
+ * @degrees: Counter-clockwise rotation in degrees
+ *
+ * Adds a rotation to the operations.
+ *
+ * Returns: `FALSE` if @degrees is not a multiple of 90.
+ *
+ * Since: 2.2
+ */
+gboolean gly_editor_add_rotation(GlyEditor *editor,
+                                 uint32_t degrees);
+
+/**
+ * gly_editor_add_mirror_horizontally:
+ * @editor:
+ *
+ * Adds mirroring along the vertical axis to the operations.
+ *
+ * Since: 2.2
+ */
+void gly_editor_add_mirror_horizontally(GlyEditor *editor);
+
+/**
+ * gly_editor_add_mirror_vertically:
+ * @editor:
+ *
+ * Adds mirroring along the horizontal axis to the operations.
+ *
+ * Since: 2.2
+ */
+void gly_editor_add_mirror_vertically(GlyEditor *editor);
+
+/**
+ * gly_editor_add_clip:
+ * @editor:
+ * @x:
+ * @y:
+ * @width:
+ * @height:
+ *
+ * Adds clipping the image to the given area to the operations.
+ *
+ * Since: 2.2
+ */
+void gly_editor_add_clip(GlyEditor *editor,
+                         uint32_t x,
+                         uint32_t y,
+                         uint32_t width,
+                         uint32_t height);
+
+/**
+ * gly_editor_apply_and_save:
+ * @editor:
+ * @file: The file to save the edited image to
+ * @flags: Options for saving the image
+ * @error:
+ *
+ * Applies the operations and saves the result to @file.
+ *
+ * If @file is the file the image was loaded from and the operations can be
+ * applied by only changing a few bytes, only those bytes are changed.
+ * Otherwise, the image is written to a temporary file in the same directory
+ * which then atomically replaces @file.
+ *
+ * Returns: `TRUE` if the image was saved.
+ *
+ * Since: 2.2
+ */
+gboolean gly_editor_apply_and_save(GlyEditor *editor,
+                                   GFile *file,
+                                   GlySaveFlags flags,
+                                   GError **error);
+
+/**
+ * gly_editor_apply_and_save_async:
+ * @editor:
+ * @file: The file to save the edited image to
+ * @flags: Options for saving the image
+ * @cancellable: (nullable): A [class@Gio.Cancellable] to cancel the operation
+ * @callback: A callback to call when the operation is complete
+ * @user_data: Data to pass to @callback
+ *
+ * Asynchronous version of [method@Editor.apply_and_save].
+ *
+ * Since: 2.2
+ */
+void gly_editor_apply_and_save_async(GlyEditor *editor,
+                                     GFile *file,
+                                     GlySaveFlags flags,
+                                     GCancellable *cancellable,
+                                     GAsyncReadyCallback callback,
+                                     gpointer user_data);
+
+/**
+ * gly_editor_apply_and_save_finish:
+ * @editor:
+ * @result: A `GAsyncResult`
+ * @error:
+ *
+ * Finishes the [method@Editor.apply_and_save_async] call.
+ *
+ * Returns: `TRUE` if the image was saved.
+ *
+ * Since: 2.2
+ */
+gboolean gly_editor_apply_and_save_finish(GlyEditor *editor,
+                                          GAsyncResult *result,
+                                          GError **error);
 
 G_END_DECLS
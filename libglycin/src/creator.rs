@@ -8,7 +8,7 @@ use glib::ffi::{GBytes, GError, GType, gpointer};
 use glib::subclass::prelude::*;
 use glib::translate::*;
 use glycin::SandboxSelector as GlySandboxSelector;
-use glycin::gobject::{self};
+use glycin::gobject::{self, GlyChromaSubsampling, GlyExrCompression, GlyTextureCompression};
 
 use crate::common::*;
 use crate::*;
@@ -35,6 +35,27 @@ pub unsafe extern "C" fn gly_creator_new(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_get_capabilities(
+    mime_type: *const c_char,
+    g_error: *mut *mut GError,
+) -> *mut GlyCreatorCapabilities {
+    unsafe {
+        let mime_type = glib::GStr::from_ptr_checked(mime_type).unwrap().to_string();
+
+        let capabilities =
+            async_global_executor::block_on(gobject::GlyCreator::capabilities(mime_type));
+
+        match capabilities {
+            Ok(capabilities) => capabilities.into_glib_ptr(),
+            Err(err) => {
+                set_error(g_error, &err);
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gly_creator_set_sandbox_selector(
     loader: *mut GlyLoader,
@@ -187,6 +208,188 @@ pub unsafe extern "C" fn gly_creator_create_finish(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_create_to_stream(
+    creator: *mut GlyCreator,
+    stream: *mut gio::ffi::GOutputStream,
+    g_error: *mut *mut GError,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let obj = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+        let stream = gio::OutputStream::from_glib_none(stream);
+
+        let result = async_global_executor::block_on(obj.create_to(stream));
+
+        match result {
+            Ok(()) => true.into_glib(),
+            Err(err) => {
+                set_context_error(g_error, &err);
+                false.into_glib()
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_create_to_stream_async(
+    creator: *mut GlyCreator,
+    stream: *mut gio::ffi::GOutputStream,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    unsafe {
+        let obj = gobject::GlyCreator::from_glib_none(creator);
+        let stream = gio::OutputStream::from_glib_none(stream);
+        let cancellable: Option<gio::Cancellable> = from_glib_none(cancellable);
+        let callback = GAsyncReadyCallbackSend::new(callback, user_data);
+
+        let cancel_signal = if let Some(cancellable) = &cancellable {
+            cancellable.connect_cancelled(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.cancellable().cancel()
+            ))
+        } else {
+            None
+        };
+
+        let cancellable_ = cancellable.clone();
+        let closure = move |task: gio::Task<bool>, obj: Option<&gobject::GlyCreator>| {
+            if let (Some(cancel_signal), Some(cancellable)) = (cancel_signal, cancellable) {
+                cancellable.disconnect_cancelled(cancel_signal);
+            }
+
+            let result = task.upcast_ref::<gio::AsyncResult>().as_ptr();
+            callback.call(obj.unwrap(), result);
+        };
+
+        let task = gio::Task::new(Some(&obj), cancellable_.as_ref(), closure);
+        let future = obj.create_to(stream);
+
+        async_global_executor::spawn(async move {
+            let res = future
+                .await
+                .map(|()| true)
+                .map_err(|x| glib_context_error(&x));
+            task.return_result(res);
+        })
+        .detach();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_create_to_stream_finish(
+    _creator: *mut GlyCreator,
+    res: *mut GAsyncResult,
+    error: *mut *mut GError,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let task = gio::Task::<bool>::from_glib_none(res as *mut GTask);
+
+        match task.propagate() {
+            Ok(created) => created.into_glib(),
+            Err(e) => {
+                if !error.is_null() {
+                    *error = e.into_glib_ptr();
+                }
+                false.into_glib()
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_create_to_file(
+    creator: *mut GlyCreator,
+    file: *mut gio::ffi::GFile,
+    g_error: *mut *mut GError,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let obj = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+        let file = gio::File::from_glib_ptr_borrow(&file).clone();
+
+        let result = async_global_executor::block_on(obj.create_to_file(file));
+
+        match result {
+            Ok(()) => true.into_glib(),
+            Err(err) => {
+                set_context_error(g_error, &err);
+                false.into_glib()
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_create_to_file_async(
+    creator: *mut GlyCreator,
+    file: *mut gio::ffi::GFile,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    unsafe {
+        let obj = gobject::GlyCreator::from_glib_none(creator);
+        let file = gio::File::from_glib_ptr_borrow(&file).clone();
+        let cancellable: Option<gio::Cancellable> = from_glib_none(cancellable);
+        let callback = GAsyncReadyCallbackSend::new(callback, user_data);
+
+        let cancel_signal = if let Some(cancellable) = &cancellable {
+            cancellable.connect_cancelled(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.cancellable().cancel()
+            ))
+        } else {
+            None
+        };
+
+        let cancellable_ = cancellable.clone();
+        let closure = move |task: gio::Task<bool>, obj: Option<&gobject::GlyCreator>| {
+            if let (Some(cancel_signal), Some(cancellable)) = (cancel_signal, cancellable) {
+                cancellable.disconnect_cancelled(cancel_signal);
+            }
+
+            let result = task.upcast_ref::<gio::AsyncResult>().as_ptr();
+            callback.call(obj.unwrap(), result);
+        };
+
+        let task = gio::Task::new(Some(&obj), cancellable_.as_ref(), closure);
+        let future = obj.create_to_file(file);
+
+        async_global_executor::spawn(async move {
+            let res = future
+                .await
+                .map(|()| true)
+                .map_err(|x| glib_context_error(&x));
+            task.return_result(res);
+        })
+        .detach();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_create_to_file_finish(
+    _creator: *mut GlyCreator,
+    res: *mut GAsyncResult,
+    error: *mut *mut GError,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let task = gio::Task::<bool>::from_glib_none(res as *mut GTask);
+
+        match task.propagate() {
+            Ok(created) => created.into_glib(),
+            Err(e) => {
+                if !error.is_null() {
+                    *error = e.into_glib_ptr();
+                }
+                false.into_glib()
+            }
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gly_creator_add_metadata_key_value(
     creator: *mut GlyCreator,
@@ -217,6 +420,33 @@ pub unsafe extern "C" fn gly_creator_set_encoding_quality(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_alpha_quality(
+    creator: *mut GlyCreator,
+    alpha_quality: u8,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_alpha_quality(alpha_quality)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_target_size(
+    creator: *mut GlyCreator,
+    target_size: u64,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator.set_target_size(target_size).is_ok().into_glib()
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gly_creator_set_encoding_compression(
     creator: *mut GlyCreator,
@@ -232,7 +462,236 @@ pub unsafe extern "C" fn gly_creator_set_encoding_compression(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_lossless(
+    creator: *mut GlyCreator,
+    lossless: glib::ffi::gboolean,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_lossless(from_glib(lossless))
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_chroma_subsampling(
+    creator: *mut GlyCreator,
+    chroma_subsampling: GlyChromaSubsampling,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_chroma_subsampling(chroma_subsampling)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_progressive(
+    creator: *mut GlyCreator,
+    progressive: glib::ffi::gboolean,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_progressive(from_glib(progressive))
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_effort(
+    creator: *mut GlyCreator,
+    effort: u8,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator.set_encoding_effort(effort).is_ok().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_bit_depth(
+    creator: *mut GlyCreator,
+    bit_depth: u8,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_bit_depth(bit_depth)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_palette_size(
+    creator: *mut GlyCreator,
+    palette_size: u16,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_palette_size(palette_size)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_texture_compression(
+    creator: *mut GlyCreator,
+    texture_compression: GlyTextureCompression,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_texture_compression(texture_compression)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_mipmaps(
+    creator: *mut GlyCreator,
+    mipmaps: glib::ffi::gboolean,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_mipmaps(from_glib(mipmaps))
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_exr_compression(
+    creator: *mut GlyCreator,
+    exr_compression: GlyExrCompression,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_encoding_exr_compression(exr_compression)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_encoding_cmyk_icc_profile(
+    creator: *mut GlyCreator,
+    icc_profile: *mut GBytes,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+        let icc_profile: glib::Bytes = from_glib_none(icc_profile);
+
+        creator
+            .set_encoding_cmyk_icc_profile(icc_profile)
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_icon_standard_sizes(
+    creator: *mut GlyCreator,
+    icon_standard_sizes: glib::ffi::gboolean,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator
+            .set_icon_standard_sizes(from_glib(icon_standard_sizes))
+            .is_ok()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_source_jpeg(
+    creator: *mut GlyCreator,
+    jpeg: *mut GBytes,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+        let jpeg: glib::Bytes = from_glib_none(jpeg);
+
+        creator.set_source_jpeg(jpeg).is_ok().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_metadata_exif(
+    creator: *mut GlyCreator,
+    exif: *mut GBytes,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+        let exif: Option<glib::Bytes> = from_glib_none(exif);
+
+        creator.set_metadata_exif(exif).is_ok().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_metadata_xmp(
+    creator: *mut GlyCreator,
+    xmp: *mut GBytes,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+        let xmp: Option<glib::Bytes> = from_glib_none(xmp);
+
+        creator.set_metadata_xmp(xmp).is_ok().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_set_loop_count(
+    creator: *mut GlyCreator,
+    loop_count: u32,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let creator = gobject::GlyCreator::from_glib_ptr_borrow(&creator);
+
+        creator.set_loop_count(loop_count).is_ok().into_glib()
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn gly_creator_get_type() -> GType {
     <gobject::GlyCreator as StaticType>::static_type().into_glib()
 }
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gly_chroma_subsampling_get_type() -> GType {
+    <GlyChromaSubsampling as StaticType>::static_type().into_glib()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gly_texture_compression_get_type() -> GType {
+    <GlyTextureCompression as StaticType>::static_type().into_glib()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gly_exr_compression_get_type() -> GType {
+    <GlyExrCompression as StaticType>::static_type().into_glib()
+}
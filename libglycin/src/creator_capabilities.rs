@@ -0,0 +1,289 @@
+use gio::prelude::*;
+use glib::ffi::GType;
+use glib::subclass::prelude::*;
+use glib::translate::*;
+use glycin::gobject;
+
+pub type GlyCreatorCapabilities =
+    <gobject::creator_capabilities::imp::GlyCreatorCapabilities as ObjectSubclass>::Instance;
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gly_creator_capabilities_get_type() -> GType {
+    <gobject::GlyCreatorCapabilities as StaticType>::static_type().into_glib()
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_memory_formats(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> u32 {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.memory_formats().bits()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_animation(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_animation().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_multi_page(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_multi_page().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_quality(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_quality().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_alpha_quality(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_alpha_quality().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_target_size(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_target_size().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_compression(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_compression().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_lossless(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_lossless().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_chroma_subsampling(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities
+            .supports_encoding_chroma_subsampling()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_progressive(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_progressive().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_effort(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_effort().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_bit_depth(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_bit_depth().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_palette_size(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_palette_size().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_texture_compression(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities
+            .supports_encoding_texture_compression()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_mipmaps(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_mipmaps().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_exr_compression(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_encoding_exr_compression().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_encoding_cmyk_icc_profile(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities
+            .supports_encoding_cmyk_icc_profile()
+            .into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_icon_standard_sizes(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_icon_standard_sizes().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_jpeg_recompression(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_jpeg_recompression().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_metadata_exif(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_metadata_exif().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_metadata_xmp(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_metadata_xmp().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_metadata_key_value(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_metadata_key_value().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_color_icc_profile(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_color_icc_profile().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_color_cicp(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_color_cicp().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_pixel_density(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_pixel_density().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_cursor_hotspot(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_cursor_hotspot().into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_creator_capabilities_get_supports_layer_name(
+    capabilities: *mut GlyCreatorCapabilities,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let capabilities = gobject::GlyCreatorCapabilities::from_glib_ptr_borrow(&capabilities);
+        capabilities.supports_layer_name().into_glib()
+    }
+}
@@ -0,0 +1,189 @@
+use std::ptr;
+
+use gio::ffi::{GAsyncReadyCallback, GAsyncResult, GTask};
+use gio::glib;
+use gio::prelude::*;
+use glib::ffi::{GError, GType, gpointer};
+use glib::subclass::prelude::*;
+use glib::translate::*;
+use glycin::gobject::{self, GlySaveFlags};
+use glycin::{Operation, SandboxSelector as GlySandboxSelector};
+
+use crate::common::*;
+use crate::*;
+
+pub type GlyEditor = <gobject::editor::imp::GlyEditor as ObjectSubclass>::Instance;
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_new(file: *mut gio::ffi::GFile) -> *mut GlyEditor {
+    unsafe {
+        let file = gio::File::from_glib_ptr_borrow(&file);
+        gobject::GlyEditor::new(file.clone()).into_glib_ptr()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_set_sandbox_selector(
+    editor: *mut GlyEditor,
+    sandbox_selector: i32,
+) {
+    unsafe {
+        let sandbox_selector = GlySandboxSelector::from_glib(sandbox_selector);
+        let obj = gobject::GlyEditor::from_glib_ptr_borrow(&editor);
+
+        obj.set_sandbox_selector(sandbox_selector);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_add_rotation(
+    editor: *mut GlyEditor,
+    degrees: u32,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let obj = gobject::GlyEditor::from_glib_ptr_borrow(&editor);
+
+        match glycin::Rotation::try_from(degrees as f64) {
+            Ok(rotation) => {
+                obj.add_operation(Operation::Rotate(rotation));
+                true.into_glib()
+            }
+            Err(_) => false.into_glib(),
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_add_mirror_horizontally(editor: *mut GlyEditor) {
+    unsafe {
+        let obj = gobject::GlyEditor::from_glib_ptr_borrow(&editor);
+        obj.add_operation(Operation::MirrorHorizontally);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_add_mirror_vertically(editor: *mut GlyEditor) {
+    unsafe {
+        let obj = gobject::GlyEditor::from_glib_ptr_borrow(&editor);
+        obj.add_operation(Operation::MirrorVertically);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_add_clip(
+    editor: *mut GlyEditor,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) {
+    unsafe {
+        let obj = gobject::GlyEditor::from_glib_ptr_borrow(&editor);
+        obj.add_operation(Operation::Clip((x, y, width, height)));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_apply_and_save(
+    editor: *mut GlyEditor,
+    file: *mut gio::ffi::GFile,
+    flags: u32,
+    g_error: *mut *mut GError,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let obj = gobject::GlyEditor::from_glib_ptr_borrow(&editor);
+        let file = gio::File::from_glib_ptr_borrow(&file).clone();
+        let flags = GlySaveFlags::from_bits_truncate(flags);
+
+        let result = async_global_executor::block_on(obj.apply_and_save(file, flags));
+
+        match result {
+            Ok(()) => true.into_glib(),
+            Err(err) => {
+                set_context_error(g_error, &err);
+                false.into_glib()
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_apply_and_save_async(
+    editor: *mut GlyEditor,
+    file: *mut gio::ffi::GFile,
+    flags: u32,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    unsafe {
+        let obj = gobject::GlyEditor::from_glib_none(editor);
+        let file = gio::File::from_glib_ptr_borrow(&file).clone();
+        let flags = GlySaveFlags::from_bits_truncate(flags);
+        let cancellable: Option<gio::Cancellable> = from_glib_none(cancellable);
+        let callback = GAsyncReadyCallbackSend::new(callback, user_data);
+
+        let cancel_signal = if let Some(cancellable) = &cancellable {
+            cancellable.connect_cancelled(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.cancellable().cancel()
+            ))
+        } else {
+            None
+        };
+
+        let cancellable_ = cancellable.clone();
+        let closure = move |task: gio::Task<bool>, obj: Option<&gobject::GlyEditor>| {
+            if let (Some(cancel_signal), Some(cancellable)) = (cancel_signal, cancellable) {
+                cancellable.disconnect_cancelled(cancel_signal);
+            }
+
+            let result = task.upcast_ref::<gio::AsyncResult>().as_ptr();
+            callback.call(obj.unwrap(), result);
+        };
+
+        let task = gio::Task::new(Some(&obj), cancellable_.as_ref(), closure);
+
+        async_global_executor::spawn(async move {
+            let res = obj
+                .apply_and_save(file, flags)
+                .await
+                .map(|()| true)
+                .map_err(|x| glib_context_error(&x));
+            task.return_result(res);
+        })
+        .detach();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_editor_apply_and_save_finish(
+    _editor: *mut GlyEditor,
+    res: *mut GAsyncResult,
+    error: *mut *mut GError,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let task = gio::Task::<bool>::from_glib_none(res as *mut GTask);
+
+        match task.propagate() {
+            Ok(saved) => saved.into_glib(),
+            Err(e) => {
+                if !error.is_null() {
+                    *error = e.into_glib_ptr();
+                }
+                false.into_glib()
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gly_editor_get_type() -> GType {
+    <gobject::GlyEditor as StaticType>::static_type().into_glib()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gly_save_flags_get_type() -> GType {
+    <GlySaveFlags as StaticType>::static_type().into_glib()
+}
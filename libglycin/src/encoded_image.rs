@@ -21,3 +21,11 @@ pub unsafe extern "C" fn gly_encoded_image_get_data(
         encoded_image.data().into_glib_ptr()
     }
 }
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_encoded_image_get_quality(encoded_image: *mut GlyEncodedImage) -> i32 {
+    unsafe {
+        let encoded_image = gobject::GlyEncodedImage::from_glib_ptr_borrow(&encoded_image);
+        encoded_image.quality()
+    }
+}
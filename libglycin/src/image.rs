@@ -144,6 +144,86 @@ pub unsafe extern "C" fn gly_image_get_specific_frame_finish(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_image_reconstruct_jpeg(
+    image: *mut GlyImage,
+    g_error: *mut *mut GError,
+) -> *mut GlyEncodedImage {
+    unsafe {
+        let obj = gobject::GlyImage::from_glib_ptr_borrow(&image);
+
+        match obj.reconstruct_jpeg() {
+            Ok(encoded_image) => encoded_image.into_glib_ptr(),
+            Err(err) => {
+                set_context_error(g_error, &err);
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_image_reconstruct_jpeg_async(
+    image: *mut GlyImage,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    unsafe {
+        let obj = gobject::GlyImage::from_glib_none(image);
+        let cancellable: Option<gio::Cancellable> = from_glib_none(cancellable);
+        let callback: GAsyncReadyCallbackSend = GAsyncReadyCallbackSend::new(callback, user_data);
+        let cancel_signal = if let Some(cancellable) = &cancellable {
+            cancellable.connect_cancelled(glib::clone!(
+                #[weak(rename_to=cancellable)]
+                obj.image().cancellable(),
+                move |_| cancellable.cancel()
+            ))
+        } else {
+            None
+        };
+        let cancellable_ = cancellable.clone();
+        let closure = move |task: gio::Task<gobject::GlyEncodedImage>,
+                            obj: Option<&gobject::GlyImage>| {
+            if let (Some(cancel_signal), Some(cancellable)) = (cancel_signal, cancellable) {
+                cancellable.disconnect_cancelled(cancel_signal);
+            }
+
+            let result = task.upcast_ref::<gio::AsyncResult>().as_ptr();
+            callback.call(obj.unwrap(), result);
+        };
+        let task = gio::Task::new(Some(&obj), cancellable_.as_ref(), closure);
+        obj.main_context().spawn(async move {
+            let res = obj
+                .reconstruct_jpeg_future()
+                .await
+                .map_err(|x| glib_context_error(&x));
+            task.return_result(res);
+        });
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_image_reconstruct_jpeg_finish(
+    _image: *mut GlyImage,
+    res: *mut GAsyncResult,
+    error: *mut *mut GError,
+) -> *mut GlyEncodedImage {
+    unsafe {
+        let task = gio::Task::<gobject::GlyEncodedImage>::from_glib_none(res as *mut GTask);
+
+        match task.propagate() {
+            Ok(encoded_image) => encoded_image.into_glib_ptr(),
+            Err(e) => {
+                if !error.is_null() {
+                    *error = e.into_glib_ptr();
+                }
+                ptr::null_mut()
+            }
+        }
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gly_image_get_mime_type(image: *mut GlyImage) -> *const c_char {
     unsafe {
@@ -1,6 +1,8 @@
 mod color_mode;
 mod common;
 mod creator;
+mod creator_capabilities;
+mod editor;
 mod encoded_image;
 mod error;
 mod frame;
@@ -14,6 +16,8 @@ mod pixel_density;
 
 pub use color_mode::*;
 pub use creator::*;
+pub use creator_capabilities::*;
+pub use editor::*;
 pub use encoded_image::*;
 pub use error::*;
 pub use frame::*;
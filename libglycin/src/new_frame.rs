@@ -1,9 +1,12 @@
+use std::ffi::c_char;
+use std::time::Duration;
+
 use gio::prelude::*;
 use glib::ffi::{GBytes, GType};
 use glib::subclass::prelude::*;
 use glib::translate::*;
 use glycin::gobject;
-use glycin::gobject::GlyPhysicalDimensionUnit;
+use glycin::gobject::{GlyCicp, GlyPhysicalDimensionUnit};
 
 use crate::GlyPixelDensity;
 
@@ -52,6 +55,76 @@ pub unsafe extern "C" fn gly_new_frame_set_pixel_density(
     }
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_new_frame_set_color_cicp(
+    new_frame: *mut GlyNewFrame,
+    cicp: *const GlyCicp,
+) -> glib::ffi::gboolean {
+    unsafe {
+        let new_frame = gobject::GlyNewFrame::from_glib_ptr_borrow(&new_frame);
+
+        let cicp = if let Some(cicp) = cicp.as_ref() {
+            let bytes = [
+                cicp.color_primaries,
+                cicp.transfer_characteristics,
+                cicp.matrix_coefficients,
+                cicp.video_full_range_flag,
+            ];
+
+            match glycin::Cicp::from_bytes(&bytes) {
+                Ok(cicp) => Some(cicp),
+                Err(_) => return false.into_glib(),
+            }
+        } else {
+            None
+        };
+
+        new_frame.set_color_cicp(cicp);
+
+        true.into_glib()
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_new_frame_set_delay(new_frame: *mut GlyNewFrame, delay: i64) {
+    unsafe {
+        let new_frame = gobject::GlyNewFrame::from_glib_ptr_borrow(&new_frame);
+
+        let delay = u64::try_from(delay)
+            .ok()
+            .filter(|x| *x > 0)
+            .map(Duration::from_micros);
+
+        new_frame.set_delay(delay);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_new_frame_set_cursor_hotspot(
+    new_frame: *mut GlyNewFrame,
+    x: u16,
+    y: u16,
+) {
+    unsafe {
+        let new_frame = gobject::GlyNewFrame::from_glib_ptr_borrow(&new_frame);
+        new_frame.set_cursor_hotspot(Some((x, y)));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gly_new_frame_set_layer_name(
+    new_frame: *mut GlyNewFrame,
+    layer_name: *const c_char,
+) {
+    unsafe {
+        let layer_name = glib::GStr::from_ptr_checked(layer_name)
+            .unwrap()
+            .to_string();
+        let new_frame = gobject::GlyNewFrame::from_glib_ptr_borrow(&new_frame);
+        new_frame.set_layer_name(Some(layer_name));
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn gly_physical_dimension_unit_get_type() -> GType {
     <GlyPhysicalDimensionUnit as StaticType>::static_type().into_glib()
@@ -3,8 +3,11 @@
 fn dbus_api_stability() {
     // TODO: This seems overly complicated
     blocking::unblock(|| async_io::block_on(abi_stability::start_dbus())).detach();
-    abi_stability::check_api_stability("org.gnome.glycin.Loader");
-    abi_stability::check_api_stability("org.gnome.glycin.Editor");
+    abi_stability::check_api_stability("org.gnome.glycin.Loader", &["org.gnome.glycin.Loader"]);
+    abi_stability::check_api_stability(
+        "org.gnome.glycin.Editor",
+        &["org.gnome.glycin.Editor", "org.gnome.glycin.EditableImage"],
+    );
 }
 
 #[cfg(feature = "external-loaders")]
@@ -13,7 +16,9 @@ mod abi_stability {
 
     use glycin_core as glycin;
 
-    pub fn check_api_stability(interface_name: &str) {
+    /// Compare the interfaces with the complete description in
+    /// `docs/<compat>+/<doc_name>.xml`
+    pub fn check_api_stability(doc_name: &str, interface_names: &[&str]) {
         let output = std::process::Command::new("busctl")
             .args([
                 "introspect",
@@ -27,20 +32,42 @@ mod abi_stability {
 
         let compat_version = glycin::COMPAT_VERSION;
         let current_api =
-            std::fs::read_to_string(format!("../docs/{compat_version}+/{interface_name}.xml"))
-                .unwrap();
+            std::fs::read_to_string(format!("../docs/{compat_version}+/{doc_name}.xml")).unwrap();
 
-        let s = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+        let introspection = String::from_utf8(output.stdout).unwrap();
+
+        let mut api =
+            r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
   "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
 <node>
 "#
-        .to_string();
+            .to_string();
+
+        for interface_name in interface_names {
+            let interface = interface_xml(&introspection, interface_name);
+            assert!(
+                !interface.is_empty(),
+                "Interface {interface_name} not found"
+            );
+            api.push_str(&interface);
+        }
 
-        let mut api = String::from_utf8(output.stdout)
-            .unwrap()
-            .lines()
-            .fold((false, s), |(mut take, mut s), line| {
-                if line.contains(interface_name) {
+        api.push_str("</node>\n");
+
+        if current_api != api {
+            eprintln!("{api}");
+        }
+
+        assert_eq!(api, current_api);
+    }
+
+    /// Lines describing the interface
+    fn interface_xml(xml: &str, interface_name: &str) -> String {
+        let start = format!("<interface name=\"{interface_name}\">");
+
+        xml.lines()
+            .fold((false, String::new()), |(mut take, mut s), line| {
+                if line.contains(&start) {
                     take = true;
                 }
 
@@ -55,15 +82,7 @@ mod abi_stability {
 
                 (take, s)
             })
-            .1;
-
-        api.push_str("</node>\n");
-
-        if current_api != api {
-            eprintln!("{api}");
-        }
-
-        assert_eq!(api, current_api);
+            .1
     }
 
     pub async fn start_dbus() {
@@ -75,6 +94,8 @@ mod abi_stability {
             .unwrap()
             .serve_at("/org/gnome/glycin/test", mock_editor())
             .unwrap()
+            .serve_at("/org/gnome/glycin/test", mock_editable_image())
+            .unwrap()
             .build()
             .await
             .unwrap();
@@ -141,4 +162,11 @@ mod abi_stability {
             image_id: Default::default(),
         }
     }
+
+    fn mock_editable_image() -> glycin_utils::EditableImage<MockEditor> {
+        glycin_utils::EditableImage::new(
+            std::sync::Arc::new(Box::new(MockEditor {})),
+            zbus::zvariant::OwnedObjectPath::try_from("/org/gnome/glycin/test").unwrap(),
+        )
+    }
 }
@@ -289,6 +289,21 @@ def main():
     frame = creator.add_frame_with_stride(1, 1, 4, Gly.MemoryFormat.R8G8B8, data)
     creator.create()
 
+    # Editor
+
+    tmp_dir = GLib.Dir.make_tmp(None)
+    edit_file = Gio.File.new_for_path(os.path.join(tmp_dir, "edit.jpg"))
+    file.copy(edit_file, Gio.FileCopyFlags.NONE, None, None)
+
+    editor = Gly.Editor.new(edit_file)
+    assert editor.add_rotation(90)
+    assert not editor.add_rotation(45)
+    assert editor.apply_and_save(edit_file, Gly.SaveFlags.BACKUP)
+
+    image = Gly.Loader.new(edit_file).load()
+    assert image.get_width() == 400, f"Wrong width: {image.get_width()} px"
+    assert os.path.exists(edit_file.get_path() + "~")
+
     # Async
     global async_tests_remaining
     async_tests_remaining = 0
@@ -4,6 +4,10 @@ use std::collections::BTreeMap;
 use glycin::{Creator, Loader, MimeType};
 use glycin_core::{self as glycin, MemoryFormat};
 use glycin_utils::MemoryFormatInfo;
+use gufo_common::orientation::Orientation;
+use gufo_common::physical_dimension::{
+    PhysicalDimensionUnit, PixelDensity, PixelsPerPhysicalDimension,
+};
 use utils::*;
 
 #[test]
@@ -171,6 +175,24 @@ fn processor_creator_jpeg_stride_invalid() {
     });
 }
 
+#[test]
+fn processor_creator_jpeg_limits() {
+    block_on(async {
+        init();
+
+        let mut encoder = Creator::new(MimeType::JPEG).await.unwrap();
+        encoder.limits(glycin::Limits::default().max_dimensions((1, 1)));
+        let memory_format = glycin::MemoryFormat::R8g8b8;
+        encoder
+            .add_frame(2, 2, memory_format, vec![0; 2 * 2 * 3])
+            .unwrap();
+
+        let res = encoder.create().await;
+
+        assert!(res.is_err());
+    });
+}
+
 #[test]
 fn processor_creator_jpeg_quality() {
     block_on(async {
@@ -209,6 +231,47 @@ fn processor_creator_jpeg_quality() {
     });
 }
 
+#[test]
+fn processor_creator_jpeg_target_size() {
+    block_on(async {
+        init();
+
+        let width = 64;
+        let height = 64;
+        let memory_format = glycin::MemoryFormat::R8g8b8;
+        let texture = (0..width * height * 3)
+            .map(|x| (x * 7 % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        creator.set_encoding_quality(100).unwrap();
+        creator
+            .add_frame(width, height, memory_format, texture.clone())
+            .unwrap();
+        let full_size = creator.create().await.unwrap().data_ref().len() as u64;
+
+        let target_size = full_size / 2;
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        creator.set_target_size(target_size).unwrap();
+        creator
+            .add_frame(width, height, memory_format, texture.clone())
+            .unwrap();
+        let encoded_image = creator.create().await.unwrap();
+
+        assert!(encoded_image.data_ref().len() as u64 <= target_size);
+        assert!(encoded_image.quality().is_some_and(|x| x < 100));
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        creator.set_target_size(10).unwrap();
+        creator
+            .add_frame(width, height, memory_format, texture)
+            .unwrap();
+        let err = creator.create().await.unwrap_err();
+
+        assert!(err.is_target_size_exceeded(), "Error: {err}");
+    });
+}
+
 #[test]
 fn processor_creator_png_compression() {
     block_on(async {
@@ -254,6 +317,878 @@ fn processor_creator_png_compression() {
     });
 }
 
+#[test]
+fn processor_creator_palette_size() {
+    block_on(async {
+        init();
+
+        let loader = glycin::Loader::new(gio::File::for_path("test-images/images/color.png"));
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        let texture = frame.buf_slice().to_vec();
+
+        for mime_type in [MimeType::PNG, MimeType::GIF] {
+            eprintln!("- {}", mime_type.as_str());
+
+            let mut sizes = Vec::new();
+
+            for palette_size in [None, Some(256), Some(4)] {
+                let mut creator = Creator::new(mime_type.clone()).await.unwrap();
+                if let Some(palette_size) = palette_size {
+                    creator.set_encoding_palette_size(palette_size).unwrap();
+                }
+                creator
+                    .add_frame(
+                        frame.width(),
+                        frame.height(),
+                        glycin::MemoryFormat::R8g8b8,
+                        texture.clone(),
+                    )
+                    .unwrap();
+                let encoded_image = creator.create().await.unwrap();
+                sizes.push(encoded_image.data_ref().len());
+
+                let mut loader = glycin::Loader::new_vec(encoded_image.data_full());
+                loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+                let mut image = loader.load().await.unwrap();
+                let new_frame = image.next_frame().await.unwrap();
+                assert_eq!(new_frame.width(), frame.width());
+            }
+
+            // Fewer colors result in smaller files
+            assert!(sizes[2] < sizes[1], "{sizes:?}");
+            if mime_type == MimeType::PNG {
+                assert!(sizes[1] < sizes[0], "{sizes:?}");
+            }
+        }
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        assert!(creator.set_encoding_palette_size(16).is_err());
+    });
+}
+
+#[test]
+fn processor_creator_webp_lossy() {
+    block_on(async {
+        init();
+
+        let loader = glycin::Loader::new(gio::File::for_path("test-images/images/color.png"));
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        let (width, height) = (frame.width(), frame.height());
+
+        // Add an alpha gradient to the image
+        let texture = frame
+            .buf_slice()
+            .chunks_exact(3)
+            .enumerate()
+            .flat_map(|(i, rgb)| [rgb[0], rgb[1], rgb[2], (i % 256) as u8])
+            .collect::<Vec<_>>();
+
+        let create =
+            async |quality: Option<u8>, alpha_quality: Option<u8>, lossless: Option<bool>| {
+                let mut creator = Creator::new(MimeType::WEBP).await.unwrap();
+                if let Some(quality) = quality {
+                    creator.set_encoding_quality(quality).unwrap();
+                }
+                if let Some(alpha_quality) = alpha_quality {
+                    creator.set_encoding_alpha_quality(alpha_quality).unwrap();
+                }
+                if let Some(lossless) = lossless {
+                    creator.set_encoding_lossless(lossless).unwrap();
+                }
+                creator
+                    .add_frame(
+                        width,
+                        height,
+                        glycin::MemoryFormat::R8g8b8a8,
+                        texture.clone(),
+                    )
+                    .unwrap();
+                let encoded_image = creator.create().await.unwrap();
+
+                let mut loader = glycin::Loader::new_vec(encoded_image.data_full());
+                loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8a8);
+                let mut image = loader.load().await.unwrap();
+                let new_frame = image.next_frame().await.unwrap();
+                assert_eq!(new_frame.width(), width);
+                assert_eq!(new_frame.height(), height);
+
+                (
+                    encoded_image.data_ref().len(),
+                    new_frame.buf_slice().to_vec(),
+                )
+            };
+
+        // Without a quality, the image is stored losslessly
+        let (lossless_size, lossless_data) = create(None, None, None).await;
+        assert_eq!(lossless_data, texture);
+        let (size, _) = create(Some(10), None, Some(true)).await;
+        assert_eq!(size, lossless_size);
+
+        let (size_90, data_90) = create(Some(90), None, None).await;
+        let (size_10, data_10) = create(Some(10), None, None).await;
+        let (size_10_alpha_0, data_10_alpha_0) = create(Some(10), Some(0), None).await;
+
+        assert!(size_90 < lossless_size, "{size_90} < {lossless_size}");
+        assert!(size_10 < size_90, "{size_10} < {size_90}");
+        assert!(size_10_alpha_0 < size_10, "{size_10_alpha_0} < {size_10}");
+
+        // Mean absolute error of the color channels
+        let mean_error = |data: &[u8]| {
+            let sum = data
+                .chunks_exact(4)
+                .zip(texture.chunks_exact(4))
+                .flat_map(|(a, b)| (0..3).map(move |i| u64::from(a[i].abs_diff(b[i]))))
+                .sum::<u64>();
+            sum as f64 / (u64::from(width) * u64::from(height) * 3) as f64
+        };
+        let (error_90, error_10) = (mean_error(&data_90), mean_error(&data_10));
+        assert!(error_90 < 8., "{error_90}");
+        assert!(error_10 < 24., "{error_10}");
+        assert!(error_90 < error_10, "{error_90} < {error_10}");
+
+        // Alpha is lossless by default
+        let alpha = |data: &[u8]| data.chunks_exact(4).map(|x| x[3]).collect::<Vec<_>>();
+        assert_eq!(alpha(&data_90), alpha(&texture));
+        assert_ne!(alpha(&data_10_alpha_0), alpha(&texture));
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        assert!(creator.set_encoding_alpha_quality(50).is_err());
+    });
+}
+
+#[test]
+fn processor_creator_animation() {
+    block_on(async {
+        init();
+
+        for mime_type in [MimeType::GIF, MimeType::PNG, MimeType::WEBP] {
+            eprintln!("- {}", mime_type.as_str());
+
+            let mut creator = Creator::new(mime_type.clone()).await.unwrap();
+            creator.set_loop_count(2).unwrap();
+
+            for color in [[255, 0, 0], [0, 0, 255]] {
+                let new_frame = creator
+                    .add_frame(2, 2, glycin::MemoryFormat::R8g8b8, color.repeat(4))
+                    .unwrap();
+                new_frame
+                    .set_delay(Some(std::time::Duration::from_millis(200)))
+                    .unwrap();
+            }
+
+            let encoded_image = creator.create().await.unwrap();
+
+            let mut loader = glycin::Loader::new_vec(encoded_image.data_full());
+            loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+            let mut image = loader.load().await.unwrap();
+
+            for color in [[255, 0, 0], [0, 0, 255]] {
+                let frame = image.next_frame().await.unwrap();
+                assert_eq!(frame.delay(), Some(std::time::Duration::from_millis(200)));
+                assert_eq!(frame.buf_slice()[..3], color);
+            }
+        }
+    });
+}
+
+#[test]
+fn processor_creator_tiff_multi_page() {
+    block_on(async {
+        init();
+
+        let capabilities = Creator::capabilities(MimeType::TIFF).await.unwrap();
+        assert!(capabilities.supports_multi_page());
+        assert!(!capabilities.supports_animation());
+
+        let mut sizes = Vec::new();
+
+        for n_pages in [1, 2] {
+            let mut creator = Creator::new(MimeType::TIFF).await.unwrap();
+            creator.set_encoding_compression(50).unwrap();
+
+            for color in [[255, 0, 0], [0, 0, 255]].into_iter().take(n_pages) {
+                creator
+                    .add_frame(16, 16, glycin::MemoryFormat::R8g8b8, color.repeat(16 * 16))
+                    .unwrap();
+            }
+
+            let encoded_image = creator.create().await.unwrap();
+            sizes.push(encoded_image.data_ref().len());
+
+            let mut loader = glycin::Loader::new_vec(encoded_image.data_full());
+            loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+            let mut image = loader.load().await.unwrap();
+
+            let frame = image.next_frame().await.unwrap();
+            assert_eq!(frame.width(), 16);
+            assert_eq!(frame.buf_slice()[..3], [255, 0, 0]);
+        }
+
+        // The second page is stored in its own directory
+        assert!(sizes[0] < sizes[1], "{} < {}", sizes[0], sizes[1]);
+
+        // Pages with their own size, ICC profile, and pixel density
+        let mut creator = Creator::new(MimeType::TIFF).await.unwrap();
+        for (width, height, color, icc_profile, dpi) in [
+            (16, 16, [255, 0, 0], vec![1, 2, 3], 72),
+            (8, 4, [0, 0, 255], vec![4, 5, 6], 300),
+        ] {
+            let new_frame = creator
+                .add_frame(
+                    width,
+                    height,
+                    glycin::MemoryFormat::R8g8b8,
+                    color.repeat((width * height) as usize),
+                )
+                .unwrap();
+            new_frame.set_color_icc_profile(Some(icc_profile)).unwrap();
+            new_frame
+                .set_pixel_density(Some(PixelDensity::new(
+                    PixelsPerPhysicalDimension::new(dpi as f64, PhysicalDimensionUnit::Inch),
+                    PixelsPerPhysicalDimension::new(dpi as f64, PhysicalDimensionUnit::Inch),
+                )))
+                .unwrap();
+        }
+
+        let encoded_image = creator.create().await.unwrap();
+
+        // The loader only provides the first page
+        let mut decoder =
+            tiff::decoder::Decoder::new(std::io::Cursor::new(encoded_image.data_full())).unwrap();
+        assert!(decoder.more_images());
+        decoder.next_image().unwrap();
+        assert!(!decoder.more_images());
+
+        assert_eq!(decoder.dimensions().unwrap(), (8, 4));
+        let tiff::decoder::DecodingResult::U8(texture) = decoder.read_image().unwrap() else {
+            panic!("Unexpected sample type");
+        };
+        assert_eq!(texture, [0, 0, 255].repeat(8 * 4));
+
+        assert_eq!(
+            decoder
+                .get_tag_u32_vec(tiff::tags::Tag::IccProfile)
+                .unwrap(),
+            [4, 5, 6]
+        );
+        assert_eq!(
+            decoder.get_tag(tiff::tags::Tag::XResolution).unwrap(),
+            tiff::decoder::ifd::Value::Rational(300, 1)
+        );
+        assert_eq!(
+            decoder.get_tag(tiff::tags::Tag::YResolution).unwrap(),
+            tiff::decoder::ifd::Value::Rational(300, 1)
+        );
+        assert_eq!(
+            decoder
+                .get_tag_unsigned::<u16>(tiff::tags::Tag::ResolutionUnit)
+                .unwrap(),
+            tiff::tags::ResolutionUnit::Inch.to_u16()
+        );
+    });
+}
+
+#[test]
+fn processor_creator_tiff_compression() {
+    block_on(async {
+        init();
+
+        let loader = glycin::Loader::new(gio::File::for_path("test-images/images/color.png"));
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        let texture = frame.buf_slice().to_vec();
+
+        let mut sizes = Vec::new();
+
+        for compression in [0, 20, 50, 100] {
+            let mut creator = Creator::new(MimeType::TIFF).await.unwrap();
+            creator.set_encoding_compression(compression).unwrap();
+            creator
+                .add_frame(
+                    frame.width(),
+                    frame.height(),
+                    glycin::MemoryFormat::R8g8b8,
+                    texture.clone(),
+                )
+                .unwrap();
+            let encoded_image = creator.create().await.unwrap();
+
+            let loader = glycin::Loader::new_vec(encoded_image.data_full());
+            let mut image = loader.load().await.unwrap();
+            let frame = image.next_frame().await.unwrap();
+            assert_eq!(frame.buf_slice()[..3], texture[..3]);
+
+            sizes.push(encoded_image.data_ref().len());
+        }
+
+        // Uncompressed is the largest and Deflate the smallest
+        assert!(sizes[0] > sizes[2], "{sizes:?}");
+        assert!(sizes[3] < sizes[2], "{sizes:?}");
+        assert!(sizes[3] < sizes[1], "{sizes:?}");
+    });
+}
+
+#[test]
+fn processor_creator_ico() {
+    block_on(async {
+        init();
+
+        let mut creator = Creator::new(MimeType::ICO).await.unwrap();
+        creator.set_icon_standard_sizes(true).unwrap();
+
+        for (size, color) in [(16, [255, 0, 0, 255]), (64, [0, 0, 255, 128])] {
+            creator
+                .add_frame(
+                    size,
+                    size,
+                    glycin::MemoryFormat::R8g8b8a8,
+                    color.repeat((size * size) as usize),
+                )
+                .unwrap();
+        }
+
+        let data = creator.create().await.unwrap().data_full();
+
+        // Type icon
+        assert_eq!(data[2..4], [1, 0]);
+        // 16, 24, 32, 48, and 64 pixels
+        assert_eq!(data[4..6], [5, 0]);
+        // Largest entry first
+        assert_eq!(data[6], 64);
+
+        let mut loader = glycin::Loader::new_vec(data);
+        loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8a8);
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        assert_eq!(frame.width(), 64);
+        assert_eq!(frame.buf_slice()[..4], [0, 0, 255, 128]);
+    });
+}
+
+#[test]
+fn processor_creator_cursor() {
+    block_on(async {
+        init();
+
+        let capabilities = Creator::capabilities(MimeType::ICO).await.unwrap();
+        assert!(!capabilities.supports_cursor_hotspot());
+
+        let mut creator = Creator::new(MimeType::CUR).await.unwrap();
+        let new_frame = creator
+            .add_frame(
+                32,
+                32,
+                glycin::MemoryFormat::R8g8b8a8,
+                [255, 255, 255, 255].repeat(32 * 32),
+            )
+            .unwrap();
+        new_frame.set_cursor_hotspot(Some((3, 5))).unwrap();
+
+        let data = creator.create().await.unwrap().data_full();
+
+        // Type cursor
+        assert_eq!(data[2..4], [2, 0]);
+        // Hotspot
+        assert_eq!(data[10..14], [3, 0, 5, 0]);
+
+        // Hotspot outside of the image
+        let mut creator = Creator::new(MimeType::CUR).await.unwrap();
+        let new_frame = creator
+            .add_frame(2, 2, glycin::MemoryFormat::R8g8b8a8, [0; 16].to_vec())
+            .unwrap();
+        new_frame.set_cursor_hotspot(Some((2, 0))).unwrap();
+        assert!(creator.create().await.is_err());
+    });
+}
+
+#[test]
+fn processor_creator_dds() {
+    block_on(async {
+        init();
+
+        let (width, height) = (16, 8);
+        let texture = [0, 128, 255, 255].repeat((width * height) as usize);
+
+        let create = async |compression: Option<glycin::TextureCompression>, mipmaps: bool| {
+            let mut creator = Creator::new(MimeType::DDS).await.unwrap();
+            if let Some(compression) = compression {
+                creator
+                    .set_encoding_texture_compression(compression)
+                    .unwrap();
+            }
+            creator.set_encoding_mipmaps(mipmaps).unwrap();
+            creator
+                .add_frame(
+                    width,
+                    height,
+                    glycin::MemoryFormat::R8g8b8a8,
+                    texture.clone(),
+                )
+                .unwrap();
+            creator.create().await.unwrap().data_full()
+        };
+
+        // Opaque images default to BC1
+        let data = create(None, false).await;
+        assert_eq!(&data[84..88], b"DXT1");
+        // Header and 8 bytes per 4x4 block
+        assert_eq!(data.len(), 128 + 8 * 8);
+
+        for compression in [
+            glycin::TextureCompression::Bc1,
+            glycin::TextureCompression::Bc3,
+        ] {
+            let data = create(Some(compression), false).await;
+
+            let mut loader = glycin::Loader::new_vec(data);
+            loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8a8);
+            let mut image = loader.load().await.unwrap();
+            let frame = image.next_frame().await.unwrap();
+            assert_eq!((frame.width(), frame.height()), (width, height));
+            for pixel in frame.buf_slice().chunks_exact(4) {
+                assert!(pixel.iter().zip(&texture).all(|(a, b)| a.abs_diff(*b) <= 4));
+            }
+        }
+
+        // DX10 header extension with BC7
+        let data = create(Some(glycin::TextureCompression::Bc7), false).await;
+        assert_eq!(&data[84..88], b"DX10");
+        assert_eq!(data[128..132], 98_u32.to_le_bytes());
+        assert_eq!(data.len(), 148 + 16 * 8);
+
+        // 16x8, 8x4, 4x2, 2x1, and 1x1
+        let data = create(Some(glycin::TextureCompression::Bc1), true).await;
+        assert_eq!(data[28..32], 5_u32.to_le_bytes());
+        assert_eq!(data.len(), 128 + 8 * (8 + 2 + 1 + 1 + 1));
+    });
+}
+
+#[test]
+fn processor_creator_radiance_hdr() {
+    block_on(async {
+        init();
+
+        let pixel = [1.0_f32, 0.5, 4.0];
+        let texture = pixel.iter().flat_map(|x| x.to_ne_bytes()).collect();
+
+        let mut creator = Creator::new(MimeType::RADIANCE_HDR).await.unwrap();
+        creator
+            .add_frame(1, 1, glycin::MemoryFormat::R32g32b32Float, texture)
+            .unwrap();
+        let data = creator.create().await.unwrap().data_full();
+        assert!(data.starts_with(b"#?RADIANCE"));
+
+        let mut loader = glycin::Loader::new_vec(data);
+        loader.accepted_memory_formats(glycin::MemoryFormatSelection::R32g32b32Float);
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        let loaded = frame
+            .buf_slice()
+            .chunks_exact(4)
+            .map(|x| f32::from_ne_bytes(x.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        // Channels share an exponent with 8-bit mantissas
+        for (loaded, pixel) in loaded.iter().zip(pixel) {
+            assert!((loaded - pixel).abs() <= pixel / 64., "{loaded} {pixel}");
+        }
+    });
+}
+
+#[test]
+fn processor_creator_exr() {
+    use exr::prelude::f16;
+
+    block_on(async {
+        init();
+
+        let (width, height) = (32, 32);
+        let beauty = |i: usize| {
+            let (x, y) = ((i % width) as f32, (i / width) as f32);
+            [x / 32., y / 32., (x + y) / 16., 1.]
+        };
+        let normal = |i: usize| {
+            let (x, y) = ((i % width) as f32, (i / width) as f32);
+            [x / 16. - 1., y / 16. - 1., 1.]
+        };
+
+        let beauty_texture = (0..width * height)
+            .flat_map(beauty)
+            .flat_map(|x| f16::from_f32(x).to_ne_bytes())
+            .collect::<Vec<_>>();
+        let normal_texture = (0..width * height)
+            .flat_map(normal)
+            .flat_map(|x| x.to_ne_bytes())
+            .collect::<Vec<_>>();
+
+        let mut sizes = Vec::new();
+        for compression in [
+            glycin::ExrCompression::Uncompressed,
+            glycin::ExrCompression::Zip,
+            glycin::ExrCompression::Piz,
+            glycin::ExrCompression::Dwaa,
+        ] {
+            // DWAA is lossy for color channels
+            let tolerance = if compression == glycin::ExrCompression::Dwaa {
+                0.05
+            } else {
+                0.
+            };
+
+            let mut creator = Creator::new(MimeType::OPEN_EXR).await.unwrap();
+            creator.set_encoding_exr_compression(compression).unwrap();
+
+            let new_frame = creator
+                .add_frame(
+                    width as u32,
+                    height as u32,
+                    glycin::MemoryFormat::R16g16b16a16Float,
+                    beauty_texture.clone(),
+                )
+                .unwrap();
+            new_frame.set_layer_name(Some("beauty".into())).unwrap();
+
+            let new_frame = creator
+                .add_frame(
+                    width as u32,
+                    height as u32,
+                    glycin::MemoryFormat::R32g32b32Float,
+                    normal_texture.clone(),
+                )
+                .unwrap();
+            new_frame.set_layer_name(Some("normal".into())).unwrap();
+
+            let data = creator.create().await.unwrap().data_full();
+            sizes.push(data.len());
+
+            // The first layer is loaded
+            let loader = glycin::Loader::new_vec(data.clone());
+            let mut image = loader.load().await.unwrap();
+            let frame = image.next_frame().await.unwrap();
+            assert_eq!(
+                frame.memory_format(),
+                glycin::MemoryFormat::R16g16b16a16Float
+            );
+            assert_eq!((frame.width(), frame.height()), (32, 32));
+            let samples = frame
+                .buf_slice()
+                .chunks_exact(2)
+                .map(|x| f16::from_ne_bytes([x[0], x[1]]).to_f32());
+            let expected = (0..width * height).flat_map(beauty);
+            for (i, (sample, expected)) in samples.zip(expected).enumerate() {
+                assert!(
+                    (sample - expected).abs() <= tolerance,
+                    "{compression:?} beauty sample {i}: {sample} != {expected}"
+                );
+            }
+
+            // The loader only provides the first layer, read the second one directly
+            let image = exr::image::read::read()
+                .no_deep_data()
+                .largest_resolution_level()
+                .all_channels()
+                .all_layers()
+                .all_attributes()
+                .from_buffered(std::io::Cursor::new(data))
+                .unwrap();
+
+            let layer_names = image
+                .layer_data
+                .iter()
+                .map(|x| x.attributes.layer_name.as_ref().map(|x| x.to_string()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                layer_names,
+                [Some("beauty".to_string()), Some("normal".to_string())]
+            );
+
+            let layer = &image.layer_data[1];
+            assert_eq!(layer.size, exr::math::Vec2(width, height));
+            let channels = &layer.channel_data.list;
+            assert_eq!(channels.len(), 3);
+            // Channels are sorted by name
+            for (channel, name, c) in [(0, "B", 2), (1, "G", 1), (2, "R", 0)] {
+                let channel = &channels[channel];
+                assert_eq!(channel.name.to_string(), name);
+                let exr::image::FlatSamples::F32(samples) = &channel.sample_data else {
+                    panic!("Unexpected sample type for {name}");
+                };
+                assert_eq!(samples.len(), width * height);
+                for (i, sample) in samples.iter().enumerate() {
+                    let expected = normal(i)[c];
+                    assert!(
+                        (sample - expected).abs() <= tolerance,
+                        "{compression:?} normal {name} sample {i}: {sample} != {expected}"
+                    );
+                }
+            }
+        }
+
+        // Gradients compress well with every method
+        assert!(sizes[1..].iter().all(|x| *x < sizes[0]), "{sizes:?}");
+    });
+}
+
+/// ICC profile for CMYK where the inks are subtracted from sRGB with a strong
+/// dot gain, such that a naive inversion doesn't result in the same colors
+fn cmyk_icc_profile() -> Vec<u8> {
+    use moxcms::{
+        ColorProfile, DataColorSpace, LutDataType, LutStore, LutType, LutWarehouse, Matrix3d,
+        ProfileClass, Xyzd,
+    };
+
+    const WHITE: [f64; 3] = [0.9642, 1.0, 0.8249];
+    const SRGB_TO_XYZ: [[f64; 3]; 3] = [
+        [0.4360747, 0.3850649, 0.1430804],
+        [0.2225045, 0.7168786, 0.0606169],
+        [0.0139322, 0.0971045, 0.7141733],
+    ];
+    const XYZ_TO_SRGB: [[f64; 3]; 3] = [
+        [3.1338561, -1.6168667, -0.4906146],
+        [-0.9787684, 1.9161415, 0.0334540],
+        [0.0719453, -0.2289914, 1.4052427],
+    ];
+    const CMYK_GRID_POINTS: usize = 9;
+    const LAB_GRID_POINTS: usize = 17;
+
+    let mul = |m: [[f64; 3]; 3], v: [f64; 3]| {
+        m.map(|row| row.iter().zip(v).map(|(a, b)| a * b).sum::<f64>())
+    };
+    // Normalized coordinates of the CLUT entry `i` with `n` inputs
+    let grid = |i: usize, n: u32, points: usize| -> Vec<f64> {
+        (0..n)
+            .map(|j| (i / points.pow(n - 1 - j) % points) as f64)
+            .map(|x| x / (points - 1) as f64)
+            .collect()
+    };
+    let to_u16 = |x: f64| (x * 65535.).round().clamp(0., 65535.) as u16;
+    let delta = 6_f64 / 29.;
+
+    let mut cmyk_to_lab = Vec::new();
+    for i in 0..CMYK_GRID_POINTS.pow(4) {
+        let cmyk = grid(i, 4, CMYK_GRID_POINTS);
+        let coverage = cmyk
+            .iter()
+            .map(|x| 1. - (1. - x).powi(2))
+            .collect::<Vec<_>>();
+        let srgb = [0, 1, 2].map(|j| (1. - coverage[j]) * (1. - coverage[3]));
+        let linear = srgb.map(|x| {
+            if x <= 0.04045 {
+                x / 12.92
+            } else {
+                ((x + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        let xyz = mul(SRGB_TO_XYZ, linear);
+        let [fx, fy, fz] = [0, 1, 2].map(|j| {
+            let t = xyz[j] / WHITE[j];
+            if t > delta.powi(3) {
+                t.cbrt()
+            } else {
+                t / (3. * delta.powi(2)) + 4. / 29.
+            }
+        });
+        let lab = [
+            (116. * fy - 16.) / 100.,
+            (500. * (fx - fy) + 128.) / 255.,
+            (200. * (fy - fz) + 128.) / 255.,
+        ];
+        cmyk_to_lab.extend(lab.map(to_u16));
+    }
+
+    let mut lab_to_cmyk = Vec::new();
+    for i in 0..LAB_GRID_POINTS.pow(3) {
+        let lab = grid(i, 3, LAB_GRID_POINTS);
+        let fy = (lab[0] * 100. + 16.) / 116.;
+        let f = [
+            fy + (lab[1] * 255. - 128.) / 500.,
+            fy,
+            fy - (lab[2] * 255. - 128.) / 200.,
+        ];
+        let xyz = [0, 1, 2].map(|j| {
+            let t = f[j];
+            WHITE[j]
+                * if t > delta {
+                    t.powi(3)
+                } else {
+                    3. * delta.powi(2) * (t - 4. / 29.)
+                }
+        });
+        let srgb = mul(XYZ_TO_SRGB, xyz).map(|x| {
+            let x = x.clamp(0., 1.);
+            if x <= 0.0031308 {
+                x * 12.92
+            } else {
+                1.055 * x.powf(1. / 2.4) - 0.055
+            }
+        });
+        let k = 1. - srgb.into_iter().fold(0., f64::max);
+        let coverage = srgb.map(|x| if k < 1. { (1. - x - k) / (1. - k) } else { 0. });
+        let cmyk = [coverage[0], coverage[1], coverage[2], k].map(|x| 1. - (1. - x).sqrt());
+        lab_to_cmyk.extend(cmyk.map(to_u16));
+    }
+
+    let lut = |n_inputs: u8, n_outputs: u8, points: usize, clut: Vec<u16>| {
+        LutWarehouse::Lut(LutDataType {
+            num_input_channels: n_inputs,
+            num_output_channels: n_outputs,
+            num_clut_grid_points: points as u8,
+            matrix: Matrix3d::IDENTITY,
+            num_input_table_entries: 2,
+            num_output_table_entries: 2,
+            input_table: LutStore::Store16([0, u16::MAX].repeat(n_inputs.into())),
+            clut_table: LutStore::Store16(clut),
+            output_table: LutStore::Store16([0, u16::MAX].repeat(n_outputs.into())),
+            lut_type: LutType::Lut16,
+        })
+    };
+
+    let mut profile = ColorProfile::default();
+    profile.profile_class = ProfileClass::OutputDevice;
+    profile.color_space = DataColorSpace::Cmyk;
+    profile.pcs = DataColorSpace::Lab;
+    profile.white_point = Xyzd::new(WHITE[0], WHITE[1], WHITE[2]);
+    profile.media_white_point = Some(profile.white_point);
+    profile.lut_a_to_b_perceptual = Some(lut(4, 3, CMYK_GRID_POINTS, cmyk_to_lab));
+    profile.lut_b_to_a_perceptual = Some(lut(3, 4, LAB_GRID_POINTS, lab_to_cmyk));
+
+    profile.encode().unwrap()
+}
+
+#[test]
+fn processor_creator_cmyk() {
+    block_on(async {
+        init();
+
+        let icc_profile = cmyk_icc_profile();
+        let (width, height) = (64, 48);
+        let texture = (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [(x * 4) as u8, (y * 5) as u8, 128]))
+            .collect::<Vec<_>>();
+
+        for mime_type in [MimeType::JPEG, MimeType::TIFF] {
+            eprintln!("- {}", mime_type.as_str());
+
+            let mut creator = Creator::new(mime_type).await.unwrap();
+            creator
+                .set_encoding_cmyk_icc_profile(icc_profile.clone())
+                .unwrap();
+            creator
+                .add_frame(width, height, MemoryFormat::R8g8b8, texture.clone())
+                .unwrap();
+            let data = creator.create().await.unwrap().data_full();
+
+            // The CMYK profile is embedded
+            assert!(data.windows(128).any(|x| x == &icc_profile[..128]));
+
+            let loader = Loader::new_vec(data);
+            let mut image = loader.load().await.unwrap();
+            let frame = image.next_frame().await.unwrap();
+            assert_eq!(frame.memory_format(), MemoryFormat::R8g8b8);
+
+            // A naive inversion is off by about 45 on average due to the dot gain
+            let diff = frame
+                .buf_slice()
+                .iter()
+                .zip(&texture)
+                .map(|(a, b)| a.abs_diff(*b) as usize)
+                .sum::<usize>();
+            let mean_diff = diff as f64 / texture.len() as f64;
+            assert!(mean_diff < 5., "{mean_diff}");
+        }
+    });
+}
+
+#[test]
+fn processor_creator_metadata() {
+    block_on(async {
+        init();
+
+        // TIFF header with an empty IFD
+        let exif = b"II*\0\x08\0\0\0\0\0\0\0\0\0".to_vec();
+        let xmp = b"<x:xmpmeta xmlns:x='adobe:ns:meta/'></x:xmpmeta>".to_vec();
+
+        for mime_type in [MimeType::JPEG, MimeType::PNG, MimeType::WEBP] {
+            eprintln!("- {}", mime_type.as_str());
+
+            let mut creator = Creator::new(mime_type.clone()).await.unwrap();
+            creator.set_metadata_exif(Some(exif.clone())).unwrap();
+            creator.set_metadata_xmp(Some(xmp.clone())).unwrap();
+            creator
+                .add_frame(1, 1, glycin::MemoryFormat::R8g8b8, vec![0, 0, 255])
+                .unwrap();
+
+            let encoded_image = creator.create().await.unwrap();
+
+            let loader = glycin::Loader::new_vec(encoded_image.data_full());
+            let image = loader.load().await.unwrap();
+
+            assert_eq!(image.details().metadata_exif(), Some(exif.as_slice()));
+            assert_eq!(image.details().metadata_xmp(), Some(xmp.as_slice()));
+        }
+
+        // TIFF header with an Exif IFD that contains an exposure time of 1/250
+        let exif = b"II*\0\x08\0\0\0\x01\0\x69\x87\x04\0\x01\0\0\0\x1a\0\0\0\0\0\0\0\
+            \x01\0\x9a\x82\x05\0\x01\0\0\0\x2c\0\0\0\0\0\0\0\x01\0\0\0\xfa\0\0\0"
+            .to_vec();
+
+        let mut creator = Creator::new(MimeType::TIFF).await.unwrap();
+        creator.set_metadata_exif(Some(exif)).unwrap();
+        creator.set_metadata_xmp(Some(xmp.clone())).unwrap();
+        creator
+            .add_frame(1, 1, glycin::MemoryFormat::R8g8b8, vec![0, 0, 255])
+            .unwrap();
+
+        let encoded_image = creator.create().await.unwrap();
+
+        let loader = glycin::Loader::new_vec(encoded_image.data_full());
+        let image = loader.load().await.unwrap();
+
+        // The complete TIFF structure is the Exif data
+        let exif =
+            gufo_exif::Exif::for_vec(image.details().metadata_exif().unwrap().to_vec()).unwrap();
+        let exposure_time = exif.exposure_time().unwrap();
+        assert_eq!(
+            (exposure_time.numerator, exposure_time.denominator),
+            (1, 250)
+        );
+        assert_eq!(image.details().metadata_xmp(), Some(xmp.as_slice()));
+    });
+}
+
+#[test]
+fn processor_creator_jpeg_encoding_options() {
+    block_on(async {
+        init();
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        creator
+            .set_encoding_chroma_subsampling(glycin::ChromaSubsampling::Yuv420)
+            .unwrap();
+        creator.set_encoding_progressive(true).unwrap();
+        creator.set_encoding_effort(100).unwrap();
+        assert!(creator.set_encoding_bit_depth(16).is_err());
+
+        creator
+            .add_frame(
+                2,
+                2,
+                glycin::MemoryFormat::R8g8b8,
+                vec![0, 0, 255].repeat(4),
+            )
+            .unwrap();
+
+        let encoded_image = creator.create().await.unwrap();
+
+        let loader = glycin::Loader::new_vec(encoded_image.data_full());
+        let image = loader.load().await.unwrap();
+
+        assert_eq!(image.details().width(), 2);
+        assert_eq!(image.details().height(), 2);
+    });
+}
+
 #[test]
 fn processor_creator_png() {
     block_on(async {
@@ -337,6 +1272,178 @@ fn processor_creator_avif() {
     });
 }
 
+#[test]
+fn processor_creator_hdr() {
+    block_on(async {
+        init();
+
+        let cicp = glycin::Cicp {
+            color_primaries: gufo_common::cicp::ColorPrimaries::Rec2020,
+            transfer_characteristics: gufo_common::cicp::TransferCharacteristics::Pq,
+            matrix_coefficients: gufo_common::cicp::MatrixCoefficients::Identity,
+            video_full_range_flag: gufo_common::cicp::VideoRangeFlag::Full,
+        };
+
+        for mime_type in [MimeType::AVIF, MimeType::JXL] {
+            if skip_file_ext(mime_type.extension().unwrap()) {
+                continue;
+            }
+
+            eprintln!("- {}", mime_type.as_str());
+
+            let mut creator = Creator::new(mime_type.clone()).await.unwrap();
+            let texture = [u16::MAX, 0, 0]
+                .iter()
+                .flat_map(|x| x.to_ne_bytes())
+                .collect();
+            let new_frame = creator
+                .add_frame(1, 1, glycin::MemoryFormat::R16g16b16, texture)
+                .unwrap();
+            new_frame.set_color_cicp(Some(cicp)).unwrap();
+
+            let encoded_image = creator.create().await.unwrap();
+
+            let loader = glycin::Loader::new_vec(encoded_image.data_full());
+            let mut image = loader.load().await.unwrap();
+            let frame = image.next_frame().await.unwrap();
+
+            assert_eq!(frame.details().color_cicp(), Some(cicp));
+        }
+    });
+}
+
+#[test]
+fn processor_creator_create_to_file() {
+    block_on(async {
+        init();
+
+        let path = format!("{}/create-to-file.png", env!("CARGO_TARGET_TMPDIR"));
+        std::fs::write(&path, b"previous content").unwrap();
+        let file = gio::File::for_path(&path);
+
+        let mut creator = Creator::new(MimeType::PNG).await.unwrap();
+        creator
+            .add_frame(1, 1, MemoryFormat::R8g8b8, vec![255, 0, 0])
+            .unwrap();
+        creator.create_to_file(file.clone()).await.unwrap();
+
+        let loader = Loader::new(file.clone());
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        assert_eq!(frame.buf_slice(), [255, 0, 0]);
+
+        // A failed creation must keep the existing file
+        let mut creator = Creator::new(MimeType::PNG).await.unwrap();
+        creator.limits(glycin::Limits::default().max_dimensions((1, 1)));
+        creator
+            .add_frame(2, 2, MemoryFormat::R8g8b8, vec![0; 2 * 2 * 3])
+            .unwrap();
+        creator.create_to_file(file).await.unwrap_err();
+
+        let loader = Loader::new(gio::File::for_path(&path));
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        assert_eq!(frame.buf_slice(), [255, 0, 0]);
+    });
+}
+
+/// Output for `create_to()` that stops accepting data after `limit` bytes
+///
+/// If a cancellable is set, it is canceled instead when the limit is reached.
+struct LimitedWriter {
+    data: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+    limit: usize,
+    cancellable: Option<gio::Cancellable>,
+}
+
+impl std::io::Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut data = self.data.lock().unwrap();
+
+        if data.len() >= self.limit {
+            match &self.cancellable {
+                Some(cancellable) => cancellable.cancel(),
+                None => return Err(std::io::Error::other("Output is full")),
+            }
+        }
+
+        data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn processor_creator_create_to_stream() {
+    block_on(async {
+        init();
+
+        let (width, height) = (1024, 1024);
+        let texture = (0..width * height)
+            .flat_map(|i| [(i % 251) as u8, (i / 7 % 253) as u8, (i % 13) as u8])
+            .collect::<Vec<_>>();
+
+        let creator = async || {
+            let mut creator = Creator::new(MimeType::BMP).await.unwrap();
+            creator
+                .add_frame(width, height, MemoryFormat::R8g8b8, texture.clone())
+                .unwrap();
+            creator
+        };
+
+        let data = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let stream = gio::WriteOutputStream::new(LimitedWriter {
+            data: data.clone(),
+            limit: usize::MAX,
+            cancellable: None,
+        });
+        unsafe { creator().await.create_to(stream) }.await.unwrap();
+
+        let data = data.lock().unwrap().clone();
+        let loader = Loader::new_vec(data);
+        let mut image = loader.load().await.unwrap();
+        let frame = image.next_frame().await.unwrap();
+        assert_eq!(frame.buf_slice(), texture);
+
+        // Output fails in the middle of the image
+        let limit = 64 * 1024;
+        let data = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let stream = gio::WriteOutputStream::new(LimitedWriter {
+            data: data.clone(),
+            limit,
+            cancellable: None,
+        });
+        let err = unsafe { creator().await.create_to(stream) }
+            .await
+            .unwrap_err();
+
+        let written = data.lock().unwrap().len() as u64;
+        assert!(written >= limit as u64);
+        assert_eq!(err.partial_output_size(), Some(written));
+
+        // Canceled in the middle of the image
+        let cancellable = gio::Cancellable::new();
+        let data = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let stream = gio::WriteOutputStream::new(LimitedWriter {
+            data: data.clone(),
+            limit,
+            cancellable: Some(cancellable.clone()),
+        });
+        let mut creator = creator().await;
+        creator.cancellable(cancellable);
+        let err = unsafe { creator.create_to(stream) }.await.unwrap_err();
+
+        let written = data.lock().unwrap().len() as u64;
+        assert!(err.is_cancelled());
+        assert!(written >= limit as u64);
+        assert!(written < (width * height * 3) as u64);
+        assert_eq!(err.partial_output_size(), Some(written));
+    });
+}
+
 #[test]
 fn processor_creator_supported_memory_formats() {
     block_on(async {
@@ -370,3 +1477,219 @@ fn processor_creator_supported_memory_formats() {
         }
     });
 }
+
+#[test]
+fn processor_creator_capabilities() {
+    block_on(async {
+        init();
+
+        let capabilities = Creator::capabilities(MimeType::JPEG).await.unwrap();
+        assert!(capabilities.supports_encoding_quality());
+        assert!(capabilities.supports_target_size());
+        assert!(!capabilities.supports_animation());
+        assert!(
+            capabilities
+                .memory_formats()
+                .contains(&MemoryFormat::R8g8b8)
+        );
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        assert_eq!(
+            creator.set_loop_count(0).is_ok(),
+            capabilities.supports_animation()
+        );
+
+        let capabilities = Creator::capabilities(MimeType::GIF).await.unwrap();
+        assert!(capabilities.supports_animation());
+
+        assert!(
+            Creator::capabilities(MimeType::new("image/x-unknown".into()))
+                .await
+                .is_err()
+        );
+    });
+}
+
+#[test]
+fn processor_creator_transcode() {
+    block_on(async {
+        init();
+
+        let mut creator = Creator::new(MimeType::GIF).await.unwrap();
+        for color in [[255, 0, 0], [0, 0, 255]] {
+            let new_frame = creator
+                .add_frame(2, 2, glycin::MemoryFormat::R8g8b8, color.repeat(4))
+                .unwrap();
+            new_frame
+                .set_delay(Some(std::time::Duration::from_millis(200)))
+                .unwrap();
+        }
+        let gif = creator.create().await.unwrap().data_full();
+
+        // Animations are carried over
+        let transcoder = glycin::Transcoder::new_vec(gif.clone(), MimeType::PNG);
+        let encoded_image = transcoder.transcode().await.unwrap();
+
+        let mut loader = glycin::Loader::new_vec(encoded_image.data_full());
+        loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+        let mut image = loader.load().await.unwrap();
+        assert_eq!(image.mime_type(), MimeType::PNG);
+
+        for color in [[255, 0, 0], [0, 0, 255]] {
+            let frame = image.next_frame().await.unwrap();
+            assert_eq!(frame.delay(), Some(std::time::Duration::from_millis(200)));
+            assert_eq!(frame.buf_slice()[..3], color);
+        }
+
+        // Only the first frame is used for formats without animations
+        let transcoder = glycin::Transcoder::new_vec(gif, MimeType::JPEG);
+        let mut creator = transcoder.creator().await.unwrap();
+        creator.set_encoding_quality(100).unwrap();
+        let encoded_image = creator.create().await.unwrap();
+
+        let loader = glycin::Loader::new_vec(encoded_image.data_full());
+        let mut image = loader.load().await.unwrap();
+        assert_eq!(image.mime_type(), MimeType::JPEG);
+
+        let frame = image.next_frame().await.unwrap();
+        assert_eq!(frame.delay(), None);
+        assert!(frame.buf_slice()[0].abs_diff(255) < 5);
+        assert!(frame.buf_slice()[2] < 5);
+    });
+}
+
+#[test]
+fn processor_creator_transcode_orientation() {
+    block_on(async {
+        init();
+
+        // TIFF header with an IFD that contains orientation 6, rotated by 90° to be
+        // displayed correctly
+        let exif = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0\x06\0\0\0\0\0\0\0".to_vec();
+        let xmp = b"<x:xmpmeta xmlns:x='adobe:ns:meta/'></x:xmpmeta>".to_vec();
+        let icc_profile = vec![1, 2, 3];
+
+        // Left half red, right half blue
+        let (width, height) = (16, 8);
+        let texture = (0..width * height)
+            .flat_map(|i| {
+                if i % width < width / 2 {
+                    [255, 0, 0]
+                } else {
+                    [0, 0, 255]
+                }
+            })
+            .collect::<Vec<u8>>();
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        creator.set_encoding_quality(100).unwrap();
+        creator.set_metadata_exif(Some(exif)).unwrap();
+        creator.set_metadata_xmp(Some(xmp.clone())).unwrap();
+        let new_frame = creator
+            .add_frame(width, height, MemoryFormat::R8g8b8, texture)
+            .unwrap();
+        new_frame
+            .set_color_icc_profile(Some(icc_profile.clone()))
+            .unwrap();
+        new_frame
+            .set_pixel_density(Some(PixelDensity::new(
+                PixelsPerPhysicalDimension::new(100., PhysicalDimensionUnit::Inch),
+                PixelsPerPhysicalDimension::new(200., PhysicalDimensionUnit::Inch),
+            )))
+            .unwrap();
+        let jpeg = creator.create().await.unwrap().data_full();
+
+        let transcoder = glycin::Transcoder::new_vec(jpeg, MimeType::PNG);
+        let encoded_image = transcoder.transcode().await.unwrap();
+
+        let mut loader = glycin::Loader::new_vec(encoded_image.data_full());
+        loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+        let mut image = loader.load().await.unwrap();
+
+        assert_eq!(image.transformation_orientation(), Orientation::Id);
+        assert_eq!(image.details().metadata_xmp(), Some(xmp.as_slice()));
+
+        let frame = image.next_frame().await.unwrap();
+        assert_eq!((frame.width(), frame.height()), (height, width));
+        assert_eq!(
+            frame.details().color_icc_profile().unwrap().to_vec(),
+            icc_profile
+        );
+
+        // The left half is now at the top
+        let buf = frame.buf_slice();
+        let top = &buf[..3];
+        let bottom = &buf[buf.len() - 3..];
+        assert!(top[0] > 245 && top[2] < 10);
+        assert!(bottom[0] < 10 && bottom[2] > 245);
+
+        let pixel_density = frame.details().pixel_density().unwrap();
+        assert_eq!(
+            pixel_density
+                .x()
+                .convert(PhysicalDimensionUnit::Inch)
+                .value()
+                .round(),
+            200.
+        );
+        assert_eq!(
+            pixel_density
+                .y()
+                .convert(PhysicalDimensionUnit::Inch)
+                .value()
+                .round(),
+            100.
+        );
+    });
+}
+
+#[test]
+fn processor_creator_jxl_jpeg_recompression() {
+    block_on(async {
+        init();
+
+        if skip_file_ext("jxl") {
+            return;
+        }
+
+        let mut creator = Creator::new(MimeType::JPEG).await.unwrap();
+        let texture = (0..32 * 32)
+            .flat_map(|x| [x as u8, (x / 4) as u8, 128])
+            .collect();
+        creator
+            .add_frame(32, 32, MemoryFormat::R8g8b8, texture)
+            .unwrap();
+        let jpeg = creator.create().await.unwrap().data_full();
+
+        let capabilities = Creator::capabilities(MimeType::JXL).await.unwrap();
+        assert!(capabilities.supports_jpeg_recompression());
+
+        let mut creator = Creator::new(MimeType::JXL).await.unwrap();
+        creator.set_source_jpeg(jpeg.clone()).unwrap();
+        let jxl = creator.create().await.unwrap().data_full();
+
+        let image = Loader::new_vec(jxl).load().await.unwrap();
+        assert_eq!(image.details().width(), 32);
+        let reconstructed = image.reconstruct_jpeg().await.unwrap();
+        assert_eq!(reconstructed.data_ref(), jpeg);
+
+        // The transcoder recompresses JPEGs automatically
+        let jxl = glycin::Transcoder::new_vec(jpeg.clone(), MimeType::JXL)
+            .transcode()
+            .await
+            .unwrap()
+            .data_full();
+        let image = Loader::new_vec(jxl).load().await.unwrap();
+        let reconstructed = image.reconstruct_jpeg().await.unwrap();
+        assert_eq!(reconstructed.data_ref(), jpeg);
+
+        // Images encoded from pixels can't be reconstructed
+        let mut creator = Creator::new(MimeType::JXL).await.unwrap();
+        creator
+            .add_frame(1, 1, MemoryFormat::R8g8b8, vec![255, 0, 0])
+            .unwrap();
+        let jxl = creator.create().await.unwrap().data_full();
+        let image = Loader::new_vec(jxl).load().await.unwrap();
+        assert!(image.reconstruct_jpeg().await.is_err());
+    });
+}
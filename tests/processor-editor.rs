@@ -1,3 +1,4 @@
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
 use glycin_core as glycin;
@@ -28,6 +29,55 @@ fn processor_editor_crop_too_large_value() {
     run_test("crop-too-large-value");
 }
 
+#[test]
+fn processor_editor_preview() {
+    init();
+
+    block_on(test_preview("rotation-90"))
+}
+
+#[test]
+fn processor_editor_sparse_file_changed() {
+    init();
+
+    block_on(test_sparse_file_changed("rotation-90"))
+}
+
+#[test]
+fn processor_editor_save() {
+    init();
+
+    block_on(test_save("crop"))
+}
+
+#[test]
+fn processor_editor_verify_output() {
+    init();
+
+    block_on(test_verify_output("rotation-90"))
+}
+
+#[test]
+fn processor_editor_still_and_animated() {
+    init();
+
+    block_on(test_still_and_animated())
+}
+
+#[test]
+fn processor_editor_webp_lossy() {
+    init();
+
+    block_on(test_webp_lossy())
+}
+
+#[test]
+fn processor_editor_heif_mirror() {
+    init();
+
+    block_on(test_heif_mirror())
+}
+
 fn run_test(test_name: &str) {
     init();
 
@@ -73,6 +123,368 @@ async fn test(test_name: &str) {
     TestResult::check_multiple(results);
 }
 
+async fn test_preview(test_name: &str) {
+    let base_path = PathBuf::from_iter(["test-images", "editing"]);
+
+    let reference_path = base_path.join(format!("{test_name}.png"));
+    let reference = glycin::Loader::new(gio::File::for_path(reference_path))
+        .load()
+        .await
+        .unwrap();
+    let width = reference.details().width();
+    let height = reference.details().height();
+
+    let reader = std::fs::File::open(base_path.join(format!("{test_name}.yml"))).unwrap();
+    let operations: glycin::Operations = serde_yaml::from_reader(reader).unwrap();
+
+    for entry in std::fs::read_dir(base_path.join(test_name)).unwrap() {
+        let path = entry.unwrap().path();
+        eprintln!("- {path:?}");
+
+        let file = gio::File::for_path(&path);
+        let editor = glycin::Editor::new(file).edit().await.unwrap();
+
+        let frame = editor
+            .preview(&operations, glycin::FrameRequest::new())
+            .await
+            .unwrap();
+        assert_eq!((frame.width(), frame.height()), (width, height));
+
+        let frame = editor
+            .preview(
+                &operations,
+                glycin::FrameRequest::new().scale(width / 2, height / 2),
+            )
+            .await
+            .unwrap();
+        assert!(frame.width() <= width / 2 && frame.height() <= height / 2);
+
+        // The preview has to show the same pixels as the applied operations
+        let applied = editor.apply_complete(&operations).await.unwrap();
+        let mut image = glycin::Loader::new_vec(applied.data().to_vec())
+            .load()
+            .await
+            .unwrap();
+        let applied_frame = image.next_frame().await.unwrap();
+        let applied_data = texture_to_bytes(&applied_frame.texture());
+
+        let editing_frame = glycin_utils::editing::EditingFrame {
+            width: applied_frame.width(),
+            height: applied_frame.height(),
+            stride: applied_frame.width() * 4,
+            memory_format: glycin_utils::ExtendedMemoryFormat::Basic(
+                glycin_utils::MemoryFormat::R8g8b8a8,
+            ),
+            texture: glycin_utils::FungibleMemory::from_vec(applied_data.clone()),
+        };
+        let scaled =
+            glycin_utils::editing::scale_down(editing_frame, (width / 2, height / 2)).unwrap();
+
+        assert_eq!(
+            (frame.width(), frame.height()),
+            (scaled.width, scaled.height)
+        );
+        assert_preview_eq(&texture_to_bytes(&frame.texture()), &scaled.texture);
+
+        let frame = editor
+            .preview(&operations, glycin::FrameRequest::new())
+            .await
+            .unwrap();
+        assert_preview_eq(&texture_to_bytes(&frame.texture()), &applied_data);
+    }
+}
+
+fn assert_preview_eq(preview: &[u8], applied: &[u8]) {
+    assert_eq!(preview.len(), applied.len());
+
+    let deviation = preview
+        .iter()
+        .zip(applied)
+        .map(|(a, b)| a.abs_diff(*b) as u64)
+        .sum::<u64>() as f64
+        / preview.len() as f64;
+
+    assert!(deviation < 3.1, "Preview deviates by {deviation}");
+}
+
+async fn test_sparse_file_changed(test_name: &str) {
+    let base_path = PathBuf::from_iter(["test-images", "editing"]);
+
+    let reader = std::fs::File::open(base_path.join(format!("{test_name}.yml"))).unwrap();
+    let operations: glycin::Operations = serde_yaml::from_reader(reader).unwrap();
+
+    for entry in std::fs::read_dir(base_path.join(test_name)).unwrap() {
+        let path = entry.unwrap().path();
+
+        let file = gio::File::for_path(&path);
+        let editor = glycin::Editor::new(file).edit().await.unwrap();
+
+        let SparseEdit::Sparse(byte_changes) = editor.apply_sparse(&operations).await.unwrap()
+        else {
+            continue;
+        };
+        eprintln!("- {path:?}");
+
+        // Change the file after the edit was created
+        let mut data = std::fs::read(&path).unwrap();
+        let offset = byte_changes.changes[0].offset as usize;
+        data[offset] = data[offset].wrapping_add(1);
+
+        let tmp_path = write_tmp(path.file_name().unwrap(), &data);
+        let tmp_file = gio::File::for_path(&tmp_path);
+
+        let err = SparseEdit::Sparse(byte_changes)
+            .apply_to(tmp_file.clone())
+            .await
+            .unwrap_err();
+        assert!(err.is_file_changed());
+        assert_eq!(std::fs::read(&tmp_path).unwrap(), data);
+
+        assert_eq!(
+            SparseEdit::recover(tmp_file).await.unwrap(),
+            glycin::EditOutcome::Unchanged
+        );
+    }
+}
+
+async fn test_save(test_name: &str) {
+    let base_path = PathBuf::from_iter(["test-images", "editing"]);
+    let reference_path = base_path.join(format!("{test_name}.png"));
+
+    let reader = std::fs::File::open(base_path.join(format!("{test_name}.yml"))).unwrap();
+    let operations: glycin::Operations = serde_yaml::from_reader(reader).unwrap();
+
+    let mut results = Vec::new();
+
+    for entry in std::fs::read_dir(base_path.join(test_name)).unwrap() {
+        let path = entry.unwrap().path();
+        eprintln!("- {path:?}");
+
+        let data = std::fs::read(&path).unwrap();
+        let tmp_path = write_tmp(
+            format!(
+                "{}-save-test-out",
+                path.file_name().unwrap().to_string_lossy()
+            ),
+            &data,
+        );
+        std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        let mut options = glycin::SaveOptions::new();
+        options.backup(true);
+
+        let file = gio::File::for_path(&tmp_path);
+        let editor = glycin::Editor::new(file.clone()).edit().await.unwrap();
+        editor
+            .apply_and_save(&operations, file, options)
+            .await
+            .unwrap();
+
+        let mut backup_path = tmp_path.clone().into_os_string();
+        backup_path.push("~");
+        assert_eq!(std::fs::read(backup_path).unwrap(), data);
+
+        let mode = std::fs::metadata(&tmp_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+
+        results.push(compare_images_path(&reference_path, &tmp_path, true).await);
+    }
+
+    TestResult::check_multiple(results);
+}
+
+async fn test_verify_output(test_name: &str) {
+    let base_path = PathBuf::from_iter(["test-images", "editing"]);
+
+    let reader = std::fs::File::open(base_path.join(format!("{test_name}.yml"))).unwrap();
+    let operations: glycin::Operations = serde_yaml::from_reader(reader).unwrap();
+
+    for entry in std::fs::read_dir(base_path.join(test_name)).unwrap() {
+        let path = entry.unwrap().path();
+        eprintln!("- {path:?}");
+
+        let mut editor = glycin::Editor::new(gio::File::for_path(&path));
+        editor.verify_output(true);
+        let editable_image = editor.edit().await.unwrap();
+        editable_image.apply_complete(&operations).await.unwrap();
+        editable_image.apply_sparse(&operations).await.unwrap();
+
+        let mut editor = glycin::Editor::new_vec(std::fs::read(&path).unwrap());
+        editor.verify_output(true);
+        let editable_image = editor.edit().await.unwrap();
+        editable_image.apply_sparse(&operations).await.unwrap();
+    }
+}
+
+/// Still images must not be turned into animations when edited
+async fn test_still_and_animated() {
+    let operations =
+        glycin::Operations::new(vec![glycin::Operation::Rotate(glycin::Rotation::_90)]);
+
+    for (mime_type, animation_marker) in [
+        (glycin::MimeType::GIF, &b"NETSCAPE2.0"[..]),
+        (glycin::MimeType::WEBP, &b"ANIM"[..]),
+    ] {
+        for n_frames in [1, 2] {
+            eprintln!("- {} with {n_frames} frames", mime_type.as_str());
+
+            let mut creator = glycin::Creator::new(mime_type.clone()).await.unwrap();
+            for color in [[255, 0, 0], [0, 0, 255]].into_iter().take(n_frames) {
+                let mut texture = color.repeat(2);
+                texture.extend([0, 255, 0].repeat(2));
+                creator
+                    .add_frame(2, 2, glycin::MemoryFormat::R8g8b8, texture)
+                    .unwrap()
+                    .set_delay(Some(std::time::Duration::from_millis(200)))
+                    .unwrap();
+            }
+            let data = creator.create().await.unwrap().data_full();
+
+            let editor = glycin::Editor::new_vec(data).edit().await.unwrap();
+            let edited = editor.apply_complete(&operations).await.unwrap();
+            let edited = edited.data();
+
+            let is_animated = edited
+                .windows(animation_marker.len())
+                .any(|x| x == animation_marker);
+            assert_eq!(is_animated, n_frames > 1);
+
+            let mut loader = glycin::Loader::new_vec(edited.to_vec());
+            loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+            let mut image = loader.load().await.unwrap();
+
+            for color in [[255, 0, 0], [0, 0, 255]].into_iter().take(n_frames) {
+                let frame = image.next_frame().await.unwrap();
+                assert_eq!((frame.width(), frame.height()), (2, 2));
+                // Top row after rotating by 90° counterclockwise
+                assert_eq!(frame.buf_slice()[..3], color);
+                assert_eq!(frame.buf_slice()[3..6], [0, 255, 0]);
+            }
+        }
+    }
+}
+
+/// Lossy WebPs must not be turned into lossless ones when edited
+async fn test_webp_lossy() {
+    // TIFF header with an IFD that contains orientation 6
+    let exif = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0\x06\0\0\0\0\0\0\0".to_vec();
+
+    let texture = (0..32 * 16)
+        .flat_map(|i| [(i % 32 * 8) as u8, (i / 32 * 16) as u8, 128])
+        .collect::<Vec<u8>>();
+
+    let mut creator = glycin::Creator::new(glycin::MimeType::WEBP).await.unwrap();
+    creator.set_encoding_quality(60).unwrap();
+    creator.set_metadata_exif(Some(exif)).unwrap();
+    creator
+        .add_frame(32, 16, glycin::MemoryFormat::R8g8b8, texture)
+        .unwrap();
+    let data = creator.create().await.unwrap().data_full();
+
+    // Only the Exif orientation is changed, the image data are kept
+    let operations =
+        glycin::Operations::new(vec![glycin::Operation::Rotate(glycin::Rotation::_90)]);
+    let editor = glycin::Editor::new_vec(data.clone()).edit().await.unwrap();
+    let edited = editor.apply_complete(&operations).await.unwrap();
+    assert!(edited.is_lossless());
+    let edited = edited.data();
+
+    assert_eq!(edited.len(), data.len());
+    assert_ne!(edited, data.as_slice());
+
+    let loader = glycin::Loader::new_vec(edited.to_vec());
+    let mut image = loader.load().await.unwrap();
+    let frame = image.next_frame().await.unwrap();
+    assert_eq!((frame.width(), frame.height()), (32, 16));
+
+    // Other operations encode the image lossy again
+    let operations = glycin::Operations::new(vec![glycin::Operation::Clip((0, 0, 8, 8))]);
+    let editor = glycin::Editor::new_vec(data).edit().await.unwrap();
+    let edited = editor.apply_complete(&operations).await.unwrap();
+    let edited = edited.data();
+
+    assert!(edited.windows(4).any(|x| x == b"VP8 "));
+    assert!(!edited.windows(4).any(|x| x == b"VP8L"));
+}
+
+/// Mirroring has to be stored with the correct `imir` axis and be decoded the
+/// same way by libheif
+async fn test_heif_mirror() {
+    if skip_file_ext(glycin::MimeType::HEIC.extension().unwrap()) {
+        return;
+    }
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const GREEN: [u8; 3] = [0, 255, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+    const WHITE: [u8; 3] = [255, 255, 255];
+
+    // Quadrants with a different color each
+    let texture = (0..32 * 32)
+        .flat_map(|i| match (i % 32 < 16, i / 32 < 16) {
+            (true, true) => RED,
+            (false, true) => BLUE,
+            (true, false) => GREEN,
+            (false, false) => WHITE,
+        })
+        .collect::<Vec<u8>>();
+
+    let mut creator = glycin::Creator::new(glycin::MimeType::HEIC).await.unwrap();
+    creator
+        .add_frame(32, 32, glycin::MemoryFormat::R8g8b8, texture)
+        .unwrap();
+    let data = creator.create().await.unwrap().data_full();
+
+    // Rewriting the file adds a new `imir` property
+    let operations = glycin::Operations::new(vec![glycin::Operation::MirrorHorizontally]);
+    let editor = glycin::Editor::new_vec(data).edit().await.unwrap();
+    let mirrored = editor.apply_complete(&operations).await.unwrap();
+    let mirrored = mirrored.data().to_vec();
+    assert_quadrants(&mirrored, [BLUE, RED, WHITE, GREEN]).await;
+
+    // Changing the existing `imir` property in place has to read it correctly
+    // and switch to the horizontal axis
+    let operations =
+        glycin::Operations::new(vec![glycin::Operation::Rotate(glycin::Rotation::_180)]);
+    let editor = glycin::Editor::new_vec(mirrored.clone())
+        .edit()
+        .await
+        .unwrap();
+    let sparse_edit = editor.apply_sparse(&operations).await.unwrap();
+    assert!(matches!(sparse_edit, SparseEdit::Sparse(_)));
+    let path = write_tmp("heif-mirror-sparse.heic", &mirrored);
+    sparse_edit
+        .apply_to(gio::File::for_path(&path))
+        .await
+        .unwrap();
+    let flipped = std::fs::read(&path).unwrap();
+    assert_eq!(flipped.len(), mirrored.len());
+    assert_quadrants(&flipped, [GREEN, WHITE, RED, BLUE]).await;
+}
+
+/// Check colors of the top left, top right, bottom left, and bottom right
+/// quadrant of a lossy image
+async fn assert_quadrants(data: &[u8], expected: [[u8; 3]; 4]) {
+    let mut loader = glycin::Loader::new_vec(data.to_vec());
+    loader.accepted_memory_formats(glycin::MemoryFormatSelection::R8g8b8);
+    let mut image = loader.load().await.unwrap();
+    let frame = image.next_frame().await.unwrap();
+    assert_eq!((frame.width(), frame.height()), (32, 32));
+
+    let stride = frame.stride() as usize;
+    for ((x, y), expected) in [(8, 8), (24, 8), (8, 24), (24, 24)]
+        .into_iter()
+        .zip(expected)
+    {
+        let i = y * stride + x * 3;
+        let pixel = &frame.buf_slice()[i..i + 3];
+        assert!(
+            pixel.iter().zip(expected).all(|(a, b)| a.abs_diff(b) < 32),
+            "Pixel at {x}x{y} is {pixel:?} instead of {expected:?}"
+        );
+    }
+}
+
 fn write_tmp(path: impl AsRef<Path>, data: &[u8]) -> PathBuf {
     let mut tmp_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
     tmp_path.push(path.as_ref());